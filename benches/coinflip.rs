@@ -0,0 +1,58 @@
+//! Per-bet cost of flipping through one `spawn_blocking` per bet versus one per batch.
+//!
+//! Run with `cargo bench --bench coinflip`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::sync::Arc;
+use vfnode::types::CoinflipRequest;
+use vfnode::vrf_engine::VrfEngine;
+
+fn requests(n: usize) -> Vec<CoinflipRequest> {
+    (0..n)
+        .map(|i| CoinflipRequest {
+            user_seed: format!("bench_{}", i),
+            timestamp: 1234567890,
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn single_vs_batch(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let engine = Arc::new(VrfEngine::from_seed([7u8; 32]));
+    let mut group = c.benchmark_group("coinflip_spawn_blocking");
+
+    for size in [1usize, 16, 128, 1024] {
+        let reqs = requests(size);
+        group.throughput(Throughput::Elements(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("single", size), &reqs, |b, reqs| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    for req in reqs {
+                        let engine = engine.clone();
+                        let req = req.clone();
+                        tokio::task::spawn_blocking(move || engine.process_coinflip(&req)).await.unwrap().unwrap();
+                    }
+                })
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("batch", size), &reqs, |b, reqs| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    let engine = engine.clone();
+                    let reqs = reqs.clone();
+                    let results = tokio::task::spawn_blocking(move || engine.process_coinflip_batch_blocking(&reqs))
+                        .await
+                        .unwrap();
+                    assert!(results.iter().all(Result::is_ok));
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, single_vs_batch);
+criterion_main!(benches);
@@ -17,7 +17,20 @@ CREATE TABLE IF NOT EXISTS pending_bets (
     settled_at TEXT NULL,
     failed_at TEXT NULL,
     error_message TEXT NULL,
-    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    batch_id TEXT NULL, -- settlement_batches.batch_id once settled
+    token_mint TEXT NULL,
+    wager_lamports INTEGER NULL, -- NULL for bets recorded before wagers were persisted
+    payout_lamports INTEGER NULL,
+    proof_status TEXT NULL, -- 'full', 'legacy' (signature only), 'unreconstructable'; NULL until backfilled
+    player_pubkey TEXT NULL, -- NULL for anonymous bets and bets recorded before players were persisted
+    client_seed TEXT NULL, -- Only set when the player supplied one; part of the VRF transcript
+    player_choice TEXT NULL, -- 'heads' or 'tails'; NULL for bets recorded before players picked a side (heads)
+    win_probability_bps INTEGER NULL, -- Player's win chance in basis points; NULL for a fair 50/50 flip
+    outcome_bit TEXT NULL, -- 'msb' or 'parity' when a fair flip didn't use the original lsb rule
+    proof_json TEXT NULL, -- Full VrfProof as JSON; NULL for legacy rows that only stored the signature
+    seed_encoding TEXT NULL, -- 'hex' or 'base64' when user_seed isn't committed as raw UTF-8; NULL for raw
+    game TEXT NULL -- Game the bet was played in; NULL for bets recorded before bets named their game (coinflip)
 );
 
 -- Table to store settlement batch results
@@ -27,12 +40,73 @@ CREATE TABLE IF NOT EXISTS settlement_batches (
     processing_time_ms INTEGER NOT NULL,
     tx_signature TEXT NOT NULL,
     success BOOLEAN NOT NULL,
+    created_at TEXT NOT NULL,
+    merkle_root TEXT NULL -- hex root over the batch's bets (see merkle.rs); NULL for older batches
+);
+
+-- Advisory lock so only one settlement loop runs per database
+CREATE TABLE IF NOT EXISTS settlement_lock (
+    id INTEGER PRIMARY KEY CHECK (id = 1), -- single row
+    owner TEXT NOT NULL,                   -- engine instance id
+    heartbeat_at INTEGER NOT NULL          -- unix millis; stale after the lock TTL
+);
+
+-- Bets that exhausted their settlement retries, moved out of pending_bets with the
+-- last error and everything needed to inspect or reprocess them
+CREATE TABLE IF NOT EXISTS dead_letter_bets (
+    bet_id TEXT PRIMARY KEY,
+    user_seed TEXT NOT NULL,
+    client_seed TEXT NULL,
+    timestamp INTEGER NOT NULL,
+    node_id TEXT NOT NULL,
+    heads BOOLEAN NOT NULL,
+    vrf_proof TEXT NOT NULL,
+    proof_json TEXT NULL,
+    proof_status TEXT NULL,
+    processing_time_ms INTEGER NOT NULL,
+    processed_at TEXT NOT NULL,
+    token_mint TEXT NULL,
+    wager_lamports INTEGER NULL,
+    payout_lamports INTEGER NULL,
+    player_pubkey TEXT NULL,
+    player_choice TEXT NULL,
+    win_probability_bps INTEGER NULL,
+    outcome_bit TEXT NULL,
+    retry_count INTEGER NOT NULL,
+    error_message TEXT NOT NULL, -- Error from the final settlement attempt
+    failed_at TEXT NOT NULL,
+    seed_encoding TEXT NULL,
+    game TEXT NULL
+);
+
+-- Every served outcome, written when AUDIT_TRAIL is on
+CREATE TABLE IF NOT EXISTS bet_results (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    bet_id TEXT NULL, -- Absent for free-play flips
+    user_seed TEXT NOT NULL,
+    seed_encoding TEXT NULL,
+    client_seed TEXT NULL,
+    timestamp INTEGER NOT NULL,
+    node_id TEXT NOT NULL,
+    player_pubkey TEXT NULL,
+    heads BOOLEAN NOT NULL,
+    vrf_proof TEXT NOT NULL,
+    proof_json TEXT NULL,
+    processing_time_ms INTEGER NOT NULL,
     created_at TEXT NOT NULL
 );
 
 -- Indexes for efficient querying
 CREATE INDEX IF NOT EXISTS idx_pending_bets_status ON pending_bets(status);
 CREATE INDEX IF NOT EXISTS idx_pending_bets_processed_at ON pending_bets(processed_at);
+CREATE INDEX IF NOT EXISTS idx_pending_bets_status_processed_at ON pending_bets(status, processed_at);
 CREATE INDEX IF NOT EXISTS idx_pending_bets_retry_count ON pending_bets(retry_count);
+CREATE INDEX IF NOT EXISTS idx_pending_bets_batch_id ON pending_bets(batch_id);
+CREATE INDEX IF NOT EXISTS idx_pending_bets_settled_at ON pending_bets(settled_at);
+CREATE INDEX IF NOT EXISTS idx_pending_bets_player ON pending_bets(player_pubkey, settled_at);
 CREATE INDEX IF NOT EXISTS idx_settlement_batches_created_at ON settlement_batches(created_at);
-CREATE INDEX IF NOT EXISTS idx_settlement_batches_success ON settlement_batches(success);
\ No newline at end of file
+CREATE INDEX IF NOT EXISTS idx_settlement_batches_success ON settlement_batches(success);
+CREATE INDEX IF NOT EXISTS idx_dead_letter_bets_failed_at ON dead_letter_bets(failed_at);
+CREATE INDEX IF NOT EXISTS idx_bet_results_created_at ON bet_results(created_at);
+CREATE INDEX IF NOT EXISTS idx_bet_results_bet_id ON bet_results(bet_id);
+CREATE INDEX IF NOT EXISTS idx_bet_results_player ON bet_results(player_pubkey, created_at);
\ No newline at end of file
@@ -0,0 +1,80 @@
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use subtle::ConstantTimeEq;
+
+/// Header carrying the admin API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Shared secret guarding the settlement admin endpoints (`ADMIN_API_KEY`). Only its
+/// digest is kept, so comparing against it takes the same time whatever the caller sends.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AdminKey([u8; 32]);
+
+impl AdminKey {
+    pub fn new(key: &str) -> Self {
+        Self(Sha256::digest(key.as_bytes()).into())
+    }
+
+    pub fn matches(&self, presented: &str) -> bool {
+        let presented: [u8; 32] = Sha256::digest(presented.as_bytes()).into();
+        self.0.ct_eq(&presented).into()
+    }
+}
+
+impl fmt::Debug for AdminKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AdminKey(..)")
+    }
+}
+
+/// Middleware rejecting requests whose `X-Api-Key` doesn't match. With no key configured
+/// every request is rejected, so the endpoints are never open by accident.
+pub async fn require_admin_key(State(key): State<Option<AdminKey>>, request: Request, next: Next) -> Response {
+    let presented = request.headers().get(API_KEY_HEADER).and_then(|value| value.to_str().ok());
+    match (key, presented) {
+        (Some(key), Some(presented)) if key.matches(presented) => next.run(request).await,
+        _ => (StatusCode::UNAUTHORIZED, "Missing or invalid X-Api-Key").into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::{middleware, Router};
+    use tower::ServiceExt;
+
+    async fn status(key: Option<AdminKey>, presented: Option<&str>) -> StatusCode {
+        let app = Router::new()
+            .route("/settlement/stats", get(|| async { "stats" }))
+            .route_layer(middleware::from_fn_with_state(key, require_admin_key));
+        let mut request = Request::get("/settlement/stats");
+        if let Some(presented) = presented {
+            request = request.header("X-Api-Key", presented);
+        }
+        app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_admin_key_is_required() {
+        let key = Some(AdminKey::new("s3cret"));
+        assert_eq!(status(key.clone(), Some("s3cret")).await, StatusCode::OK);
+        assert_eq!(status(key.clone(), Some("s3cret ")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(key.clone(), Some("")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(key, None).await, StatusCode::UNAUTHORIZED);
+
+        // No key configured: locked, not open
+        assert_eq!(status(None, Some("")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(None, None).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_admin_key_debug_is_redacted() {
+        assert_eq!(format!("{:?}", AdminKey::new("s3cret")), "AdminKey(..)");
+    }
+}
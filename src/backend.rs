@@ -0,0 +1,545 @@
+use crate::games::DEFAULT_GAME;
+use crate::settlement_engine::{BatchResult, PendingBet, RequeueOutcome};
+use crate::storage::Storage;
+use crate::types::{BatchRecord, BetStatus, CoinSide, OutboxEntry, OutcomeBit, ProofStatus, SeedEncoding, VfError, VrfProof};
+use async_trait::async_trait;
+use sqlx::SqlitePool;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Whether `DATABASE_URL` selects the PostgreSQL backend rather than SQLite
+pub fn is_postgres_url(database_url: &str) -> bool {
+    database_url.starts_with("postgres://") || database_url.starts_with("postgresql://")
+}
+
+/// Database behind the settlement pipeline, picked by the `DATABASE_URL` scheme.
+///
+/// Covers the `pending_bets`, `settlement_batches`, `settlement_outbox`, `dead_letter_bets`
+/// and `settlement_lock` operations the settlement engine runs, plus the bet status, batch
+/// list and summary reads. Audit features (proof exports, receipts, inclusion proofs,
+/// ledgers, realized edge, replay, the audit trail) query SQLite directly through
+/// [`Storage`] and have no PostgreSQL implementation.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// `sqlite` or `postgres`, for logs
+    fn name(&self) -> &'static str;
+
+    /// Connections the pool opens at most; concurrent write transactions are capped to it
+    fn max_connections(&self) -> u32;
+
+    /// Store freshly flipped bets in one transaction. A bet whose id is already stored is
+    /// skipped, and the number skipped is returned.
+    async fn insert_pending_bets(&self, bets: &[PendingBet]) -> Result<u64, VfError>;
+
+    /// Up to `limit` unsettled bets, oldest first
+    async fn pending_bets(&self, limit: i64) -> Result<Vec<PendingBet>, VfError>;
+
+    /// Proofs of the bets each player stored last, for the `players` players who bet most
+    /// recently: the rounds a restarted node's seed chains resume from
+    async fn latest_player_proofs(&self, players: i64) -> Result<Vec<(String, VrfProof)>, VfError>;
+
+    /// Unsettled bets that have already failed an attempt, oldest first
+    async fn retrying_bets(&self) -> Result<Vec<PendingBet>, VfError>;
+
+    /// Mark bets `'settling'` under `batch_id` in one transaction, before the batch is sent
+    /// for settlement. Only bets still pending are claimed; the claimed ids are returned.
+    async fn claim_batch(&self, batch_id: &Uuid, bets: &[PendingBet]) -> Result<Vec<Uuid>, VfError>;
+
+    /// Return the claimed bets of a failed batch to `'pending'`, storing their retry counts
+    async fn release_batch(&self, bets: &[PendingBet]) -> Result<(), VfError>;
+
+    /// Bets claimed by a batch that never recorded an outcome, with that batch's id, oldest first
+    async fn settling_bets(&self) -> Result<Vec<(Uuid, PendingBet)>, VfError>;
+
+    /// Mark a batch's bets settled and record the batch with its Merkle root, atomically
+    async fn record_settled_batch(&self, batch: &[PendingBet], result: &BatchResult, merkle_root: &str) -> Result<(), VfError>;
+
+    /// Record a batch's settlement transaction before it is submitted, keeping any entry
+    /// already recorded for the batch, and return the stored entry
+    async fn insert_outbox_tx(&self, batch_id: &Uuid, bet_count: u64, payout_lamports: u64, merkle_root: &str) -> Result<OutboxEntry, VfError>;
+
+    /// Mark a batch's outbox transaction submitted under `tx_signature`, awaiting
+    /// confirmation, unless it already confirmed
+    async fn submit_outbox_tx(&self, batch_id: &Uuid, tx_signature: &str) -> Result<bool, VfError>;
+
+    /// Outbox transactions submitted and not yet confirmed or failed, oldest first
+    async fn submitted_outbox_txs(&self) -> Result<Vec<OutboxEntry>, VfError>;
+
+    /// Mark a batch's outbox transaction confirmed under `tx_signature`
+    async fn confirm_outbox_tx(&self, batch_id: &Uuid, tx_signature: &str) -> Result<bool, VfError>;
+
+    /// Mark a batch's outbox transaction failed, unless it already confirmed
+    async fn fail_outbox_tx(&self, batch_id: &Uuid, error: &str) -> Result<bool, VfError>;
+
+    /// Move a bet into `dead_letter_bets` with its final error. False if it wasn't stored.
+    async fn dead_letter_bet(&self, bet: &PendingBet, error: &str) -> Result<bool, VfError>;
+
+    /// Move a dead-lettered bet back into `pending_bets` with its retries reset
+    async fn requeue_dead_letter(&self, bet_id: &Uuid) -> Result<RequeueOutcome, VfError>;
+
+    /// Delete bets settled, or dead-lettered, before `cutoff`; returns how many went
+    async fn purge_settled_before(&self, cutoff: time::OffsetDateTime) -> Result<u64, VfError>;
+
+    /// Settlement status of one bet, `None` if it was never stored
+    async fn bet_status(&self, bet_id: &Uuid) -> Result<Option<BetStatus>, VfError>;
+
+    /// One page of recorded settlement batches, newest first
+    async fn list_batches(&self, limit: i64, offset: i64) -> Result<Vec<BatchRecord>, VfError>;
+
+    /// Bet and batch counts and averages, as served by `/settlement/summary`
+    async fn settlement_summary(&self) -> Result<serde_json::Value, VfError>;
+
+    /// Current holder of the settlement lock
+    async fn lock_owner(&self) -> Result<Option<String>, VfError>;
+
+    /// Take the settlement lock for `owner`, or refresh its heartbeat, unless another owner
+    /// heartbeated at or after `stale_before_ms`. True if `owner` now holds it.
+    async fn upsert_lock(&self, owner: &str, now_ms: i64, stale_before_ms: i64) -> Result<bool, VfError>;
+
+    /// Drop the settlement lock if `owner` holds it
+    async fn release_lock(&self, owner: &str) -> Result<(), VfError>;
+}
+
+/// The default backend: the node's SQLite database, with compile-time checked queries
+pub struct SqliteBackend {
+    pool: SqlitePool,
+}
+
+impl SqliteBackend {
+    pub fn new(pool: &SqlitePool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    fn storage(&self) -> Storage {
+        Storage::from_pool(&self.pool)
+    }
+}
+
+/// The `pending_bets` columns a [`PendingBet`] is read back from
+struct PendingBetRow {
+    bet_id: Option<String>,
+    batch_id: Option<String>,
+    game: Option<String>,
+    user_seed: String,
+    seed_encoding: Option<String>,
+    client_seed: Option<String>,
+    timestamp: i64,
+    node_id: String,
+    heads: bool,
+    vrf_proof: String,
+    processing_time_ms: i64,
+    processed_at: String,
+    retry_count: Option<i64>,
+    token_mint: Option<String>,
+    wager_lamports: Option<i64>,
+    payout_lamports: Option<i64>,
+    player_pubkey: Option<String>,
+    player_choice: Option<String>,
+    win_probability_bps: Option<i64>,
+    outcome_bit: Option<String>,
+    proof_json: Option<String>,
+}
+
+impl PendingBetRow {
+    fn into_bet(self) -> Result<PendingBet, VfError> {
+        Ok(PendingBet {
+            bet_id: Uuid::parse_str(self.bet_id.as_deref().unwrap_or_default())?,
+            game: self.game.unwrap_or_else(|| DEFAULT_GAME.to_string()),
+            user_seed: self.user_seed,
+            seed_encoding: self.seed_encoding.as_deref().and_then(SeedEncoding::parse).unwrap_or_default(),
+            client_seed: self.client_seed,
+            timestamp: self.timestamp as u64,
+            node_id: self.node_id,
+            heads: self.heads,
+            vrf_proof: self.vrf_proof,
+            processing_time_ms: self.processing_time_ms as u64,
+            processed_at: time::OffsetDateTime::parse(&self.processed_at, &time::format_description::well_known::Rfc3339)?,
+            retry_count: self.retry_count.unwrap_or(0) as u32,
+            token_mint: self.token_mint,
+            wager_lamports: self.wager_lamports.map(|w| w as u64),
+            payout_lamports: self.payout_lamports.map(|p| p as u64),
+            player_pubkey: self.player_pubkey,
+            player_choice: self.player_choice.as_deref().and_then(CoinSide::parse).unwrap_or_default(),
+            win_probability_bps: self.win_probability_bps.map(|bps| bps as u16),
+            outcome_bit: self.outcome_bit.as_deref().and_then(OutcomeBit::parse),
+            proof: PendingBet::stored_proof(self.proof_json.as_deref()),
+        })
+    }
+}
+
+#[async_trait]
+impl StorageBackend for SqliteBackend {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn max_connections(&self) -> u32 {
+        self.pool.options().get_max_connections()
+    }
+
+    async fn insert_pending_bets(&self, bets: &[PendingBet]) -> Result<u64, VfError> {
+        let mut tx = self.pool.begin().await?;
+        let mut duplicates = 0u64;
+
+        for bet in bets {
+            let bet_id = bet.bet_id.to_string();
+            let timestamp = bet.timestamp as i64;
+            let processing_time_ms = bet.processing_time_ms as i64;
+            let processed_at = bet.processed_at.format(&time::format_description::well_known::Rfc3339).unwrap();
+            let retry_count = bet.retry_count as i32;
+            let wager_lamports = bet.wager_lamports.map(|w| w as i64);
+            let payout_lamports = bet.payout_lamports.map(|p| p as i64);
+            let player_choice = bet.player_choice.as_str();
+            let seed_encoding = bet.seed_encoding.as_str();
+            let win_probability_bps = bet.win_probability_bps.map(i64::from);
+            let outcome_bit = bet.outcome_bit.map(|bit| bit.as_str());
+            // A bet carrying its full proof is stored whole; one without is signature-only
+            let proof_json = bet.proof.as_ref()
+                .map(serde_json::to_string)
+                .transpose()
+                .map_err(|e| VfError::InvalidInput(format!("Cannot encode proof: {}", e)))?;
+            let proof_status = if proof_json.is_some() { ProofStatus::Full } else { ProofStatus::Legacy }.as_str();
+
+            let inserted = sqlx::query!(
+                r#"
+                INSERT INTO pending_bets (
+                    bet_id, user_seed, timestamp, node_id, heads, 
+                    vrf_proof, processing_time_ms, processed_at, retry_count, status,
+                    token_mint, wager_lamports, payout_lamports, proof_status, player_pubkey, client_seed,
+                    player_choice, win_probability_bps, outcome_bit, proof_json, seed_encoding, game
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, 'pending', ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(bet_id) DO NOTHING
+                "#,
+                bet_id,
+                bet.user_seed,
+                timestamp,
+                bet.node_id,
+                bet.heads,
+                bet.vrf_proof,
+                processing_time_ms,
+                processed_at,
+                retry_count,
+                bet.token_mint,
+                wager_lamports,
+                payout_lamports,
+                proof_status,
+                bet.player_pubkey,
+                bet.client_seed,
+                player_choice,
+                win_probability_bps,
+                outcome_bit,
+                proof_json,
+                seed_encoding,
+                bet.game
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            // A bet already stored under this id is a resubmission; the original row stands
+            if inserted.rows_affected() == 0 {
+                duplicates += 1;
+                warn!(bet_id = %bet.bet_id, "Duplicate bet id, keeping the stored bet");
+            }
+        }
+
+        tx.commit().await?;
+        Ok(duplicates)
+    }
+
+    async fn pending_bets(&self, limit: i64) -> Result<Vec<PendingBet>, VfError> {
+        let rows = sqlx::query_as!(
+            PendingBetRow,
+            "SELECT bet_id, batch_id, game, user_seed, seed_encoding, client_seed, timestamp, node_id, heads, vrf_proof,
+                    processing_time_ms, processed_at, retry_count, token_mint, wager_lamports, payout_lamports,
+                    player_pubkey, player_choice, win_probability_bps, outcome_bit, proof_json
+             FROM pending_bets WHERE status = 'pending' ORDER BY processed_at ASC LIMIT ?",
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(PendingBetRow::into_bet).collect()
+    }
+
+    async fn latest_player_proofs(&self, players: i64) -> Result<Vec<(String, VrfProof)>, VfError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT p.player_pubkey as "player_pubkey!", p.proof_json as "proof_json!"
+            FROM pending_bets p
+            JOIN (
+                SELECT player_pubkey, MAX(created_at) as latest FROM pending_bets
+                WHERE player_pubkey IS NOT NULL AND proof_json IS NOT NULL
+                GROUP BY player_pubkey ORDER BY latest DESC LIMIT ?
+            ) l ON p.player_pubkey = l.player_pubkey AND p.created_at = l.latest
+            WHERE p.proof_json IS NOT NULL
+            "#,
+            players
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| Some((row.player_pubkey, PendingBet::stored_proof(Some(&row.proof_json))?)))
+            .collect())
+    }
+
+    async fn retrying_bets(&self) -> Result<Vec<PendingBet>, VfError> {
+        let rows = sqlx::query_as!(
+            PendingBetRow,
+            "SELECT bet_id, batch_id, game, user_seed, seed_encoding, client_seed, timestamp, node_id, heads, vrf_proof,
+                    processing_time_ms, processed_at, retry_count, token_mint, wager_lamports, payout_lamports,
+                    player_pubkey, player_choice, win_probability_bps, outcome_bit, proof_json
+             FROM pending_bets WHERE status = 'pending' AND retry_count > 0 ORDER BY processed_at ASC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(PendingBetRow::into_bet).collect()
+    }
+
+    async fn claim_batch(&self, batch_id: &Uuid, bets: &[PendingBet]) -> Result<Vec<Uuid>, VfError> {
+        let mut tx = self.pool.begin().await?;
+        let batch_id = batch_id.to_string();
+        let mut claimed = Vec::with_capacity(bets.len());
+
+        for bet in bets {
+            let bet_id = bet.bet_id.to_string();
+            let updated = sqlx::query!(
+                "UPDATE pending_bets SET status = 'settling', batch_id = ? WHERE bet_id = ? AND status = 'pending'",
+                batch_id,
+                bet_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            if updated.rows_affected() > 0 {
+                claimed.push(bet.bet_id);
+            }
+        }
+
+        tx.commit().await?;
+        Ok(claimed)
+    }
+
+    async fn release_batch(&self, bets: &[PendingBet]) -> Result<(), VfError> {
+        let mut tx = self.pool.begin().await?;
+
+        for bet in bets {
+            let bet_id = bet.bet_id.to_string();
+            let retry_count = bet.retry_count as i32;
+            sqlx::query!(
+                "UPDATE pending_bets SET status = 'pending', batch_id = NULL, retry_count = ? WHERE bet_id = ? AND status = 'settling'",
+                retry_count,
+                bet_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn settling_bets(&self) -> Result<Vec<(Uuid, PendingBet)>, VfError> {
+        let rows = sqlx::query_as!(
+            PendingBetRow,
+            "SELECT bet_id, batch_id, game, user_seed, seed_encoding, client_seed, timestamp, node_id, heads, vrf_proof,
+                    processing_time_ms, processed_at, retry_count, token_mint, wager_lamports, payout_lamports,
+                    player_pubkey, player_choice, win_probability_bps, outcome_bit, proof_json
+             FROM pending_bets WHERE status = 'settling' ORDER BY processed_at ASC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let batch_id = Uuid::parse_str(row.batch_id.as_deref().unwrap_or_default())?;
+                Ok((batch_id, row.into_bet()?))
+            })
+            .collect()
+    }
+
+    async fn record_settled_batch(&self, batch: &[PendingBet], result: &BatchResult, merkle_root: &str) -> Result<(), VfError> {
+        let mut tx = self.pool.begin().await?;
+
+        let settled_at = result.timestamp.format(&time::format_description::well_known::Rfc3339).unwrap();
+        let batch_id = result.batch_id.to_string();
+
+        // Update bet statuses
+        for bet in batch {
+            let bet_id = bet.bet_id.to_string();
+            sqlx::query!(
+                "UPDATE pending_bets SET status = 'settled', tx_signature = ?, settled_at = ?, batch_id = ? WHERE bet_id = ?",
+                result.mock_tx_signature,
+                settled_at,
+                batch_id,
+                bet_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let bet_count = result.processed_count as i32;
+        let processing_time_ms = result.processing_time_ms as i64;
+        sqlx::query!(
+            r#"
+            INSERT INTO settlement_batches (
+                batch_id, bet_count, processing_time_ms, 
+                tx_signature, success, created_at, merkle_root
+            ) VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            batch_id,
+            bet_count,
+            processing_time_ms,
+            result.mock_tx_signature,
+            result.success,
+            settled_at,
+            merkle_root
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn dead_letter_bet(&self, bet: &PendingBet, error: &str) -> Result<bool, VfError> {
+        let failed_at = time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap();
+        let bet_id = bet.bet_id.to_string();
+        let retry_count = bet.retry_count as i64;
+
+        let mut tx = self.pool.begin().await?;
+        let moved = sqlx::query!(
+            r#"
+            INSERT OR REPLACE INTO dead_letter_bets (
+                bet_id, user_seed, client_seed, timestamp, node_id, heads, vrf_proof, proof_json, proof_status,
+                processing_time_ms, processed_at, token_mint, wager_lamports, payout_lamports, player_pubkey,
+                player_choice, win_probability_bps, outcome_bit, seed_encoding, game, retry_count, error_message, failed_at
+            )
+            SELECT bet_id, user_seed, client_seed, timestamp, node_id, heads, vrf_proof, proof_json, proof_status,
+                   processing_time_ms, processed_at, token_mint, wager_lamports, payout_lamports, player_pubkey,
+                   player_choice, win_probability_bps, outcome_bit, seed_encoding, game, ?, ?, ?
+            FROM pending_bets
+            WHERE bet_id = ?
+            "#,
+            retry_count,
+            error,
+            failed_at,
+            bet_id
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        sqlx::query!("DELETE FROM pending_bets WHERE bet_id = ?", bet_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(moved > 0)
+    }
+
+    async fn requeue_dead_letter(&self, bet_id: &Uuid) -> Result<RequeueOutcome, VfError> {
+        let id = bet_id.to_string();
+
+        let mut tx = self.pool.begin().await?;
+        let pending = sqlx::query_scalar!("SELECT bet_id FROM pending_bets WHERE bet_id = ?", id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if pending.is_some() {
+            return Ok(RequeueOutcome::AlreadyPending);
+        }
+
+        let moved = sqlx::query!(
+            r#"
+            INSERT INTO pending_bets (
+                bet_id, user_seed, client_seed, timestamp, node_id, heads, vrf_proof, proof_json, proof_status,
+                processing_time_ms, processed_at, token_mint, wager_lamports, payout_lamports, player_pubkey,
+                player_choice, win_probability_bps, outcome_bit, seed_encoding, game, retry_count, status
+            )
+            SELECT bet_id, user_seed, client_seed, timestamp, node_id, heads, vrf_proof, proof_json, proof_status,
+                   processing_time_ms, processed_at, token_mint, wager_lamports, payout_lamports, player_pubkey,
+                   player_choice, win_probability_bps, outcome_bit, seed_encoding, game, 0, 'pending'
+            FROM dead_letter_bets
+            WHERE bet_id = ?
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if moved == 0 {
+            return Ok(RequeueOutcome::NotDeadLettered);
+        }
+        sqlx::query!("DELETE FROM dead_letter_bets WHERE bet_id = ?", id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(RequeueOutcome::Requeued)
+    }
+
+    async fn insert_outbox_tx(&self, batch_id: &Uuid, bet_count: u64, payout_lamports: u64, merkle_root: &str) -> Result<OutboxEntry, VfError> {
+        self.storage().insert_outbox_tx(batch_id, bet_count, payout_lamports, merkle_root).await
+    }
+
+    async fn submit_outbox_tx(&self, batch_id: &Uuid, tx_signature: &str) -> Result<bool, VfError> {
+        self.storage().submit_outbox_tx(batch_id, tx_signature).await
+    }
+
+    async fn submitted_outbox_txs(&self) -> Result<Vec<OutboxEntry>, VfError> {
+        self.storage().submitted_outbox_txs().await
+    }
+
+    async fn confirm_outbox_tx(&self, batch_id: &Uuid, tx_signature: &str) -> Result<bool, VfError> {
+        self.storage().confirm_outbox_tx(batch_id, tx_signature).await
+    }
+
+    async fn fail_outbox_tx(&self, batch_id: &Uuid, error: &str) -> Result<bool, VfError> {
+        self.storage().fail_outbox_tx(batch_id, error).await
+    }
+
+    async fn purge_settled_before(&self, cutoff: time::OffsetDateTime) -> Result<u64, VfError> {
+        self.storage().purge_settled_before(cutoff).await
+    }
+
+    async fn bet_status(&self, bet_id: &Uuid) -> Result<Option<BetStatus>, VfError> {
+        self.storage().get_bet_by_id(bet_id).await
+    }
+
+    async fn list_batches(&self, limit: i64, offset: i64) -> Result<Vec<BatchRecord>, VfError> {
+        self.storage().list_batches(limit, offset).await
+    }
+
+    async fn settlement_summary(&self) -> Result<serde_json::Value, VfError> {
+        self.storage().get_settlement_summary().await
+    }
+
+    async fn lock_owner(&self) -> Result<Option<String>, VfError> {
+        let row = sqlx::query!("SELECT owner FROM settlement_lock WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| row.owner))
+    }
+
+    async fn upsert_lock(&self, owner: &str, now_ms: i64, stale_before_ms: i64) -> Result<bool, VfError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO settlement_lock (id, owner, heartbeat_at) VALUES (1, ?1, ?2)
+            ON CONFLICT(id) DO UPDATE SET owner = excluded.owner, heartbeat_at = excluded.heartbeat_at
+            WHERE settlement_lock.owner = excluded.owner OR settlement_lock.heartbeat_at < ?3
+            "#,
+            owner,
+            now_ms,
+            stale_before_ms
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn release_lock(&self, owner: &str) -> Result<(), VfError> {
+        sqlx::query!("DELETE FROM settlement_lock WHERE id = 1 AND owner = ?", owner)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
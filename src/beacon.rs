@@ -0,0 +1,25 @@
+use crate::types::VfError;
+use std::time::Duration;
+
+/// How long a beacon may go without a new round before flips fail (`BEACON_MAX_AGE_SECS`);
+/// three periods of drand's default chain
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(90);
+
+/// One published round of an external randomness beacon
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BeaconRound {
+    pub round: u64,
+    pub randomness: Vec<u8>,
+}
+
+/// Public randomness mixed into every flip (e.g. a drand relay).
+///
+/// Mixing in a value the node cannot know ahead of time means a leaked node key alone is
+/// not enough to predict outcomes. The round and value are revealed in the proof, so anyone
+/// can check them against the beacon's public history.
+///
+/// `latest` is called on the flip hot path from blocking threads, so implementations should
+/// serve a cached round that a background task keeps fresh rather than doing network I/O.
+pub trait EntropyBeacon: Send + Sync {
+    fn latest(&self) -> Result<BeaconRound, VfError>;
+}
@@ -0,0 +1,94 @@
+use crate::types::VfError;
+use sha2::{Digest, Sha256};
+
+/// Per-request cap on loop iterations for games with data-dependent work.
+///
+/// Rejection sampling re-draws until a value lands outside the biased tail, so a crafted
+/// range could otherwise keep a worker spinning. Every re-draw spends one unit; running out
+/// aborts the request with [`VfError::ComputeBudgetExceeded`]. A coinflip never spends any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeBudget {
+    limit: u32,
+    spent: u32,
+}
+
+impl ComputeBudget {
+    /// Iterations per request when no budget is configured
+    pub const DEFAULT_LIMIT: u32 = 1024;
+
+    pub fn new(limit: u32) -> Self {
+        Self { limit, spent: 0 }
+    }
+
+    pub fn spent(&self) -> u32 {
+        self.spent
+    }
+
+    /// Spend one iteration, or fail once the budget is used up
+    pub fn spend(&mut self) -> Result<(), VfError> {
+        if self.spent >= self.limit {
+            return Err(VfError::ComputeBudgetExceeded(format!(
+                "request needed more than {} iterations",
+                self.limit
+            )));
+        }
+        self.spent += 1;
+        Ok(())
+    }
+}
+
+/// Uniform value in `[0, max)` drawn from `entropy` without modulo bias.
+///
+/// Draw `n` is the first 8 bytes of `SHA-256(entropy || n)` as little-endian u64. Draws in
+/// the tail that would over-represent small values are rejected and the counter advances.
+pub fn sample_below(entropy: &[u8], max: u64, budget: &mut ComputeBudget) -> Result<u64, VfError> {
+    if max == 0 {
+        return Err(VfError::InvalidInput("range must be non-empty".to_string()));
+    }
+    // Largest multiple of max that fits in 2^64 draws; anything at or above it is biased
+    let accept_below = (1u128 << 64) / max as u128 * max as u128;
+
+    for counter in 0u64.. {
+        budget.spend()?;
+        let digest = Sha256::new()
+            .chain_update(entropy)
+            .chain_update(counter.to_le_bytes())
+            .finalize();
+        let mut block = [0u8; 8];
+        block.copy_from_slice(&digest[..8]);
+        let draw = u64::from_le_bytes(block);
+
+        if (draw as u128) < accept_below {
+            return Ok(draw % max);
+        }
+    }
+    unreachable!("counter space exhausted")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vrf_engine::{EngineConfig, VrfEngine};
+
+    #[test]
+    fn test_tight_budget_aborts_rejection_sampling() {
+        // Just over 2^63: nearly half of all draws land in the rejected tail
+        let max = (1u64 << 63) + 1;
+        let engine = VrfEngine::with_config(EngineConfig { compute_budget: Some(1), ..Default::default() });
+
+        let seeds: Vec<[u8; 32]> = (0..64u8).map(|i| Sha256::digest([i]).into()).collect();
+        let rejected = seeds
+            .iter()
+            .find(|seed| sample_below(&seed[..], max, &mut engine.compute_budget()).is_err())
+            .expect("some seed needs a second draw");
+
+        let mut budget = engine.compute_budget();
+        assert!(matches!(sample_below(rejected, max, &mut budget), Err(VfError::ComputeBudgetExceeded(_))));
+        assert_eq!(budget.spent(), 1);
+
+        // The same request completes under the default budget
+        let mut budget = VrfEngine::new().compute_budget();
+        assert!(sample_below(rejected, max, &mut budget).unwrap() < max);
+        assert!(budget.spent() > 1);
+    }
+}
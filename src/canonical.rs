@@ -0,0 +1,123 @@
+use merlin::Transcript;
+use std::borrow::Cow;
+
+/// A single value inside a canonical payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanonicalValue<'a> {
+    Bytes(Cow<'a, [u8]>),
+    U64(u64),
+}
+
+impl CanonicalValue<'_> {
+    const TAG_BYTES: u8 = 0x01;
+    const TAG_U64: u8 = 0x02;
+}
+
+/// Deterministic encoding for everything the node signs.
+///
+/// Fields are kept in insertion order (never sorted or hashed into a map), strings are
+/// raw UTF-8 bytes, and integers are fixed-width little-endian, matching how merlin frames
+/// `append_u64`. The same payload drives both the merlin transcript and `to_bytes`, so a
+/// verifier can diff its own reconstruction byte-for-byte against the node's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanonicalPayload<'a> {
+    domain: &'static [u8],
+    fields: Vec<(&'static [u8], CanonicalValue<'a>)>,
+}
+
+impl<'a> CanonicalPayload<'a> {
+    pub fn new(domain: &'static [u8]) -> Self {
+        Self { domain, fields: Vec::with_capacity(4) }
+    }
+
+    pub fn bytes(self, label: &'static [u8], value: &'a [u8]) -> Self {
+        self.decoded_bytes(label, Cow::Borrowed(value))
+    }
+
+    /// Like [`bytes`](Self::bytes), for a value that may have been decoded into a new buffer
+    pub fn decoded_bytes(mut self, label: &'static [u8], value: Cow<'a, [u8]>) -> Self {
+        self.fields.push((label, CanonicalValue::Bytes(value)));
+        self
+    }
+
+    pub fn u64(mut self, label: &'static [u8], value: u64) -> Self {
+        self.fields.push((label, CanonicalValue::U64(value)));
+        self
+    }
+
+    pub fn domain(&self) -> &'static [u8] {
+        self.domain
+    }
+
+    pub fn fields(&self) -> &[(&'static [u8], CanonicalValue<'a>)] {
+        &self.fields
+    }
+
+    /// Layout: `len(domain) || domain`, then per field
+    /// `len(label) || label || tag || len(value) || value`, all lengths `u32` LE.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64);
+        write_len_prefixed(&mut out, self.domain);
+        for (label, value) in &self.fields {
+            write_len_prefixed(&mut out, label);
+            match value {
+                CanonicalValue::Bytes(bytes) => {
+                    out.push(CanonicalValue::TAG_BYTES);
+                    write_len_prefixed(&mut out, bytes);
+                }
+                CanonicalValue::U64(n) => {
+                    out.push(CanonicalValue::TAG_U64);
+                    write_len_prefixed(&mut out, &n.to_le_bytes());
+                }
+            }
+        }
+        out
+    }
+
+    /// Feed the payload into a fresh merlin transcript in canonical field order.
+    pub fn to_transcript(&self) -> Transcript {
+        let mut transcript = Transcript::new(self.domain);
+        for (label, value) in &self.fields {
+            match value {
+                CanonicalValue::Bytes(bytes) => transcript.append_message(label, bytes),
+                CanonicalValue::U64(n) => transcript.append_u64(label, *n),
+            }
+        }
+        transcript
+    }
+}
+
+#[inline]
+fn write_len_prefixed(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_order_is_preserved() {
+        let a = CanonicalPayload::new(b"test").bytes(b"a", b"1").u64(b"b", 2);
+        let b = CanonicalPayload::new(b"test").u64(b"b", 2).bytes(b"a", b"1");
+        assert_ne!(a.to_bytes(), b.to_bytes());
+    }
+
+    #[test]
+    fn test_fixed_vector() {
+        let payload = CanonicalPayload::new(b"vf")
+            .bytes(b"s", b"ab")
+            .u64(b"t", 0x0102030405060708);
+
+        let expected: Vec<u8> = [
+            &[2, 0, 0, 0][..], b"vf",
+            &[1, 0, 0, 0], b"s", &[0x01], &[2, 0, 0, 0], b"ab",
+            &[1, 0, 0, 0], b"t", &[0x02], &[8, 0, 0, 0], &[8, 7, 6, 5, 4, 3, 2, 1],
+        ]
+        .concat();
+
+        assert_eq!(payload.to_bytes(), expected);
+        assert_eq!(payload.to_bytes(), payload.clone().to_bytes());
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Each player's latest output hash in seed-chaining mode, the `prev_output` their next
+/// round commits to.
+///
+/// Players are spread over shards, each behind its own lock, so a flip only waits on flips
+/// for players in the same shard. A flip holds its player's shard from reading the head to
+/// storing the new one, which keeps every chain linear. A head unused for `ttl` expires,
+/// and past `capacity` the least recently used heads are dropped first; either way that
+/// player's next round starts a new chain.
+pub struct ChainHeads {
+    shards: Box<[Mutex<HashMap<String, Head>>]>,
+    hasher: RandomState,
+    shard_capacity: usize,
+    ttl: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Head {
+    output: [u8; 32],
+    used: Instant,
+}
+
+/// A player's shard, locked while their next round is flipped
+pub struct ChainLink<'a> {
+    shard: MutexGuard<'a, HashMap<String, Head>>,
+    heads: &'a ChainHeads,
+    player: &'a str,
+    now: Instant,
+}
+
+impl ChainHeads {
+    pub const DEFAULT_CAPACITY: usize = 100_000;
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+    const SHARDS: usize = 64;
+
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            shards: (0..Self::SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            hasher: RandomState::new(),
+            shard_capacity: capacity.div_ceil(Self::SHARDS).max(1),
+            ttl,
+        }
+    }
+
+    fn shard(&self, player: &str) -> MutexGuard<'_, HashMap<String, Head>> {
+        let index = self.hasher.hash_one(player) as usize % self.shards.len();
+        self.shards[index].lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Lock the player's chain for their next round
+    pub fn link<'a>(&'a self, player: &'a str) -> ChainLink<'a> {
+        self.link_at(player, Instant::now())
+    }
+
+    pub fn link_at<'a>(&'a self, player: &'a str, now: Instant) -> ChainLink<'a> {
+        ChainLink { shard: self.shard(player), heads: self, player, now }
+    }
+
+    /// The player's current head, without holding their chain
+    pub fn head(&self, player: &str) -> Option<[u8; 32]> {
+        self.link(player).prev_output()
+    }
+
+    /// Seed a head recovered from storage, unless the player already has a newer one
+    pub fn restore(&self, player: &str, output: [u8; 32]) {
+        let mut link = self.link(player);
+        if link.prev_output().is_none() {
+            let now = link.now;
+            link.store(Head { output, used: now });
+        }
+    }
+
+    /// Heads currently held, expired ones included until they are evicted
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().unwrap_or_else(|e| e.into_inner()).len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ChainLink<'_> {
+    /// Output hash of the player's previous round, unless it expired
+    pub fn prev_output(&self) -> Option<[u8; 32]> {
+        self.shard
+            .get(self.player)
+            .filter(|head| self.now.saturating_duration_since(head.used) < self.heads.ttl)
+            .map(|head| head.output)
+    }
+
+    /// Record the round just flipped as the player's head and release the chain
+    pub fn advance(mut self, output: [u8; 32]) {
+        let now = self.now;
+        self.store(Head { output, used: now });
+    }
+
+    fn store(&mut self, head: Head) {
+        self.shard.insert(self.player.to_string(), head);
+        if self.shard.len() <= self.heads.shard_capacity {
+            return;
+        }
+        let (now, ttl) = (self.now, self.heads.ttl);
+        self.shard.retain(|_, head| now.saturating_duration_since(head.used) < ttl);
+        while self.shard.len() > self.heads.shard_capacity {
+            let Some(oldest) = self.shard.iter().min_by_key(|(_, head)| head.used).map(|(player, _)| player.clone()) else {
+                break;
+            };
+            self.shard.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heads_expire_and_least_recently_used_go_first() {
+        // One head per shard, so every player past the first in a shard evicts
+        let heads = ChainHeads::new(1, Duration::from_secs(60));
+        let start = Instant::now();
+
+        heads.link_at("alice", start).advance([1; 32]);
+        assert_eq!(heads.link_at("alice", start + Duration::from_secs(59)).prev_output(), Some([1; 32]));
+        assert_eq!(heads.link_at("alice", start + Duration::from_secs(60)).prev_output(), None);
+
+        // Players sharing alice's shard push out whoever was used least recently
+        let rivals: Vec<String> = (0..1000)
+            .map(|i| format!("player_{}", i))
+            .filter(|p| heads.hasher.hash_one(p.as_str()) % 64 == heads.hasher.hash_one("alice") % 64)
+            .take(1)
+            .collect();
+        heads.link_at(&rivals[0], start + Duration::from_secs(1)).advance([2; 32]);
+        assert_eq!(heads.link_at("alice", start + Duration::from_secs(2)).prev_output(), None);
+        assert_eq!(heads.link_at(&rivals[0], start + Duration::from_secs(2)).prev_output(), Some([2; 32]));
+        assert_eq!(heads.len(), 1);
+    }
+
+    #[test]
+    fn test_restore_keeps_a_newer_head() {
+        let heads = ChainHeads::new(ChainHeads::DEFAULT_CAPACITY, ChainHeads::DEFAULT_TTL);
+        heads.restore("alice", [1; 32]);
+        assert_eq!(heads.head("alice"), Some([1; 32]));
+
+        heads.link("alice").advance([2; 32]);
+        heads.restore("alice", [1; 32]);
+        assert_eq!(heads.head("alice"), Some([2; 32]));
+    }
+}
@@ -0,0 +1,128 @@
+use rand::{thread_rng, RngCore};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Node seeds committed to by `POST /commit` and not yet revealed.
+///
+/// The node draws a fresh seed and publishes only its hash, so it is bound to the seed before
+/// it sees the player's request. `POST /reveal` takes the seed back out exactly once; seeds
+/// not revealed within `ttl` are rejected. Past `capacity` the oldest commitments are dropped.
+pub struct CommitStore {
+    ttl: Duration,
+    capacity: usize,
+    state: Mutex<CommitState>,
+}
+
+#[derive(Default)]
+struct CommitState {
+    seeds: HashMap<Uuid, ([u8; 32], Instant)>,
+    order: VecDeque<Uuid>,
+}
+
+/// Why a commitment can't be revealed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum RevealError {
+    #[error("Unknown commit_id")]
+    Unknown,
+    #[error("Commitment expired")]
+    Expired,
+}
+
+impl CommitStore {
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(300);
+    pub const DEFAULT_CAPACITY: usize = 100_000;
+
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self { ttl, capacity: capacity.max(1), state: Mutex::new(CommitState::default()) }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Hex SHA-256 of a node seed, as published before the reveal
+    pub fn commitment(node_seed: &[u8; 32]) -> String {
+        hex::encode(Sha256::digest(node_seed))
+    }
+
+    /// Draw and remember a fresh node seed, returning its id and commitment
+    pub fn commit(&self) -> (Uuid, String) {
+        let mut node_seed = [0u8; 32];
+        thread_rng().fill_bytes(&mut node_seed);
+        let commit_id = Uuid::new_v4();
+        self.insert_at(commit_id, node_seed, Instant::now());
+        (commit_id, Self::commitment(&node_seed))
+    }
+
+    pub fn insert_at(&self, commit_id: Uuid, node_seed: [u8; 32], now: Instant) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Drop expired and overflowing commitments, oldest first
+        while let Some(&oldest) = state.order.front() {
+            let live = state
+                .seeds
+                .get(&oldest)
+                .is_some_and(|&(_, at)| now.saturating_duration_since(at) < self.ttl);
+            if live && state.order.len() < self.capacity {
+                break;
+            }
+            state.order.pop_front();
+            state.seeds.remove(&oldest);
+        }
+
+        state.seeds.insert(commit_id, (node_seed, now));
+        state.order.push_back(commit_id);
+    }
+
+    /// Take the node seed behind `commit_id`. Each commitment is revealed at most once.
+    pub fn reveal(&self, commit_id: &Uuid) -> Result<[u8; 32], RevealError> {
+        self.reveal_at(commit_id, Instant::now())
+    }
+
+    pub fn reveal_at(&self, commit_id: &Uuid, now: Instant) -> Result<[u8; 32], RevealError> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (node_seed, at) = state.seeds.remove(commit_id).ok_or(RevealError::Unknown)?;
+        state.order.retain(|id| id != commit_id);
+        if now.saturating_duration_since(at) >= self.ttl {
+            return Err(RevealError::Expired);
+        }
+        Ok(node_seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commitments_reveal_once_within_ttl() {
+        let store = CommitStore::new(Duration::from_secs(60), 10);
+        let (commit_id, commitment) = store.commit();
+        let node_seed = store.reveal(&commit_id).unwrap();
+        assert_eq!(CommitStore::commitment(&node_seed), commitment);
+        assert_eq!(store.reveal(&commit_id), Err(RevealError::Unknown));
+
+        let start = Instant::now();
+        let stale = Uuid::new_v4();
+        store.insert_at(stale, [1u8; 32], start);
+        assert_eq!(store.reveal_at(&stale, start + Duration::from_secs(60)), Err(RevealError::Expired));
+        assert_eq!(store.reveal_at(&stale, start), Err(RevealError::Unknown));
+    }
+
+    #[test]
+    fn test_oldest_commitments_are_dropped_past_capacity() {
+        let store = CommitStore::new(Duration::from_secs(60), 2);
+        let now = Instant::now();
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        for (i, id) in ids.iter().enumerate() {
+            store.insert_at(*id, [i as u8; 32], now);
+        }
+
+        assert_eq!(store.reveal_at(&ids[0], now), Err(RevealError::Unknown));
+        assert_eq!(store.reveal_at(&ids[1], now), Ok([1u8; 32]));
+        assert_eq!(store.reveal_at(&ids[2], now), Ok([2u8; 32]));
+    }
+}
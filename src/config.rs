@@ -0,0 +1,1084 @@
+use crate::auth::AdminKey;
+use crate::beacon;
+use crate::backend::is_postgres_url;
+use crate::chain::ChainHeads;
+use crate::commit::CommitStore;
+use crate::dedup::SeenBetIds;
+use crate::hooks::{CommandHook, OutcomeHooks};
+use crate::listen::{self, BindAddr};
+use crate::logging::LogFormat;
+use crate::settlement_engine::{ConfirmationLevel, SettlementConfig, SettlementMode};
+use crate::storage::{SqliteJournalMode, StorageOptions};
+use crate::throttle::ClientRateLimiter;
+use crate::tokens::TokenRegistry;
+use crate::types::{OutcomeBit, NATIVE_TOKEN_MINT};
+use crate::verifier::VerifierPool;
+use crate::vrf_engine::{
+    EngineConfig, VrfEngine, BPS_SCALE, CHALLENGE_LENS, DEFAULT_CHALLENGE_LEN, DEFAULT_PAYOUT_MULTIPLIER_BPS,
+    FAIR_WIN_PROBABILITY_BPS,
+};
+use axum::http::{header, HeaderValue, Method};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// Every problem found while validating startup configuration, reported together so an
+/// operator can fix them in one pass instead of one restart per mistake.
+#[derive(Debug, Default)]
+pub struct ConfigErrors(Vec<String>);
+
+impl ConfigErrors {
+    pub fn push(&mut self, problem: impl Into<String>) {
+        self.0.push(problem.into());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn problems(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Invalid configuration ({} problem(s)):", self.0.len())?;
+        for problem in &self.0 {
+            writeln!(f, "  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+/// Node settings read from the environment.
+#[derive(Debug, Clone)]
+pub struct NodeConfig {
+    pub port: u16,
+    /// Full listen address from `BIND_ADDR`, replacing `0.0.0.0:PORT`
+    pub listen_addr: Option<BindAddr>,
+    /// PEM certificate chain and private key; when both are set the node serves HTTPS
+    pub tls_cert_path: Option<PathBuf>,
+    pub tls_key_path: Option<PathBuf>,
+    pub database_url: String,
+    /// Database pool size, journal mode and lock wait
+    pub db_max_connections: u32,
+    pub db_journal_mode: SqliteJournalMode,
+    pub db_busy_timeout: Duration,
+    /// Hex-encoded 32-byte signing seed; a fresh key is generated when unset
+    pub node_key_file: Option<PathBuf>,
+    pub max_flips_per_second: Option<u32>,
+    /// Sustained requests per second allowed from one client IP (None = unlimited)
+    pub client_rate_limit_per_second: Option<u32>,
+    /// Requests one client IP may make back to back; defaults to the per-second rate
+    pub client_rate_limit_burst: Option<u32>,
+    /// Coinflips processed at once (None = unlimited)
+    pub max_concurrent_flips: Option<usize>,
+    /// Verification requests processed at once, independent of the flip limit
+    pub max_concurrent_verifications: usize,
+    pub latency_floor: Option<Duration>,
+    pub seed_chaining: bool,
+    /// Players whose chain heads are kept in seed-chaining mode
+    pub seed_chain_capacity: usize,
+    /// How long a player's chain may sit unused before it restarts
+    pub seed_chain_ttl: Duration,
+    /// Reject wagered bets without a valid wallet signature
+    pub require_wallet_sig: bool,
+    /// Put the full 32-byte VRF output hash in every proof
+    pub full_output_hash: bool,
+    /// Player's win chance in basis points; below 5000 gives the house an edge
+    pub win_probability_bps: u16,
+    /// Bits of the random value that decide a fair flip
+    pub outcome_bit: OutcomeBit,
+    /// Winning payout in basis points of the wager; 20000 pays double
+    pub payout_multiplier_bps: u32,
+    /// Bytes of transcript challenge each proof signs, 32 or 64
+    pub challenge_len: usize,
+    /// Window for rejecting exact-duplicate flips (None = off)
+    pub duplicate_window: Option<Duration>,
+    /// Bet ids remembered for replay protection (None = off)
+    pub seen_bet_ids: Option<usize>,
+    /// How long a `/commit` commitment may wait for its `/reveal`
+    pub commit_ttl: Duration,
+    /// drand relay whose rounds are mixed into every flip (None = no beacon)
+    pub beacon_url: Option<String>,
+    /// How long the beacon may go without a new round before flips fail
+    pub beacon_max_age: Duration,
+    /// Request timestamps further than this from the node clock are logged and counted
+    pub max_clock_drift: Option<Duration>,
+    /// Reject requests with a timestamp older than this (None = off)
+    pub max_request_age: Option<Duration>,
+    /// Iterations one request may spend in data-dependent game loops
+    pub max_compute_iterations: Option<u32>,
+    /// Accepted `user_seed` length in bytes, inclusive
+    pub min_seed_len: usize,
+    pub max_seed_len: usize,
+    /// Summed payout one settlement batch may carry (None = uncapped)
+    pub max_batch_payout_lamports: Option<u64>,
+    /// Derive settlement bet ids from bet inputs so resubmissions dedupe on the primary key
+    pub deterministic_bet_ids: bool,
+    /// Bets buffered between a flip and the settlement database before flips get `503`
+    pub settlement_channel_capacity: usize,
+    /// Bets waiting for settlement retry past which `/health/deep` returns `503`
+    pub retry_queue_health_limit: usize,
+    pub settlement_mode: SettlementMode,
+    /// Chance a mock settlement fails, between 0 and 1
+    pub mock_failure_rate: f64,
+    /// JSON-RPC endpoint `solana` settlement submits to
+    pub solana_rpc_url: Option<String>,
+    /// `solana-keygen` keypair paying for and signing `solana` settlement transactions
+    pub solana_keypair_file: Option<PathBuf>,
+    /// Commitment a `solana` settlement transaction must reach before its batch settles
+    pub solana_confirmation_level: ConfirmationLevel,
+    /// Accept `free_play` flips (outcome and proof, no settlement)
+    pub free_play: bool,
+    pub dev_mode: bool,
+    /// Log user seeds verbatim instead of hashed
+    pub log_raw_seeds: bool,
+    /// Human-readable or JSON log lines
+    pub log_format: LogFormat,
+    /// Flag signature-only proof rows at startup
+    pub backfill_legacy_proofs: bool,
+    /// Age past which settled and dead-lettered bets are purged (None = kept forever)
+    pub retention: Option<Duration>,
+    /// Migrate the database at startup; when off, a stale schema fails startup instead
+    pub auto_migrate: bool,
+    /// Record every served coinflip in `bet_results`
+    pub audit_trail: bool,
+    /// NDJSON file every outcome is appended to as an `OutcomeEventV1` (None = no feed)
+    pub outcome_feed_file: Option<PathBuf>,
+    /// Program every outcome is piped to as a post-outcome hook (None = no hook)
+    pub outcome_hook_command: Option<String>,
+    /// Browser origins allowed to call the API (None = any origin)
+    pub cors_allowed_origins: Option<Vec<String>>,
+    /// Key required in `X-Api-Key` on `/settlement/*` (None = those endpoints reject everyone)
+    pub admin_api_key: Option<AdminKey>,
+    /// Largest request body buffered before a request is rejected with `413`
+    pub max_body_bytes: usize,
+    pub tokens: TokenRegistry,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        Self {
+            port: 3001,
+            listen_addr: None,
+            tls_cert_path: None,
+            tls_key_path: None,
+            database_url: "sqlite:./vfnode.db".to_string(),
+            db_max_connections: StorageOptions::DEFAULT_MAX_CONNECTIONS,
+            db_journal_mode: SqliteJournalMode::Wal,
+            db_busy_timeout: StorageOptions::DEFAULT_BUSY_TIMEOUT,
+            node_key_file: None,
+            max_flips_per_second: None,
+            client_rate_limit_per_second: None,
+            client_rate_limit_burst: None,
+            max_concurrent_flips: None,
+            max_concurrent_verifications: VerifierPool::default_limit(),
+            latency_floor: None,
+            seed_chaining: false,
+            seed_chain_capacity: ChainHeads::DEFAULT_CAPACITY,
+            seed_chain_ttl: ChainHeads::DEFAULT_TTL,
+            require_wallet_sig: false,
+            full_output_hash: false,
+            win_probability_bps: FAIR_WIN_PROBABILITY_BPS,
+            outcome_bit: OutcomeBit::Lsb,
+            payout_multiplier_bps: DEFAULT_PAYOUT_MULTIPLIER_BPS,
+            challenge_len: DEFAULT_CHALLENGE_LEN,
+            duplicate_window: Some(Duration::from_secs(300)),
+            seen_bet_ids: Some(SeenBetIds::DEFAULT_CAPACITY),
+            commit_ttl: CommitStore::DEFAULT_TTL,
+            beacon_url: None,
+            beacon_max_age: beacon::DEFAULT_MAX_AGE,
+            max_clock_drift: Some(Duration::from_secs(60)),
+            max_request_age: Some(Duration::from_secs(60)),
+            max_compute_iterations: None,
+            min_seed_len: 1,
+            max_seed_len: 1024,
+            max_batch_payout_lamports: None,
+            deterministic_bet_ids: false,
+            settlement_channel_capacity: SettlementConfig::DEFAULT_CHANNEL_CAPACITY,
+            retry_queue_health_limit: SettlementConfig::DEFAULT_RETRY_QUEUE_HEALTH_LIMIT,
+            settlement_mode: SettlementMode::default(),
+            mock_failure_rate: SettlementConfig::DEFAULT_MOCK_FAILURE_RATE,
+            solana_rpc_url: None,
+            solana_keypair_file: None,
+            solana_confirmation_level: ConfirmationLevel::default(),
+            free_play: false,
+            dev_mode: false,
+            log_raw_seeds: false,
+            log_format: LogFormat::default(),
+            backfill_legacy_proofs: true,
+            retention: None,
+            auto_migrate: true,
+            audit_trail: false,
+            outcome_feed_file: None,
+            outcome_hook_command: None,
+            cors_allowed_origins: None,
+            admin_api_key: None,
+            max_body_bytes: Self::DEFAULT_MAX_BODY_BYTES,
+            tokens: TokenRegistry::default(),
+        }
+    }
+}
+
+impl NodeConfig {
+    /// Room for a batch of a few dozen flips; a single flip is well under 2 KiB.
+    pub const DEFAULT_MAX_BODY_BYTES: usize = 16 * 1024;
+
+    pub fn from_env() -> (Self, ConfigErrors) {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// Parse every setting, falling back to the default for invalid ones so the remaining
+    /// startup checks can still run and report their own problems.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> (Self, ConfigErrors) {
+        let mut errors = ConfigErrors::default();
+        let defaults = Self::default();
+
+        let port = parse_var(&lookup, &mut errors, "PORT", "a port number (1-65535)")
+            .filter(|&port: &u16| {
+                if port == 0 {
+                    errors.push("PORT must be between 1 and 65535, got '0'");
+                }
+                port != 0
+            })
+            .unwrap_or(defaults.port);
+
+        let listen_addr = lookup("BIND_ADDR").filter(|v| !v.trim().is_empty()).and_then(|value| {
+            let addr = BindAddr::parse(value.trim());
+            if addr.is_none() {
+                errors.push(format!(
+                    "BIND_ADDR must be ip:port (e.g. 127.0.0.1:3001) or unix:/path/to.sock, got '{}'",
+                    value
+                ));
+            }
+            addr
+        });
+
+        let tls_path = |key| lookup(key).filter(|path: &String| !path.trim().is_empty()).map(PathBuf::from);
+        let (tls_cert_path, tls_key_path) = match (tls_path("TLS_CERT_PATH"), tls_path("TLS_KEY_PATH")) {
+            (Some(_), None) => {
+                errors.push("TLS_CERT_PATH is set but TLS_KEY_PATH is not; set both to serve HTTPS, or neither");
+                (None, None)
+            }
+            (None, Some(_)) => {
+                errors.push("TLS_KEY_PATH is set but TLS_CERT_PATH is not; set both to serve HTTPS, or neither");
+                (None, None)
+            }
+            paths => paths,
+        };
+        if tls_cert_path.is_some() && matches!(listen_addr, Some(BindAddr::Unix(_))) {
+            errors.push("TLS_CERT_PATH/TLS_KEY_PATH cannot be used with a unix: BIND_ADDR");
+        }
+
+        let database_url = lookup("DATABASE_URL").unwrap_or(defaults.database_url);
+        if is_postgres_url(&database_url) {
+            if !cfg!(feature = "postgres") {
+                errors.push("DATABASE_URL is a PostgreSQL URL, but this build lacks the `postgres` feature");
+            }
+        } else if !database_url.starts_with("sqlite:") {
+            errors.push(format!(
+                "DATABASE_URL must be a sqlite: or postgres:// URL (e.g. sqlite:./vfnode.db), got '{}'",
+                database_url
+            ));
+        }
+
+        let db_max_connections = parse_positive(&lookup, &mut errors, "DB_MAX_CONNECTIONS").unwrap_or(defaults.db_max_connections);
+        let db_journal_mode = parse_var(&lookup, &mut errors, "DB_JOURNAL_MODE", "one of wal, delete, truncate, persist, memory, off")
+            .unwrap_or(defaults.db_journal_mode);
+        let db_busy_timeout = parse_var(&lookup, &mut errors, "DB_BUSY_TIMEOUT_MS", "a number of milliseconds")
+            .map(Duration::from_millis)
+            .unwrap_or(defaults.db_busy_timeout);
+
+        let node_key_file = lookup("NODE_KEY_FILE")
+            .filter(|path| !path.trim().is_empty())
+            .map(PathBuf::from);
+
+        let outcome_feed_file = lookup("OUTCOME_FEED_FILE")
+            .filter(|path| !path.trim().is_empty())
+            .map(PathBuf::from);
+
+        let outcome_hook_command = lookup("OUTCOME_HOOK_COMMAND").filter(|command| !command.trim().is_empty());
+
+        let max_flips_per_second = parse_var(&lookup, &mut errors, "MAX_FLIPS_PER_SECOND", "a positive integer")
+            .filter(|&rate: &u32| {
+                if rate == 0 {
+                    errors.push("MAX_FLIPS_PER_SECOND must be a positive integer, got '0' (unset it to disable the ceiling)");
+                }
+                rate > 0
+            });
+
+        let client_rate_limit_per_second = parse_positive(&lookup, &mut errors, "CLIENT_RATE_LIMIT_PER_SECOND");
+        let client_rate_limit_burst = parse_positive(&lookup, &mut errors, "CLIENT_RATE_LIMIT_BURST");
+        if client_rate_limit_burst.is_some() && lookup("CLIENT_RATE_LIMIT_PER_SECOND").is_none() {
+            errors.push("CLIENT_RATE_LIMIT_BURST needs CLIENT_RATE_LIMIT_PER_SECOND to be set");
+        }
+
+        let max_body_bytes = parse_positive(&lookup, &mut errors, "MAX_BODY_BYTES").unwrap_or(defaults.max_body_bytes);
+        let max_concurrent_flips = parse_positive(&lookup, &mut errors, "MAX_CONCURRENT_FLIPS");
+        let max_concurrent_verifications = parse_positive(&lookup, &mut errors, "MAX_CONCURRENT_VERIFICATIONS")
+            .unwrap_or(defaults.max_concurrent_verifications);
+
+        let latency_floor = parse_var(&lookup, &mut errors, "LATENCY_FLOOR_MS", "a number of milliseconds")
+            .filter(|&ms: &u64| ms > 0)
+            .map(Duration::from_millis);
+
+        let duplicate_window = match parse_var::<u64>(&lookup, &mut errors, "DUPLICATE_WINDOW_SECS", "a number of seconds") {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => defaults.duplicate_window,
+        };
+
+        let max_clock_drift = match parse_var::<u64>(&lookup, &mut errors, "MAX_CLOCK_DRIFT_SECS", "a number of seconds") {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => defaults.max_clock_drift,
+        };
+
+        let seen_bet_ids = match parse_var::<usize>(&lookup, &mut errors, "SEEN_BET_ID_CAPACITY", "a number of bet ids") {
+            Some(0) => None,
+            Some(capacity) => Some(capacity),
+            None => defaults.seen_bet_ids,
+        };
+
+        let commit_ttl = parse_positive(&lookup, &mut errors, "COMMIT_TTL_SECS")
+            .map(Duration::from_secs)
+            .unwrap_or(defaults.commit_ttl);
+
+        let beacon_url = lookup("BEACON_URL").filter(|url| !url.trim().is_empty());
+        if beacon_url.is_some() && !cfg!(feature = "drand") {
+            errors.push("BEACON_URL is set, but this build lacks the `drand` feature");
+        }
+        let beacon_max_age = parse_positive(&lookup, &mut errors, "BEACON_MAX_AGE_SECS")
+            .map(Duration::from_secs)
+            .unwrap_or(defaults.beacon_max_age);
+
+        let max_request_age = match parse_var::<u64>(&lookup, &mut errors, "MAX_REQUEST_AGE_SECS", "a number of seconds") {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => defaults.max_request_age,
+        };
+
+        let win_probability_bps = parse_var(&lookup, &mut errors, "WIN_PROBABILITY_BPS", "a number of basis points")
+            .filter(|&bps: &u16| {
+                if u64::from(bps) > BPS_SCALE {
+                    errors.push(format!("WIN_PROBABILITY_BPS must be at most {}, got '{}'", BPS_SCALE, bps));
+                }
+                u64::from(bps) <= BPS_SCALE
+            })
+            .unwrap_or(defaults.win_probability_bps);
+
+        let payout_multiplier_bps =
+            parse_positive(&lookup, &mut errors, "PAYOUT_MULTIPLIER_BPS").unwrap_or(defaults.payout_multiplier_bps);
+
+        let challenge_len = parse_var(&lookup, &mut errors, "CHALLENGE_LEN", "a number of bytes")
+            .filter(|len| {
+                if !CHALLENGE_LENS.contains(len) {
+                    errors.push(format!("CHALLENGE_LEN must be one of 32, 64, got '{}'", len));
+                }
+                CHALLENGE_LENS.contains(len)
+            })
+            .unwrap_or(defaults.challenge_len);
+
+        let outcome_bit = match lookup("OUTCOME_BIT").filter(|v| !v.trim().is_empty()) {
+            Some(value) => OutcomeBit::parse(value.trim()).unwrap_or_else(|| {
+                errors.push(format!("OUTCOME_BIT must be one of lsb, msb, parity, got '{}'", value));
+                defaults.outcome_bit
+            }),
+            None => defaults.outcome_bit,
+        };
+
+        let max_compute_iterations = parse_positive(&lookup, &mut errors, "MAX_COMPUTE_ITERATIONS");
+        let mut min_seed_len = parse_positive(&lookup, &mut errors, "MIN_SEED_LEN").unwrap_or(defaults.min_seed_len);
+        let mut max_seed_len = parse_positive(&lookup, &mut errors, "MAX_SEED_LEN").unwrap_or(defaults.max_seed_len);
+        if min_seed_len > max_seed_len {
+            errors.push(format!(
+                "MIN_SEED_LEN ({}) cannot be greater than MAX_SEED_LEN ({})",
+                min_seed_len, max_seed_len
+            ));
+            (min_seed_len, max_seed_len) = (defaults.min_seed_len, defaults.max_seed_len);
+        }
+        let max_batch_payout_lamports = parse_positive(&lookup, &mut errors, "MAX_BATCH_PAYOUT_LAMPORTS");
+        let retention = parse_positive::<u64>(&lookup, &mut errors, "RETENTION_DAYS")
+            .map(|days| Duration::from_secs(days * 24 * 60 * 60));
+        let settlement_channel_capacity = parse_positive(&lookup, &mut errors, "SETTLEMENT_CHANNEL_CAPACITY")
+            .unwrap_or(defaults.settlement_channel_capacity);
+        let retry_queue_health_limit = parse_positive(&lookup, &mut errors, "RETRY_QUEUE_HEALTH_LIMIT")
+            .unwrap_or(defaults.retry_queue_health_limit);
+        let settlement_mode = match lookup("SETTLEMENT_MODE").filter(|v| !v.trim().is_empty()) {
+            Some(value) => SettlementMode::parse(value.trim()).unwrap_or_else(|| {
+                errors.push(format!("SETTLEMENT_MODE must be one of mock, dry_run, solana, got '{}'", value));
+                defaults.settlement_mode
+            }),
+            None => defaults.settlement_mode,
+        };
+        let mock_failure_rate = parse_var(&lookup, &mut errors, "MOCK_FAILURE_RATE", "a number between 0 and 1")
+            .filter(|rate: &f64| {
+                let valid = (0.0..=1.0).contains(rate);
+                if !valid {
+                    errors.push(format!("MOCK_FAILURE_RATE must be a number between 0 and 1, got '{}'", rate));
+                }
+                valid
+            })
+            .unwrap_or(defaults.mock_failure_rate);
+        let solana_rpc_url = lookup("SOLANA_RPC_URL").filter(|url| !url.trim().is_empty());
+        let solana_keypair_file = lookup("SOLANA_KEYPAIR_FILE")
+            .filter(|path| !path.trim().is_empty())
+            .map(PathBuf::from);
+        let solana_confirmation_level = match lookup("SOLANA_CONFIRMATION_LEVEL").filter(|v| !v.trim().is_empty()) {
+            Some(value) => ConfirmationLevel::parse(value.trim()).unwrap_or_else(|| {
+                errors.push(format!(
+                    "SOLANA_CONFIRMATION_LEVEL must be one of processed, confirmed, finalized, got '{}'",
+                    value
+                ));
+                defaults.solana_confirmation_level
+            }),
+            None => defaults.solana_confirmation_level,
+        };
+        if settlement_mode == SettlementMode::Solana {
+            if !cfg!(feature = "solana") {
+                errors.push("SETTLEMENT_MODE is solana, but this build lacks the `solana` feature");
+            }
+            match &solana_rpc_url {
+                None => errors.push("SETTLEMENT_MODE=solana needs SOLANA_RPC_URL"),
+                Some(url) if !url.starts_with("http://") && !url.starts_with("https://") => {
+                    errors.push(format!("SOLANA_RPC_URL must be an http:// or https:// URL, got '{}'", url))
+                }
+                Some(_) => {}
+            }
+            if solana_keypair_file.is_none() {
+                errors.push("SETTLEMENT_MODE=solana needs SOLANA_KEYPAIR_FILE");
+            }
+        }
+
+        let seed_chaining = parse_flag(&lookup, &mut errors, "SEED_CHAINING", false);
+        let seed_chain_capacity = parse_positive(&lookup, &mut errors, "SEED_CHAIN_CAPACITY").unwrap_or(defaults.seed_chain_capacity);
+        let seed_chain_ttl = parse_positive(&lookup, &mut errors, "SEED_CHAIN_TTL_SECS")
+            .map(Duration::from_secs)
+            .unwrap_or(defaults.seed_chain_ttl);
+        let require_wallet_sig = parse_flag(&lookup, &mut errors, "REQUIRE_WALLET_SIG", false);
+        let full_output_hash = parse_flag(&lookup, &mut errors, "FULL_OUTPUT_HASH", false);
+        let free_play = parse_flag(&lookup, &mut errors, "FREE_PLAY", false);
+        let dev_mode = parse_flag(&lookup, &mut errors, "DEV_MODE", false);
+        let log_raw_seeds = parse_flag(&lookup, &mut errors, "LOG_RAW_SEEDS", false);
+        let log_format = match lookup("LOG_FORMAT").filter(|v| !v.trim().is_empty()) {
+            Some(value) => LogFormat::parse(value.trim()).unwrap_or_else(|| {
+                errors.push(format!("LOG_FORMAT must be one of compact, json, got '{}'", value));
+                defaults.log_format
+            }),
+            None => defaults.log_format,
+        };
+        let backfill_legacy_proofs = parse_flag(&lookup, &mut errors, "BACKFILL_LEGACY_PROOFS", true);
+        let auto_migrate = parse_flag(&lookup, &mut errors, "AUTO_MIGRATE", true);
+        let audit_trail = parse_flag(&lookup, &mut errors, "AUDIT_TRAIL", false);
+        if audit_trail && is_postgres_url(&database_url) {
+            errors.push("AUDIT_TRAIL needs a sqlite: DATABASE_URL; bet_results is not kept in PostgreSQL");
+        }
+        let deterministic_bet_ids = parse_flag(&lookup, &mut errors, "DETERMINISTIC_BET_IDS", false);
+
+        let admin_api_key = lookup("ADMIN_API_KEY")
+            .filter(|key| !key.trim().is_empty())
+            .map(|key| AdminKey::new(&key));
+
+        let cors_allowed_origins = lookup("CORS_ALLOWED_ORIGINS").map(|raw| {
+            let origins: Vec<String> = raw.split(',').map(str::trim).filter(|o| !o.is_empty()).map(String::from).collect();
+            if origins.is_empty() {
+                errors.push("CORS_ALLOWED_ORIGINS must list at least one origin (unset it to allow any origin)");
+            }
+            for origin in &origins {
+                let scheme_ok = origin.starts_with("http://") || origin.starts_with("https://");
+                if !scheme_ok || origin.ends_with('/') || HeaderValue::from_str(origin).is_err() {
+                    errors.push(format!(
+                        "CORS_ALLOWED_ORIGINS entries must be origins like https://app.example.com, got '{}'",
+                        origin
+                    ));
+                }
+            }
+            origins
+        });
+
+        let tokens = match lookup("TOKENS").filter(|v| !v.trim().is_empty()) {
+            Some(json) => TokenRegistry::from_json(&json).unwrap_or_else(|problems| {
+                problems.into_iter().for_each(|p| errors.push(p));
+                TokenRegistry::default()
+            }),
+            None => defaults.tokens,
+        };
+        if settlement_mode == SettlementMode::Solana {
+            let mut other_mints: Vec<String> =
+                tokens.enabled_mints().into_iter().filter(|mint| mint != NATIVE_TOKEN_MINT).collect();
+            other_mints.sort();
+            if !other_mints.is_empty() {
+                errors.push(format!(
+                    "SETTLEMENT_MODE=solana only pays out {}, but TOKENS enables {}",
+                    NATIVE_TOKEN_MINT,
+                    other_mints.join(", ")
+                ));
+            }
+        }
+
+        let config = Self {
+            port,
+            listen_addr,
+            tls_cert_path,
+            tls_key_path,
+            database_url,
+            db_max_connections,
+            db_journal_mode,
+            db_busy_timeout,
+            node_key_file,
+            max_flips_per_second,
+            client_rate_limit_per_second,
+            client_rate_limit_burst,
+            max_concurrent_flips,
+            max_concurrent_verifications,
+            latency_floor,
+            seed_chaining,
+            seed_chain_capacity,
+            seed_chain_ttl,
+            require_wallet_sig,
+            full_output_hash,
+            win_probability_bps,
+            outcome_bit,
+            payout_multiplier_bps,
+            challenge_len,
+            duplicate_window,
+            seen_bet_ids,
+            commit_ttl,
+            beacon_url,
+            beacon_max_age,
+            max_clock_drift,
+            max_request_age,
+            max_compute_iterations,
+            min_seed_len,
+            max_seed_len,
+            max_batch_payout_lamports,
+            deterministic_bet_ids,
+            settlement_channel_capacity,
+            retry_queue_health_limit,
+            settlement_mode,
+            mock_failure_rate,
+            solana_rpc_url,
+            solana_keypair_file,
+            solana_confirmation_level,
+            free_play,
+            dev_mode,
+            log_raw_seeds,
+            log_format,
+            backfill_legacy_proofs,
+            retention,
+            auto_migrate,
+            audit_trail,
+            outcome_feed_file,
+            outcome_hook_command,
+            cors_allowed_origins,
+            admin_api_key,
+            max_body_bytes,
+            tokens,
+        };
+        (config, errors)
+    }
+
+    pub fn bind_addr(&self) -> BindAddr {
+        self.listen_addr.clone().unwrap_or(BindAddr::Tcp(([0, 0, 0, 0], self.port).into()))
+    }
+
+    /// Per-client-IP limiter, when `CLIENT_RATE_LIMIT_PER_SECOND` is set
+    pub fn client_rate_limiter(&self) -> Option<ClientRateLimiter> {
+        let rate = self.client_rate_limit_per_second?;
+        Some(ClientRateLimiter::new(rate, self.client_rate_limit_burst.unwrap_or(rate)))
+    }
+
+    pub fn engine_config(&self) -> EngineConfig {
+        let (min_wager_lamports, max_wager_lamports) = self.tokens.wager_bounds();
+        EngineConfig {
+            latency_floor: self.latency_floor,
+            seed_chaining: self.seed_chaining,
+            seed_chain_capacity: self.seed_chain_capacity,
+            seed_chain_ttl: self.seed_chain_ttl,
+            duplicate_window: self.duplicate_window,
+            seen_bet_ids: self.seen_bet_ids,
+            max_clock_drift: self.max_clock_drift,
+            max_request_age: self.max_request_age,
+            compute_budget: self.max_compute_iterations,
+            min_seed_len: self.min_seed_len,
+            max_seed_len: self.max_seed_len,
+            full_output_hash: self.full_output_hash,
+            win_probability_bps: self.win_probability_bps,
+            outcome_bit: self.outcome_bit,
+            payout_multiplier_bps: self.payout_multiplier_bps,
+            challenge_len: self.challenge_len,
+            // Per-token limits are checked against `tokens` before the engine runs; the engine
+            // enforces the widest of them and the enabled mints as a backstop
+            min_wager_lamports,
+            max_wager_lamports,
+            allowed_mints: self.tokens.enabled_mints(),
+            require_wallet_sig: self.require_wallet_sig,
+        }
+    }
+
+    /// CORS policy for the HTTP API: only the allowed origins, or any origin when no
+    /// allowlist is configured. Preflight `OPTIONS` requests are answered by the layer.
+    /// Credentials are never allowed; the API doesn't use cookies.
+    pub fn cors_layer(&self) -> CorsLayer {
+        let Some(origins) = &self.cors_allowed_origins else {
+            return CorsLayer::permissive();
+        };
+        let origins: Vec<HeaderValue> = origins.iter().filter_map(|o| HeaderValue::from_str(o).ok()).collect();
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers([header::CONTENT_TYPE])
+            .allow_credentials(false)
+            .max_age(Duration::from_secs(3600))
+    }
+
+    /// How to open `DATABASE_URL`
+    pub fn storage_options(&self) -> StorageOptions {
+        StorageOptions {
+            max_connections: self.db_max_connections,
+            journal_mode: self.db_journal_mode,
+            busy_timeout: self.db_busy_timeout,
+        }
+    }
+
+    /// The TLS acceptor to serve with, or None for plain HTTP
+    pub fn load_tls(&self) -> Result<Option<listen::TlsAcceptor>, String> {
+        match (&self.tls_cert_path, &self.tls_key_path) {
+            (Some(cert), Some(key)) => listen::tls_acceptor(cert, key).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Build the VRF engine, loading the signing key from `NODE_KEY_FILE` when set, polling
+    /// the `BEACON_URL` relay and starting the `OUTCOME_HOOK_COMMAND` hook. The beacon is
+    /// polled on the current Tokio runtime.
+    pub fn load_engine(&self) -> Result<VrfEngine, String> {
+        let engine = match &self.node_key_file {
+            Some(path) => VrfEngine::from_seed_with_config(load_key_seed(path)?, self.engine_config()),
+            None => VrfEngine::with_config(self.engine_config()),
+        };
+        #[cfg(feature = "drand")]
+        let engine = match &self.beacon_url {
+            Some(url) => engine.with_beacon(
+                crate::drand::DrandBeacon::start(url, self.beacon_max_age)
+                    .map_err(|e| format!("BEACON_URL '{}': {}", url, e))?,
+            ),
+            None => engine,
+        };
+        let Some(command) = &self.outcome_hook_command else {
+            return Ok(engine);
+        };
+        let hook = CommandHook::spawn(command)
+            .map_err(|e| format!("OUTCOME_HOOK_COMMAND '{}' cannot be started: {}", command, e))?;
+        let hooks = OutcomeHooks::start(vec![Arc::new(hook)], OutcomeHooks::DEFAULT_CAPACITY)
+            .map_err(|e| format!("OUTCOME_HOOK_COMMAND: cannot start the hook thread: {}", e))?;
+        Ok(engine.with_hooks(hooks))
+    }
+
+    /// Build the settler `SettlementMode::Solana` submits through, or None for the other modes
+    #[cfg(feature = "solana")]
+    pub fn load_settler(&self) -> Result<Option<std::sync::Arc<dyn crate::settlement_engine::Settler>>, String> {
+        match (self.settlement_mode, &self.solana_rpc_url, &self.solana_keypair_file) {
+            (SettlementMode::Solana, Some(rpc_url), Some(path)) => {
+                Ok(Some(std::sync::Arc::new(crate::solana::SolanaSettler::from_keypair_file(
+                    rpc_url,
+                    path,
+                    self.solana_confirmation_level,
+                )?)))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+fn load_key_seed(path: &Path) -> Result<[u8; 32], String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("NODE_KEY_FILE '{}' could not be read: {}", path.display(), e))?;
+    let bytes = hex::decode(contents.trim())
+        .map_err(|e| format!("NODE_KEY_FILE '{}' is not valid hex: {}", path.display(), e))?;
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        format!(
+            "NODE_KEY_FILE '{}' must hold a 32-byte seed (64 hex chars), found {} bytes",
+            path.display(),
+            bytes.len()
+        )
+    })
+}
+
+fn parse_var<T: FromStr>(
+    lookup: &impl Fn(&str) -> Option<String>,
+    errors: &mut ConfigErrors,
+    key: &str,
+    expected: &str,
+) -> Option<T> {
+    let raw = lookup(key)?;
+    match raw.trim().parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            errors.push(format!("{} must be {}, got '{}'", key, expected, raw));
+            None
+        }
+    }
+}
+
+fn parse_positive<T: FromStr + Default + PartialEq>(
+    lookup: &impl Fn(&str) -> Option<String>,
+    errors: &mut ConfigErrors,
+    key: &str,
+) -> Option<T> {
+    let value: T = parse_var(lookup, errors, key, "a positive integer")?;
+    if value == T::default() {
+        errors.push(format!("{} must be a positive integer, got '0'", key));
+        return None;
+    }
+    Some(value)
+}
+
+fn parse_flag(lookup: &impl Fn(&str) -> Option<String>, errors: &mut ConfigErrors, key: &str, default: bool) -> bool {
+    match lookup(key).as_deref().map(str::trim) {
+        None | Some("") => default,
+        Some("0") | Some("false") => false,
+        Some("1") | Some("true") => true,
+        Some(other) => {
+            errors.push(format!("{} must be one of 1, true, 0, false, got '{}'", key, other));
+            default
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn test_defaults_are_valid() {
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[]));
+        assert!(errors.is_empty(), "{}", errors);
+        assert_eq!(config.bind_addr().to_string(), "0.0.0.0:3001");
+        assert!(config.max_flips_per_second.is_none());
+        assert_eq!(config.max_body_bytes, 16 * 1024);
+    }
+
+    #[test]
+    fn test_invalid_config_reports_every_problem() {
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[
+            ("PORT", "http"),
+            ("MAX_FLIPS_PER_SECOND", "0"),
+            ("LATENCY_FLOOR_MS", "-5"),
+            ("SEED_CHAINING", "yes"),
+            ("DATABASE_URL", "mysql://db"),
+            ("TOKENS", r#"[{"mint": "SOL", "decimals": 9, "min_wager_lamports": 9, "max_wager_lamports": 1}]"#),
+        ]));
+
+        assert_eq!(errors.problems().len(), 6, "{}", errors);
+        let report = errors.to_string();
+        for key in ["PORT", "MAX_FLIPS_PER_SECOND", "LATENCY_FLOOR_MS", "SEED_CHAINING", "DATABASE_URL", "TOKENS"] {
+            assert!(report.contains(key), "missing {} in:\n{}", key, report);
+        }
+        assert!(report.contains("got 'http'"));
+
+        // Invalid values fall back to defaults so later checks can still run
+        assert_eq!(config.port, 3001);
+    }
+
+    #[test]
+    fn test_seed_length_bounds() {
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("MIN_SEED_LEN", "16"), ("MAX_SEED_LEN", "64")]));
+        assert!(errors.is_empty(), "{}", errors);
+        let engine = config.engine_config();
+        assert_eq!((engine.min_seed_len, engine.max_seed_len), (16, 64));
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("MIN_SEED_LEN", "2048")]));
+        assert_eq!(errors.problems().len(), 1, "{}", errors);
+        assert!(errors.to_string().contains("MIN_SEED_LEN (2048) cannot be greater than MAX_SEED_LEN (1024)"));
+        assert_eq!((config.min_seed_len, config.max_seed_len), (1, 1024));
+    }
+
+    #[test]
+    fn test_payout_multiplier() {
+        let (config, _) = NodeConfig::from_lookup(lookup(&[]));
+        assert_eq!(config.engine_config().payout_multiplier_bps, 20_000);
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("PAYOUT_MULTIPLIER_BPS", "19600")]));
+        assert!(errors.is_empty(), "{}", errors);
+        assert_eq!(config.engine_config().payout_multiplier_bps, 19_600);
+
+        for bad in ["0", "2x", "-1"] {
+            let (_, errors) = NodeConfig::from_lookup(lookup(&[("PAYOUT_MULTIPLIER_BPS", bad)]));
+            assert_eq!(errors.problems().len(), 1, "{}", errors);
+        }
+    }
+
+    #[test]
+    fn test_log_format() {
+        let (config, _) = NodeConfig::from_lookup(lookup(&[]));
+        assert_eq!(config.log_format, LogFormat::Compact);
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("LOG_FORMAT", "json")]));
+        assert!(errors.is_empty(), "{}", errors);
+        assert_eq!(config.log_format, LogFormat::Json);
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("LOG_FORMAT", "logfmt")]));
+        assert!(errors.to_string().contains("LOG_FORMAT must be one of compact, json, got 'logfmt'"), "{}", errors);
+        assert_eq!(config.log_format, LogFormat::Compact);
+    }
+
+    #[test]
+    fn test_challenge_len() {
+        let (config, _) = NodeConfig::from_lookup(lookup(&[]));
+        assert_eq!(config.engine_config().challenge_len, 64);
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("CHALLENGE_LEN", "32")]));
+        assert!(errors.is_empty(), "{}", errors);
+        assert_eq!(config.engine_config().challenge_len, 32);
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("CHALLENGE_LEN", "48")]));
+        assert!(errors.to_string().contains("CHALLENGE_LEN must be one of 32, 64, got '48'"), "{}", errors);
+        assert_eq!(config.challenge_len, 64);
+    }
+
+    #[test]
+    fn test_engine_wager_limits_come_from_tokens() {
+        let (config, _) = NodeConfig::from_lookup(lookup(&[]));
+        let engine = config.engine_config();
+        assert_eq!((engine.min_wager_lamports, engine.max_wager_lamports), (1, u64::MAX));
+
+        let tokens = r#"[
+            {"mint": "SOL", "decimals": 9, "min_wager_lamports": 1000, "max_wager_lamports": 50000},
+            {"mint": "USDC", "decimals": 6, "min_wager_lamports": 500, "max_wager_lamports": 9000},
+            {"mint": "BONK", "decimals": 5, "min_wager_lamports": 1, "max_wager_lamports": 90000, "enabled": false}
+        ]"#;
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("TOKENS", tokens)]));
+        assert!(errors.is_empty(), "{}", errors);
+        let engine = config.engine_config();
+        assert_eq!((engine.min_wager_lamports, engine.max_wager_lamports), (500, 50000));
+        assert_eq!(engine.allowed_mints, HashSet::from(["SOL".to_string(), "USDC".to_string()]));
+    }
+
+    #[test]
+    fn test_commit_ttl() {
+        let (config, _) = NodeConfig::from_lookup(lookup(&[]));
+        assert_eq!(config.commit_ttl, Duration::from_secs(300));
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("COMMIT_TTL_SECS", "30")]));
+        assert!(errors.is_empty(), "{}", errors);
+        assert_eq!(config.commit_ttl, Duration::from_secs(30));
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("COMMIT_TTL_SECS", "0")]));
+        assert!(errors.to_string().contains("COMMIT_TTL_SECS must be a positive integer"), "{}", errors);
+        assert_eq!(config.commit_ttl, Duration::from_secs(300));
+    }
+
+    #[test]
+    fn test_database_pool_settings() {
+        let (config, _) = NodeConfig::from_lookup(lookup(&[]));
+        assert_eq!(config.storage_options(), StorageOptions::default());
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[
+            ("DB_MAX_CONNECTIONS", "4"),
+            ("DB_JOURNAL_MODE", "DELETE"),
+            ("DB_BUSY_TIMEOUT_MS", "1500"),
+        ]));
+        assert!(errors.is_empty(), "{}", errors);
+        let options = config.storage_options();
+        assert_eq!(options.max_connections, 4);
+        assert_eq!(options.journal_mode, SqliteJournalMode::Delete);
+        assert_eq!(options.busy_timeout, Duration::from_millis(1500));
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[
+            ("DB_MAX_CONNECTIONS", "0"),
+            ("DB_JOURNAL_MODE", "fast"),
+            ("DB_BUSY_TIMEOUT_MS", "soon"),
+        ]));
+        assert_eq!(errors.problems().len(), 3, "{}", errors);
+        assert_eq!(config.storage_options(), StorageOptions::default());
+    }
+
+    #[test]
+    fn test_solana_settlement_needs_rpc_url_and_keypair() {
+        let (_, errors) = NodeConfig::from_lookup(lookup(&[("SETTLEMENT_MODE", "solana")]));
+        let errors = errors.to_string();
+        assert!(errors.contains("needs SOLANA_RPC_URL"), "{}", errors);
+        assert!(errors.contains("needs SOLANA_KEYPAIR_FILE"), "{}", errors);
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[
+            ("SETTLEMENT_MODE", "solana"),
+            ("SOLANA_RPC_URL", "https://api.devnet.solana.com"),
+            ("SOLANA_KEYPAIR_FILE", "/etc/vfnode/payer.json"),
+        ]));
+        assert_eq!(config.settlement_mode, SettlementMode::Solana);
+        assert_eq!(config.solana_keypair_file, Some(PathBuf::from("/etc/vfnode/payer.json")));
+        if cfg!(feature = "solana") {
+            assert!(errors.is_empty(), "{}", errors);
+        } else {
+            assert!(errors.to_string().contains("`solana` feature"), "{}", errors);
+        }
+
+        let (_, errors) = NodeConfig::from_lookup(lookup(&[
+            ("SETTLEMENT_MODE", "solana"),
+            ("SOLANA_RPC_URL", "devnet"),
+            ("SOLANA_KEYPAIR_FILE", "/etc/vfnode/payer.json"),
+        ]));
+        assert!(errors.to_string().contains("SOLANA_RPC_URL must be an http:// or https:// URL"), "{}", errors);
+
+        let (_, errors) = NodeConfig::from_lookup(lookup(&[
+            ("SETTLEMENT_MODE", "solana"),
+            ("SOLANA_RPC_URL", "https://api.devnet.solana.com"),
+            ("SOLANA_KEYPAIR_FILE", "/etc/vfnode/payer.json"),
+            ("TOKENS", r#"[
+                {"mint": "SOL", "decimals": 9, "min_wager_lamports": 1000, "max_wager_lamports": 50000},
+                {"mint": "USDC", "decimals": 6, "min_wager_lamports": 500, "max_wager_lamports": 9000}
+            ]"#),
+        ]));
+        assert!(errors.to_string().contains("only pays out SOL, but TOKENS enables USDC"), "{}", errors);
+
+        assert_eq!(NodeConfig::default().solana_confirmation_level, ConfirmationLevel::Confirmed);
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("SOLANA_CONFIRMATION_LEVEL", "finalized")]));
+        assert!(errors.is_empty(), "{}", errors);
+        assert_eq!(config.solana_confirmation_level, ConfirmationLevel::Finalized);
+        let (_, errors) = NodeConfig::from_lookup(lookup(&[("SOLANA_CONFIRMATION_LEVEL", "rooted")]));
+        assert!(errors.to_string().contains("SOLANA_CONFIRMATION_LEVEL must be one of"), "{}", errors);
+    }
+
+    #[test]
+    fn test_postgres_database_url_needs_feature() {
+        for url in ["postgres://vfnode@db/vfnode", "postgresql://vfnode@db/vfnode"] {
+            let (config, errors) = NodeConfig::from_lookup(lookup(&[("DATABASE_URL", url)]));
+            assert_eq!(config.database_url, url);
+            if cfg!(feature = "postgres") {
+                assert!(errors.is_empty(), "{}", errors);
+            } else {
+                assert!(errors.to_string().contains("`postgres` feature"), "{}", errors);
+            }
+        }
+
+        let (_, errors) = NodeConfig::from_lookup(lookup(&[("DATABASE_URL", "postgres://db/vfnode"), ("AUDIT_TRAIL", "1")]));
+        assert!(errors.to_string().contains("AUDIT_TRAIL"), "{}", errors);
+    }
+
+    #[test]
+    fn test_client_rate_limit() {
+        let (config, _) = NodeConfig::from_lookup(lookup(&[]));
+        assert!(config.client_rate_limiter().is_none());
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("CLIENT_RATE_LIMIT_PER_SECOND", "5")]));
+        assert!(errors.is_empty(), "{}", errors);
+        assert_eq!((config.client_rate_limit_per_second, config.client_rate_limit_burst), (Some(5), None));
+        assert!(config.client_rate_limiter().is_some());
+
+        let (_, errors) = NodeConfig::from_lookup(lookup(&[("CLIENT_RATE_LIMIT_BURST", "20")]));
+        assert_eq!(errors.problems().len(), 1, "{}", errors);
+        assert!(errors.to_string().contains("CLIENT_RATE_LIMIT_PER_SECOND"), "{}", errors);
+
+        let (_, errors) = NodeConfig::from_lookup(lookup(&[("CLIENT_RATE_LIMIT_PER_SECOND", "0")]));
+        assert_eq!(errors.problems().len(), 1, "{}", errors);
+    }
+
+    #[test]
+    fn test_tls_paths_come_in_pairs() {
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[]));
+        assert!(errors.is_empty(), "{}", errors);
+        assert!(config.load_tls().unwrap().is_none());
+
+        for key in ["TLS_CERT_PATH", "TLS_KEY_PATH"] {
+            let (config, errors) = NodeConfig::from_lookup(lookup(&[(key, "/etc/vfnode/tls.pem")]));
+            assert_eq!(errors.problems().len(), 1, "{}", errors);
+            assert!(errors.to_string().contains(&format!("{} is set", key)), "{}", errors);
+            assert!(config.load_tls().unwrap().is_none());
+        }
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[
+            ("TLS_CERT_PATH", "/nonexistent/cert.pem"),
+            ("TLS_KEY_PATH", "/nonexistent/key.pem"),
+        ]));
+        assert!(errors.is_empty(), "{}", errors);
+        let err = config.load_tls().err().unwrap();
+        assert!(err.contains("TLS_CERT_PATH '/nonexistent/cert.pem' could not be read"), "{}", err);
+
+        let (_, errors) = NodeConfig::from_lookup(lookup(&[
+            ("BIND_ADDR", "unix:/run/vfnode.sock"),
+            ("TLS_CERT_PATH", "cert.pem"),
+            ("TLS_KEY_PATH", "key.pem"),
+        ]));
+        assert_eq!(errors.problems().len(), 1, "{}", errors);
+        assert!(errors.to_string().contains("unix:"), "{}", errors);
+    }
+
+    #[test]
+    fn test_bind_addr_overrides_port() {
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("PORT", "4000"), ("BIND_ADDR", "127.0.0.1:8080")]));
+        assert!(errors.is_empty(), "{}", errors);
+        assert_eq!(config.bind_addr().to_string(), "127.0.0.1:8080");
+
+        let (config, _) = NodeConfig::from_lookup(lookup(&[("BIND_ADDR", "unix:/run/vfnode.sock")]));
+        assert_eq!(config.bind_addr(), BindAddr::Unix("/run/vfnode.sock".into()));
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("PORT", "4000"), ("BIND_ADDR", "localhost")]));
+        assert_eq!(errors.problems().len(), 1, "{}", errors);
+        assert!(errors.to_string().contains("BIND_ADDR"));
+        assert_eq!(config.bind_addr().to_string(), "0.0.0.0:4000");
+    }
+
+    #[test]
+    fn test_cors_origins_are_validated() {
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[(
+            "CORS_ALLOWED_ORIGINS",
+            "https://app.example.com, http://localhost:5173 ,",
+        )]));
+        assert!(errors.is_empty(), "{}", errors);
+        assert_eq!(
+            config.cors_allowed_origins.unwrap(),
+            vec!["https://app.example.com".to_string(), "http://localhost:5173".to_string()]
+        );
+
+        let (_, errors) = NodeConfig::from_lookup(lookup(&[("CORS_ALLOWED_ORIGINS", "app.example.com,https://a.example.com/")]));
+        assert_eq!(errors.problems().len(), 2, "{}", errors);
+        let (_, errors) = NodeConfig::from_lookup(lookup(&[("CORS_ALLOWED_ORIGINS", " , ")]));
+        assert!(errors.to_string().contains("at least one origin"), "{}", errors);
+    }
+
+    #[test]
+    fn test_beacon_url_needs_feature() {
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("BEACON_URL", "https://api.drand.sh"), ("BEACON_MAX_AGE_SECS", "30")]));
+        assert_eq!(config.beacon_max_age, Duration::from_secs(30));
+        if cfg!(feature = "drand") {
+            assert!(errors.is_empty(), "{}", errors);
+            assert_eq!(config.beacon_url.as_deref(), Some("https://api.drand.sh"));
+        } else {
+            assert!(errors.to_string().contains("`drand` feature"), "{}", errors);
+        }
+    }
+
+    #[test]
+    fn test_outcome_hook_command() {
+        let (config, _) = NodeConfig::from_lookup(lookup(&[("OUTCOME_HOOK_COMMAND", "  ")]));
+        assert!(config.outcome_hook_command.is_none());
+
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("OUTCOME_HOOK_COMMAND", "cat > /dev/null")]));
+        assert!(errors.is_empty(), "{}", errors);
+        assert_eq!(config.outcome_hook_command.as_deref(), Some("cat > /dev/null"));
+        assert!(config.load_engine().is_ok());
+    }
+
+    #[test]
+    fn test_unloadable_key_file_is_descriptive() {
+        let (config, errors) = NodeConfig::from_lookup(lookup(&[("NODE_KEY_FILE", "/nonexistent/vfnode.key")]));
+        assert!(errors.is_empty());
+
+        let err = config.load_engine().err().expect("missing key file must fail");
+        assert!(err.contains("NODE_KEY_FILE '/nonexistent/vfnode.key' could not be read"), "{}", err);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::canonical::CanonicalPayload;
+use crate::types::{CoinflipRequest, VfError};
+use sha2::{Digest, Sha256};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Bounded memory of recently flipped `(seed, timestamp)` tuples.
+///
+/// The same tuple always produces the same outcome, so letting it through twice would let a
+/// player preview a result and then resubmit it. Entries expire after `window`; past
+/// `capacity` the oldest entries are dropped first.
+pub struct RecentTuples {
+    window: Duration,
+    capacity: usize,
+    state: Mutex<RecentState>,
+}
+
+#[derive(Default)]
+struct RecentState {
+    keys: HashSet<[u8; 32]>,
+    order: VecDeque<([u8; 32], Instant)>,
+}
+
+impl RecentTuples {
+    pub const DEFAULT_CAPACITY: usize = 100_000;
+
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self { window, capacity: capacity.max(1), state: Mutex::new(RecentState::default()) }
+    }
+
+    /// Key a flip by the request inputs its transcript commits to, so neither another
+    /// player, choice or wager nor another encoding of the same seed bytes makes it new.
+    /// Fails only when `user_seed` doesn't decode under its `seed_encoding`.
+    pub fn key(req: &CoinflipRequest, node_seed: Option<&[u8; 32]>) -> Result<[u8; 32], VfError> {
+        let mut payload = CanonicalPayload::new(b"vf_dedup")
+            .decoded_bytes(b"user_seed", req.seed_bytes()?)
+            .u64(b"timestamp", req.timestamp);
+        // Each of these is a different outcome, not a replay
+        if let Some(client_seed) = &req.client_seed {
+            payload = payload.bytes(b"client_seed", client_seed.as_bytes());
+        }
+        if let Some(bet_id) = &req.bet_id {
+            payload = payload.bytes(b"bet_id", bet_id.as_bytes());
+        }
+        if let Some(node_seed) = node_seed {
+            payload = payload.bytes(b"node_seed", node_seed);
+        }
+        if req.free_play {
+            payload = payload.u64(b"free_play", 1);
+        }
+        Ok(Sha256::digest(payload.to_bytes()).into())
+    }
+
+    /// Record the tuple, returning false if it was already seen within the window
+    pub fn insert(&self, key: [u8; 32]) -> bool {
+        self.insert_at(key, Instant::now())
+    }
+
+    pub fn insert_at(&self, key: [u8; 32], now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        while let Some(&(oldest, seen_at)) = state.order.front() {
+            if now.saturating_duration_since(seen_at) < self.window && state.order.len() < self.capacity {
+                break;
+            }
+            state.order.pop_front();
+            state.keys.remove(&oldest);
+        }
+
+        if !state.keys.insert(key) {
+            return false;
+        }
+        state.order.push_back((key, now));
+        true
+    }
+
+    /// Forget a tuple whose flip failed, so the player can retry it
+    pub fn remove(&self, key: &[u8; 32]) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.keys.remove(key) {
+            state.order.retain(|(k, _)| k != key);
+        }
+    }
+}
+
+/// Bounded memory of bet ids already flipped.
+///
+/// A replayed request carrying the same `bet_id` would get the same proof and could be
+/// settled twice, so each id is accepted once. Ids never expire by time; past `capacity`
+/// the oldest ids are forgotten first.
+pub struct SeenBetIds {
+    capacity: usize,
+    state: Mutex<SeenState>,
+}
+
+#[derive(Default)]
+struct SeenState {
+    ids: HashSet<Uuid>,
+    order: VecDeque<Uuid>,
+}
+
+impl SeenBetIds {
+    pub const DEFAULT_CAPACITY: usize = 100_000;
+
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), state: Mutex::new(SeenState::default()) }
+    }
+
+    /// Record the id, returning false if it was already seen
+    pub fn insert(&self, bet_id: Uuid) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if !state.ids.insert(bet_id) {
+            return false;
+        }
+        state.order.push_back(bet_id);
+        while state.order.len() > self.capacity {
+            if let Some(oldest) = state.order.pop_front() {
+                state.ids.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    /// Forget an id whose flip failed, so the player can retry it
+    pub fn remove(&self, bet_id: &Uuid) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.ids.remove(bet_id) {
+            state.order.retain(|id| id != bet_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry_and_capacity() {
+        let recent = RecentTuples::new(Duration::from_secs(10), 2);
+        let start = Instant::now();
+
+        assert!(recent.insert_at([1; 32], start));
+        assert!(!recent.insert_at([1; 32], start + Duration::from_secs(9)));
+        assert!(recent.insert_at([1; 32], start + Duration::from_secs(10)));
+
+        // Capacity evicts the oldest entry
+        assert!(recent.insert_at([2; 32], start + Duration::from_secs(11)));
+        assert!(recent.insert_at([3; 32], start + Duration::from_secs(11)));
+        assert!(recent.insert_at([1; 32], start + Duration::from_secs(11)));
+    }
+
+    #[test]
+    fn test_seen_bet_ids_evict_oldest_past_capacity() {
+        let seen = SeenBetIds::new(2);
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+
+        assert!(seen.insert(ids[0]));
+        assert!(!seen.insert(ids[0]));
+        assert!(seen.insert(ids[1]));
+        assert!(seen.insert(ids[2])); // Evicts ids[0]
+        assert!(seen.insert(ids[0]));
+        assert!(!seen.insert(ids[2]));
+
+        seen.remove(&ids[2]);
+        assert!(seen.insert(ids[2]));
+    }
+}
@@ -0,0 +1,140 @@
+use crate::beacon::{BeaconRound, EntropyBeacon};
+use crate::types::VfError;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// [`EntropyBeacon`] backed by a drand HTTP relay (`BEACON_URL`).
+///
+/// A background task polls `{url}/public/latest` and caches the newest round, so `latest`
+/// never touches the network. The relay isn't trusted for the value: every proof names its
+/// round, which anyone can check against the chain's public history. If no new round has
+/// arrived within `max_age`, flips fail until one does, rather than reusing stale entropy.
+pub struct DrandBeacon {
+    url: String,
+    max_age: Duration,
+    // Newest round seen and when it first arrived
+    current: RwLock<Option<(BeaconRound, Instant)>>,
+}
+
+impl DrandBeacon {
+    /// How often the relay is asked for a new round
+    const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+    const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+    pub fn new(url: &str, max_age: Duration) -> Self {
+        Self { url: url.trim_end_matches('/').to_string(), max_age, current: RwLock::new(None) }
+    }
+
+    /// Start polling `url` on the current Tokio runtime. Flips fail until the first round arrives.
+    pub fn start(url: &str, max_age: Duration) -> Result<Arc<Self>, VfError> {
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|_| VfError::InvalidInput("The drand beacon needs a Tokio runtime".to_string()))?;
+        let client = reqwest::Client::builder()
+            .timeout(Self::HTTP_TIMEOUT)
+            .build()
+            .map_err(|e| VfError::InvalidInput(format!("Cannot build drand client: {}", e)))?;
+        let beacon = Arc::new(Self::new(url, max_age));
+
+        // Holds only a weak handle, so the task ends once the engine is gone
+        let polled = Arc::downgrade(&beacon);
+        runtime.spawn(async move {
+            let mut interval = tokio::time::interval(Self::POLL_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let Some(beacon) = polled.upgrade() else { return };
+                match beacon.fetch(&client).await {
+                    Ok(round) => beacon.observe(round),
+                    Err(e) => tracing::warn!(error = %e, url = %beacon.url, "Failed to fetch drand round"),
+                }
+            }
+        });
+        Ok(beacon)
+    }
+
+    async fn fetch(&self, client: &reqwest::Client) -> Result<BeaconRound, VfError> {
+        let body = client
+            .get(format!("{}/public/latest", self.url))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| VfError::VrfFailed(format!("drand relay request failed: {}", e)))?
+            .text()
+            .await
+            .map_err(|e| VfError::VrfFailed(format!("drand relay response unreadable: {}", e)))?;
+        Self::parse_round(&body)
+    }
+
+    /// A relay's `{"round": …, "randomness": "<hex>", …}` response
+    pub fn parse_round(body: &str) -> Result<BeaconRound, VfError> {
+        #[derive(serde::Deserialize)]
+        struct Latest {
+            round: u64,
+            randomness: String,
+        }
+
+        let latest: Latest = serde_json::from_str(body)
+            .map_err(|e| VfError::VrfFailed(format!("Malformed drand round: {}", e)))?;
+        let randomness = hex::decode(&latest.randomness)
+            .ok()
+            .filter(|bytes| bytes.len() == 32)
+            .ok_or_else(|| VfError::VrfFailed("drand randomness must be 32 hex-encoded bytes".to_string()))?;
+        Ok(BeaconRound { round: latest.round, randomness })
+    }
+
+    /// Cache a fetched round; an old or repeated round doesn't count as fresh
+    fn observe(&self, round: BeaconRound) {
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+        if current.as_ref().is_none_or(|(newest, _)| round.round > newest.round) {
+            *current = Some((round, Instant::now()));
+        }
+    }
+}
+
+impl EntropyBeacon for DrandBeacon {
+    fn latest(&self) -> Result<BeaconRound, VfError> {
+        let current = self.current.read().unwrap_or_else(|e| e.into_inner());
+        match current.as_ref() {
+            Some((round, seen)) if seen.elapsed() <= self.max_age => Ok(round.clone()),
+            Some((round, _)) => Err(VfError::VrfFailed(format!("drand round {} is stale", round.round))),
+            None => Err(VfError::VrfFailed("No drand round fetched yet".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round(round: u64) -> BeaconRound {
+        BeaconRound { round, randomness: vec![round as u8; 32] }
+    }
+
+    #[test]
+    fn test_parse_relay_response() {
+        let body = format!(r#"{{"round":42,"randomness":"{}","signature":"ab"}}"#, "07".repeat(32));
+        assert_eq!(DrandBeacon::parse_round(&body).unwrap(), BeaconRound { round: 42, randomness: vec![7; 32] });
+
+        assert!(DrandBeacon::parse_round(r#"{"round":42,"randomness":"07"}"#).is_err());
+        assert!(DrandBeacon::parse_round(r#"{"round":42}"#).is_err());
+    }
+
+    #[test]
+    fn test_only_newer_rounds_stay_fresh() {
+        let beacon = DrandBeacon::new("https://relay.invalid/", Duration::from_millis(200));
+        assert!(beacon.latest().is_err());
+
+        beacon.observe(round(5));
+        assert_eq!(beacon.latest().unwrap().round, 5);
+
+        // A relay stuck on the same round goes stale; an older one is ignored
+        std::thread::sleep(Duration::from_millis(250));
+        beacon.observe(round(5));
+        beacon.observe(round(4));
+        assert!(beacon.latest().is_err());
+
+        beacon.observe(round(6));
+        assert_eq!(beacon.latest().unwrap().round, 6);
+    }
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Observed gap between client request timestamps and the node clock.
+///
+/// Purely diagnostic: a drifting client is logged and counted so broken integrations show
+/// up, but its bet is still processed.
+pub struct ClockDrift {
+    bound: Duration,
+    checked: AtomicU64,
+    exceeded: AtomicU64,
+    max_observed_secs: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockDriftStats {
+    pub bound_secs: u64,
+    pub checked: u64,
+    pub exceeded: u64,
+    pub max_observed_secs: u64,
+}
+
+impl ClockDrift {
+    pub fn new(bound: Duration) -> Self {
+        Self {
+            bound,
+            checked: AtomicU64::new(0),
+            exceeded: AtomicU64::new(0),
+            max_observed_secs: AtomicU64::new(0),
+        }
+    }
+
+    /// Record one request; returns the drift in seconds (positive when the client is behind)
+    pub fn record(&self, request_timestamp: u64, server_timestamp: u64) -> i64 {
+        let drift = server_timestamp as i64 - request_timestamp as i64;
+        let magnitude = drift.unsigned_abs();
+
+        self.checked.fetch_add(1, Ordering::Relaxed);
+        self.max_observed_secs.fetch_max(magnitude, Ordering::Relaxed);
+        if magnitude > self.bound.as_secs() {
+            self.exceeded.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(
+                request_timestamp,
+                server_timestamp,
+                drift_secs = drift,
+                bound_secs = self.bound.as_secs(),
+                "Client clock drift exceeds bound"
+            );
+        }
+        drift
+    }
+
+    pub fn stats(&self) -> ClockDriftStats {
+        ClockDriftStats {
+            bound_secs: self.bound.as_secs(),
+            checked: self.checked.load(Ordering::Relaxed),
+            exceeded: self.exceeded.load(Ordering::Relaxed),
+            max_observed_secs: self.max_observed_secs.load(Ordering::Relaxed),
+        }
+    }
+}
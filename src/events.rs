@@ -0,0 +1,215 @@
+use crate::games::{GameRequest, GameResponse, DEFAULT_GAME};
+use crate::types::{CoinflipRequest, CoinflipResponse, NATIVE_TOKEN_MINT};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::JoinHandle;
+use uuid::Uuid;
+
+/// Value of [`OutcomeEventV1::version`]
+pub const OUTCOME_EVENT_VERSION: u32 = 1;
+
+/// One flip outcome as published on the outcome feed.
+///
+/// This is a wire contract for downstream consumers. It only evolves additively: new fields
+/// are optional and defaulted, so V1 parsers keep working and should ignore keys they don't
+/// know. Renaming, removing or retyping a field means a new `OutcomeEventV2` with its own
+/// `version`, never an edit here. User seeds are deliberately not part of the event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutcomeEventV1 {
+    pub version: u32,
+    pub node_id: String,
+    pub heads: bool, // Coin side for coinflips; false for every other game
+    pub timestamp: u64, // Request timestamp the outcome was derived for
+    pub seed_commitment: String,
+    pub vrf_output: String,
+    pub signature: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bet_id: Option<Uuid>, // Settlement id, when the bet was queued for settlement
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_mint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wager_lamports: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payout_lamports: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub free_play: bool, // Demo flip: no settlement, no payout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game: Option<String>, // Set for games other than coinflip
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<serde_json::Value>, // The game's `/play` outcome, alongside `game`
+}
+
+impl OutcomeEventV1 {
+    pub fn new(bet_id: Option<Uuid>, request: &CoinflipRequest, response: &CoinflipResponse) -> Self {
+        Self {
+            version: OUTCOME_EVENT_VERSION,
+            node_id: response.node_id.clone(),
+            heads: response.heads,
+            timestamp: request.timestamp,
+            seed_commitment: response.proof.seed_commitment.clone(),
+            vrf_output: response.proof.vrf_output.clone(),
+            signature: response.proof.signature.clone(),
+            bet_id,
+            token_mint: request.wager_lamports.map(|_| {
+                request.token_mint.clone().unwrap_or_else(|| NATIVE_TOKEN_MINT.to_string())
+            }),
+            wager_lamports: request.wager_lamports,
+            payout_lamports: response.payout_lamports,
+            free_play: request.free_play,
+            game: None,
+            outcome: None,
+        }
+    }
+
+    /// Event for a `/play/{game}` outcome; a coinflip publishes the same event as `/coinflip`
+    pub fn for_game(bet_id: Option<Uuid>, request: &GameRequest, response: &GameResponse) -> Self {
+        let coinflip = response.game == DEFAULT_GAME;
+        Self {
+            version: OUTCOME_EVENT_VERSION,
+            node_id: response.node_id.clone(),
+            heads: response.outcome["heads"].as_bool().unwrap_or(false),
+            timestamp: request.bet.timestamp,
+            seed_commitment: response.proof.seed_commitment.clone(),
+            vrf_output: response.proof.vrf_output.clone(),
+            signature: response.proof.signature.clone(),
+            bet_id,
+            token_mint: request.bet.wager_lamports.map(|_| {
+                request.bet.token_mint.clone().unwrap_or_else(|| NATIVE_TOKEN_MINT.to_string())
+            }),
+            wager_lamports: request.bet.wager_lamports,
+            payout_lamports: response.outcome["payout_lamports"].as_u64(),
+            free_play: request.bet.free_play,
+            game: (!coinflip).then(|| response.game.clone()),
+            outcome: (!coinflip).then(|| response.outcome.clone()),
+        }
+    }
+}
+
+/// Newline-delimited JSON feed of [`OutcomeEventV1`]s appended to a file (`OUTCOME_FEED_FILE`).
+///
+/// Events are written by a dedicated thread so publishing never blocks a flip; if the writer
+/// falls `capacity` events behind, new events are dropped with a warning.
+pub struct OutcomeFeed {
+    sender: Option<SyncSender<OutcomeEventV1>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl OutcomeFeed {
+    pub const DEFAULT_CAPACITY: usize = 10_000;
+
+    pub fn open(path: &Path, capacity: usize) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+        let writer = std::thread::Builder::new()
+            .name("outcome-feed".to_string())
+            .spawn(move || write_events(receiver, BufWriter::new(file)))?;
+        Ok(Self { sender: Some(sender), writer: Some(writer) })
+    }
+
+    pub fn publish(&self, event: OutcomeEventV1) {
+        let Some(sender) = &self.sender else { return };
+        match sender.try_send(event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(event)) => {
+                tracing::warn!(bet_id = ?event.bet_id, "Outcome feed writer is behind, dropping event");
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                tracing::warn!("Outcome feed writer has stopped, dropping event");
+            }
+        }
+    }
+}
+
+impl Drop for OutcomeFeed {
+    /// Close the channel and wait for queued events to reach the file
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+fn write_events(receiver: Receiver<OutcomeEventV1>, mut out: BufWriter<File>) {
+    while let Ok(event) = receiver.recv() {
+        let mut result = write_line(&mut out, &event);
+        // Drain whatever else is queued before paying for a flush
+        while let (Ok(()), Ok(event)) = (&result, receiver.try_recv()) {
+            result = write_line(&mut out, &event);
+        }
+        if let Err(e) = result.and_then(|_| out.flush()) {
+            tracing::error!(error = %e, "Failed to write outcome feed");
+        }
+    }
+}
+
+fn write_line(out: &mut impl Write, event: &OutcomeEventV1) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *out, event)?;
+    out.write_all(b"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VrfEngine;
+
+    #[test]
+    fn test_feed_events_parse_as_v1() {
+        let path = std::env::temp_dir().join(format!("vfnode-outcome-feed-{}.ndjson", Uuid::new_v4()));
+        let engine = VrfEngine::new();
+        let req = CoinflipRequest {
+            user_seed: "feed".to_string(),
+            timestamp: 1234567890,
+            wager_lamports: Some(1000),
+            ..Default::default()
+        };
+        let response = engine.process_coinflip(&req).unwrap();
+        let bet_id = Uuid::new_v4();
+
+        let feed = OutcomeFeed::open(&path, OutcomeFeed::DEFAULT_CAPACITY).unwrap();
+        feed.publish(OutcomeEventV1::new(Some(bet_id), &req, &response));
+        drop(feed);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let line = contents.lines().next().unwrap();
+
+        let raw: serde_json::Value = serde_json::from_str(line).unwrap();
+        for field in ["version", "node_id", "heads", "timestamp", "seed_commitment", "vrf_output", "signature"] {
+            assert!(raw.get(field).is_some(), "missing required field {}", field);
+        }
+
+        let event: OutcomeEventV1 = serde_json::from_str(line).unwrap();
+        assert_eq!(event.version, OUTCOME_EVENT_VERSION);
+        assert_eq!(event.bet_id, Some(bet_id));
+        assert_eq!(event.heads, response.heads);
+        assert_eq!(event.signature, response.proof.signature);
+        assert_eq!(event.token_mint.as_deref(), Some(NATIVE_TOKEN_MINT));
+        assert!(response.payout_lamports.is_some());
+        assert_eq!(event.payout_lamports, response.payout_lamports);
+    }
+
+    #[test]
+    fn test_game_events_match_coinflip_events() {
+        let engine = VrfEngine::from_seed([12u8; 32]);
+        let games = crate::games::GameRegistry::default();
+        let request: GameRequest = serde_json::from_str(
+            r#"{"user_seed": "feed", "timestamp": 1234567890, "wager_lamports": 1000, "params": {"target": 2}}"#,
+        )
+        .unwrap();
+
+        let played = games.get("coinflip").unwrap().play(&engine, &request).unwrap();
+        let flipped = engine.process_coinflip(&request.bet).unwrap();
+        assert_eq!(OutcomeEventV1::for_game(None, &request, &played), OutcomeEventV1::new(None, &request.bet, &flipped));
+
+        let rolled = games.get("dice").unwrap().play(&engine, &request).unwrap();
+        let event = OutcomeEventV1::for_game(None, &request, &rolled);
+        assert_eq!(event.game.as_deref(), Some("dice"));
+        assert_eq!(event.outcome.as_ref().unwrap()["roll"], rolled.outcome["roll"]);
+        assert_eq!(event.signature, rolled.proof.signature);
+        assert!(!event.heads);
+    }
+}
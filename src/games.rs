@@ -0,0 +1,193 @@
+use crate::types::{CoinflipRequest, DiceRequest, VfError, VrfProof};
+use crate::vrf_engine::VrfEngine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A game playable through `POST /play/{game}`.
+///
+/// Games share request validation, the VRF transcript and the proof format; each only
+/// decides how the signed randomness becomes its outcome.
+pub trait Game: Send + Sync {
+    /// Path segment the game is served under
+    fn name(&self) -> &'static str;
+
+    fn play(&self, engine: &VrfEngine, req: &GameRequest) -> Result<GameResponse, VfError>;
+}
+
+/// Request for any game: the common bet fields plus game-specific `params`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameRequest {
+    #[serde(flatten)]
+    pub bet: CoinflipRequest,
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub params: serde_json::Value,
+}
+
+/// Result of any game: the common proof plus a game-specific `outcome`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameResponse {
+    pub game: String,
+    pub node_id: String,
+    pub proof: VrfProof,
+    pub timestamp: u64, // Unix timestamp
+    pub processing_time_ms: u64,
+    pub outcome: serde_json::Value,
+}
+
+/// Game recorded for bets stored before bets named their game
+pub const DEFAULT_GAME: &str = "coinflip";
+
+/// Games by name
+#[derive(Clone)]
+pub struct GameRegistry {
+    games: HashMap<&'static str, Arc<dyn Game>>,
+}
+
+impl GameRegistry {
+    pub fn empty() -> Self {
+        Self { games: HashMap::new() }
+    }
+
+    pub fn register(&mut self, game: Arc<dyn Game>) {
+        self.games.insert(game.name(), game);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Game>> {
+        self.games.get(name).cloned()
+    }
+
+    /// Registered game names, sorted
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names: Vec<_> = self.games.keys().copied().collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+impl Default for GameRegistry {
+    /// Every built-in game
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(Arc::new(Coinflip));
+        registry.register(Arc::new(Dice));
+        registry
+    }
+}
+
+/// Move the fields every game shares out of a game's own response, leaving its `outcome`
+fn outcome_of<T: Serialize>(game: &str, response: &T) -> Result<serde_json::Value, VfError> {
+    let mut outcome = serde_json::to_value(response)
+        .map_err(|e| VfError::VrfFailed(format!("Cannot encode {} outcome: {}", game, e)))?;
+    // Common fields live on the GameResponse itself
+    if let Some(fields) = outcome.as_object_mut() {
+        for common in ["node_id", "proof", "timestamp", "processing_time_ms"] {
+            fields.remove(common);
+        }
+    }
+    Ok(outcome)
+}
+
+/// The original game; `outcome` carries the same fields as a `/coinflip` response
+pub struct Coinflip;
+
+impl Game for Coinflip {
+    fn name(&self) -> &'static str {
+        "coinflip"
+    }
+
+    fn play(&self, engine: &VrfEngine, req: &GameRequest) -> Result<GameResponse, VfError> {
+        let response = engine.process_coinflip(&req.bet)?;
+        let outcome = outcome_of(self.name(), &response)?;
+
+        Ok(GameResponse {
+            game: self.name().to_string(),
+            node_id: response.node_id,
+            proof: response.proof,
+            timestamp: response.timestamp,
+            processing_time_ms: response.processing_time_ms,
+            outcome,
+        })
+    }
+}
+
+/// Game-specific `params` for dice
+#[derive(Debug, Deserialize)]
+struct DiceParams {
+    target: u8,
+}
+
+/// A single die; `params` names the `target` face and `outcome` carries the `roll` and `win`
+pub struct Dice;
+
+impl Game for Dice {
+    fn name(&self) -> &'static str {
+        "dice"
+    }
+
+    fn play(&self, engine: &VrfEngine, req: &GameRequest) -> Result<GameResponse, VfError> {
+        let params: DiceParams = serde_json::from_value(req.params.clone())
+            .map_err(|e| VfError::InvalidInput(format!("Invalid dice params: {}", e)))?;
+        let response = engine.process_dice(&DiceRequest {
+            user_seed: req.bet.user_seed.clone(),
+            timestamp: req.bet.timestamp,
+            target: params.target,
+        })?;
+        let outcome = outcome_of(self.name(), &response)?;
+
+        Ok(GameResponse {
+            game: self.name().to_string(),
+            node_id: response.node_id,
+            proof: response.proof,
+            timestamp: response.timestamp,
+            processing_time_ms: response.processing_time_ms,
+            outcome,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coinflip_game_matches_direct_flip() {
+        let engine = VrfEngine::from_seed([10u8; 32]);
+        let request: GameRequest =
+            serde_json::from_str(r#"{"user_seed": "game", "timestamp": 1700000000, "player_choice": "tails"}"#).unwrap();
+        let games = GameRegistry::default();
+        assert_eq!(games.names(), vec!["coinflip", "dice"]);
+        assert!(games.get("roulette").is_none());
+
+        let played = games.get("coinflip").unwrap().play(&engine, &request).unwrap();
+        let direct = engine.process_coinflip(&request.bet).unwrap();
+
+        assert_eq!(played.game, "coinflip");
+        assert_eq!(played.proof.signature, direct.proof.signature);
+        assert_eq!(played.outcome["heads"], direct.heads);
+        assert_eq!(played.outcome["win"], direct.win);
+        assert!(played.outcome.get("proof").is_none());
+        assert!(engine.verify_proof(&played.proof, &request.bet).unwrap());
+    }
+
+    #[test]
+    fn test_dice_game_matches_direct_roll() {
+        let engine = VrfEngine::from_seed([11u8; 32]);
+        let request: GameRequest =
+            serde_json::from_str(r#"{"user_seed": "die", "timestamp": 1700000000, "params": {"target": 4}}"#).unwrap();
+        let dice = GameRegistry::default().get("dice").unwrap();
+
+        let played = dice.play(&engine, &request).unwrap();
+        let direct = engine
+            .process_dice(&DiceRequest { user_seed: "die".to_string(), timestamp: 1700000000, target: 4 })
+            .unwrap();
+
+        assert_eq!(played.game, "dice");
+        assert_eq!(played.proof.signature, direct.proof.signature);
+        assert_eq!(played.outcome["roll"], direct.roll);
+        assert_eq!(played.outcome["win"], direct.win);
+
+        let missing: GameRequest = serde_json::from_str(r#"{"user_seed": "die", "timestamp": 1700000000}"#).unwrap();
+        assert!(matches!(dice.play(&engine, &missing), Err(VfError::InvalidInput(_))));
+    }
+}
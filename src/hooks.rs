@@ -0,0 +1,288 @@
+use crate::types::{CoinflipRequest, CoinflipResponse, VfError};
+use std::io::Write;
+use std::os::unix::process::CommandExt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Operator logic run after every successful flip (loyalty points, jackpot triggers, ...).
+///
+/// Hooks see copies of the request and response after the response is final, so they can't
+/// change an outcome. They run on a dedicated thread, never on the flip path; an error or
+/// panic is logged and the remaining hooks still run.
+pub trait PostOutcomeHook: Send + Sync {
+    /// Name used in logs when the hook fails
+    fn name(&self) -> &str;
+
+    fn on_outcome(&self, request: &CoinflipRequest, response: &CoinflipResponse) -> Result<(), VfError>;
+}
+
+/// Background dispatcher for post-outcome hooks; register it with [`crate::VrfEngine::with_hooks`].
+///
+/// If the hook thread falls `capacity` outcomes behind, new outcomes are dropped with a
+/// warning rather than slowing flips down.
+pub struct OutcomeHooks {
+    sender: Option<SyncSender<(CoinflipRequest, CoinflipResponse)>>,
+    runner: Option<JoinHandle<()>>,
+}
+
+impl OutcomeHooks {
+    pub const DEFAULT_CAPACITY: usize = 10_000;
+
+    /// Longest shutdown waits for queued outcomes to finish running
+    const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+    pub fn start(hooks: Vec<Arc<dyn PostOutcomeHook>>, capacity: usize) -> std::io::Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+        let runner = std::thread::Builder::new()
+            .name("outcome-hooks".to_string())
+            .spawn(move || run_hooks(receiver, hooks))?;
+        Ok(Self { sender: Some(sender), runner: Some(runner) })
+    }
+
+    pub fn dispatch(&self, request: &CoinflipRequest, response: &CoinflipResponse) {
+        let Some(sender) = &self.sender else { return };
+        match sender.try_send((request.clone(), response.clone())) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                tracing::warn!("Outcome hooks are behind, dropping outcome");
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                tracing::warn!("Outcome hook thread has stopped, dropping outcome");
+            }
+        }
+    }
+}
+
+impl Drop for OutcomeHooks {
+    /// Close the channel and let queued outcomes finish running, for up to `DRAIN_TIMEOUT`.
+    /// A hook still stuck after that is left behind rather than holding up shutdown.
+    fn drop(&mut self) {
+        self.sender.take();
+        let Some(runner) = self.runner.take() else { return };
+        let deadline = Instant::now() + Self::DRAIN_TIMEOUT;
+        while !runner.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        if runner.is_finished() {
+            let _ = runner.join();
+        } else {
+            tracing::warn!("Outcome hooks still running at shutdown, leaving them behind");
+        }
+    }
+}
+
+/// Hands each outcome to an operator program (`OUTCOME_HOOK_COMMAND`), so custom logic can
+/// run without building against the crate.
+///
+/// The command runs through `sh -c` once, at startup, and reads one JSON object per line on
+/// stdin: `{"request": …, "response": …}`. If it exits, the outcome that found it gone is
+/// reported as failed and the command is started again for the next one. On shutdown its
+/// stdin is closed, and a command that hasn't exited within `EXIT_TIMEOUT` is killed.
+pub struct CommandHook {
+    command: String,
+    process: Mutex<Option<(Child, ChildStdin)>>,
+}
+
+impl CommandHook {
+    /// How long a command gets to exit once its stdin closes
+    const EXIT_TIMEOUT: Duration = Duration::from_secs(3);
+
+    pub fn spawn(command: &str) -> std::io::Result<Self> {
+        let process = Self::start(command)?;
+        Ok(Self { command: command.to_string(), process: Mutex::new(Some(process)) })
+    }
+
+    fn start(command: &str) -> std::io::Result<(Child, ChildStdin)> {
+        // Its own process group, so everything the shell starts can be killed together
+        let mut child = Command::new("sh").arg("-c").arg(command).stdin(Stdio::piped()).process_group(0).spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        Ok((child, stdin))
+    }
+
+    /// Close the command's stdin and reap it, killing it if it outlives `EXIT_TIMEOUT`
+    fn stop(mut child: Child, stdin: ChildStdin) {
+        drop(stdin);
+        let deadline = Instant::now() + Self::EXIT_TIMEOUT;
+        while matches!(child.try_wait(), Ok(None)) {
+            if Instant::now() >= deadline {
+                tracing::warn!(pid = child.id(), "Hook command ignored end of input, killing it");
+                // SAFETY: kill(2) only reads its arguments; the group id is the child's pid
+                unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let _ = child.wait();
+    }
+}
+
+impl PostOutcomeHook for CommandHook {
+    fn name(&self) -> &str {
+        "command"
+    }
+
+    fn on_outcome(&self, request: &CoinflipRequest, response: &CoinflipResponse) -> Result<(), VfError> {
+        let mut line = serde_json::to_vec(&serde_json::json!({ "request": request, "response": response }))
+            .map_err(|e| VfError::Hook(format!("Outcome can't be encoded: {}", e)))?;
+        line.push(b'\n');
+
+        let mut process = self.process.lock().unwrap_or_else(|e| e.into_inner());
+        if process.is_none() {
+            *process = Some(Self::start(&self.command)
+                .map_err(|e| VfError::Hook(format!("Hook command can't be restarted: {}", e)))?);
+        }
+        let (_, stdin) = process.as_mut().expect("started above");
+        if let Err(e) = stdin.write_all(&line).and_then(|_| stdin.flush()) {
+            // Gone, most likely; reap it and start afresh next time
+            if let Some((child, stdin)) = process.take() {
+                Self::stop(child, stdin);
+            }
+            return Err(VfError::Hook(format!("Hook command stopped reading outcomes: {}", e)));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CommandHook {
+    fn drop(&mut self) {
+        let process = self.process.get_mut().unwrap_or_else(|e| e.into_inner());
+        if let Some((child, stdin)) = process.take() {
+            Self::stop(child, stdin);
+        }
+    }
+}
+
+fn run_hooks(receiver: Receiver<(CoinflipRequest, CoinflipResponse)>, hooks: Vec<Arc<dyn PostOutcomeHook>>) {
+    while let Ok((request, response)) = receiver.recv() {
+        for hook in &hooks {
+            match catch_unwind(AssertUnwindSafe(|| hook.on_outcome(&request, &response))) {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::error!(hook = hook.name(), error = %e, "Outcome hook failed"),
+                Err(_) => tracing::error!(hook = hook.name(), "Outcome hook panicked"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VrfEngine;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    struct Counting {
+        calls: AtomicU32,
+        seen: Mutex<mpsc::Sender<bool>>,
+    }
+
+    impl PostOutcomeHook for Counting {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn on_outcome(&self, _request: &CoinflipRequest, response: &CoinflipResponse) -> Result<(), VfError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let _ = self.seen.lock().unwrap().send(response.heads);
+            Ok(())
+        }
+    }
+
+    struct Failing;
+
+    impl PostOutcomeHook for Failing {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn on_outcome(&self, _request: &CoinflipRequest, _response: &CoinflipResponse) -> Result<(), VfError> {
+            panic!("jackpot service unreachable");
+        }
+    }
+
+    #[test]
+    fn test_hooks_run_once_per_flip_and_failures_are_isolated() {
+        let (tx, rx) = mpsc::channel();
+        let counting = Arc::new(Counting { calls: AtomicU32::new(0), seen: Mutex::new(tx) });
+        // The failing hook runs first, so the counting hook also proves later hooks still run
+        let hooks = OutcomeHooks::start(vec![Arc::new(Failing), counting.clone()], OutcomeHooks::DEFAULT_CAPACITY).unwrap();
+        let engine = VrfEngine::new().with_hooks(hooks);
+
+        let mut outcomes = Vec::new();
+        for i in 0..3 {
+            let req = CoinflipRequest {
+                user_seed: format!("hook-{}", i),
+                timestamp: 1234567890,
+                ..Default::default()
+            };
+            let response = engine.process_coinflip(&req).expect("a failing hook must not break the flip");
+            assert!(engine.verify_proof(&response.proof, &req).unwrap());
+            outcomes.push(response.heads);
+        }
+
+        for heads in outcomes {
+            assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), heads);
+        }
+        drop(engine);
+        assert_eq!(counting.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_command_hook_receives_each_outcome() {
+        let path = std::env::temp_dir().join(format!("vfnode-hook-{}.ndjson", uuid::Uuid::new_v4()));
+        let hook = CommandHook::spawn(&format!("cat > '{}'", path.display())).unwrap();
+        let engine = VrfEngine::new().with_hooks(OutcomeHooks::start(vec![Arc::new(hook)], OutcomeHooks::DEFAULT_CAPACITY).unwrap());
+
+        let reqs: Vec<_> = (0..2)
+            .map(|i| CoinflipRequest { user_seed: format!("command-{}", i), timestamp: 1234567890, ..Default::default() })
+            .collect();
+        let responses: Vec<_> = reqs.iter().map(|req| engine.process_coinflip(req).unwrap()).collect();
+        // Dropping the engine drains the hooks and waits for the command to exit
+        drop(engine);
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let lines: Vec<serde_json::Value> = written.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        for ((line, req), response) in lines.iter().zip(&reqs).zip(&responses) {
+            assert_eq!(line["request"]["user_seed"], req.user_seed);
+            assert_eq!(line["response"]["heads"], response.heads);
+            assert_eq!(line["response"]["proof"]["signature"], response.proof.signature);
+        }
+    }
+
+    #[test]
+    fn test_command_ignoring_end_of_input_is_killed_on_shutdown() {
+        // The shell waits on a child of its own, which must not outlive the node either
+        let path = std::env::temp_dir().join(format!("vfnode-hook-{}.pid", uuid::Uuid::new_v4()));
+        let hook = CommandHook::spawn(&format!("sleep 3600 & echo $! > '{}'; wait", path.display())).unwrap();
+        let hooks = OutcomeHooks::start(vec![Arc::new(hook)], OutcomeHooks::DEFAULT_CAPACITY).unwrap();
+        let sleeper = loop {
+            match std::fs::read_to_string(&path).map(|pid| pid.trim().parse::<u32>()) {
+                Ok(Ok(pid)) => break pid,
+                _ => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        let _ = std::fs::remove_file(&path);
+
+        let start = Instant::now();
+        drop(hooks);
+        assert!(start.elapsed() < CommandHook::EXIT_TIMEOUT + Duration::from_secs(2), "took {:?}", start.elapsed());
+
+        // Gone, or a zombie awaiting its new parent
+        let running = || {
+            std::fs::read_to_string(format!("/proc/{}/stat", sleeper))
+                .is_ok_and(|stat| stat.rsplit(')').next().is_some_and(|rest| !rest.trim_start().starts_with('Z')))
+        };
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while running() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!running(), "sleep {} outlived the hook", sleeper);
+    }
+}
@@ -0,0 +1,351 @@
+use axum::extract::ConnectInfo;
+use axum::{Extension, Router};
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::sync::watch;
+use tokio_rustls::rustls;
+
+pub use tokio_rustls::TlsAcceptor;
+
+/// A client that hasn't finished the TLS handshake by then is dropped
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where the HTTP server listens: `ip:port`, or `unix:/path/to.sock` for a sidecar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl BindAddr {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.strip_prefix("unix:") {
+            Some(path) if !path.is_empty() => Some(BindAddr::Unix(PathBuf::from(path))),
+            Some(_) => None,
+            None => value.parse().ok().map(BindAddr::Tcp),
+        }
+    }
+}
+
+impl fmt::Display for BindAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindAddr::Tcp(addr) => write!(f, "{}", addr),
+            BindAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// A bound listener, ready for [`serve`]
+pub enum Listener {
+    Tcp(TcpListener),
+    Tls(TcpListener, TlsAcceptor),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    /// Bind `addr`. A socket file left behind by an earlier run is replaced; any other
+    /// file at that path is an error.
+    pub async fn bind(addr: &BindAddr) -> std::io::Result<Self> {
+        match addr {
+            BindAddr::Tcp(addr) => Ok(Listener::Tcp(TcpListener::bind(addr).await?)),
+            #[cfg(unix)]
+            BindAddr::Unix(path) => {
+                use std::os::unix::fs::FileTypeExt;
+                if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                    std::fs::remove_file(path)?;
+                }
+                Ok(Listener::Unix(UnixListener::bind(path)?, path.clone()))
+            }
+            #[cfg(not(unix))]
+            BindAddr::Unix(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Unix sockets are not supported on this platform",
+            )),
+        }
+    }
+
+    /// Bind `addr` and terminate TLS on every accepted connection. Only TCP addresses
+    /// can carry TLS.
+    pub async fn bind_tls(addr: &BindAddr, acceptor: TlsAcceptor) -> std::io::Result<Self> {
+        match addr {
+            BindAddr::Tcp(addr) => Ok(Listener::Tls(TcpListener::bind(addr).await?, acceptor)),
+            BindAddr::Unix(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "TLS is only served on TCP addresses",
+            )),
+        }
+    }
+}
+
+/// Build a TLS acceptor from a PEM certificate chain and a PEM private key (PKCS#8,
+/// PKCS#1 or SEC1). Errors name the file at fault.
+pub fn tls_acceptor(cert_path: &Path, key_path: &Path) -> Result<TlsAcceptor, String> {
+    let read = |path: &Path, key: &str| {
+        std::fs::File::open(path)
+            .map(std::io::BufReader::new)
+            .map_err(|e| format!("{} '{}' could not be read: {}", key, path.display(), e))
+    };
+
+    let certs = rustls_pemfile::certs(&mut read(cert_path, "TLS_CERT_PATH")?)
+        .map_err(|e| format!("TLS_CERT_PATH '{}' is not valid PEM: {}", cert_path.display(), e))?;
+    if certs.is_empty() {
+        return Err(format!("TLS_CERT_PATH '{}' contains no certificates", cert_path.display()));
+    }
+
+    let key = rustls_pemfile::read_all(&mut read(key_path, "TLS_KEY_PATH")?)
+        .map_err(|e| format!("TLS_KEY_PATH '{}' is not valid PEM: {}", key_path.display(), e))?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(rustls::PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| format!("TLS_KEY_PATH '{}' contains no private key", key_path.display()))?;
+
+    let mut config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs.into_iter().map(rustls::Certificate).collect(), key)
+        .map_err(|e| {
+            format!(
+                "TLS_CERT_PATH '{}' and TLS_KEY_PATH '{}' are not a usable pair: {}",
+                cert_path.display(),
+                key_path.display(),
+                e
+            )
+        })?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Serve `app` until `shutdown` resolves, then let in-flight requests finish
+pub async fn serve(
+    listener: Listener,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    match listener {
+        Listener::Tcp(listener) => {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                .with_graceful_shutdown(shutdown)
+                .await
+        }
+        Listener::Tls(listener, acceptor) => {
+            let accept = || listener.accept();
+            serve_http1(accept, shutdown, |(stream, peer): (TcpStream, SocketAddr), stopping| {
+                // Same peer address axum::serve hands plain TCP handlers
+                let app = app.clone().layer(Extension(ConnectInfo(peer)));
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let _ = stream.set_nodelay(true);
+                    match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => serve_connection(stream, app, stopping).await,
+                        Ok(Err(e)) => tracing::debug!(error = %e, "TLS handshake failed"),
+                        Err(_) => tracing::debug!("TLS handshake timed out"),
+                    }
+                });
+            })
+            .await;
+            Ok(())
+        }
+        #[cfg(unix)]
+        Listener::Unix(listener, path) => {
+            let accept = || async { listener.accept().await.map(|(stream, _)| stream) };
+            serve_http1(accept, shutdown, |stream, stopping| {
+                tokio::spawn(serve_connection(stream, app.clone(), stopping));
+            })
+            .await;
+            let _ = std::fs::remove_file(path);
+            Ok(())
+        }
+    }
+}
+
+/// Accept loop for listeners `axum::serve` can't drive. Every connection holds a receiver
+/// of `stopping`; on shutdown each is asked to finish its current request, and the sender
+/// closing tells us the last one is done.
+async fn serve_http1<S, A>(
+    mut accept: impl FnMut() -> A,
+    shutdown: impl Future<Output = ()>,
+    mut spawn: impl FnMut(S, watch::Receiver<bool>),
+) where
+    A: Future<Output = std::io::Result<S>>,
+{
+    let (stopping, connections) = watch::channel(false);
+    let mut shutdown = std::pin::pin!(shutdown);
+
+    loop {
+        let stream = tokio::select! {
+            accepted = accept() => match accepted {
+                Ok(stream) => stream,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to accept connection");
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+        spawn(stream, connections.clone());
+    }
+
+    drop(connections);
+    stopping.send_replace(true);
+    stopping.closed().await;
+}
+
+/// Serve HTTP/1.1 on one connection, finishing the current request once `stopping` flips
+async fn serve_connection<I>(io: I, app: Router, mut stopping: watch::Receiver<bool>)
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let connection = hyper::server::conn::http1::Builder::new()
+        .serve_connection(TokioIo::new(io), TowerToHyperService::new(app))
+        .with_upgrades();
+    let mut connection = std::pin::pin!(connection);
+    let finished = tokio::select! {
+        result = connection.as_mut() => Some(result),
+        _ = stopping.wait_for(|stop| *stop) => None,
+    };
+    let result = match finished {
+        Some(result) => result,
+        None => {
+            connection.as_mut().graceful_shutdown();
+            connection.await
+        }
+    };
+    if let Err(e) = result {
+        tracing::debug!(error = %e, "Connection closed with an error");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_bind_addr_parsing() {
+        assert_eq!(BindAddr::parse("127.0.0.1:8080"), Some(BindAddr::Tcp(([127, 0, 0, 1], 8080).into())));
+        assert_eq!(BindAddr::parse("[::1]:8080").map(|a| a.to_string()).as_deref(), Some("[::1]:8080"));
+        assert_eq!(BindAddr::parse("unix:/run/vfnode.sock"), Some(BindAddr::Unix("/run/vfnode.sock".into())));
+        assert_eq!(BindAddr::parse("unix:/run/vfnode.sock").unwrap().to_string(), "unix:/run/vfnode.sock");
+        for invalid in ["", "8080", "localhost:8080", "0.0.0.0", "unix:"] {
+            assert_eq!(BindAddr::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_serves_over_unix_socket() {
+        let path = std::env::temp_dir().join(format!("vfnode-{}.sock", uuid::Uuid::new_v4()));
+        let listener = Listener::bind(&BindAddr::Unix(path.clone())).await.unwrap();
+        let app = Router::new().route("/health", get(|| async { "ok" }));
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(listener, app, async {
+            let _ = stopped.await;
+        }));
+
+        let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        stream.write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("ok"), "{}", response);
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!path.exists(), "socket file is removed on shutdown");
+    }
+
+    /// Write a self-signed `localhost` certificate and its key, returning their paths
+    fn write_self_signed(dir: &Path) -> (PathBuf, PathBuf, rcgen::Certificate) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let (cert_path, key_path) = (dir.join("cert.pem"), dir.join("key.pem"));
+        std::fs::write(&cert_path, cert.serialize_pem().unwrap()).unwrap();
+        std::fs::write(&key_path, cert.serialize_private_key_pem()).unwrap();
+        (cert_path, key_path, cert)
+    }
+
+    #[test]
+    fn test_tls_acceptor_reports_bad_files() {
+        let dir = std::env::temp_dir().join(format!("vfnode-tls-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (cert_path, key_path, _) = write_self_signed(&dir);
+        assert!(tls_acceptor(&cert_path, &key_path).is_ok());
+
+        let missing = dir.join("missing.pem");
+        let err = tls_acceptor(&missing, &key_path).err().unwrap();
+        assert!(err.starts_with("TLS_CERT_PATH") && err.contains("could not be read"), "{}", err);
+
+        // Certificate and key swapped: neither file holds what it should
+        let err = tls_acceptor(&key_path, &key_path).err().unwrap();
+        assert!(err.contains("contains no certificates"), "{}", err);
+        let err = tls_acceptor(&cert_path, &cert_path).err().unwrap();
+        assert!(err.starts_with("TLS_KEY_PATH") && err.contains("contains no private key"), "{}", err);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_serves_over_tls() {
+        let dir = std::env::temp_dir().join(format!("vfnode-tls-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (cert_path, key_path, cert) = write_self_signed(&dir);
+        let acceptor = tls_acceptor(&cert_path, &key_path).unwrap();
+        let listener = Listener::bind_tls(&BindAddr::Tcp(([127, 0, 0, 1], 0).into()), acceptor).await.unwrap();
+        let Listener::Tls(tcp, _) = &listener else { unreachable!() };
+        let addr = tcp.local_addr().unwrap();
+        // Handlers see the client address, as they do on plain TCP
+        let app = Router::new().route(
+            "/health",
+            get(|ConnectInfo(peer): ConnectInfo<SocketAddr>| async move {
+                assert!(peer.ip().is_loopback());
+                "ok"
+            }),
+        );
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(listener, app, async {
+            let _ = stopped.await;
+        }));
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(&rustls::Certificate(cert.serialize_der().unwrap())).unwrap();
+        let client = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(client));
+        let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut stream = connector.connect("localhost".try_into().unwrap(), stream).await.unwrap();
+        stream.write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("ok"), "{}", response);
+
+        // Plain HTTP on the TLS port gets no response
+        let mut plain = tokio::net::TcpStream::connect(addr).await.unwrap();
+        plain.write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let mut response = Vec::new();
+        let _ = plain.read_to_end(&mut response).await;
+        assert!(!String::from_utf8_lossy(&response).contains("200"));
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
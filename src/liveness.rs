@@ -0,0 +1,235 @@
+use serde::Serialize;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+/// Last time a background task reported progress.
+pub struct Heartbeat {
+    epoch: Instant,
+    last_beat_ms: AtomicU64, // Millis since `epoch`
+}
+
+impl Heartbeat {
+    fn new() -> Self {
+        Self { epoch: Instant::now(), last_beat_ms: AtomicU64::new(0) }
+    }
+
+    pub fn beat(&self) {
+        let now = self.epoch.elapsed().as_millis() as u64;
+        self.last_beat_ms.store(now, Ordering::Relaxed);
+    }
+
+    pub fn age(&self) -> Duration {
+        let last = Duration::from_millis(self.last_beat_ms.load(Ordering::Relaxed));
+        self.epoch.elapsed().saturating_sub(last)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaskStatus {
+    pub name: &'static str,
+    pub running: bool,
+    pub healthy: bool, // Running and heartbeat within its threshold
+    pub last_heartbeat_ms_ago: u64,
+    pub stale_after_ms: u64,
+    pub restarts: u32,
+}
+
+type TaskFactory = Box<dyn Fn(Arc<Heartbeat>) -> JoinHandle<()> + Send + Sync>;
+
+struct Supervised {
+    name: &'static str,
+    stale_after: Duration,
+    heartbeat: Arc<Heartbeat>,
+    handle: JoinHandle<()>,
+    spawn: TaskFactory,
+    restarts: u32,
+}
+
+/// Keeps long-running background tasks alive and observable.
+///
+/// Each task gets a [`Heartbeat`] to beat on every iteration. A task whose future has
+/// finished (returned early or panicked) is respawned by [`Supervisor::check`]; one that is
+/// still running but hasn't beaten within `stale_after` is reported unhealthy.
+#[derive(Default)]
+pub struct Supervisor {
+    tasks: Mutex<Vec<Supervised>>,
+    stopping: AtomicBool, // Set by `shutdown`; finished tasks are no longer respawned
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn<F, Fut>(&self, name: &'static str, stale_after: Duration, task: F)
+    where
+        F: Fn(Arc<Heartbeat>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let spawn: TaskFactory = Box::new(move |heartbeat| tokio::spawn(task(heartbeat)));
+        let heartbeat = Arc::new(Heartbeat::new());
+        let handle = spawn(heartbeat.clone());
+
+        self.tasks.lock().unwrap_or_else(|e| e.into_inner()).push(Supervised {
+            name,
+            stale_after,
+            heartbeat,
+            handle,
+            spawn,
+            restarts: 0,
+        });
+    }
+
+    /// Respawn any task that has died, then report every task's status
+    pub fn check(&self) -> Vec<TaskStatus> {
+        let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        if self.stopping.load(Ordering::Relaxed) {
+            return tasks.iter().map(Self::status_of).collect();
+        }
+        for task in tasks.iter_mut().filter(|t| t.handle.is_finished()) {
+            task.restarts += 1;
+            error!(task = task.name, restarts = task.restarts, "💀 Background task died, restarting");
+            task.heartbeat.beat(); // Fresh grace period for the new run
+            task.handle = (task.spawn)(task.heartbeat.clone());
+        }
+        tasks.iter().map(Self::status_of).collect()
+    }
+
+    /// Current status of every task, without restarting anything
+    pub fn status(&self) -> Vec<TaskStatus> {
+        let tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        tasks.iter().map(Self::status_of).collect()
+    }
+
+    fn status_of(task: &Supervised) -> TaskStatus {
+        let age = task.heartbeat.age();
+        let running = !task.handle.is_finished();
+        let healthy = running && age <= task.stale_after;
+        if running && !healthy {
+            warn!(task = task.name, heartbeat_age_ms = age.as_millis() as u64, "Background task heartbeat is stale");
+        }
+        TaskStatus {
+            name: task.name,
+            running,
+            healthy,
+            last_heartbeat_ms_ago: age.as_millis() as u64,
+            stale_after_ms: task.stale_after.as_millis() as u64,
+            restarts: task.restarts,
+        }
+    }
+
+    /// Stop respawning tasks and give them up to `grace` to finish on their own; any still
+    /// running after that are aborted. Tasks are expected to watch their own stop signal.
+    pub async fn shutdown(&self, grace: Duration) {
+        self.stopping.store(true, Ordering::Relaxed);
+        let deadline = Instant::now() + grace;
+        while !self.all_finished() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        for task in tasks.iter().filter(|t| !t.handle.is_finished()) {
+            warn!(task = task.name, "Background task did not stop in time, aborting");
+            task.handle.abort();
+        }
+    }
+
+    fn all_finished(&self) -> bool {
+        let tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        tasks.iter().all(|t| t.handle.is_finished())
+    }
+
+    /// Run [`Supervisor::check`] every `period` for as long as the supervisor exists
+    pub fn start_watchdog(self: &Arc<Self>, period: Duration) {
+        let supervisor = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                let Some(supervisor) = supervisor.upgrade() else { break };
+                supervisor.check();
+            }
+        });
+    }
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        for task in self.tasks.get_mut().unwrap_or_else(|e| e.into_inner()) {
+            task.handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+
+    #[tokio::test]
+    async fn test_dead_task_is_detected_and_restarted() {
+        let supervisor = Supervisor::new();
+        let runs = Arc::new(AtomicU32::new(0));
+
+        // The first run dies right away; later runs keep beating
+        let task_runs = runs.clone();
+        supervisor.spawn("worker", Duration::from_millis(200), move |heartbeat| {
+            let run = task_runs.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if run == 0 {
+                    return;
+                }
+                loop {
+                    heartbeat.beat();
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let status = &supervisor.status()[0];
+        assert!(!status.running);
+        assert!(!status.healthy);
+
+        let status = &supervisor.check()[0];
+        assert!(status.running);
+        assert_eq!(status.restarts, 1);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let status = &supervisor.check()[0];
+        assert!(status.healthy, "{:?}", status);
+        assert_eq!(status.restarts, 1);
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_restarts_and_aborts_stragglers() {
+        let supervisor = Supervisor::new();
+        supervisor.spawn("quick", Duration::from_secs(1), |_heartbeat| async {});
+        supervisor.spawn("stuck", Duration::from_secs(1), |_heartbeat| std::future::pending());
+
+        supervisor.shutdown(Duration::from_millis(50)).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        // Neither is restarted once the supervisor is stopping
+        let statuses = supervisor.check();
+        assert!(statuses.iter().all(|status| !status.running && status.restarts == 0), "{:?}", statuses);
+    }
+
+    #[tokio::test]
+    async fn test_hung_task_is_reported_stale() {
+        let supervisor = Supervisor::new();
+        supervisor.spawn("stuck", Duration::from_millis(50), |_heartbeat| std::future::pending());
+
+        assert!(supervisor.status()[0].healthy);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let status = &supervisor.check()[0];
+        assert!(status.running);
+        assert!(!status.healthy);
+        assert_eq!(status.restarts, 0);
+    }
+}
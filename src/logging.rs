@@ -0,0 +1,105 @@
+use crate::types::CoinflipResponse;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use uuid::Uuid;
+
+/// Target of the per-bet outcome events, so pipelines can route them apart from other logs
+pub const OUTCOME_TARGET: &str = "vfnode::outcome";
+
+/// How log lines are written to stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable single lines
+    #[default]
+    Compact,
+    /// One JSON object per line, fields included, for log pipelines
+    Json,
+}
+
+impl LogFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogFormat::Compact => "compact",
+            LogFormat::Json => "json",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "compact" => Some(LogFormat::Compact),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Install the global subscriber. `RUST_LOG` filters as usual in either format.
+pub fn init(format: LogFormat) {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "vfnode=info,tower_http=info".into());
+    let registry = tracing_subscriber::registry().with(filter);
+    match format {
+        LogFormat::Compact => registry.with(tracing_subscriber::fmt::layer().with_target(false).compact()).init(),
+        LogFormat::Json => registry.with(tracing_subscriber::fmt::layer().json().flatten_event(true)).init(),
+    }
+}
+
+/// One structured event per served bet. `bet_id` is the settlement id when the bet was
+/// queued, else the request's own.
+pub fn log_outcome(bet_id: Option<Uuid>, response: &CoinflipResponse) {
+    tracing::info!(
+        target: OUTCOME_TARGET,
+        bet_id = bet_id.or(response.bet_id).map(tracing::field::display),
+        heads = response.heads,
+        win = response.win,
+        processing_time_ms = response.processing_time_ms,
+        node_id = %response.node_id,
+        free_play = response.free_play,
+        "Bet outcome"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_outcome_event_is_flat_json() {
+        let capture = CapturedLogs::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .flatten_event(true)
+            .with_writer(move || writer.clone())
+            .finish();
+
+        let bet_id = Uuid::new_v4();
+        let req = crate::CoinflipRequest { user_seed: "logged".to_string(), ..Default::default() };
+        let response = crate::VrfEngine::new().process_coinflip(&req).unwrap();
+        tracing::subscriber::with_default(subscriber, || log_outcome(Some(bet_id), &response));
+
+        let line = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        let event: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+        assert_eq!(event["target"], OUTCOME_TARGET);
+        assert_eq!(event["bet_id"], bet_id.to_string());
+        assert_eq!(event["heads"], response.heads);
+        assert_eq!(event["win"], response.win);
+        assert_eq!(event["processing_time_ms"], response.processing_time_ms);
+        assert_eq!(event["node_id"], response.node_id);
+    }
+}
@@ -1,56 +1,296 @@
-mod types;
-mod vrf_engine;
-
-use types::{CoinflipRequest, CoinflipResponse};
-use vrf_engine::VrfEngine;
+use vfnode::commit::{CommitStore, RevealError};
+use vfnode::config::NodeConfig;
+use vfnode::events::{OutcomeEventV1, OutcomeFeed};
+use vfnode::listen::{self, Listener};
+use vfnode::games::{GameRegistry, GameRequest, GameResponse};
+use vfnode::metrics::METRICS;
+use vfnode::tokens::TokenRegistry;
+use vfnode::throttle::{limit_clients, ClientRateLimiter, TokenBucket};
+use vfnode::types::{CoinflipBatchResult, CoinflipRequest, CoinflipResponse, CommitResponse, ReplayRequest, ReplayResult, RevealRequest, RevealResponse, TranscriptDebug, VerifyRequest, VerifyResult, VfError};
+use vfnode::verifier::VerifierPool;
+use vfnode::vrf_engine::VrfEngine;
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::Json,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    middleware,
+    response::{IntoResponse, Json},
     routing::{get, post},
     Router,
 };
 use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tower_http::{
-    cors::CorsLayer, 
     trace::TraceLayer,
     compression::CompressionLayer,
     timeout::TimeoutLayer,
+    limit::RequestBodyLimitLayer,
 };
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use std::time::Duration;
 
 #[derive(Clone)]
 struct AppState {
     vrf_engine: Arc<VrfEngine>,
+    // Node-wide flips-per-second ceiling (None = unlimited)
+    flip_limiter: Option<Arc<TokenBucket>>,
+    // Per-client-IP request rate, shared by every worker (None = unlimited)
+    client_limiter: Option<Arc<ClientRateLimiter>>,
+    // Concurrent coinflips (None = unlimited), sized independently of verification
+    flip_permits: Option<Arc<Semaphore>>,
+    verifier: Arc<VerifierPool>,
+    tokens: Arc<TokenRegistry>,
+    outcome_feed: Option<Arc<OutcomeFeed>>,
+    // Games served under /play/{game}
+    games: Arc<GameRegistry>,
+    // Node seeds committed by /commit, awaiting /reveal
+    commits: Arc<CommitStore>,
+    // Whether free-play flips are accepted
+    free_play: bool,
 }
 
+/// Largest batch accepted by `/verify/batch`
+const MAX_VERIFY_BATCH: usize = 1000;
+
+/// Largest batch accepted by `/coinflip/batch`
+const MAX_COINFLIP_BATCH: usize = 256;
+
 async fn coinflip(
     State(state): State<AppState>,
     Json(req): Json<CoinflipRequest>,
 ) -> Result<Json<CoinflipResponse>, StatusCode> {
+    flip(&state, req, None).await.map(Json).map_err(|(status, _)| status)
+}
+
+/// Flip many bets in one request. Each entry succeeds or fails on its own, and results come
+/// back in request order.
+async fn coinflip_batch(
+    State(state): State<AppState>,
+    Json(reqs): Json<Vec<CoinflipRequest>>,
+) -> Result<Json<Vec<CoinflipBatchResult>>, (StatusCode, String)> {
+    if reqs.len() > MAX_COINFLIP_BATCH {
+        return Err((StatusCode::BAD_REQUEST, format!("Batch exceeds {} flips", MAX_COINFLIP_BATCH)));
+    }
+
+    // Each flip runs on the blocking pool, so awaiting them together spreads the batch across it
+    let results = futures_util::future::join_all(reqs.into_iter().map(|req| flip(&state, req, None))).await;
+    Ok(Json(
+        results
+            .into_iter()
+            .map(|result| match result {
+                Ok(response) => CoinflipBatchResult { response: Some(response), error: None },
+                Err((_, error)) => CoinflipBatchResult { response: None, error: Some(error) },
+            })
+            .collect(),
+    ))
+}
+
+/// Play any registered game; `/coinflip` is the same as `/play/coinflip` with its original
+/// response shape
+async fn play(
+    State(state): State<AppState>,
+    Path(game): Path<String>,
+    Json(req): Json<GameRequest>,
+) -> Result<Json<GameResponse>, (StatusCode, String)> {
+    let game = state
+        .games
+        .get(&game)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Unknown game '{}'", game)))?;
+    let _permit = admit(&state, &req.bet).await?;
+
     let start = std::time::Instant::now();
     let engine = state.vrf_engine.clone();
+    let feed_req = state.outcome_feed.as_ref().map(|_| req.clone());
+    let result = tokio::task::spawn_blocking(move || game.play(&engine, &req)).await;
+
+    match result {
+        Ok(Ok(mut response)) => {
+            response.processing_time_ms = start.elapsed().as_millis() as u64;
+            if let (Some(feed), Some(req)) = (&state.outcome_feed, &feed_req) {
+                feed.publish(OutcomeEventV1::for_game(None, req, &response));
+            }
+            Ok(Json(response))
+        }
+        Ok(Err(e)) => Err((vrf_error_status(&e), e.to_string())),
+        Err(_) => {
+            tracing::error!("Game processing failed");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Game processing failed".to_string()))
+        }
+    }
+}
+
+/// Commit to a fresh node seed, to be mixed into a later `/reveal` flip
+async fn commit(State(state): State<AppState>) -> Json<CommitResponse> {
+    let (commit_id, commitment) = state.commits.commit();
+    Json(CommitResponse { commit_id, commitment, expires_in_secs: state.commits.ttl().as_secs() })
+}
+
+/// Flip against a commitment from `/commit`, revealing the node seed behind it
+async fn reveal(
+    State(state): State<AppState>,
+    Json(req): Json<RevealRequest>,
+) -> Result<Json<RevealResponse>, (StatusCode, String)> {
+    let _permit = admit(&state, &req.bet).await?;
+    let node_seed = state.commits.reveal(&req.commit_id).map_err(|e| {
+        let status = match e {
+            RevealError::Unknown => StatusCode::NOT_FOUND,
+            RevealError::Expired => StatusCode::GONE,
+        };
+        (status, e.to_string())
+    })?;
+
+    let response = flip(&state, req.bet, Some(node_seed)).await?;
+    Ok(Json(RevealResponse {
+        response,
+        commit_id: req.commit_id,
+        commitment: CommitStore::commitment(&node_seed),
+        node_seed: hex::encode(node_seed),
+    }))
+}
+
+/// One coinflip with every node-level check, shared by `/coinflip`, `/coinflip/batch` and
+/// `/reveal` (which passes its committed node seed and has already been admitted).
+/// Failures carry the status `/coinflip` answers with and a reason for batch entries.
+async fn flip(
+    state: &AppState,
+    req: CoinflipRequest,
+    node_seed: Option<[u8; 32]>,
+) -> Result<CoinflipResponse, (StatusCode, String)> {
+    let _permit = match node_seed {
+        Some(_) => None,
+        None => admit(state, &req).await?,
+    };
+
+    let start = std::time::Instant::now();
+    let engine = state.vrf_engine.clone();
+    let feed_req = state.outcome_feed.as_ref().map(|_| req.clone());
     
-    let result = tokio::task::spawn_blocking(move || engine.process_coinflip(&req)).await;
+    let result = tokio::task::spawn_blocking(move || match node_seed {
+        Some(node_seed) => engine.process_committed_coinflip(&req, &node_seed),
+        None => engine.process_coinflip(&req),
+    })
+    .await;
     
     match result {
         Ok(response) => {
             match response {
                 Ok(mut coinflip_response) => {
                     coinflip_response.processing_time_ms = start.elapsed().as_millis() as u64;
-                    Ok(Json(coinflip_response))
+                    vfnode::logging::log_outcome(None, &coinflip_response);
+                    if let (Some(feed), Some(req)) = (&state.outcome_feed, &feed_req) {
+                        feed.publish(OutcomeEventV1::new(None, req, &coinflip_response));
+                    }
+                    Ok(coinflip_response)
                 }
-                Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+                Err(e) => Err((vrf_error_status(&e), e.to_string())),
             }
         }
         Err(_) => {
             tracing::error!("Coinflip processing failed");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Coinflip processing failed".to_string()))
+        }
+    }
+}
+
+fn vrf_error_status(error: &VfError) -> StatusCode {
+    match error {
+        VfError::InvalidInput(_) | VfError::InvalidTimestamp(_) => StatusCode::BAD_REQUEST,
+        VfError::DuplicateRequest(_) | VfError::DuplicateBet(_) => StatusCode::CONFLICT,
+        VfError::ComputeBudgetExceeded(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        VfError::BadWalletSignature(_) => StatusCode::UNAUTHORIZED,
+        VfError::SettlementBackpressure(_) | VfError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Node-level checks every game request passes before any VRF work: throughput ceiling,
+/// free play, wager limits, then a concurrency permit held for the duration of the play
+async fn admit(state: &AppState, req: &CoinflipRequest) -> Result<Option<OwnedSemaphorePermit>, (StatusCode, String)> {
+    if let Some(limiter) = &state.flip_limiter {
+        if !limiter.try_acquire() {
+            tracing::warn!("Node throughput ceiling reached, shedding coinflip");
+            return Err((StatusCode::SERVICE_UNAVAILABLE, "Node throughput ceiling reached".to_string()));
+        }
+    }
+
+    if req.free_play && !state.free_play {
+        return Err((StatusCode::BAD_REQUEST, "Free play is disabled on this node".to_string()));
+    }
+
+    if let Err(e) = state.tokens.check_wager(req) {
+        tracing::debug!(error = %e, "Rejected wager");
+        return Err((StatusCode::BAD_REQUEST, e.to_string()));
+    }
+
+    match &state.flip_permits {
+        Some(permits) => permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map(Some)
+            .map_err(|_| (StatusCode::SERVICE_UNAVAILABLE, "Node is shutting down".to_string())),
+        None => Ok(None),
+    }
+}
+
+async fn debug_transcript(
+    State(state): State<AppState>,
+    Json(req): Json<CoinflipRequest>,
+) -> Result<Json<TranscriptDebug>, (StatusCode, String)> {
+    state.vrf_engine.debug_transcript(&req).map(Json).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+async fn verify(
+    State(state): State<AppState>,
+    Json(req): Json<VerifyRequest>,
+) -> Result<Json<VerifyResult>, StatusCode> {
+    let node_pubkey = state.vrf_engine.node_pubkey();
+    match state.verifier.verify(&node_pubkey, req).await {
+        Ok(result) => Ok(Json(result)),
+        Err(e) => {
+            tracing::error!(error = %e, "Verification failed");
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
+/// Recompute a stored flip for dispute resolution and compare it with the proof it was
+/// served with
+async fn replay(
+    State(state): State<AppState>,
+    Json(req): Json<ReplayRequest>,
+) -> Result<Json<ReplayResult>, (StatusCode, String)> {
+    match state.verifier.replay(state.vrf_engine.clone(), req).await {
+        Ok(result) => Ok(Json(result)),
+        Err(e @ (VfError::InvalidProof(_) | VfError::InvalidInput(_))) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e) => {
+            tracing::error!(error = %e, "Replay failed");
+            Err((vrf_error_status(&e), e.to_string()))
+        }
+    }
+}
+
+async fn verify_batch(
+    State(state): State<AppState>,
+    Json(reqs): Json<Vec<VerifyRequest>>,
+) -> Result<Json<Vec<VerifyResult>>, (StatusCode, String)> {
+    if reqs.len() > MAX_VERIFY_BATCH {
+        return Err((StatusCode::BAD_REQUEST, format!("Batch exceeds {} proofs", MAX_VERIFY_BATCH)));
+    }
+
+    let node_pubkey = state.vrf_engine.node_pubkey();
+    match state.verifier.verify_batch(&node_pubkey, reqs).await {
+        Ok(results) => Ok(Json(results)),
+        Err(e) => {
+            tracing::error!(error = %e, "Batch verification failed");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Batch verification failed".to_string()))
+        }
+    }
+}
+
+/// Prometheus scrape target
+async fn metrics() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], METRICS.render())
+}
+
 async fn health() -> Json<serde_json::Value> {
     Json(serde_json::json!({
         "status": "ok",
@@ -67,14 +307,55 @@ async fn health() -> Json<serde_json::Value> {
 async fn node_info(State(state): State<AppState>) -> Json<serde_json::Value> {
     Json(serde_json::json!({
         "node_pubkey": state.vrf_engine.node_pubkey(),
+        "pubkey": {
+            "base64": state.vrf_engine.node_pubkey(),
+            "base58": state.vrf_engine.node_pubkey_base58(),
+            "hex": state.vrf_engine.node_pubkey_hex()
+        },
         "service": "vfnode",
         "version": env!("CARGO_PKG_VERSION"),
-        "supported_games": ["coinflip"],
+        "supported_games": state.games.names(),
         "max_concurrent": 10,
-        "features": ["multi-threaded", "async", "optimized"]
+        "features": ["multi-threaded", "async", "optimized"],
+        "clock_drift": state.vrf_engine.clock_drift(),
+        "tokens": state.tokens.tokens()
     }))
 }
 
+/// Optimized router with performance middleware
+fn router(state: AppState, config: &NodeConfig) -> Router {
+    let mut app = Router::new()
+        .route("/coinflip", post(coinflip))
+        .route("/coinflip/batch", post(coinflip_batch))
+        .route("/commit", post(commit))
+        .route("/reveal", post(reveal))
+        .route("/play/:game", post(play))
+        .route("/verify", post(verify))
+        .route("/verify/batch", post(verify_batch))
+        .route("/replay", post(replay))
+        .route("/info", get(node_info));
+
+    // Debug-only endpoints, never exposed in production
+    if config.dev_mode {
+        tracing::warn!("DEV_MODE enabled: debug endpoints are exposed");
+        app = app.route("/debug/transcript", post(debug_transcript));
+    }
+
+    // Health checks and scrapes come from a few fixed addresses; keep them out of the limit
+    if let Some(limiter) = &state.client_limiter {
+        app = app.route_layer(middleware::from_fn_with_state(limiter.clone(), limit_clients));
+    }
+
+    app.route("/health", get(health))
+        .route("/metrics", get(metrics))
+        .layer(CompressionLayer::new()) // Compress responses
+        .layer(TimeoutLayer::new(Duration::from_secs(5))) // Request timeout
+        .layer(RequestBodyLimitLayer::new(config.max_body_bytes)) // 413 before oversized bodies are buffered
+        .layer(config.cors_layer())
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         tokio::signal::ctrl_c()
@@ -101,20 +382,50 @@ async fn shutdown_signal() {
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 8)]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Enhanced tracing for performance monitoring
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "vfnode=info,tower_http=info".into())
-        )
-        .with(tracing_subscriber::fmt::layer()
-            .with_target(false)
-            .compact()
-        )
-        .init();
-
-    // Initialize VRF engine
-    let vrf_engine = Arc::new(VrfEngine::new());
+    // Validate everything up front so all problems are reported together
+    let (config, mut problems) = NodeConfig::from_env();
+    vfnode::logging::init(config.log_format);
+
+    let vrf_engine = config
+        .load_engine()
+        .map_err(|e| problems.push(e))
+        .ok();
+
+    let outcome_feed = config.outcome_feed_file.as_deref().and_then(|path| {
+        OutcomeFeed::open(path, OutcomeFeed::DEFAULT_CAPACITY)
+            .map_err(|e| problems.push(format!("OUTCOME_FEED_FILE '{}' cannot be opened: {}", path.display(), e)))
+            .ok()
+            .map(Arc::new)
+    });
+
+    let tls = config.load_tls().map_err(|e| problems.push(e)).ok().flatten();
+
+    let addr = config.bind_addr();
+    let listener = match tls {
+        Some(acceptor) => Listener::bind_tls(&addr, acceptor).await,
+        None => Listener::bind(&addr).await,
+    };
+    let listener = listener
+        .map_err(|e| {
+            let key = if config.listen_addr.is_some() { "BIND_ADDR".to_string() } else { format!("PORT {}", config.port) };
+            problems.push(format!("{}: cannot bind {}: {}", key, addr, e))
+        })
+        .ok();
+
+    if !problems.is_empty() {
+        eprintln!("❌ {}", problems);
+        std::process::exit(1);
+    }
+
+    vfnode::redact::set_log_raw_seeds(config.log_raw_seeds);
+    if config.log_raw_seeds {
+        tracing::warn!("LOG_RAW_SEEDS enabled: user seeds are written to logs in cleartext");
+    }
+    let (Some(vrf_engine), Some(listener)) = (vrf_engine, listener) else {
+        unreachable!("startup failures are recorded in problems");
+    };
+
+    let vrf_engine = Arc::new(vrf_engine);
     
     tracing::info!(
         node_pubkey = vrf_engine.node_pubkey(),
@@ -122,25 +433,48 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "VF Node initializing"
     );
 
-    let state = AppState { vrf_engine };
+    let flip_limiter = config.max_flips_per_second.map(|rate| Arc::new(TokenBucket::per_second(rate)));
 
-    // Optimized router with performance middleware
-    let app = Router::new()
-        .route("/coinflip", post(coinflip))
-        .route("/health", get(health))
-        .route("/info", get(node_info))
-        .layer(CompressionLayer::new()) // Compress responses
-        .layer(TimeoutLayer::new(Duration::from_secs(5))) // Request timeout
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
+    if let Some(rate) = config.max_flips_per_second {
+        tracing::info!(max_flips_per_second = rate, "Node throughput ceiling enabled");
+    }
+
+    let client_limiter = config.client_rate_limiter().map(Arc::new);
+    if let Some(rate) = config.client_rate_limit_per_second {
+        tracing::info!(
+            client_rate_limit_per_second = rate,
+            client_rate_limit_burst = config.client_rate_limit_burst.unwrap_or(rate),
+            "Per-client rate limit enabled"
+        );
+    }
+
+    let flip_permits = config.max_concurrent_flips.map(|n| Arc::new(Semaphore::new(n)));
+    let verifier = Arc::new(VerifierPool::new(config.max_concurrent_verifications));
+    tracing::info!(
+        max_concurrent_flips = ?config.max_concurrent_flips,
+        max_concurrent_verifications = config.max_concurrent_verifications,
+        "Concurrency limits configured"
+    );
+
+    let tokens = Arc::new(config.tokens.clone());
+    let state = AppState {
+        vrf_engine,
+        flip_limiter,
+        client_limiter,
+        flip_permits,
+        verifier,
+        tokens,
+        outcome_feed,
+        games: Arc::new(GameRegistry::default()),
+        commits: Arc::new(CommitStore::new(config.commit_ttl, CommitStore::DEFAULT_CAPACITY)),
+        free_play: config.free_play,
+    };
+
+    if config.cors_allowed_origins.is_none() {
+        tracing::warn!("CORS_ALLOWED_ORIGINS unset: accepting cross-origin requests from any origin");
+    }
+    let app = router(state, &config);
 
-    // Optimized server configuration
-    let port = std::env::var("PORT").unwrap_or_else(|_| "3001".to_string());
-    let addr = format!("0.0.0.0:{}", port);
-    
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    
     // Enhanced startup info
     tracing::info!(
         addr = %addr,
@@ -148,16 +482,281 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "VF Node server starting"
     );
     
-    println!("🚀 VF Node running on http://{}", addr);
+    let scheme = if config.tls_cert_path.is_some() { "https" } else { "http" };
+    println!("🚀 VF Node running on {}://{}", scheme, addr);
     println!("⚡ Multi-threaded with {} worker threads", num_cpus::get());
     println!("🎯 Optimized for high-throughput, low-latency");
     
-    axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
-    )
-    .with_graceful_shutdown(shutdown_signal())
-    .await?;
+    listen::serve(listener, app, shutdown_signal()).await?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use vfnode::games::Game;
+    use axum::extract::ConnectInfo;
+    use axum::http::{header, Method, Request};
+    use std::collections::HashSet;
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+    use vfnode::types::SeedEncoding;
+    use vfnode::vrf_engine::EngineConfig;
+
+    fn test_state(config: &NodeConfig) -> AppState {
+        AppState {
+            vrf_engine: Arc::new(VrfEngine::new()),
+            flip_limiter: None,
+            client_limiter: config.client_rate_limiter().map(Arc::new),
+            flip_permits: None,
+            verifier: Arc::new(VerifierPool::new(1)),
+            tokens: Arc::new(config.tokens.clone()),
+            outcome_feed: None,
+            games: Arc::new(GameRegistry::default()),
+            commits: Arc::new(CommitStore::new(CommitStore::DEFAULT_TTL, 16)),
+            free_play: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_rejected() {
+        let (config, errors) = NodeConfig::from_lookup(|key| (key == "MAX_BODY_BYTES").then(|| "1024".to_string()));
+        assert!(errors.is_empty(), "{}", errors);
+
+        let post = |body: String| {
+            Request::builder()
+                .method(Method::POST)
+                .uri("/coinflip")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let oversized = format!(r#"{{"user_seed":"{}"}}"#, "a".repeat(2048));
+        let response = router(test_state(&config), &config).oneshot(post(oversized)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let response = router(test_state(&config), &config).oneshot(post(r#"{"user_seed":"small"}"#.to_string())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_client_rate_limit_returns_429() {
+        let (config, errors) = NodeConfig::from_lookup(|key| match key {
+            "CLIENT_RATE_LIMIT_PER_SECOND" => Some("1".to_string()),
+            "CLIENT_RATE_LIMIT_BURST" => Some("2".to_string()),
+            _ => None,
+        });
+        assert!(errors.is_empty(), "{}", errors);
+        let app = router(test_state(&config), &config);
+
+        let get_from = |uri: &str, ip: [u8; 4]| {
+            let mut request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            request.extensions_mut().insert(ConnectInfo(SocketAddr::from((ip, 40000))));
+            request
+        };
+
+        for _ in 0..2 {
+            let response = app.clone().oneshot(get_from("/info", [203, 0, 113, 7])).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let limited = app.clone().oneshot(get_from("/info", [203, 0, 113, 7])).await.unwrap();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limited.headers()[header::RETRY_AFTER], "1");
+
+        // Other clients and health checks are unaffected
+        let other = app.clone().oneshot(get_from("/info", [203, 0, 113, 8])).await.unwrap();
+        assert_eq!(other.status(), StatusCode::OK);
+        let health = app.clone().oneshot(get_from("/health", [203, 0, 113, 7])).await.unwrap();
+        assert_eq!(health.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight_honours_allowlist() {
+        let (config, errors) =
+            NodeConfig::from_lookup(|key| (key == "CORS_ALLOWED_ORIGINS").then(|| "https://app.example.com".to_string()));
+        assert!(errors.is_empty(), "{}", errors);
+
+        let preflight = |origin: &str| {
+            Request::builder()
+                .method(Method::OPTIONS)
+                .uri("/coinflip")
+                .header(header::ORIGIN, origin)
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let allowed = router(test_state(&config), &config).oneshot(preflight("https://app.example.com")).await.unwrap();
+        assert!(allowed.status().is_success());
+        assert_eq!(allowed.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example.com");
+        assert!(!allowed.headers().contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+        let denied = router(test_state(&config), &config).oneshot(preflight("https://evil.example.com")).await.unwrap();
+        assert!(!denied.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn test_coinflip_batch_preserves_order() {
+        let (config, errors) = NodeConfig::from_lookup(|_| None);
+        assert!(errors.is_empty(), "{}", errors);
+        let engine = Arc::new(VrfEngine::new());
+        let state = AppState {
+            vrf_engine: engine.clone(),
+            flip_permits: Some(Arc::new(Semaphore::new(4))),
+            ..test_state(&config)
+        };
+
+        let reqs: Vec<CoinflipRequest> = (0..40)
+            .map(|i| CoinflipRequest {
+                user_seed: if i == 7 { String::new() } else { format!("batch-{}", i) },
+                free_play: i == 11,
+                ..Default::default()
+            })
+            .collect();
+        let Json(results) = coinflip_batch(State(state.clone()), Json(reqs.clone())).await.unwrap();
+
+        assert_eq!(results.len(), reqs.len());
+        for (i, (req, result)) in reqs.iter().zip(&results).enumerate() {
+            match i {
+                7 | 11 => assert!(result.response.is_none() && result.error.is_some(), "entry {}", i),
+                _ => {
+                    let response = result.response.as_ref().expect("valid entries still flip");
+                    assert!(engine.verify_proof(&response.proof, req).unwrap(), "entry {} is out of order", i);
+                }
+            }
+        }
+
+        let oversized = vec![CoinflipRequest::default(); MAX_COINFLIP_BATCH + 1];
+        let (status, _) = coinflip_batch(State(state), Json(oversized)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_validation_failures_are_client_errors() {
+        let (config, errors) = NodeConfig::from_lookup(|_| None);
+        assert!(errors.is_empty(), "{}", errors);
+        let engine = VrfEngine::with_config(EngineConfig {
+            min_seed_len: 8,
+            allowed_mints: HashSet::from(["USDC".to_string()]),
+            compute_budget: Some(0),
+            ..Default::default()
+        });
+        let state = AppState { vrf_engine: Arc::new(engine), ..test_state(&config) };
+
+        let rejected = [
+            // Too short once decoded
+            CoinflipRequest { user_seed: "short".to_string(), ..Default::default() },
+            CoinflipRequest { user_seed: "not hex".to_string(), seed_encoding: SeedEncoding::Hex, ..Default::default() },
+            // A registered token the engine's mint allowlist refuses
+            CoinflipRequest { user_seed: "long enough".to_string(), wager_lamports: Some(1_000_000), ..Default::default() },
+        ];
+        for req in rejected {
+            let status = coinflip(State(state.clone()), Json(req.clone())).await.unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{:?}", req);
+        }
+
+        let roll = GameRequest {
+            bet: CoinflipRequest { user_seed: "long enough".to_string(), ..Default::default() },
+            params: serde_json::json!({ "target": 6 }),
+        };
+        let (status, _) = play(State(state), Path("dice".to_string()), Json(roll)).await.unwrap_err();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Raised by settlement-backed engines
+        assert_eq!(vrf_error_status(&VfError::SettlementBackpressure("full".into())), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(vrf_error_status(&VfError::Unavailable("shutting down".into())), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_play_dispatches_by_game_name() {
+        let (config, errors) = NodeConfig::from_lookup(|_| None);
+        assert!(errors.is_empty(), "{}", errors);
+        let engine = Arc::new(VrfEngine::new());
+        let state = AppState { vrf_engine: engine.clone(), ..test_state(&config) };
+        let req = GameRequest {
+            bet: CoinflipRequest { user_seed: "play".to_string(), ..Default::default() },
+            ..Default::default()
+        };
+
+        let Json(played) = play(State(state.clone()), Path("coinflip".to_string()), Json(req.clone())).await.unwrap();
+        assert_eq!(played.game, "coinflip");
+        assert!(engine.verify_proof(&played.proof, &req.bet).unwrap());
+
+        let (status, _) = play(State(state), Path("roulette".to_string()), Json(req)).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_reveal_plays_against_commitment() {
+        let (config, _) = NodeConfig::from_lookup(|_| None);
+        let state = test_state(&config);
+        let Json(committed) = commit(State(state.clone())).await;
+        assert_eq!(committed.expires_in_secs, 300);
+
+        let req = RevealRequest {
+            commit_id: committed.commit_id,
+            bet: CoinflipRequest { user_seed: "revealed".to_string(), ..Default::default() },
+        };
+        let Json(revealed) = reveal(State(state.clone()), Json(req.clone())).await.unwrap();
+        assert_eq!(revealed.commitment, committed.commitment);
+        let node_seed: [u8; 32] = hex::decode(&revealed.node_seed).unwrap().try_into().unwrap();
+        assert_eq!(CommitStore::commitment(&node_seed), committed.commitment);
+        assert_eq!(revealed.response.proof.node_seed.as_deref(), Some(revealed.node_seed.as_str()));
+        assert!(state.vrf_engine.verify_proof(&revealed.response.proof, &req.bet).unwrap());
+
+        // Each commitment is played once
+        let (status, _) = reveal(State(state), Json(req)).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_info_lists_registered_games() {
+        struct Roulette;
+
+        impl Game for Roulette {
+            fn name(&self) -> &'static str {
+                "roulette"
+            }
+
+            fn play(&self, _engine: &VrfEngine, _req: &GameRequest) -> Result<GameResponse, VfError> {
+                Err(VfError::InvalidInput("not implemented".to_string()))
+            }
+        }
+
+        let (config, errors) = NodeConfig::from_lookup(|_| None);
+        assert!(errors.is_empty(), "{}", errors);
+        let Json(info) = node_info(State(test_state(&config))).await;
+        assert_eq!(info["supported_games"], serde_json::json!(["coinflip", "dice"]));
+
+        let mut games = GameRegistry::default();
+        games.register(Arc::new(Roulette));
+        let state = AppState { games: Arc::new(games), ..test_state(&config) };
+        let Json(info) = node_info(State(state)).await;
+        assert_eq!(info["supported_games"], serde_json::json!(["coinflip", "dice", "roulette"]));
+    }
+
+    #[tokio::test]
+    async fn test_info_reflects_token_limits() {
+        let tokens = r#"[{"mint": "USDC", "decimals": 6, "min_wager_lamports": 500, "max_wager_lamports": 9000}]"#;
+        let (config, errors) = NodeConfig::from_lookup(|key| (key == "TOKENS").then(|| tokens.to_string()));
+        assert!(errors.is_empty(), "{}", errors);
+
+        let state = test_state(&config);
+
+        let engine = state.vrf_engine.clone();
+        let Json(info) = node_info(State(state)).await;
+        assert_eq!(info["pubkey"]["base64"], info["node_pubkey"]);
+        assert_eq!(info["pubkey"]["base58"], engine.node_pubkey_base58());
+        assert_eq!(info["pubkey"]["hex"], engine.node_pubkey_hex());
+        assert_eq!(info["tokens"], serde_json::json!([{
+            "mint": "USDC",
+            "decimals": 6,
+            "min_wager_lamports": 500,
+            "max_wager_lamports": 9000,
+            "enabled": true
+        }]));
+    }
+}
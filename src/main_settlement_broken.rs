@@ -1,69 +1,238 @@
-mod types;
-mod vrf_engine;
-mod settlement_engine;
-mod storage;
-
-use types::{CoinflipRequest, CoinflipResponse, VfError};
-use settlement_engine::SettlementEngine;
-use storage::Storage;
-use vrf_engine::VrfEngine;
+use vfnode::auth::require_admin_key;
+use vfnode::backend::{SqliteBackend, StorageBackend};
+use vfnode::commit::{CommitStore, RevealError};
+use vfnode::config::NodeConfig;
+use vfnode::events::{OutcomeEventV1, OutcomeFeed};
+use vfnode::games::{Coinflip, GameRegistry};
+use vfnode::listen::{self, Listener};
+use vfnode::metrics::METRICS;
+use vfnode::throttle::{limit_clients, ClientRateLimiter};
+use vfnode::tokens::TokenRegistry;
+use vfnode::types::{BatchRecord, BatchReplay, BetStatus, CoinflipRequest, CoinflipResponse, CommitResponse, DeadLetterBet, InclusionProof, PlayerLedger, RevealRequest, RevealResponse, SettlementReceipt, VfError};
+use vfnode::settlement_engine::{BatchResult, RequeueOutcome, SettlementConfig, SettlementEngine};
+use vfnode::storage::Storage;
+use vfnode::vrf_engine::VrfEngine;
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::Json,
+    body::Body,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{header, StatusCode},
+    middleware,
+    response::{IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
+use futures_util::StreamExt;
+use serde::Deserialize;
 use std::sync::Arc;
+use tokio::sync::broadcast;
+use uuid::Uuid;
 use tower_http::{
-    cors::CorsLayer, 
     trace::TraceLayer,
     compression::CompressionLayer,
     timeout::TimeoutLayer,
+    limit::RequestBodyLimitLayer,
 };
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use std::time::Duration;
 
 #[derive(Clone)]
 struct AppState {
     vrf_engine: Arc<VrfEngine>,
     settlement_engine: Arc<SettlementEngine>,
-    storage: Arc<Storage>,
+    // Settlement tables, on SQLite or PostgreSQL
+    backend: Arc<dyn StorageBackend>,
+    // The SQLite store behind the audit endpoints (None on PostgreSQL)
+    storage: Option<Arc<Storage>>,
+    tokens: Arc<TokenRegistry>,
+    outcome_feed: Option<Arc<OutcomeFeed>>,
+    // Games advertised in /info
+    games: Arc<GameRegistry>,
+    // Node seeds committed by /commit, awaiting /reveal
+    commits: Arc<CommitStore>,
+    // Per-client-IP request rate, shared by every worker (None = unlimited)
+    client_limiter: Option<Arc<ClientRateLimiter>>,
+    // Whether free-play flips are accepted
+    free_play: bool,
+    // Whether every served flip is written to bet_results
+    audit_trail: bool,
+}
+
+impl AppState {
+    /// The SQLite store, for endpoints with no PostgreSQL implementation
+    fn storage(&self) -> Result<&Arc<Storage>, (StatusCode, String)> {
+        self.storage
+            .as_ref()
+            .ok_or((StatusCode::NOT_IMPLEMENTED, "This endpoint needs the SQLite backend".to_string()))
+    }
 }
 
 async fn coinflip(
     State(state): State<AppState>,
     Json(req): Json<CoinflipRequest>,
 ) -> Result<Json<CoinflipResponse>, StatusCode> {
+    flip(&state, req, None).await.map(Json)
+}
+
+/// Commit to a fresh node seed, to be mixed into a later `/reveal` flip
+async fn commit(State(state): State<AppState>) -> Json<CommitResponse> {
+    let (commit_id, commitment) = state.commits.commit();
+    Json(CommitResponse { commit_id, commitment, expires_in_secs: state.commits.ttl().as_secs() })
+}
+
+/// Flip against a commitment from `/commit`, revealing the node seed behind it. The flip
+/// is queued for settlement like any other.
+async fn reveal(
+    State(state): State<AppState>,
+    Json(req): Json<RevealRequest>,
+) -> Result<Json<RevealResponse>, StatusCode> {
+    let node_seed = state.commits.reveal(&req.commit_id).map_err(|e| {
+        tracing::debug!(error = %e, commit_id = %req.commit_id, "Rejected reveal");
+        match e {
+            RevealError::Unknown => StatusCode::NOT_FOUND,
+            RevealError::Expired => StatusCode::GONE,
+        }
+    })?;
+
+    let response = flip(&state, req.bet, Some(node_seed)).await?;
+    Ok(Json(RevealResponse {
+        response,
+        commit_id: req.commit_id,
+        commitment: CommitStore::commitment(&node_seed),
+        node_seed: hex::encode(node_seed),
+    }))
+}
+
+/// One settled coinflip, shared by `/coinflip` and `/reveal` (which passes its committed
+/// node seed)
+async fn flip(state: &AppState, req: CoinflipRequest, node_seed: Option<[u8; 32]>) -> Result<CoinflipResponse, StatusCode> {
+    if req.free_play && !state.free_play {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    if let Err(e) = state.tokens.check_wager(&req) {
+        tracing::debug!(error = %e, "Rejected wager");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // A client-chosen bet id is claimed first, so reusing one is refused instead of the
+    // second bet being answered and then dropped by `pending_bets`
+    let reserved = match req.bet_id {
+        Some(bet_id) if !req.free_play => {
+            state
+                .settlement_engine
+                .reserve_bet_id(bet_id)
+                .await
+                .map_err(|e| CoinflipFailure::Reserve(e).into_status())?;
+            Some(bet_id)
+        }
+        _ => None,
+    };
+    let release = || {
+        if let Some(bet_id) = &reserved {
+            state.settlement_engine.release_bet_id(bet_id);
+        }
+    };
+
     let start = std::time::Instant::now();
     let engine = state.vrf_engine.clone();
     let req_clone = req.clone(); // Clone for settlement
     
-    let result = tokio::task::spawn_blocking(move || engine.process_coinflip(&req)).await;
-    
-    match result {
-        Ok(vrf_result) => {
-            match vrf_result {
-                Ok(mut response) => {
-                    response.processing_time_ms = start.elapsed().as_millis() as u64;
-                    
-                    // Enqueue bet for settlement processing (non-blocking)
-                    if let Err(e) = state.settlement_engine.enqueue_bet_fast(&response, &req_clone) {
-                        tracing::warn!("Failed to enqueue bet for settlement: {}", e);
-                    }
-                    
-                    Ok(Json(response))
-                }
-                Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
+    let flipped = tokio::task::spawn_blocking(move || match node_seed {
+        Some(node_seed) => engine.process_committed_coinflip(&req, &node_seed),
+        None => engine.process_coinflip(&req),
+    })
+        .await
+        .map_err(CoinflipFailure::Task)
+        .and_then(|result| result.map_err(CoinflipFailure::Vrf));
+    let mut response = match flipped {
+        Ok(response) => response,
+        Err(failure) => {
+            release();
+            return Err(failure.into_status());
         }
+    };
+    response.processing_time_ms = start.elapsed().as_millis() as u64;
+
+    // Enqueue bet for settlement processing (non-blocking); free play is skipped.
+    // A bet that can't be queued is never settled, so the outcome is withheld.
+    let bet_id = match state.settlement_engine.enqueue_bet_fast(&response, &req_clone) {
+        Ok(bet_id) => bet_id,
         Err(e) => {
-            tracing::error!("Coinflip processing failed: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            release();
+            return Err(CoinflipFailure::Enqueue(e).into_status());
+        }
+    };
+    response.bet_id = bet_id.or(response.bet_id);
+    vfnode::logging::log_outcome(bet_id, &response);
+    if let Some(feed) = &state.outcome_feed {
+        feed.publish(OutcomeEventV1::new(bet_id, &req_clone, &response));
+    }
+    // Off the response path; store_bet logs its own failures
+    if let (true, Some(storage)) = (state.audit_trail, state.storage.clone()) {
+        let response = response.clone();
+        tokio::spawn(async move {
+            let _ = storage.store_bet(&req_clone, &response).await;
+        });
+    }
+
+    Ok(response)
+}
+
+/// Why a validated coinflip request failed, so each cause gets its own status
+#[derive(Debug)]
+enum CoinflipFailure {
+    /// The engine rejected or couldn't complete the flip
+    Vrf(VfError),
+    /// The blocking flip task panicked or was cancelled
+    Task(tokio::task::JoinError),
+    /// The flip succeeded but its bet couldn't be queued for settlement
+    Enqueue(VfError),
+    /// The client-chosen bet id is taken, or couldn't be checked
+    Reserve(VfError),
+}
+
+impl CoinflipFailure {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Vrf(VfError::InvalidInput(_) | VfError::InvalidTimestamp(_)) => StatusCode::BAD_REQUEST,
+            Self::Vrf(VfError::DuplicateRequest(_) | VfError::DuplicateBet(_)) => StatusCode::CONFLICT,
+            Self::Vrf(VfError::ComputeBudgetExceeded(_)) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Vrf(VfError::BadWalletSignature(_)) => StatusCode::UNAUTHORIZED,
+            Self::Vrf(VfError::Unavailable(_)) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Vrf(_) | Self::Task(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            // The settlement channel is full, or the engine has shut down
+            Self::Enqueue(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Reserve(VfError::DuplicateBet(_)) => StatusCode::CONFLICT,
+            Self::Reserve(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Log the failure at a level matching its cause and return its status
+    fn into_status(self) -> StatusCode {
+        let status = self.status();
+        match &self {
+            Self::Vrf(e) if status.is_client_error() => tracing::debug!(error = %e, "Rejected coinflip"),
+            Self::Vrf(e) => tracing::error!(error = %e, "Coinflip failed"),
+            Self::Task(e) if e.is_panic() => tracing::error!(error = %e, "Coinflip task panicked"),
+            Self::Task(e) => tracing::error!(error = %e, "Coinflip task was cancelled"),
+            Self::Enqueue(e @ VfError::SettlementBackpressure(_)) => {
+                tracing::warn!(error = %e, "Settlement queue is full; withholding outcome")
+            }
+            Self::Enqueue(e) => tracing::error!(error = %e, "Failed to enqueue bet for settlement; withholding outcome"),
+            Self::Reserve(e @ VfError::DuplicateBet(_)) => tracing::debug!(error = %e, "Rejected coinflip"),
+            Self::Reserve(e) => tracing::error!(error = %e, "Failed to check bet id"),
         }
+        status
     }
 }
 
+/// Prometheus scrape target
+async fn metrics() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], METRICS.render())
+}
+
 async fn health() -> Json<serde_json::Value> {
     Json(serde_json::json!({
         "status": "ok",
@@ -80,21 +249,82 @@ async fn health() -> Json<serde_json::Value> {
 async fn node_info(State(state): State<AppState>) -> Json<serde_json::Value> {
     Json(serde_json::json!({
         "node_pubkey": state.vrf_engine.node_pubkey(),
+        "pubkey": {
+            "base64": state.vrf_engine.node_pubkey(),
+            "base58": state.vrf_engine.node_pubkey_base58(),
+            "hex": state.vrf_engine.node_pubkey_hex()
+        },
         "service": "vfnode",
         "version": env!("CARGO_PKG_VERSION"),
-        "supported_games": ["coinflip"],
+        "supported_games": state.games.names(),
         "max_concurrent": num_cpus::get(),
-        "features": ["multi-threaded", "async", "optimized", "settlement-engine"]
+        "features": ["multi-threaded", "async", "optimized", "settlement-engine"],
+        "clock_drift": state.vrf_engine.clock_drift(),
+        "tokens": state.tokens.tokens()
     }))
 }
 
+/// Readiness: 503 while any settlement background task is dead or not heartbeating
+async fn ready(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    let tasks = state.settlement_engine.task_health();
+    let healthy = tasks.iter().all(|task| task.healthy);
+    let status = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(serde_json::json!({
+        "status": if healthy { "ready" } else { "degraded" },
+        "tasks": tasks,
+    })))
+}
+
+/// Health for load balancers: `503` when a settlement task has stalled or the retry queue
+/// is past `RETRY_QUEUE_HEALTH_LIMIT`
+async fn deep_health(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    let health = state.settlement_engine.health().await;
+    let status = if health.healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(serde_json::json!({
+        "status": if health.healthy { "ok" } else { "degraded" },
+        "tasks": health.tasks,
+        "retry_queue_size": health.retry_queue_size,
+        "retry_queue_limit": health.retry_queue_limit,
+    })))
+}
+
 async fn settlement_stats(State(state): State<AppState>) -> Json<serde_json::Value> {
     let stats = state.settlement_engine.get_stats().await;
     Json(serde_json::to_value(stats).unwrap_or_default())
 }
 
+/// Live feed of settled batches: one JSON `BatchResult` per message
+async fn settlement_socket(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
+    let batches = state.settlement_engine.subscribe_settled_batches();
+    ws.on_upgrade(move |socket| stream_settled_batches(socket, batches))
+}
+
+async fn stream_settled_batches(mut socket: WebSocket, mut batches: broadcast::Receiver<BatchResult>) {
+    loop {
+        tokio::select! {
+            batch = batches.recv() => match batch {
+                Ok(batch) => {
+                    let Ok(json) = serde_json::to_string(&batch) else { continue };
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+                // A slow client misses batches rather than holding up settlement
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "Settlement feed client fell behind, batches dropped");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
 async fn settlement_summary(State(state): State<AppState>) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    match state.storage.get_settlement_summary().await {
+    match state.backend.settlement_summary().await {
         Ok(summary) => Ok(Json(summary)),
         Err(e) => {
             tracing::error!(error = %e, "Failed to get settlement summary");
@@ -103,66 +333,485 @@ async fn settlement_summary(State(state): State<AppState>) -> Result<Json<serde_
     }
 }
 
+/// Largest page served by `/settlement/batches` and `/settlement/dead-letters`
+const MAX_PAGE: i64 = 100;
+
+#[derive(Deserialize)]
+struct Page {
+    #[serde(default)]
+    offset: i64, // Entries to skip
+    limit: Option<i64>,
+}
+
+impl Page {
+    /// `(limit, offset)`, defaulting to a full page
+    fn bounds(&self) -> Result<(i64, i64), (StatusCode, String)> {
+        let limit = self.limit.unwrap_or(MAX_PAGE);
+        if !(1..=MAX_PAGE).contains(&limit) {
+            return Err((StatusCode::BAD_REQUEST, format!("`limit` must be between 1 and {}", MAX_PAGE)));
+        }
+        if self.offset < 0 {
+            return Err((StatusCode::BAD_REQUEST, "`offset` must not be negative".to_string()));
+        }
+        Ok((limit, self.offset))
+    }
+}
+
+/// Recorded settlement batches, newest first
+async fn settlement_batches(
+    State(state): State<AppState>,
+    Query(page): Query<Page>,
+) -> Result<Json<Vec<BatchRecord>>, (StatusCode, String)> {
+    let (limit, offset) = page.bounds()?;
+    match state.backend.list_batches(limit, offset).await {
+        Ok(batches) => Ok(Json(batches)),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to list settlement batches");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to list settlement batches".to_string()))
+        }
+    }
+}
+
+/// Bets that exhausted their settlement retries, most recently failed first
+async fn dead_letters(
+    State(state): State<AppState>,
+    Query(page): Query<Page>,
+) -> Result<Json<Vec<DeadLetterBet>>, (StatusCode, String)> {
+    let (limit, offset) = page.bounds()?;
+    match state.storage()?.list_dead_letters(limit, offset).await {
+        Ok(bets) => Ok(Json(bets)),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to list dead-lettered bets");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to list dead-lettered bets".to_string()))
+        }
+    }
+}
+
+/// Send a dead-lettered bet back for settlement with its retries reset
+async fn requeue_dead_letter(
+    State(state): State<AppState>,
+    Path(bet_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    match state.settlement_engine.requeue_dead_letter(bet_id).await {
+        Ok(RequeueOutcome::Requeued) => Ok(Json(serde_json::json!({
+            "bet_id": bet_id,
+            "status": "requeued"
+        }))),
+        Ok(RequeueOutcome::NotDeadLettered) => Err((StatusCode::NOT_FOUND, "Bet is not dead-lettered".to_string())),
+        Ok(RequeueOutcome::AlreadyPending) => Err((StatusCode::CONFLICT, "Bet is already pending".to_string())),
+        Err(e) => {
+            tracing::error!(error = %e, bet_id = %bet_id, "Failed to requeue dead-lettered bet");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to requeue bet".to_string()))
+        }
+    }
+}
+
+/// Settlement tunables to change; omitted fields keep their current value
+#[derive(Deserialize)]
+struct SettlementTuning {
+    batch_size: Option<usize>,
+    processing_interval_seconds: Option<u64>,
+}
+
+/// Change the settlement batch size and interval without a restart
+async fn update_settlement_config(
+    State(state): State<AppState>,
+    Json(tuning): Json<SettlementTuning>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let engine = &state.settlement_engine;
+    let batch_size = tuning.batch_size.unwrap_or_else(|| engine.batch_size());
+    let interval = tuning.processing_interval_seconds.unwrap_or_else(|| engine.processing_interval_seconds());
+    engine
+        .update_config(batch_size, interval)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    Ok(Json(serde_json::json!({
+        "batch_size": batch_size,
+        "processing_interval_seconds": interval,
+    })))
+}
+
+#[derive(Deserialize)]
+struct EdgeWindow {
+    from: Option<String>, // RFC 3339, inclusive
+    to: Option<String>,   // RFC 3339, exclusive
+}
+
+/// Realized house edge per token over bets settled in the window
+async fn realized_edge(
+    State(state): State<AppState>,
+    Query(window): Query<EdgeWindow>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let parse = |bound: &Option<String>, name: &str| {
+        bound
+            .as_deref()
+            .map(|v| time::OffsetDateTime::parse(v, &time::format_description::well_known::Rfc3339))
+            .transpose()
+            .map_err(|_| (StatusCode::BAD_REQUEST, format!("`{}` must be an RFC 3339 timestamp", name)))
+    };
+    let from = parse(&window.from, "from")?;
+    let to = parse(&window.to, "to")?;
+
+    match state.storage()?.get_realized_edge(from, to).await {
+        Ok(tokens) => Ok(Json(serde_json::json!({
+            "from": window.from,
+            "to": window.to,
+            "tokens": tokens,
+        }))),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to compute realized edge");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to compute realized edge".to_string()))
+        }
+    }
+}
+
+/// Settlement status of one bet, for players polling whether their flip settled
+async fn bet_status(
+    State(state): State<AppState>,
+    Path(bet_id): Path<Uuid>,
+) -> Result<Json<BetStatus>, (StatusCode, String)> {
+    match state.backend.bet_status(&bet_id).await {
+        Ok(Some(bet)) => Ok(Json(bet)),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Unknown bet".to_string())),
+        Err(e) => {
+            tracing::error!(error = %e, bet_id = %bet_id, "Failed to load bet status");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to load bet status".to_string()))
+        }
+    }
+}
+
+/// Merkle proof that a settled bet was included in its batch's settlement tx
+async fn bet_inclusion(
+    State(state): State<AppState>,
+    Path(bet_id): Path<Uuid>,
+) -> Result<Json<InclusionProof>, (StatusCode, String)> {
+    match state.storage()?.get_inclusion_proof(&bet_id).await {
+        Ok(Some(proof)) => Ok(Json(proof)),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Bet is not settled in a Merkle batch".to_string())),
+        Err(e) => {
+            tracing::error!(error = %e, bet_id = %bet_id, "Failed to build inclusion proof");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to build inclusion proof".to_string()))
+        }
+    }
+}
+
+/// Node-signed proof of payment binding a settled bet to its settlement tx
+async fn bet_receipt(
+    State(state): State<AppState>,
+    Path(bet_id): Path<Uuid>,
+) -> Result<Json<SettlementReceipt>, (StatusCode, String)> {
+    match state.storage()?.get_settlement_receipt(&bet_id).await {
+        Ok(Some(receipt)) => Ok(Json(state.vrf_engine.sign_receipt(receipt))),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Bet is not settled".to_string())),
+        Err(e) => {
+            tracing::error!(error = %e, bet_id = %bet_id, "Failed to load settlement receipt");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to load settlement receipt".to_string()))
+        }
+    }
+}
+
+/// Largest page served by `/player/:pubkey/ledger`
+const MAX_LEDGER_PAGE: u64 = 1000;
+
+#[derive(Deserialize)]
+struct LedgerPage {
+    #[serde(default)]
+    offset: u64, // Settled bets to skip
+    limit: Option<u64>,
+}
+
+/// A player's settled wagers and payouts with running balances, oldest first
+async fn player_ledger(
+    State(state): State<AppState>,
+    Path(player_pubkey): Path<String>,
+    Query(page): Query<LedgerPage>,
+) -> Result<Json<PlayerLedger>, (StatusCode, String)> {
+    let limit = page.limit.unwrap_or(100);
+    if limit == 0 || limit > MAX_LEDGER_PAGE {
+        return Err((StatusCode::BAD_REQUEST, format!("`limit` must be between 1 and {}", MAX_LEDGER_PAGE)));
+    }
+
+    match state.storage()?.get_player_ledger(&player_pubkey, page.offset, limit).await {
+        Ok(ledger) => Ok(Json(ledger)),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to load player ledger");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to load player ledger".to_string()))
+        }
+    }
+}
+
+/// Re-derive a batch's outcomes from stored inputs and report any mismatch (dev only)
+async fn replay_batch(
+    State(state): State<AppState>,
+    Path(batch_id): Path<Uuid>,
+) -> Result<Json<BatchReplay>, (StatusCode, String)> {
+    match state.storage()?.batch_exists(&batch_id).await {
+        Ok(true) => {}
+        Ok(false) => return Err((StatusCode::NOT_FOUND, "Unknown batch".to_string())),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to look up settlement batch");
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to replay batch".to_string()));
+        }
+    }
+
+    match state.storage()?.replay_batch(&batch_id).await {
+        Ok(replay) => Ok(Json(replay)),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to replay settlement batch");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to replay batch".to_string()))
+        }
+    }
+}
+
+/// Rows fetched per page while streaming a batch's proofs
+const PROOF_EXPORT_PAGE_SIZE: i64 = 500;
+
+/// Stream every proof bundle in a settled batch as one JSON array
+async fn batch_proofs(
+    State(state): State<AppState>,
+    Path(batch_id): Path<Uuid>,
+) -> Result<Response, (StatusCode, String)> {
+    match state.storage()?.batch_exists(&batch_id).await {
+        Ok(true) => {}
+        Ok(false) => return Err((StatusCode::NOT_FOUND, "Unknown batch".to_string())),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to look up settlement batch");
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to export batch proofs".to_string()));
+        }
+    }
+
+    let mut first = true;
+    let items = state
+        .storage()?
+        .stream_batch_proofs(batch_id, PROOF_EXPORT_PAGE_SIZE)
+        .map(move |page| {
+            let mut chunk = String::new();
+            for bundle in page? {
+                if !first {
+                    chunk.push(',');
+                }
+                first = false;
+                chunk.push_str(&serde_json::to_string(&bundle).unwrap_or_default());
+            }
+            Ok::<_, VfError>(chunk)
+        });
+
+    let body = futures_util::stream::once(async { Ok("[".to_string()) })
+        .chain(items)
+        .chain(futures_util::stream::once(async { Ok("]".to_string()) }));
+
+    Ok(([(header::CONTENT_TYPE, "application/json")], Body::from_stream(body)).into_response())
+}
+
+/// Games this node plays and settles; only coinflips go through the settlement engine
+fn settled_games() -> GameRegistry {
+    let mut games = GameRegistry::empty();
+    games.register(Arc::new(Coinflip));
+    games
+}
+
+/// Open `DATABASE_URL`, migrating when `AUTO_MIGRATE` is on and checking the schema.
+/// SQLite serves every endpoint; PostgreSQL carries only the settlement tables.
+async fn open_database(config: &NodeConfig) -> Result<(Option<Arc<Storage>>, Arc<dyn StorageBackend>), String> {
+    let unreachable = |e: VfError| format!("DATABASE_URL '{}' is not reachable: {}", config.database_url, e);
+    let stale = |e: VfError| format!("DATABASE_URL '{}': {}", config.database_url, e);
+
+    #[cfg(feature = "postgres")]
+    if vfnode::backend::is_postgres_url(&config.database_url) {
+        let backend = vfnode::postgres::PostgresBackend::connect(&config.database_url, &config.storage_options())
+            .await
+            .map_err(unreachable)?;
+        if config.auto_migrate {
+            backend.migrate().await.map_err(unreachable)?;
+        }
+        backend.check_schema().await.map_err(stale)?;
+        return Ok((None, Arc::new(backend)));
+    }
+
+    let storage = Storage::open_with(&config.database_url, config.auto_migrate, &config.storage_options())
+        .await
+        .map_err(unreachable)?;
+    storage.check_schema().await.map_err(stale)?;
+    let backend = Arc::new(SqliteBackend::new(&storage.pool()));
+    Ok((Some(Arc::new(storage)), backend))
+}
+
 #[tokio::main(flavor = "multi_thread", worker_threads = 8)]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Enhanced tracing for performance monitoring
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "vfnode=info,tower_http=info".into())
-        )
-        .with(tracing_subscriber::fmt::layer()
-            .with_target(false)
-            .compact()
-        )
-        .init();
-
-    // Initialize storage
-    let storage = Arc::new(Storage::new("sqlite:./vfnode.db").await?);
-
-    // Initialize VRF engine
-    let vrf_engine = Arc::new(VrfEngine::new());
+    // Validate everything up front so all problems are reported together
+    let (config, mut problems) = NodeConfig::from_env();
+    vfnode::logging::init(config.log_format);
+
+    let vrf_engine = config
+        .load_engine()
+        .map_err(|e| problems.push(e))
+        .ok();
+
+    let database = open_database(&config).await.map_err(|e| problems.push(e)).ok();
+
+    let outcome_feed = config.outcome_feed_file.as_deref().and_then(|path| {
+        OutcomeFeed::open(path, OutcomeFeed::DEFAULT_CAPACITY)
+            .map_err(|e| problems.push(format!("OUTCOME_FEED_FILE '{}' cannot be opened: {}", path.display(), e)))
+            .ok()
+            .map(Arc::new)
+    });
+
+    #[cfg(feature = "solana")]
+    let settler = config.load_settler().map_err(|e| problems.push(e)).ok().flatten();
+    #[cfg(not(feature = "solana"))]
+    let settler: Option<Arc<dyn vfnode::settlement_engine::Settler>> = None;
+
+    let tls = config.load_tls().map_err(|e| problems.push(e)).ok().flatten();
+
+    let addr = config.bind_addr();
+    let listener = match tls {
+        Some(acceptor) => Listener::bind_tls(&addr, acceptor).await,
+        None => Listener::bind(&addr).await,
+    };
+    let listener = listener
+        .map_err(|e| {
+            let key = if config.listen_addr.is_some() { "BIND_ADDR".to_string() } else { format!("PORT {}", config.port) };
+            problems.push(format!("{}: cannot bind {}: {}", key, addr, e))
+        })
+        .ok();
+
+    if !problems.is_empty() {
+        eprintln!("❌ {}", problems);
+        std::process::exit(1);
+    }
+
+    vfnode::redact::set_log_raw_seeds(config.log_raw_seeds);
+    if config.log_raw_seeds {
+        tracing::warn!("LOG_RAW_SEEDS enabled: user seeds are written to logs in cleartext");
+    }
+    let (Some(vrf_engine), Some((storage, backend)), Some(listener)) = (vrf_engine, database, listener) else {
+        unreachable!("startup failures are recorded in problems");
+    };
+
+    if let (true, Some(storage)) = (config.backfill_legacy_proofs, &storage) {
+        if let Err(e) = storage.backfill_legacy_proofs().await {
+            tracing::warn!(error = %e, "Legacy proof backfill failed; export will flag rows on the fly");
+        }
+    }
+    let vrf_engine = Arc::new(vrf_engine);
     
     // Initialize settlement engine with high-performance configuration
-    let settlement_engine = SettlementEngine::new(
-        storage.pool(),
-        50,  // batch_size: Process up to 50 bets per settlement
-        10   // processing_interval_seconds: Process every 10 seconds (for testing)
-    )?;
+    let settlement_config = SettlementConfig {
+        batch_size: 50,                  // Process up to 50 bets per settlement
+        processing_interval_seconds: 10, // Process every 10 seconds (for testing)
+        max_batch_payout_lamports: config.max_batch_payout_lamports,
+        deterministic_bet_ids: config.deterministic_bet_ids,
+        channel_capacity: config.settlement_channel_capacity,
+        retry_queue_health_limit: config.retry_queue_health_limit,
+        mode: config.settlement_mode,
+        mock_failure_rate: config.mock_failure_rate,
+        mock_rng_seed: None,
+        retention: config.retention,
+        confirmation_poll_interval: SettlementConfig::DEFAULT_CONFIRMATION_POLL_INTERVAL,
+        confirmation_timeout: SettlementConfig::DEFAULT_CONFIRMATION_TIMEOUT,
+    };
+    let settlement_engine = match settler {
+        Some(settler) => SettlementEngine::with_settler(backend.clone(), settlement_config, settler)?,
+        None => SettlementEngine::with_backend(backend.clone(), settlement_config)?,
+    };
     
+    // Chains continue from the last stored round of each player rather than restarting
+    if config.seed_chaining {
+        let bets = backend.latest_player_proofs(config.seed_chain_capacity as i64).await?;
+        let players = vrf_engine.restore_chain_heads(&bets);
+        tracing::info!(players, "Restored seed chain heads");
+    }
+
     tracing::info!(
         node_pubkey = vrf_engine.node_pubkey(),
         worker_threads = num_cpus::get(),
         settlement_interval_seconds = 10,
         settlement_batch_size = 50,
+        max_batch_payout_lamports = ?config.max_batch_payout_lamports,
+        deterministic_bet_ids = config.deterministic_bet_ids,
+        settlement_mode = config.settlement_mode.as_str(),
+        database_backend = backend.name(),
         "VF Node with Settlement Engine initializing"
     );
 
     let state = AppState { 
         vrf_engine,
-        settlement_engine,
+        settlement_engine: settlement_engine.clone(),
+        backend,
         storage,
+        tokens: Arc::new(config.tokens.clone()),
+        outcome_feed,
+        games: Arc::new(settled_games()),
+        commits: Arc::new(CommitStore::new(config.commit_ttl, CommitStore::DEFAULT_CAPACITY)),
+        client_limiter: config.client_rate_limiter().map(Arc::new),
+        free_play: config.free_play,
+        audit_trail: config.audit_trail,
     };
 
+    if config.cors_allowed_origins.is_none() {
+        tracing::warn!("CORS_ALLOWED_ORIGINS unset: accepting cross-origin requests from any origin");
+    }
+
+    if config.admin_api_key.is_none() {
+        tracing::warn!("ADMIN_API_KEY unset: /settlement/* endpoints reject every request");
+    }
+
+    // Operational endpoints, behind X-Api-Key
+    let mut admin = Router::new()
+        .route("/settlement/stats", get(settlement_stats))
+        .route("/settlement/summary", get(settlement_summary))
+        .route("/settlement/batches", get(settlement_batches))
+        .route("/settlement/dead-letters", get(dead_letters))
+        .route("/settlement/requeue/:bet_id", post(requeue_dead_letter))
+        .route("/settlement/config", post(update_settlement_config))
+        .route("/settlement/batch/:id/proofs", get(batch_proofs))
+        .route("/settlement/bet/:id/inclusion", get(bet_inclusion))
+        .route("/settlement/bet/:id/receipt", get(bet_receipt));
+
+    // Debug-only endpoints, never exposed in production
+    if config.dev_mode {
+        tracing::warn!("DEV_MODE enabled: debug endpoints are exposed");
+        admin = admin.route("/settlement/batch/:id/replay", get(replay_batch));
+    }
+
+    let admin = admin.route_layer(middleware::from_fn_with_state(config.admin_api_key.clone(), require_admin_key));
+
     // Optimized router with settlement endpoints
-    let app = Router::new()
+    let mut app = Router::new()
         .route("/coinflip", post(coinflip))
-        .route("/health", get(health))
+        .route("/commit", post(commit))
+        .route("/reveal", post(reveal))
         .route("/info", get(node_info))
-        .route("/settlement/stats", get(settlement_stats))
-        .route("/settlement/summary", get(settlement_summary))
+        .route("/ws/settlements", get(settlement_socket))
+        .route("/bet/:id", get(bet_status))
+        .route("/player/:pubkey/ledger", get(player_ledger))
+        .route("/stats/realized-edge", get(realized_edge))
+        .merge(admin);
+
+    // Health checks and scrapes come from a few fixed addresses; keep them out of the limit
+    if let Some(limiter) = &state.client_limiter {
+        tracing::info!(
+            client_rate_limit_per_second = config.client_rate_limit_per_second,
+            client_rate_limit_burst = config.client_rate_limit_burst.or(config.client_rate_limit_per_second),
+            "Per-client rate limit enabled"
+        );
+        app = app.route_layer(middleware::from_fn_with_state(limiter.clone(), limit_clients));
+    }
+
+    let app = app
+        .route("/health", get(health))
+        .route("/health/deep", get(deep_health))
+        .route("/ready", get(ready))
+        .route("/metrics", get(metrics));
+
+    let app = app
         .layer(CompressionLayer::new()) // Compress responses
         .layer(TimeoutLayer::new(Duration::from_secs(5))) // Request timeout
-        .layer(CorsLayer::permissive())
+        .layer(RequestBodyLimitLayer::new(config.max_body_bytes)) // 413 before oversized bodies are buffered
+        .layer(config.cors_layer())
         .layer(TraceLayer::new_for_http())
         .with_state(state);
 
-    // Optimized server configuration
-    let port = std::env::var("PORT").unwrap_or_else(|_| "3001".to_string());
-    let addr = format!("0.0.0.0:{}", port);
-    
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    
     // Enhanced startup info
     tracing::info!(
         addr = %addr,
@@ -170,18 +819,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "VF Node with Settlement Engine server starting"
     );
     
-    println!("🚀 VF Node running on http://{}", addr);
+    let scheme = if config.tls_cert_path.is_some() { "https" } else { "http" };
+    println!("🚀 VF Node running on {}://{}", scheme, addr);
     println!("⚡ Multi-threaded with {} worker threads", num_cpus::get());
     println!("🎯 Optimized for high-throughput, low-latency");
     println!("🏦 Settlement engine: 50 bets per batch, 10 second intervals");
-    println!("📊 Settlement stats: http://{}/settlement/stats", addr);
+    println!("📊 Settlement stats: {}://{}/settlement/stats", scheme, addr);
     
-    axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
-    )
-    .with_graceful_shutdown(shutdown_signal())
-    .await?;
+    listen::serve(listener, app, shutdown_signal()).await?;
+
+    // Requests have drained; land any bets still buffered before exiting
+    settlement_engine.shutdown().await;
 
     Ok(())
 }
@@ -210,4 +858,83 @@ async fn shutdown_signal() {
     }
 
     tracing::info!("Shutdown signal received, starting graceful shutdown");
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn test_state() -> AppState {
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let backend: Arc<dyn StorageBackend> = Arc::new(SqliteBackend::new(&storage.pool()));
+        let settlement_engine = SettlementEngine::with_backend(backend.clone(), SettlementConfig::default()).unwrap();
+        AppState {
+            vrf_engine: Arc::new(VrfEngine::new()),
+            settlement_engine,
+            backend,
+            storage: Some(storage),
+            tokens: Arc::new(TokenRegistry::default()),
+            outcome_feed: None,
+            games: Arc::new(settled_games()),
+            commits: Arc::new(CommitStore::new(CommitStore::DEFAULT_TTL, 16)),
+            client_limiter: None,
+            free_play: false,
+            audit_trail: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_revealed_flips_are_queued_for_settlement() {
+        let state = test_state().await;
+        let Json(committed) = commit(State(state.clone())).await;
+
+        let req = RevealRequest {
+            commit_id: committed.commit_id,
+            bet: CoinflipRequest { user_seed: "revealed".to_string(), wager_lamports: Some(1000), ..Default::default() },
+        };
+        let Json(revealed) = reveal(State(state.clone()), Json(req.clone())).await.unwrap();
+        assert_eq!(revealed.commitment, committed.commitment);
+        assert!(revealed.response.bet_id.is_some());
+        assert_eq!(revealed.response.proof.node_seed.as_deref(), Some(revealed.node_seed.as_str()));
+        assert!(state.vrf_engine.verify_proof(&revealed.response.proof, &req.bet).unwrap());
+
+        assert_eq!(reveal(State(state), Json(req)).await.unwrap_err(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_reused_bet_id_is_refused() {
+        let state = test_state().await;
+        let bet_id = uuid::Uuid::new_v4();
+        let req = CoinflipRequest { user_seed: "first".to_string(), bet_id: Some(bet_id), wager_lamports: Some(1000), ..Default::default() };
+
+        let Json(first) = coinflip(State(state.clone()), Json(req.clone())).await.unwrap();
+        assert_eq!(first.bet_id, Some(bet_id));
+
+        let reused = CoinflipRequest { user_seed: "second".to_string(), ..req };
+        assert_eq!(coinflip(State(state), Json(reused)).await.unwrap_err(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_coinflip_failures_map_to_status() {
+        let vrf = |e| CoinflipFailure::Vrf(e).status();
+        assert_eq!(vrf(VfError::InvalidInput("empty seed".into())), StatusCode::BAD_REQUEST);
+        assert_eq!(vrf(VfError::InvalidTimestamp("stale".into())), StatusCode::BAD_REQUEST);
+        assert_eq!(vrf(VfError::DuplicateRequest("seen".into())), StatusCode::CONFLICT);
+        assert_eq!(vrf(VfError::DuplicateBet("replayed".into())), StatusCode::CONFLICT);
+        assert_eq!(vrf(VfError::ComputeBudgetExceeded("spent".into())), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(vrf(VfError::BadWalletSignature("forged".into())), StatusCode::UNAUTHORIZED);
+        assert_eq!(vrf(VfError::Unavailable("shutting down".into())), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(vrf(VfError::VrfFailed("beacon down".into())), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let panicked = tokio::task::spawn_blocking(|| panic!("flip blew up")).await.unwrap_err();
+        assert!(panicked.is_panic());
+        assert_eq!(CoinflipFailure::Task(panicked).into_status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let enqueue = CoinflipFailure::Enqueue(VfError::Unavailable("Settlement channel closed".into()));
+        assert_eq!(enqueue.into_status(), StatusCode::SERVICE_UNAVAILABLE);
+        let backpressure = CoinflipFailure::Enqueue(VfError::SettlementBackpressure("full".into()));
+        assert_eq!(backpressure.into_status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let reused = CoinflipFailure::Reserve(VfError::DuplicateBet("taken".into()));
+        assert_eq!(reused.into_status(), StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+use vfnode::verifier::verify_bundle_file;
+
+/// Offline auditor: verify every bundle in an exported proof file without a node or database.
+///
+/// Usage: vfnode-verify <bundles.json>
+fn main() {
+    let Some(path) = std::env::args_os().nth(1).map(PathBuf::from) else {
+        eprintln!("Usage: vfnode-verify <bundles.json>");
+        std::process::exit(2);
+    };
+
+    let verdicts = match verify_bundle_file(&path) {
+        Ok(verdicts) => verdicts,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let invalid: Vec<_> = verdicts.iter().filter(|v| !v.valid).collect();
+    for verdict in &invalid {
+        println!("❌ {}: {}", verdict.bet_id, verdict.error.as_deref().unwrap_or("invalid"));
+    }
+    println!("{} bundles checked, {} valid, {} invalid", verdicts.len(), verdicts.len() - invalid.len(), invalid.len());
+
+    if !invalid.is_empty() {
+        std::process::exit(1);
+    }
+}
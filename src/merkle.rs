@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Which side a sibling hash sits on when folding a proof up to the root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleStep {
+    pub side: Side,
+    pub hash: String, // Hex sibling hash
+}
+
+/// Leaf committing to one settled bet: its id, VRF signature and outcome.
+pub fn leaf_hash(bet_id: &Uuid, vrf_signature: &str, heads: bool) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(bet_id.as_bytes());
+    hasher.update((vrf_signature.len() as u32).to_le_bytes());
+    hasher.update(vrf_signature.as_bytes());
+    hasher.update([heads as u8]);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Binary Merkle tree over a batch's leaves. Leaf and node hashes are domain separated,
+/// and an odd node at the end of a level is carried up unchanged.
+pub struct MerkleTree {
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<[u8; 32]>) -> Self {
+        let mut levels = vec![leaves];
+        while levels.last().is_some_and(|level| level.len() > 1) {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    /// Root of the tree, or all zeroes for an empty batch
+    pub fn root(&self) -> [u8; 32] {
+        self.levels.last().and_then(|level| level.first()).copied().unwrap_or([0u8; 32])
+    }
+
+    /// Sibling path from leaf `index` up to the root
+    pub fn proof(&self, mut index: usize) -> Option<Vec<MerkleStep>> {
+        if index >= self.levels[0].len() {
+            return None;
+        }
+
+        let mut path = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = index ^ 1;
+            if let Some(hash) = level.get(sibling) {
+                let side = if sibling < index { Side::Left } else { Side::Right };
+                path.push(MerkleStep { side, hash: hex::encode(hash) });
+            }
+            index /= 2;
+        }
+        Some(path)
+    }
+}
+
+/// Tree over a settlement batch, with leaves in `bet_id` order so the root never depends
+/// on the order bets happened to be collected in. Returns the ordered ids alongside.
+pub fn batch_tree<'a>(bets: impl IntoIterator<Item = (Uuid, &'a str, bool)>) -> (Vec<Uuid>, MerkleTree) {
+    let mut bets: Vec<_> = bets.into_iter().collect();
+    bets.sort_by_key(|(bet_id, _, _)| *bet_id);
+
+    let ids = bets.iter().map(|(bet_id, _, _)| *bet_id).collect();
+    let leaves = bets.iter().map(|(bet_id, sig, heads)| leaf_hash(bet_id, sig, *heads)).collect();
+    (ids, MerkleTree::new(leaves))
+}
+
+/// Fold `leaf` up through `path` and check it lands on `root`
+pub fn verify_path(leaf: [u8; 32], path: &[MerkleStep], root: &[u8; 32]) -> bool {
+    let mut acc = leaf;
+    for step in path {
+        let Some(sibling) = hex::decode(&step.hash).ok().and_then(|b| <[u8; 32]>::try_from(b).ok()) else {
+            return false;
+        };
+        acc = match step.side {
+            Side::Left => node_hash(&sibling, &acc),
+            Side::Right => node_hash(&acc, &sibling),
+        };
+    }
+    &acc == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_leaf_proves_membership() {
+        for size in 1..=9 {
+            let leaves: Vec<_> = (0..size).map(|i| leaf_hash(&Uuid::from_u128(i), "sig", i % 2 == 0)).collect();
+            let tree = MerkleTree::new(leaves.clone());
+            let root = tree.root();
+
+            for (i, leaf) in leaves.iter().enumerate() {
+                let path = tree.proof(i).unwrap();
+                assert!(verify_path(*leaf, &path, &root), "size {} leaf {}", size, i);
+            }
+            assert!(tree.proof(size as usize).is_none());
+        }
+    }
+
+    #[test]
+    fn test_wrong_leaf_fails() {
+        let leaves: Vec<_> = (0..4).map(|i| leaf_hash(&Uuid::from_u128(i), "sig", true)).collect();
+        let tree = MerkleTree::new(leaves);
+        let forged = leaf_hash(&Uuid::from_u128(1), "sig", false);
+        assert!(!verify_path(forged, &tree.proof(1).unwrap(), &tree.root()));
+    }
+}
@@ -0,0 +1,148 @@
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Process-wide counters for the Prometheus `/metrics` endpoint.
+///
+/// Updated with relaxed atomics on the flip and settlement paths, so scraping never takes a
+/// lock or touches the database.
+pub static METRICS: Metrics = Metrics::new();
+
+/// Upper bounds (inclusive, ms) of the `processing_time_ms` histogram buckets
+pub const PROCESSING_TIME_BUCKETS_MS: [u64; 12] = [1, 2, 5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000];
+
+pub struct Metrics {
+    coinflips: AtomicU64,
+    heads: AtomicU64,
+    tails: AtomicU64,
+    batches_succeeded: AtomicU64,
+    batches_failed: AtomicU64,
+    retry_queue_size: AtomicU64,
+    processing_time_buckets: [AtomicU64; PROCESSING_TIME_BUCKETS_MS.len()],
+    processing_time_sum_ms: AtomicU64,
+}
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Self {
+            coinflips: AtomicU64::new(0),
+            heads: AtomicU64::new(0),
+            tails: AtomicU64::new(0),
+            batches_succeeded: AtomicU64::new(0),
+            batches_failed: AtomicU64::new(0),
+            retry_queue_size: AtomicU64::new(0),
+            processing_time_buckets: [const { AtomicU64::new(0) }; PROCESSING_TIME_BUCKETS_MS.len()],
+            processing_time_sum_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a completed flip
+    pub fn record_flip(&self, heads: bool, processing_time_ms: u64) {
+        self.coinflips.fetch_add(1, Ordering::Relaxed);
+        if heads {
+            self.heads.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.tails.fetch_add(1, Ordering::Relaxed);
+        }
+        // Buckets are stored non-cumulatively and summed when rendered; slower flips only
+        // land in +Inf, which is the total count
+        if let Some(bucket) = PROCESSING_TIME_BUCKETS_MS.iter().position(|&le| processing_time_ms <= le) {
+            self.processing_time_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.processing_time_sum_ms.fetch_add(processing_time_ms, Ordering::Relaxed);
+    }
+
+    pub fn record_batch(&self, success: bool) {
+        let counter = if success { &self.batches_succeeded } else { &self.batches_failed };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_retry_queue_size(&self, size: usize) {
+        self.retry_queue_size.store(size as u64, Ordering::Relaxed);
+    }
+
+    /// Prometheus text exposition format (version 0.0.4)
+    pub fn render(&self) -> String {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}\n{} {}", name, help, name, kind, name, value);
+        };
+
+        metric("vf_coinflips_total", "counter", "Coinflips processed", load(&self.coinflips));
+        metric("vf_coinflip_heads_total", "counter", "Coinflips that landed heads", load(&self.heads));
+        metric("vf_coinflip_tails_total", "counter", "Coinflips that landed tails", load(&self.tails));
+        metric(
+            "vf_settlement_batches_succeeded_total",
+            "counter",
+            "Settlement batches that settled",
+            load(&self.batches_succeeded),
+        );
+        metric(
+            "vf_settlement_batches_failed_total",
+            "counter",
+            "Settlement batches that failed",
+            load(&self.batches_failed),
+        );
+        metric(
+            "vf_settlement_retry_queue_size",
+            "gauge",
+            "Bets waiting to be retried after a failed batch",
+            load(&self.retry_queue_size),
+        );
+
+        let name = "vf_coinflip_processing_time_ms";
+        let _ = writeln!(out, "# HELP {} Coinflip processing time in milliseconds\n# TYPE {} histogram", name, name);
+        let mut cumulative = 0;
+        for (le, bucket) in PROCESSING_TIME_BUCKETS_MS.iter().zip(&self.processing_time_buckets) {
+            cumulative += load(bucket);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, le, cumulative);
+        }
+        let count = load(&self.coinflips);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let _ = writeln!(out, "{}_sum {}", name, load(&self.processing_time_sum_ms));
+        let _ = writeln!(out, "{}_count {}", name, count);
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counts_and_cumulative_buckets() {
+        let metrics = Metrics::new();
+        metrics.record_flip(true, 0);
+        metrics.record_flip(false, 7);
+        metrics.record_flip(true, 60_000);
+        metrics.record_batch(true);
+        metrics.record_batch(false);
+        metrics.set_retry_queue_size(4);
+
+        let text = metrics.render();
+        for line in [
+            "# TYPE vf_coinflips_total counter",
+            "vf_coinflips_total 3",
+            "vf_coinflip_heads_total 2",
+            "vf_coinflip_tails_total 1",
+            "vf_settlement_batches_succeeded_total 1",
+            "vf_settlement_batches_failed_total 1",
+            "vf_settlement_retry_queue_size 4",
+            "# TYPE vf_coinflip_processing_time_ms histogram",
+            "vf_coinflip_processing_time_ms_bucket{le=\"1\"} 1",
+            "vf_coinflip_processing_time_ms_bucket{le=\"10\"} 2",
+            "vf_coinflip_processing_time_ms_bucket{le=\"5000\"} 2",
+            "vf_coinflip_processing_time_ms_bucket{le=\"+Inf\"} 3",
+            "vf_coinflip_processing_time_ms_sum 60007",
+            "vf_coinflip_processing_time_ms_count 3",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {:?} in:\n{}", line, text);
+        }
+    }
+}
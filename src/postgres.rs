@@ -0,0 +1,883 @@
+use crate::backend::StorageBackend;
+use crate::games::DEFAULT_GAME;
+use crate::settlement_engine::{BatchResult, PendingBet, RequeueOutcome};
+use crate::storage::{game_summary, StorageOptions, EXPECTED_SCHEMA};
+use crate::types::{
+    BatchRecord, BetStatus, CoinSide, OutboxEntry, OutboxStatus, OutcomeBit, ProofStatus, SeedEncoding, SettlementStatus, VfError,
+    VrfProof,
+};
+use async_trait::async_trait;
+use sqlx::postgres::{PgPool, PgPoolOptions, PgRow};
+use sqlx::Row;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Tables the settlement pipeline needs; the audit tables stay SQLite-only
+const SETTLEMENT_TABLES: &[&str] = &["pending_bets", "settlement_batches", "settlement_outbox", "settlement_lock", "dead_letter_bets"];
+
+/// Settlement backend on PostgreSQL, for nodes sharing one database across hosts.
+///
+/// The tables mirror the SQLite schema column for column (timestamps stay RFC 3339
+/// text), so bets read back identically. Queries are built at runtime since the
+/// compile-time checked ones are tied to SQLite.
+pub struct PostgresBackend {
+    pool: PgPool,
+}
+
+impl PostgresBackend {
+    /// Connect to a `postgres://` URL. Only `max_connections` applies; the journal mode and
+    /// busy timeout are SQLite settings.
+    pub async fn connect(database_url: &str, options: &StorageOptions) -> Result<Self, VfError> {
+        info!("🐘 Connecting to PostgreSQL");
+        let pool = PgPoolOptions::new()
+            .max_connections(options.max_connections)
+            .connect(database_url)
+            .await
+            .map_err(|e| VfError::InvalidInput(format!("Database error: cannot connect to PostgreSQL: {}", e)))?;
+        Ok(Self { pool })
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Create the settlement tables and indexes if they don't exist yet
+    pub async fn migrate(&self) -> Result<(), VfError> {
+        info!("🔄 Running PostgreSQL migrations...");
+
+        let statements = [
+            r#"
+            CREATE TABLE IF NOT EXISTS pending_bets (
+                bet_id TEXT PRIMARY KEY,
+                user_seed TEXT NOT NULL,
+                timestamp BIGINT NOT NULL,
+                node_id TEXT NOT NULL,
+                heads BOOLEAN NOT NULL,
+                vrf_proof TEXT NOT NULL,
+                processing_time_ms BIGINT NOT NULL,
+                processed_at TEXT NOT NULL,
+                retry_count BIGINT DEFAULT 0,
+                status TEXT DEFAULT 'pending',
+                tx_signature TEXT NULL,
+                settled_at TEXT NULL,
+                failed_at TEXT NULL,
+                error_message TEXT NULL,
+                created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+                batch_id TEXT NULL,
+                token_mint TEXT NULL,
+                wager_lamports BIGINT NULL,
+                payout_lamports BIGINT NULL,
+                proof_status TEXT NULL,
+                player_pubkey TEXT NULL,
+                client_seed TEXT NULL,
+                player_choice TEXT NULL,
+                win_probability_bps BIGINT NULL,
+                outcome_bit TEXT NULL,
+                proof_json TEXT NULL,
+                seed_encoding TEXT NULL,
+                game TEXT NULL
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS settlement_batches (
+                batch_id TEXT PRIMARY KEY,
+                bet_count BIGINT NOT NULL,
+                processing_time_ms BIGINT NOT NULL,
+                tx_signature TEXT NOT NULL,
+                success BOOLEAN NOT NULL,
+                created_at TEXT NOT NULL,
+                merkle_root TEXT NULL
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS settlement_lock (
+                id BIGINT PRIMARY KEY CHECK (id = 1),
+                owner TEXT NOT NULL,
+                heartbeat_at BIGINT NOT NULL
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS settlement_outbox (
+                batch_id TEXT PRIMARY KEY,
+                bet_count BIGINT NOT NULL,
+                payout_lamports BIGINT NOT NULL,
+                merkle_root TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                tx_signature TEXT NULL,
+                error_message TEXT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS dead_letter_bets (
+                bet_id TEXT PRIMARY KEY,
+                user_seed TEXT NOT NULL,
+                client_seed TEXT NULL,
+                timestamp BIGINT NOT NULL,
+                node_id TEXT NOT NULL,
+                heads BOOLEAN NOT NULL,
+                vrf_proof TEXT NOT NULL,
+                proof_json TEXT NULL,
+                proof_status TEXT NULL,
+                processing_time_ms BIGINT NOT NULL,
+                processed_at TEXT NOT NULL,
+                token_mint TEXT NULL,
+                wager_lamports BIGINT NULL,
+                payout_lamports BIGINT NULL,
+                player_pubkey TEXT NULL,
+                player_choice TEXT NULL,
+                win_probability_bps BIGINT NULL,
+                outcome_bit TEXT NULL,
+                retry_count BIGINT NOT NULL,
+                error_message TEXT NOT NULL,
+                failed_at TEXT NOT NULL,
+                seed_encoding TEXT NULL,
+                game TEXT NULL
+            )
+            "#,
+            // Columns added after the first PostgreSQL schema
+            "ALTER TABLE pending_bets ADD COLUMN IF NOT EXISTS game TEXT NULL",
+            "ALTER TABLE dead_letter_bets ADD COLUMN IF NOT EXISTS game TEXT NULL",
+            "CREATE INDEX IF NOT EXISTS idx_pending_bets_status ON pending_bets(status)",
+            "CREATE INDEX IF NOT EXISTS idx_pending_bets_processed_at ON pending_bets(processed_at)",
+            "CREATE INDEX IF NOT EXISTS idx_pending_bets_status_processed_at ON pending_bets(status, processed_at)",
+            "CREATE INDEX IF NOT EXISTS idx_pending_bets_retry_count ON pending_bets(retry_count)",
+            "CREATE INDEX IF NOT EXISTS idx_pending_bets_batch_id ON pending_bets(batch_id)",
+            "CREATE INDEX IF NOT EXISTS idx_pending_bets_settled_at ON pending_bets(settled_at)",
+            "CREATE INDEX IF NOT EXISTS idx_settlement_batches_created_at ON settlement_batches(created_at)",
+            "CREATE INDEX IF NOT EXISTS idx_settlement_outbox_status ON settlement_outbox(status)",
+            "CREATE INDEX IF NOT EXISTS idx_dead_letter_bets_failed_at ON dead_letter_bets(failed_at)",
+        ];
+        for statement in statements {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        info!("✅ PostgreSQL migrations completed");
+        Ok(())
+    }
+
+    /// Fail fast if a settlement table or column is missing, like [`crate::storage::Storage::check_schema`]
+    pub async fn check_schema(&self) -> Result<(), VfError> {
+        let mut missing = Vec::new();
+        for &(table, columns) in EXPECTED_SCHEMA.iter().filter(|(table, _)| SETTLEMENT_TABLES.contains(table)) {
+            let present: Vec<String> = sqlx::query_scalar(
+                "SELECT column_name::TEXT FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = $1",
+            )
+            .bind(table)
+            .fetch_all(&self.pool)
+            .await?;
+
+            if present.is_empty() {
+                missing.push(format!("table {}", table));
+                continue;
+            }
+            missing.extend(
+                columns
+                    .iter()
+                    .filter(|column| !present.iter().any(|p| p == *column))
+                    .map(|column| format!("{}.{}", table, column)),
+            );
+        }
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(VfError::InvalidInput(format!(
+            "Database error: schema is out of date, run migrations (start once with AUTO_MIGRATE=1). Missing: {}",
+            missing.join(", ")
+        )))
+    }
+
+    fn outbox_entry(row: &PgRow) -> Result<OutboxEntry, VfError> {
+        let status: String = row.try_get("status")?;
+        Ok(OutboxEntry {
+            batch_id: Uuid::parse_str(row.try_get("batch_id")?)?,
+            bet_count: row.try_get::<i64, _>("bet_count")? as u64,
+            payout_lamports: row.try_get::<i64, _>("payout_lamports")? as u64,
+            merkle_root: row.try_get("merkle_root")?,
+            status: OutboxStatus::parse(&status)
+                .ok_or_else(|| VfError::InvalidInput(format!("Unknown outbox status '{}'", status)))?,
+            tx_signature: row.try_get("tx_signature")?,
+            error_message: row.try_get("error_message")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+
+    fn pending_bet(row: &PgRow) -> Result<PendingBet, VfError> {
+        let processed_at: String = row.try_get("processed_at")?;
+        Ok(PendingBet {
+            bet_id: Uuid::parse_str(row.try_get("bet_id")?)?,
+            game: row.try_get::<Option<String>, _>("game")?.unwrap_or_else(|| DEFAULT_GAME.to_string()),
+            user_seed: row.try_get("user_seed")?,
+            seed_encoding: row.try_get::<Option<&str>, _>("seed_encoding")?.and_then(SeedEncoding::parse).unwrap_or_default(),
+            client_seed: row.try_get("client_seed")?,
+            timestamp: row.try_get::<i64, _>("timestamp")? as u64,
+            node_id: row.try_get("node_id")?,
+            heads: row.try_get("heads")?,
+            vrf_proof: row.try_get("vrf_proof")?,
+            processing_time_ms: row.try_get::<i64, _>("processing_time_ms")? as u64,
+            processed_at: time::OffsetDateTime::parse(&processed_at, &time::format_description::well_known::Rfc3339)?,
+            retry_count: row.try_get::<Option<i64>, _>("retry_count")?.unwrap_or(0) as u32,
+            token_mint: row.try_get("token_mint")?,
+            wager_lamports: row.try_get::<Option<i64>, _>("wager_lamports")?.map(|w| w as u64),
+            payout_lamports: row.try_get::<Option<i64>, _>("payout_lamports")?.map(|p| p as u64),
+            player_pubkey: row.try_get("player_pubkey")?,
+            player_choice: row.try_get::<Option<&str>, _>("player_choice")?.and_then(CoinSide::parse).unwrap_or_default(),
+            win_probability_bps: row.try_get::<Option<i64>, _>("win_probability_bps")?.map(|bps| bps as u16),
+            outcome_bit: row.try_get::<Option<&str>, _>("outcome_bit")?.and_then(OutcomeBit::parse),
+            proof: PendingBet::stored_proof(row.try_get("proof_json")?),
+        })
+    }
+}
+
+#[async_trait]
+impl StorageBackend for PostgresBackend {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn max_connections(&self) -> u32 {
+        self.pool.options().get_max_connections()
+    }
+
+    async fn insert_pending_bets(&self, bets: &[PendingBet]) -> Result<u64, VfError> {
+        let mut tx = self.pool.begin().await?;
+        let mut duplicates = 0u64;
+
+        for bet in bets {
+            let proof_json = bet.proof.as_ref()
+                .map(serde_json::to_string)
+                .transpose()
+                .map_err(|e| VfError::InvalidInput(format!("Cannot encode proof: {}", e)))?;
+            let proof_status = if proof_json.is_some() { ProofStatus::Full } else { ProofStatus::Legacy };
+
+            let inserted = sqlx::query(
+                r#"
+                INSERT INTO pending_bets (
+                    bet_id, user_seed, timestamp, node_id, heads,
+                    vrf_proof, processing_time_ms, processed_at, retry_count, status,
+                    token_mint, wager_lamports, payout_lamports, proof_status, player_pubkey, client_seed,
+                    player_choice, win_probability_bps, outcome_bit, proof_json, seed_encoding, game
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, 'pending', $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21)
+                ON CONFLICT (bet_id) DO NOTHING
+                "#,
+            )
+            .bind(bet.bet_id.to_string())
+            .bind(&bet.user_seed)
+            .bind(bet.timestamp as i64)
+            .bind(&bet.node_id)
+            .bind(bet.heads)
+            .bind(&bet.vrf_proof)
+            .bind(bet.processing_time_ms as i64)
+            .bind(bet.processed_at.format(&time::format_description::well_known::Rfc3339).unwrap())
+            .bind(bet.retry_count as i64)
+            .bind(&bet.token_mint)
+            .bind(bet.wager_lamports.map(|w| w as i64))
+            .bind(bet.payout_lamports.map(|p| p as i64))
+            .bind(proof_status.as_str())
+            .bind(&bet.player_pubkey)
+            .bind(&bet.client_seed)
+            .bind(bet.player_choice.as_str())
+            .bind(bet.win_probability_bps.map(i64::from))
+            .bind(bet.outcome_bit.map(|bit| bit.as_str()))
+            .bind(proof_json)
+            .bind(bet.seed_encoding.as_str())
+            .bind(&bet.game)
+            .execute(&mut *tx)
+            .await?;
+
+            if inserted.rows_affected() == 0 {
+                duplicates += 1;
+                warn!(bet_id = %bet.bet_id, "Duplicate bet id, keeping the stored bet");
+            }
+        }
+
+        tx.commit().await?;
+        Ok(duplicates)
+    }
+
+    async fn pending_bets(&self, limit: i64) -> Result<Vec<PendingBet>, VfError> {
+        let rows = sqlx::query("SELECT * FROM pending_bets WHERE status = 'pending' ORDER BY processed_at ASC LIMIT $1")
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(Self::pending_bet).collect()
+    }
+
+    async fn latest_player_proofs(&self, players: i64) -> Result<Vec<(String, VrfProof)>, VfError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT p.player_pubkey, p.proof_json
+            FROM pending_bets p
+            JOIN (
+                SELECT player_pubkey, MAX(created_at) AS latest FROM pending_bets
+                WHERE player_pubkey IS NOT NULL AND proof_json IS NOT NULL
+                GROUP BY player_pubkey ORDER BY latest DESC LIMIT $1
+            ) l ON p.player_pubkey = l.player_pubkey AND p.created_at = l.latest
+            WHERE p.proof_json IS NOT NULL
+            "#,
+        )
+        .bind(players)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut proofs = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let proof_json: String = row.try_get("proof_json")?;
+            if let Some(proof) = PendingBet::stored_proof(Some(&proof_json)) {
+                proofs.push((row.try_get("player_pubkey")?, proof));
+            }
+        }
+        Ok(proofs)
+    }
+
+    async fn retrying_bets(&self) -> Result<Vec<PendingBet>, VfError> {
+        let rows = sqlx::query(
+            "SELECT * FROM pending_bets WHERE status = 'pending' AND retry_count > 0 ORDER BY processed_at ASC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(Self::pending_bet).collect()
+    }
+
+    async fn claim_batch(&self, batch_id: &Uuid, bets: &[PendingBet]) -> Result<Vec<Uuid>, VfError> {
+        let mut tx = self.pool.begin().await?;
+        let mut claimed = Vec::with_capacity(bets.len());
+
+        for bet in bets {
+            let updated = sqlx::query(
+                "UPDATE pending_bets SET status = 'settling', batch_id = $1 WHERE bet_id = $2 AND status = 'pending'",
+            )
+            .bind(batch_id.to_string())
+            .bind(bet.bet_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+            if updated.rows_affected() > 0 {
+                claimed.push(bet.bet_id);
+            }
+        }
+
+        tx.commit().await?;
+        Ok(claimed)
+    }
+
+    async fn release_batch(&self, bets: &[PendingBet]) -> Result<(), VfError> {
+        let mut tx = self.pool.begin().await?;
+
+        for bet in bets {
+            sqlx::query(
+                "UPDATE pending_bets SET status = 'pending', batch_id = NULL, retry_count = $1 WHERE bet_id = $2 AND status = 'settling'",
+            )
+            .bind(bet.retry_count as i64)
+            .bind(bet.bet_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn settling_bets(&self) -> Result<Vec<(Uuid, PendingBet)>, VfError> {
+        let rows = sqlx::query("SELECT * FROM pending_bets WHERE status = 'settling' ORDER BY processed_at ASC")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter()
+            .map(|row| {
+                let batch_id: Option<String> = row.try_get("batch_id")?;
+                Ok((Uuid::parse_str(batch_id.as_deref().unwrap_or_default())?, Self::pending_bet(row)?))
+            })
+            .collect()
+    }
+
+    async fn record_settled_batch(&self, batch: &[PendingBet], result: &BatchResult, merkle_root: &str) -> Result<(), VfError> {
+        let mut tx = self.pool.begin().await?;
+
+        let settled_at = result.timestamp.format(&time::format_description::well_known::Rfc3339).unwrap();
+        let batch_id = result.batch_id.to_string();
+
+        for bet in batch {
+            sqlx::query(
+                "UPDATE pending_bets SET status = 'settled', tx_signature = $1, settled_at = $2, batch_id = $3 WHERE bet_id = $4",
+            )
+            .bind(&result.mock_tx_signature)
+            .bind(&settled_at)
+            .bind(&batch_id)
+            .bind(bet.bet_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO settlement_batches (
+                batch_id, bet_count, processing_time_ms,
+                tx_signature, success, created_at, merkle_root
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&batch_id)
+        .bind(result.processed_count as i64)
+        .bind(result.processing_time_ms as i64)
+        .bind(&result.mock_tx_signature)
+        .bind(result.success)
+        .bind(&settled_at)
+        .bind(merkle_root)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_outbox_tx(&self, batch_id: &Uuid, bet_count: u64, payout_lamports: u64, merkle_root: &str) -> Result<OutboxEntry, VfError> {
+        let now = time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO settlement_outbox (batch_id, bet_count, payout_lamports, merkle_root, status, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, 'pending', $5, $5)
+            ON CONFLICT (batch_id) DO NOTHING
+            "#,
+        )
+        .bind(batch_id.to_string())
+        .bind(bet_count as i64)
+        .bind(payout_lamports as i64)
+        .bind(merkle_root)
+        .bind(&now)
+        .execute(&self.pool)
+        .await?;
+
+        let row = sqlx::query("SELECT * FROM settlement_outbox WHERE batch_id = $1")
+            .bind(batch_id.to_string())
+            .fetch_one(&self.pool)
+            .await?;
+        Self::outbox_entry(&row)
+    }
+
+    async fn submit_outbox_tx(&self, batch_id: &Uuid, tx_signature: &str) -> Result<bool, VfError> {
+        let now = time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap();
+        let updated = sqlx::query(
+            "UPDATE settlement_outbox SET status = 'submitted', tx_signature = $1, error_message = NULL, updated_at = $2 WHERE batch_id = $3 AND status != 'confirmed'",
+        )
+        .bind(tx_signature)
+        .bind(&now)
+        .bind(batch_id.to_string())
+        .execute(&self.pool)
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    async fn submitted_outbox_txs(&self) -> Result<Vec<OutboxEntry>, VfError> {
+        let rows = sqlx::query("SELECT * FROM settlement_outbox WHERE status = 'submitted' ORDER BY created_at ASC")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(Self::outbox_entry).collect()
+    }
+
+    async fn confirm_outbox_tx(&self, batch_id: &Uuid, tx_signature: &str) -> Result<bool, VfError> {
+        let now = time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap();
+        let updated = sqlx::query(
+            "UPDATE settlement_outbox SET status = 'confirmed', tx_signature = $1, error_message = NULL, updated_at = $2 WHERE batch_id = $3",
+        )
+        .bind(tx_signature)
+        .bind(&now)
+        .bind(batch_id.to_string())
+        .execute(&self.pool)
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    async fn fail_outbox_tx(&self, batch_id: &Uuid, error: &str) -> Result<bool, VfError> {
+        let now = time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap();
+        let updated = sqlx::query(
+            "UPDATE settlement_outbox SET status = 'failed', error_message = $1, updated_at = $2 WHERE batch_id = $3 AND status != 'confirmed'",
+        )
+        .bind(error)
+        .bind(&now)
+        .bind(batch_id.to_string())
+        .execute(&self.pool)
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    async fn dead_letter_bet(&self, bet: &PendingBet, error: &str) -> Result<bool, VfError> {
+        let failed_at = time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap();
+        let bet_id = bet.bet_id.to_string();
+
+        let mut tx = self.pool.begin().await?;
+        // Replaces an earlier dead letter for the bet, like SQLite's INSERT OR REPLACE; one
+        // for a bet that is no longer pending is left alone
+        let moved = sqlx::query(
+            r#"
+            INSERT INTO dead_letter_bets (
+                bet_id, user_seed, client_seed, timestamp, node_id, heads, vrf_proof, proof_json, proof_status,
+                processing_time_ms, processed_at, token_mint, wager_lamports, payout_lamports, player_pubkey,
+                player_choice, win_probability_bps, outcome_bit, seed_encoding, game, retry_count, error_message, failed_at
+            )
+            SELECT bet_id, user_seed, client_seed, timestamp, node_id, heads, vrf_proof, proof_json, proof_status,
+                   processing_time_ms, processed_at, token_mint, wager_lamports, payout_lamports, player_pubkey,
+                   player_choice, win_probability_bps, outcome_bit, seed_encoding, game, $1, $2, $3
+            FROM pending_bets
+            WHERE bet_id = $4
+            ON CONFLICT (bet_id) DO UPDATE SET
+                user_seed = excluded.user_seed, client_seed = excluded.client_seed, timestamp = excluded.timestamp,
+                node_id = excluded.node_id, heads = excluded.heads, vrf_proof = excluded.vrf_proof,
+                proof_json = excluded.proof_json, proof_status = excluded.proof_status,
+                processing_time_ms = excluded.processing_time_ms, processed_at = excluded.processed_at,
+                token_mint = excluded.token_mint, wager_lamports = excluded.wager_lamports,
+                payout_lamports = excluded.payout_lamports, player_pubkey = excluded.player_pubkey,
+                player_choice = excluded.player_choice, win_probability_bps = excluded.win_probability_bps,
+                outcome_bit = excluded.outcome_bit, seed_encoding = excluded.seed_encoding, game = excluded.game,
+                retry_count = excluded.retry_count, error_message = excluded.error_message, failed_at = excluded.failed_at
+            "#,
+        )
+        .bind(bet.retry_count as i64)
+        .bind(error)
+        .bind(&failed_at)
+        .bind(&bet_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        sqlx::query("DELETE FROM pending_bets WHERE bet_id = $1")
+            .bind(&bet_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(moved > 0)
+    }
+
+    async fn requeue_dead_letter(&self, bet_id: &Uuid) -> Result<RequeueOutcome, VfError> {
+        let id = bet_id.to_string();
+
+        let mut tx = self.pool.begin().await?;
+        let pending: Option<String> = sqlx::query_scalar("SELECT bet_id FROM pending_bets WHERE bet_id = $1")
+            .bind(&id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if pending.is_some() {
+            return Ok(RequeueOutcome::AlreadyPending);
+        }
+
+        let moved = sqlx::query(
+            r#"
+            INSERT INTO pending_bets (
+                bet_id, user_seed, client_seed, timestamp, node_id, heads, vrf_proof, proof_json, proof_status,
+                processing_time_ms, processed_at, token_mint, wager_lamports, payout_lamports, player_pubkey,
+                player_choice, win_probability_bps, outcome_bit, seed_encoding, game, retry_count, status
+            )
+            SELECT bet_id, user_seed, client_seed, timestamp, node_id, heads, vrf_proof, proof_json, proof_status,
+                   processing_time_ms, processed_at, token_mint, wager_lamports, payout_lamports, player_pubkey,
+                   player_choice, win_probability_bps, outcome_bit, seed_encoding, game, 0, 'pending'
+            FROM dead_letter_bets
+            WHERE bet_id = $1
+            "#,
+        )
+        .bind(&id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if moved == 0 {
+            return Ok(RequeueOutcome::NotDeadLettered);
+        }
+        sqlx::query("DELETE FROM dead_letter_bets WHERE bet_id = $1")
+            .bind(&id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(RequeueOutcome::Requeued)
+    }
+
+    async fn purge_settled_before(&self, cutoff: time::OffsetDateTime) -> Result<u64, VfError> {
+        let cutoff = cutoff
+            .format(&time::format_description::well_known::Rfc3339)
+            .map_err(|e| VfError::InvalidInput(format!("Invalid retention cutoff: {}", e)))?;
+
+        let mut tx = self.pool.begin().await?;
+        let settled = sqlx::query("DELETE FROM pending_bets WHERE status = 'settled' AND settled_at < $1")
+            .bind(&cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        let failed = sqlx::query("DELETE FROM dead_letter_bets WHERE failed_at < $1")
+            .bind(&cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+
+        Ok(settled + failed)
+    }
+
+    async fn bet_status(&self, bet_id: &Uuid) -> Result<Option<BetStatus>, VfError> {
+        let id = bet_id.to_string();
+        let row = sqlx::query("SELECT status, tx_signature, processed_at, settled_at, failed_at FROM pending_bets WHERE bet_id = $1")
+            .bind(&id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let Some(row) = row else {
+            // Bets that exhausted their retries live in the dead-letter table
+            let dead = sqlx::query("SELECT processed_at, failed_at FROM dead_letter_bets WHERE bet_id = $1")
+                .bind(&id)
+                .fetch_optional(&self.pool)
+                .await?;
+            return dead
+                .map(|dead| {
+                    Ok(BetStatus {
+                        bet_id: *bet_id,
+                        status: SettlementStatus::Failed,
+                        tx_signature: None,
+                        processed_at: dead.try_get("processed_at")?,
+                        settled_at: None,
+                        failed_at: Some(dead.try_get("failed_at")?),
+                    })
+                })
+                .transpose();
+        };
+        let status = match row.try_get::<Option<&str>, _>("status")? {
+            None => SettlementStatus::default(),
+            Some(status) => SettlementStatus::parse(status)
+                .ok_or_else(|| VfError::InvalidInput(format!("Unknown settlement status '{}'", status)))?,
+        };
+
+        Ok(Some(BetStatus {
+            bet_id: *bet_id,
+            status,
+            tx_signature: row.try_get("tx_signature")?,
+            processed_at: row.try_get("processed_at")?,
+            settled_at: row.try_get("settled_at")?,
+            failed_at: row.try_get("failed_at")?,
+        }))
+    }
+
+    async fn list_batches(&self, limit: i64, offset: i64) -> Result<Vec<BatchRecord>, VfError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT batch_id, bet_count, processing_time_ms, tx_signature, success, created_at, merkle_root
+            FROM settlement_batches
+            ORDER BY created_at DESC, batch_id DESC
+            LIMIT $1 OFFSET $2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(BatchRecord {
+                    batch_id: Uuid::parse_str(row.try_get("batch_id")?)?,
+                    bet_count: row.try_get::<i64, _>("bet_count")? as u64,
+                    processing_time_ms: row.try_get::<i64, _>("processing_time_ms")? as u64,
+                    tx_signature: row.try_get("tx_signature")?,
+                    success: row.try_get("success")?,
+                    created_at: row.try_get("created_at")?,
+                    merkle_root: row.try_get("merkle_root")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn settlement_summary(&self) -> Result<serde_json::Value, VfError> {
+        // Postgres sums integers into BIGINT and averages into NUMERIC; cast to match SQLite
+        let stats = sqlx::query(
+            r#"
+            SELECT
+                COUNT(*) as total_bets,
+                SUM(CASE WHEN status = 'settled' THEN 1 ELSE 0 END)::BIGINT as settled_bets,
+                SUM(CASE WHEN status = 'pending' THEN 1 ELSE 0 END)::BIGINT as pending_bets,
+                (SELECT COUNT(*) FROM dead_letter_bets) as failed_bets,
+                AVG(CASE WHEN status = 'settled' THEN processing_time_ms END)::DOUBLE PRECISION as avg_processing_time,
+                SUM(CASE WHEN proof_status = 'legacy' THEN 1 ELSE 0 END)::BIGINT as legacy_proofs,
+                SUM(CASE WHEN proof_status = 'unreconstructable' THEN 1 ELSE 0 END)::BIGINT as unreconstructable_proofs
+            FROM pending_bets
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let batch_stats = sqlx::query(
+            r#"
+            SELECT
+                COUNT(*) as total_batches,
+                SUM(CASE WHEN success THEN 1 ELSE 0 END)::BIGINT as successful_batches,
+                AVG(bet_count)::DOUBLE PRECISION as avg_batch_size,
+                AVG(processing_time_ms)::DOUBLE PRECISION as avg_batch_processing_time
+            FROM settlement_batches
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let game_stats = sqlx::query(
+            r#"
+            SELECT
+                COALESCE(game, 'coinflip') as game,
+                COUNT(*) as total_bets,
+                SUM(CASE WHEN status = 'settled' THEN 1 ELSE 0 END)::BIGINT as settled_bets,
+                SUM(CASE WHEN status = 'pending' THEN 1 ELSE 0 END)::BIGINT as pending_bets,
+                SUM(CASE WHEN heads = (COALESCE(player_choice, 'heads') = 'heads') THEN 1 ELSE 0 END)::BIGINT as wins
+            FROM pending_bets
+            GROUP BY COALESCE(game, 'coinflip')
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut games = serde_json::Map::new();
+        for row in &game_stats {
+            let summary = game_summary(
+                row.try_get("total_bets")?,
+                row.try_get("settled_bets")?,
+                row.try_get("pending_bets")?,
+                row.try_get("wins")?,
+            );
+            games.insert(row.try_get("game")?, summary);
+        }
+
+        Ok(serde_json::json!({
+            "bets": {
+                "total": stats.try_get::<i64, _>("total_bets")?,
+                "settled": stats.try_get::<Option<i64>, _>("settled_bets")?,
+                "pending": stats.try_get::<Option<i64>, _>("pending_bets")?,
+                "failed": stats.try_get::<i64, _>("failed_bets")?,
+                "avg_processing_time_ms": stats.try_get::<Option<f64>, _>("avg_processing_time")?
+            },
+            "proofs": {
+                "legacy": stats.try_get::<Option<i64>, _>("legacy_proofs")?,
+                "unreconstructable": stats.try_get::<Option<i64>, _>("unreconstructable_proofs")?
+            },
+            "batches": {
+                "total": batch_stats.try_get::<i64, _>("total_batches")?,
+                "successful": batch_stats.try_get::<Option<i64>, _>("successful_batches")?,
+                "avg_size": batch_stats.try_get::<Option<f64>, _>("avg_batch_size")?,
+                "avg_processing_time_ms": batch_stats.try_get::<Option<f64>, _>("avg_batch_processing_time")?
+            },
+            "games": games
+        }))
+    }
+
+    async fn lock_owner(&self) -> Result<Option<String>, VfError> {
+        Ok(sqlx::query_scalar("SELECT owner FROM settlement_lock WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn upsert_lock(&self, owner: &str, now_ms: i64, stale_before_ms: i64) -> Result<bool, VfError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO settlement_lock (id, owner, heartbeat_at) VALUES (1, $1, $2)
+            ON CONFLICT (id) DO UPDATE SET owner = excluded.owner, heartbeat_at = excluded.heartbeat_at
+            WHERE settlement_lock.owner = excluded.owner OR settlement_lock.heartbeat_at < $3
+            "#,
+        )
+        .bind(owner)
+        .bind(now_ms)
+        .bind(stale_before_ms)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn release_lock(&self, owner: &str) -> Result<(), VfError> {
+        sqlx::query("DELETE FROM settlement_lock WHERE id = 1 AND owner = $1")
+            .bind(owner)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settlement_lock::SettlementLock;
+    use crate::types::CoinflipRequest;
+    use std::sync::Arc;
+
+    /// A fresh backend on `TEST_POSTGRES_URL`, or `None` to skip when no server is configured
+    async fn test_backend() -> Option<PostgresBackend> {
+        let url = std::env::var("TEST_POSTGRES_URL").ok()?;
+        let backend = PostgresBackend::connect(&url, &StorageOptions::default()).await.unwrap();
+        for table in SETTLEMENT_TABLES {
+            sqlx::query(&format!("DROP TABLE IF EXISTS {}", table)).execute(backend.pool()).await.unwrap();
+        }
+        backend.migrate().await.unwrap();
+        Some(backend)
+    }
+
+    #[tokio::test]
+    async fn test_postgres_backend_settles_bets() {
+        let Some(backend) = test_backend().await else { return };
+        backend.check_schema().await.unwrap();
+
+        let vrf = crate::VrfEngine::new();
+        let mut bets: Vec<PendingBet> = (0..3)
+            .map(|i| {
+                let req = CoinflipRequest { user_seed: format!("pg_{}", i), wager_lamports: Some(1_000), ..Default::default() };
+                PendingBet::from_bet(&req, &vrf.process_coinflip(&req).unwrap())
+            })
+            .collect();
+        assert_eq!(backend.insert_pending_bets(&bets).await.unwrap(), 0);
+        assert_eq!(backend.insert_pending_bets(&bets[..1]).await.unwrap(), 1);
+
+        // Bets read back whole, proof included
+        let pending = backend.pending_bets(10).await.unwrap();
+        assert_eq!(pending.len(), 3);
+        assert_eq!(pending[0].proof.as_ref().map(|p| &p.signature), Some(&bets[0].vrf_proof));
+        assert_eq!(pending[0].wager_lamports, Some(1_000));
+
+        let result = BatchResult {
+            batch_id: Uuid::new_v4(),
+            success: true,
+            processed_count: 2,
+            processing_time_ms: 5,
+            mock_tx_signature: "pg_tx".to_string(),
+            timestamp: time::OffsetDateTime::now_utc(),
+        };
+        backend.record_settled_batch(&bets[..2], &result, "00").await.unwrap();
+        let status = backend.bet_status(&bets[0].bet_id).await.unwrap().unwrap();
+        assert_eq!(status.status, SettlementStatus::Settled);
+        assert_eq!(status.tx_signature.as_deref(), Some("pg_tx"));
+        let batches = backend.list_batches(10, 0).await.unwrap();
+        assert_eq!(batches[0].batch_id, result.batch_id);
+        assert_eq!(batches[0].bet_count, 2);
+
+        // Dead letter and back
+        bets[2].retry_count = 4;
+        assert!(backend.dead_letter_bet(&bets[2], "rpc down").await.unwrap());
+        assert_eq!(backend.bet_status(&bets[2].bet_id).await.unwrap().unwrap().status, SettlementStatus::Failed);
+        // A repeat for a bet no longer pending keeps the dead letter it already has
+        assert!(!backend.dead_letter_bet(&bets[2], "rpc down again").await.unwrap());
+        assert_eq!(backend.bet_status(&bets[2].bet_id).await.unwrap().unwrap().status, SettlementStatus::Failed);
+        assert_eq!(backend.requeue_dead_letter(&bets[2].bet_id).await.unwrap(), RequeueOutcome::Requeued);
+        assert_eq!(backend.requeue_dead_letter(&bets[2].bet_id).await.unwrap(), RequeueOutcome::AlreadyPending);
+        assert_eq!(backend.pending_bets(10).await.unwrap()[0].retry_count, 0);
+
+        let summary = backend.settlement_summary().await.unwrap();
+        assert_eq!(summary["bets"]["settled"], 2);
+        assert_eq!(summary["bets"]["pending"], 1);
+        assert_eq!(summary["batches"]["successful"], 1);
+        assert_eq!(summary["games"]["coinflip"]["total"], 3);
+
+        let later = time::OffsetDateTime::now_utc() + time::Duration::days(1);
+        assert_eq!(backend.purge_settled_before(later).await.unwrap(), 2);
+
+        // The settlement lock keeps a second settler out until the first goes stale
+        let backend: Arc<dyn StorageBackend> = Arc::new(backend);
+        let ttl = std::time::Duration::from_secs(30);
+        let first = SettlementLock::new(backend.clone(), ttl);
+        let second = SettlementLock::new(backend, ttl);
+        let start = time::OffsetDateTime::now_utc();
+
+        assert!(first.try_acquire_at(start).await.unwrap());
+        assert!(!second.try_acquire_at(start).await.unwrap());
+        assert!(second.try_acquire_at(start + time::Duration::seconds(31)).await.unwrap());
+
+        second.release().await.unwrap();
+        assert!(first.try_acquire_at(start).await.unwrap());
+    }
+}
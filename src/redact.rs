@@ -0,0 +1,37 @@
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Raw seeds in logs could let anyone with log access predict or replay outcomes, so
+/// they are hashed unless an operator explicitly opts in (`LOG_RAW_SEEDS`).
+static LOG_RAW_SEEDS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_log_raw_seeds(enabled: bool) {
+    LOG_RAW_SEEDS.store(enabled, Ordering::Relaxed);
+}
+
+/// Value to put in a log line for a user seed
+pub fn seed_for_log(seed: &str) -> String {
+    if LOG_RAW_SEEDS.load(Ordering::Relaxed) {
+        seed.to_string()
+    } else {
+        hash_seed(seed)
+    }
+}
+
+/// Truncated SHA-256 of the seed: stable for correlating log lines, useless for recovery
+pub fn hash_seed(seed: &str) -> String {
+    let digest = Sha256::digest(seed.as_bytes());
+    format!("sha256:{}", hex::encode(&digest[..6]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_is_stable_and_truncated() {
+        assert_eq!(hash_seed("seed"), hash_seed("seed"));
+        assert_ne!(hash_seed("seed"), hash_seed("seed2"));
+        assert_eq!(hash_seed("seed").len(), "sha256:".len() + 12);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::backend::StorageBackend;
+use crate::types::VfError;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Database-backed advisory lock so only one settlement loop runs per database.
+///
+/// The lock is the single `settlement_lock` row. Its owner refreshes `heartbeat_at` on
+/// every acquire; once the heartbeat is older than the TTL the lock is stale and any
+/// other settler may take it over.
+pub struct SettlementLock {
+    backend: Arc<dyn StorageBackend>,
+    owner: String,
+    ttl: Duration,
+}
+
+impl SettlementLock {
+    pub fn new(backend: Arc<dyn StorageBackend>, ttl: Duration) -> Self {
+        Self { backend, owner: Uuid::new_v4().to_string(), ttl }
+    }
+
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// Take the lock or refresh our heartbeat. Returns false while another live owner holds it.
+    pub async fn try_acquire(&self) -> Result<bool, VfError> {
+        self.try_acquire_at(time::OffsetDateTime::now_utc()).await
+    }
+
+    pub async fn try_acquire_at(&self, now: time::OffsetDateTime) -> Result<bool, VfError> {
+        let now_ms = unix_millis(now);
+        let stale_before = now_ms - self.ttl.as_millis() as i64;
+
+        let previous = self.backend.lock_owner().await?;
+        let acquired = self.backend.upsert_lock(&self.owner, now_ms, stale_before).await?;
+        match previous {
+            Some(prev) if acquired && prev != self.owner => {
+                warn!(owner = %self.owner, stale_owner = %prev, "🔓 Took over stale settlement lock");
+            }
+            None if acquired => info!(owner = %self.owner, "🔒 Acquired settlement lock"),
+            _ => {}
+        }
+        Ok(acquired)
+    }
+
+    /// Give the lock up early (e.g. on shutdown) instead of waiting for it to go stale.
+    pub async fn release(&self) -> Result<(), VfError> {
+        self.backend.release_lock(&self.owner).await
+    }
+}
+
+fn unix_millis(t: time::OffsetDateTime) -> i64 {
+    (t.unix_timestamp_nanos() / 1_000_000) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::SqliteBackend;
+    use crate::storage::Storage;
+
+    #[tokio::test]
+    async fn test_second_settler_is_locked_out_until_stale() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+        let ttl = Duration::from_secs(30);
+        let backend: Arc<dyn StorageBackend> = Arc::new(SqliteBackend::new(&storage.pool()));
+        let first = SettlementLock::new(backend.clone(), ttl);
+        let second = SettlementLock::new(backend, ttl);
+        let start = time::OffsetDateTime::now_utc();
+
+        assert!(first.try_acquire_at(start).await.unwrap());
+        assert!(!second.try_acquire_at(start).await.unwrap());
+
+        // Heartbeats keep the lock alive past the original TTL
+        let later = start + time::Duration::seconds(20);
+        assert!(first.try_acquire_at(later).await.unwrap());
+        assert!(!second.try_acquire_at(later + time::Duration::seconds(20)).await.unwrap());
+
+        // Once the owner stops heartbeating the lock expires
+        let stale = later + time::Duration::seconds(31);
+        assert!(second.try_acquire_at(stale).await.unwrap());
+        assert!(!first.try_acquire_at(stale).await.unwrap());
+
+        second.release().await.unwrap();
+        assert!(first.try_acquire_at(stale).await.unwrap());
+    }
+}
@@ -0,0 +1,519 @@
+use crate::settlement_engine::{ConfirmationLevel, PendingBet, SettlementBatch, SettlementEngine, SignatureStatus, Settler};
+use crate::types::{VfError, NATIVE_TOKEN_MINT};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as Base64Engine, Engine as _};
+use serde_json::{json, Value};
+use solana_hash::Hash;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_keypair::Keypair;
+use solana_message::Message;
+use solana_pubkey::Pubkey;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+use std::path::Path;
+use std::time::Duration;
+use tracing::debug;
+
+/// SPL Memo program, which records the batch commitment in the transaction's logs
+const MEMO_PROGRAM_ID: Pubkey = solana_pubkey::pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+/// System program, which moves lamports between accounts
+const SYSTEM_PROGRAM_ID: Pubkey = solana_pubkey::pubkey!("11111111111111111111111111111111");
+
+/// Largest serialized transaction a Solana node accepts
+const MAX_TRANSACTION_SIZE: usize = 1232;
+
+/// Settles each batch as a Solana transaction paid for and signed by the node's fee payer.
+///
+/// The transaction pays each winning bet its `payout_lamports` with a system transfer from
+/// the payer to the bet's `player_pubkey`, and carries one memo committing to the batch id,
+/// its bet count and payout total, and the Merkle root its bets prove inclusion against.
+/// Only SOL is paid out: a batch holding a payout in another mint, or one owed to a bet
+/// without a valid player address, is refused rather than settled unpaid. It is submitted with
+/// `sendTransaction` over JSON-RPC and the signature is returned once the RPC node accepts
+/// it. The batch settles once `getSignatureStatuses` shows the transaction at
+/// `confirmation_level`.
+///
+/// Before submitting, the payer's recent transactions are searched for the batch's memo, so
+/// a batch resumed after a crash between submitting and recording the signature gets the
+/// transaction that already landed back instead of paying out twice.
+pub struct SolanaSettler {
+    client: reqwest::Client,
+    rpc_url: String,
+    payer: Keypair,
+    confirmation_level: ConfirmationLevel,
+}
+
+impl SolanaSettler {
+    pub const RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Most signatures one `getSignatureStatuses` call accepts
+    const MAX_STATUS_QUERY: usize = 256;
+
+    /// Payer transactions searched for an earlier submission of a batch, newest first
+    const RESUBMIT_LOOKBACK: usize = 1_000;
+
+    /// Most bets one transaction settles, so a transfer to each winner still fits in a packet
+    pub const MAX_BATCH_BETS: usize = 16;
+
+    pub fn new(rpc_url: &str, payer: Keypair, confirmation_level: ConfirmationLevel) -> Result<Self, VfError> {
+        let client = reqwest::Client::builder()
+            .timeout(Self::RPC_TIMEOUT)
+            .build()
+            .map_err(|e| VfError::Settlement(format!("Cannot build Solana RPC client: {}", e)))?;
+        Ok(Self { client, rpc_url: rpc_url.to_string(), payer, confirmation_level })
+    }
+
+    /// Load the fee payer from a `solana-keygen` JSON keypair file
+    pub fn from_keypair_file(rpc_url: &str, path: &Path, confirmation_level: ConfirmationLevel) -> Result<Self, String> {
+        let payer = solana_keypair::read_keypair_file(path)
+            .map_err(|e| format!("SOLANA_KEYPAIR_FILE '{}' is not a valid keypair: {}", path.display(), e))?;
+        Self::new(rpc_url, payer, confirmation_level).map_err(|e| e.to_string())
+    }
+
+    /// The fee payer's address
+    pub fn payer(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    /// The memo a batch's transaction carries
+    pub fn memo(batch: &SettlementBatch) -> String {
+        let payout_lamports = batch.bets.iter().fold(0u64, |total, bet| total.saturating_add(bet.payout_lamports.unwrap_or(0)));
+        format!(
+            "vfnode:settle:{}:{}:{}:{}",
+            batch.batch_id,
+            batch.bet_count,
+            payout_lamports,
+            SettlementEngine::batch_merkle_root(&batch.bets)
+        )
+    }
+
+    /// The lamports `bet` is owed and the address they go to, or None for a bet that pays nothing
+    fn payout(bet: &PendingBet) -> Result<Option<(Pubkey, u64)>, VfError> {
+        let lamports = bet.payout_lamports.unwrap_or(0);
+        if lamports == 0 {
+            return Ok(None);
+        }
+        let mint = bet.token_mint.as_deref().unwrap_or(NATIVE_TOKEN_MINT);
+        if mint != NATIVE_TOKEN_MINT {
+            return Err(VfError::Settlement(format!(
+                "Bet {} pays out in {}, which solana settlement can't transfer",
+                bet.bet_id, mint
+            )));
+        }
+        let player = bet
+            .player_pubkey
+            .as_deref()
+            .and_then(|player| player.parse().ok())
+            .ok_or_else(|| VfError::Settlement(format!("Bet {} has no valid player_pubkey to pay out to", bet.bet_id)))?;
+        Ok(Some((player, lamports)))
+    }
+
+    /// System program transfer of `lamports` from `from` to `to`
+    fn transfer(from: Pubkey, to: Pubkey, lamports: u64) -> Instruction {
+        let mut data = 2u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&lamports.to_le_bytes());
+        Instruction {
+            program_id: SYSTEM_PROGRAM_ID,
+            accounts: vec![AccountMeta::new(from, true), AccountMeta::new(to, false)],
+            data,
+        }
+    }
+
+    /// Build and sign a batch's transaction against `recent_blockhash`
+    pub fn build_transaction(&self, batch: &SettlementBatch, recent_blockhash: Hash) -> Result<Transaction, VfError> {
+        let payer = self.payer.pubkey();
+        let mut instructions = vec![Instruction {
+            program_id: MEMO_PROGRAM_ID,
+            accounts: vec![AccountMeta::new_readonly(payer, true)],
+            data: Self::memo(batch).into_bytes(),
+        }];
+        for bet in &batch.bets {
+            if let Some((player, lamports)) = Self::payout(bet)? {
+                instructions.push(Self::transfer(payer, player, lamports));
+            }
+        }
+        let message = Message::new_with_blockhash(&instructions, Some(&payer), &recent_blockhash);
+        Ok(Transaction::new(&[&self.payer], message, recent_blockhash))
+    }
+
+    async fn rpc(&self, method: &str, params: Value) -> Result<Value, VfError> {
+        let rpc_error = |e: String| VfError::Settlement(format!("Solana RPC error: {} failed: {}", method, e));
+        let mut response: Value = self
+            .client
+            .post(&self.rpc_url)
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| rpc_error(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| rpc_error(e.to_string()))?;
+
+        if let Some(error) = response.get("error") {
+            return Err(rpc_error(error.to_string()));
+        }
+        match response.get_mut("result") {
+            Some(result) => Ok(result.take()),
+            None => Err(rpc_error("response has no result".to_string())),
+        }
+    }
+
+    /// Read one entry of a `getSignatureStatuses` result: `null` for a signature the node
+    /// doesn't know, else its error and how far it has confirmed
+    fn signature_status(&self, status: &Value) -> SignatureStatus {
+        if status.is_null() {
+            return SignatureStatus::Missing;
+        }
+        if let Some(error) = status.get("err").filter(|err| !err.is_null()) {
+            return SignatureStatus::Failed(error.to_string());
+        }
+        match status["confirmationStatus"].as_str().and_then(ConfirmationLevel::parse) {
+            Some(level) if level >= self.confirmation_level => SignatureStatus::Confirmed,
+            _ => SignatureStatus::Pending,
+        }
+    }
+
+    /// Signature of a transaction carrying `memo` that the payer already landed without error
+    async fn landed_signature(&self, memo: &str) -> Result<Option<String>, VfError> {
+        let result = self
+            .rpc(
+                "getSignaturesForAddress",
+                json!([self.payer.pubkey().to_string(), { "limit": Self::RESUBMIT_LOOKBACK, "commitment": "confirmed" }]),
+            )
+            .await?;
+        let entries = result
+            .as_array()
+            .ok_or_else(|| VfError::Settlement(format!("Solana RPC error: bad getSignaturesForAddress result {}", result)))?;
+        // The RPC node reports memos as "[<length>] <memo>"
+        Ok(entries
+            .iter()
+            .filter(|entry| entry["err"].is_null())
+            .find(|entry| entry["memo"].as_str().is_some_and(|m| m.strip_suffix(memo).is_some_and(|prefix| prefix.ends_with("] "))))
+            .and_then(|entry| entry["signature"].as_str().map(String::from)))
+    }
+
+    async fn latest_blockhash(&self) -> Result<Hash, VfError> {
+        let result = self.rpc("getLatestBlockhash", json!([{ "commitment": "confirmed" }])).await?;
+        result["value"]["blockhash"]
+            .as_str()
+            .and_then(|blockhash| blockhash.parse().ok())
+            .ok_or_else(|| VfError::Settlement(format!("Solana RPC error: bad getLatestBlockhash result {}", result)))
+    }
+}
+
+#[async_trait]
+impl Settler for SolanaSettler {
+    async fn settle(&self, batch: &SettlementBatch) -> Result<String, VfError> {
+        if let Some(tx_signature) = self.landed_signature(&Self::memo(batch)).await? {
+            debug!(batch_id = %batch.batch_id, tx_signature = %tx_signature, "⛓️ Settlement transaction already landed");
+            return Ok(tx_signature);
+        }
+
+        let transaction = self.build_transaction(batch, self.latest_blockhash().await?)?;
+        let wire = bincode::serialize(&transaction)
+            .map_err(|e| VfError::Settlement(format!("Cannot encode settlement transaction: {}", e)))?;
+        if wire.len() > MAX_TRANSACTION_SIZE {
+            return Err(VfError::Settlement(format!(
+                "Settlement transaction is {} bytes, over the {} byte limit",
+                wire.len(),
+                MAX_TRANSACTION_SIZE
+            )));
+        }
+
+        let result = self
+            .rpc(
+                "sendTransaction",
+                json!([Base64Engine.encode(wire), { "encoding": "base64", "preflightCommitment": "confirmed" }]),
+            )
+            .await?;
+        let tx_signature = result
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| VfError::Settlement(format!("Solana RPC error: bad sendTransaction result {}", result)))?;
+
+        debug!(
+            batch_id = %batch.batch_id,
+            tx_signature = %tx_signature,
+            bet_count = batch.bet_count,
+            "⛓️ Settlement transaction submitted"
+        );
+        Ok(tx_signature)
+    }
+
+    async fn signature_statuses(&self, signatures: &[String]) -> Result<Vec<SignatureStatus>, VfError> {
+        let mut statuses = Vec::with_capacity(signatures.len());
+        for chunk in signatures.chunks(Self::MAX_STATUS_QUERY) {
+            // The status cache only covers recent slots; a transaction that landed before it
+            // would otherwise read as missing and be failed, then paid out a second time
+            let result = self.rpc("getSignatureStatuses", json!([chunk, { "searchTransactionHistory": true }])).await?;
+            match result["value"].as_array() {
+                Some(values) if values.len() == chunk.len() => {
+                    statuses.extend(values.iter().map(|status| self.signature_status(status)))
+                }
+                _ => {
+                    return Err(VfError::Settlement(format!(
+                        "Solana RPC error: bad getSignatureStatuses result {}",
+                        result
+                    )))
+                }
+            }
+        }
+        Ok(statuses)
+    }
+
+    fn max_batch_size(&self) -> Option<usize> {
+        Some(Self::MAX_BATCH_BETS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settlement_engine::PendingBet;
+    use crate::types::CoinflipRequest;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    /// A batch of `count` flips wagering 1000 lamports each, every player with their own address
+    fn batch_of(count: usize) -> SettlementBatch {
+        let vrf = crate::VrfEngine::new();
+        let bets: Vec<PendingBet> = (0..count)
+            .map(|i| {
+                let req = CoinflipRequest {
+                    user_seed: format!("solana_{}", i),
+                    wager_lamports: Some(1_000),
+                    player_pubkey: Some(Keypair::new().pubkey().to_string()),
+                    ..Default::default()
+                };
+                PendingBet::from_bet(&req, &vrf.process_coinflip(&req).unwrap())
+            })
+            .collect();
+        SettlementBatch { batch_id: Uuid::new_v4(), bet_count: bets.len(), bets, created_at: time::OffsetDateTime::now_utc() }
+    }
+
+    fn batch() -> SettlementBatch {
+        batch_of(3)
+    }
+
+    fn settler() -> SolanaSettler {
+        SolanaSettler::new("http://127.0.0.1:8899", Keypair::new(), ConfirmationLevel::default()).unwrap()
+    }
+
+    #[test]
+    fn test_transaction_commits_to_batch_and_is_signed_by_payer() {
+        let settler = settler();
+        let batch = batch();
+        let blockhash = Hash::new_from_array([9u8; 32]);
+        let tx = settler.build_transaction(&batch, blockhash).unwrap();
+
+        assert_eq!(tx.message.account_keys[0], settler.payer());
+        assert_eq!(tx.message.recent_blockhash, blockhash);
+        let memo = String::from_utf8(tx.message.instructions[0].data.clone()).unwrap();
+        assert_eq!(memo, SolanaSettler::memo(&batch));
+        assert!(memo.contains(&batch.batch_id.to_string()));
+        assert!(memo.ends_with(&SettlementEngine::batch_merkle_root(&batch.bets)));
+
+        let payer = VerifyingKey::from_bytes(&settler.payer().to_bytes()).unwrap();
+        let signature = Signature::from_slice(tx.signatures[0].as_ref()).unwrap();
+        assert!(payer.verify(&tx.message_data(), &signature).is_ok());
+    }
+
+    #[test]
+    fn test_transaction_pays_each_winner() {
+        let settler = settler();
+        let mut batch = batch_of(8);
+        // Two wins and a loss for the same player are paid separately
+        batch.bets[1].player_pubkey = batch.bets[0].player_pubkey.clone();
+        batch.bets[0].payout_lamports = Some(2_000);
+        batch.bets[1].payout_lamports = Some(1_980);
+        batch.bets[2].payout_lamports = Some(0);
+        let tx = settler.build_transaction(&batch, Hash::new_from_array([9u8; 32])).unwrap();
+
+        // Decode the transfers from the wire, as the validator would
+        let wire = bincode::serialize(&tx).unwrap();
+        let tx: Transaction = bincode::deserialize(&wire).unwrap();
+        let keys = &tx.message.account_keys;
+        let transfers: Vec<(Pubkey, Pubkey, u64)> = tx.message.instructions[1..]
+            .iter()
+            .map(|ix| {
+                assert_eq!(keys[ix.program_id_index as usize], SYSTEM_PROGRAM_ID);
+                assert_eq!(ix.data[..4], 2u32.to_le_bytes());
+                let lamports = u64::from_le_bytes(ix.data[4..].try_into().unwrap());
+                assert!(tx.message.is_signer(ix.accounts[0] as usize));
+                assert!(tx.message.is_maybe_writable(ix.accounts[1] as usize, None));
+                (keys[ix.accounts[0] as usize], keys[ix.accounts[1] as usize], lamports)
+            })
+            .collect();
+
+        let expected: Vec<(Pubkey, Pubkey, u64)> = batch
+            .bets
+            .iter()
+            .filter(|bet| bet.payout_lamports.unwrap_or(0) > 0)
+            .map(|bet| (settler.payer(), bet.player_pubkey.as_deref().unwrap().parse().unwrap(), bet.payout_lamports.unwrap()))
+            .collect();
+        assert!(expected.len() >= 2);
+        assert_eq!(transfers, expected);
+        let paid: u64 = transfers.iter().map(|(_, _, lamports)| lamports).sum();
+        assert!(SolanaSettler::memo(&batch).contains(&format!(":{}:", paid)));
+    }
+
+    #[test]
+    fn test_unpayable_batches_are_refused() {
+        let settler = settler();
+        let blockhash = Hash::new_from_array([9u8; 32]);
+        let winning = || {
+            let mut batch = batch();
+            batch.bets[0].payout_lamports = Some(2_000);
+            batch
+        };
+
+        let mut in_token = winning();
+        in_token.bets[0].token_mint = Some("USDC".to_string());
+        let err = settler.build_transaction(&in_token, blockhash).err().unwrap();
+        assert!(matches!(&err, VfError::Settlement(msg) if msg.contains("pays out in USDC")), "{}", err);
+
+        for player in [None, Some("not a pubkey".to_string())] {
+            let mut unaddressed = winning();
+            unaddressed.bets[0].player_pubkey = player;
+            let err = settler.build_transaction(&unaddressed, blockhash).err().unwrap();
+            assert!(matches!(&err, VfError::Settlement(msg) if msg.contains("no valid player_pubkey")), "{}", err);
+        }
+
+        // A bet that pays nothing needs neither
+        let mut batch = batch();
+        for bet in &mut batch.bets {
+            bet.payout_lamports = Some(0);
+            bet.token_mint = Some("USDC".to_string());
+            bet.player_pubkey = None;
+        }
+        assert_eq!(settler.build_transaction(&batch, blockhash).unwrap().message.instructions.len(), 1);
+    }
+
+    #[test]
+    fn test_full_batch_fits_in_one_transaction() {
+        let mut batch = batch_of(SolanaSettler::MAX_BATCH_BETS);
+        for bet in &mut batch.bets {
+            bet.payout_lamports = Some(u64::MAX / 32);
+        }
+        let tx = settler().build_transaction(&batch, Hash::new_from_array([9u8; 32])).unwrap();
+        assert_eq!(tx.message.instructions.len(), SolanaSettler::MAX_BATCH_BETS + 1);
+        assert!(bincode::serialize(&tx).unwrap().len() <= MAX_TRANSACTION_SIZE);
+    }
+
+    /// A JSON-RPC endpoint answering each method with its entry in `results`, recording each
+    /// request body it receives
+    async fn mock_rpc(results: Value) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::post(move |axum::Json(request): axum::Json<Value>| {
+                let seen = seen.clone();
+                let result = results[request["method"].as_str().unwrap_or_default()].clone();
+                async move {
+                    seen.lock().unwrap().push(request);
+                    match result {
+                        Value::Null => axum::Json(json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32601, "message": "Method not found" } })),
+                        result => axum::Json(json!({ "jsonrpc": "2.0", "id": 1, "result": result })),
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn test_signature_statuses_follow_confirmation_level() {
+        let (url, requests) = mock_rpc(json!({ "getSignatureStatuses": { "context": { "slot": 42 }, "value": [
+            null,
+            { "slot": 40, "confirmations": 0, "err": null, "confirmationStatus": "processed" },
+            { "slot": 40, "confirmations": 10, "err": null, "confirmationStatus": "confirmed" },
+            { "slot": 40, "confirmations": null, "err": null, "confirmationStatus": "finalized" },
+            { "slot": 40, "confirmations": null, "err": { "InstructionError": [0, "Custom"] }, "confirmationStatus": "finalized" },
+        ] } }))
+        .await;
+        let signatures: Vec<String> = (0..5).map(|i| format!("sig_{}", i)).collect();
+        let failed = SignatureStatus::Failed(r#"{"InstructionError":[0,"Custom"]}"#.to_string());
+
+        let expected = [
+            (ConfirmationLevel::Processed, [SignatureStatus::Confirmed, SignatureStatus::Confirmed, SignatureStatus::Confirmed]),
+            (ConfirmationLevel::Confirmed, [SignatureStatus::Pending, SignatureStatus::Confirmed, SignatureStatus::Confirmed]),
+            (ConfirmationLevel::Finalized, [SignatureStatus::Pending, SignatureStatus::Pending, SignatureStatus::Confirmed]),
+        ];
+        for (level, landed) in expected {
+            let settler = SolanaSettler::new(&url, Keypair::new(), level).unwrap();
+            let statuses = settler.signature_statuses(&signatures).await.unwrap();
+            assert_eq!(statuses[0], SignatureStatus::Missing, "{:?}", level);
+            assert_eq!(statuses[1..4], landed, "{:?}", level);
+            assert_eq!(statuses[4], failed, "{:?}", level);
+        }
+
+        let request = requests.lock().unwrap()[0].clone();
+        assert_eq!(request["method"], "getSignatureStatuses");
+        assert_eq!(request["params"][0], json!(signatures));
+        assert_eq!(request["params"][1]["searchTransactionHistory"], true);
+
+        // A result that doesn't line up with the signatures is an error, not a guess
+        let settler = SolanaSettler::new(&url, Keypair::new(), ConfirmationLevel::default()).unwrap();
+        assert!(matches!(settler.signature_statuses(&signatures[..2]).await, Err(VfError::Settlement(_))));
+    }
+
+    #[tokio::test]
+    async fn test_resumed_batch_reuses_landed_transaction() {
+        let batch = batch();
+        let memo = SolanaSettler::memo(&batch);
+        let landed = |err: Value| json!([
+            { "signature": "other_sig", "err": null, "memo": "[12] unrelated" },
+            { "signature": "landed_sig", "err": err, "memo": format!("[{}] {}", memo.len(), memo) },
+        ]);
+        let blockhash = Hash::new_from_array([9u8; 32]).to_string();
+
+        // Already on chain: the earlier signature comes back and nothing is sent
+        let (url, requests) = mock_rpc(json!({ "getSignaturesForAddress": landed(Value::Null) })).await;
+        let settler = SolanaSettler::new(&url, Keypair::new(), ConfirmationLevel::default()).unwrap();
+        assert_eq!(settler.settle(&batch).await.unwrap(), "landed_sig");
+        let request = requests.lock().unwrap()[0].clone();
+        assert_eq!(request["params"][0], settler.payer().to_string());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // An earlier attempt that failed on chain is submitted again
+        let (url, requests) = mock_rpc(json!({
+            "getSignaturesForAddress": landed(json!({ "InstructionError": [0, "Custom"] })),
+            "getLatestBlockhash": { "context": { "slot": 42 }, "value": { "blockhash": blockhash, "lastValidBlockHeight": 100 } },
+            "sendTransaction": "new_sig",
+        }))
+        .await;
+        let settler = SolanaSettler::new(&url, Keypair::new(), ConfirmationLevel::default()).unwrap();
+        assert_eq!(settler.settle(&batch).await.unwrap(), "new_sig");
+        let methods: Vec<Value> = requests.lock().unwrap().iter().map(|r| r["method"].clone()).collect();
+        assert_eq!(methods, ["getSignaturesForAddress", "getLatestBlockhash", "sendTransaction"]);
+
+        // The submitted transaction carries the memo and a transfer to each winner
+        let wire = Base64Engine.decode(requests.lock().unwrap()[2]["params"][0].as_str().unwrap()).unwrap();
+        let sent: Transaction = bincode::deserialize(&wire).unwrap();
+        assert_eq!(sent.message.recent_blockhash.to_string(), blockhash);
+        assert_eq!(sent.message.instructions[0].data, memo.as_bytes());
+        let winners = batch.bets.iter().filter(|bet| bet.payout_lamports.unwrap_or(0) > 0).count();
+        assert_eq!(sent.message.instructions.len(), winners + 1);
+    }
+
+    #[test]
+    fn test_keypair_file_must_hold_a_keypair() {
+        let path = std::env::temp_dir().join(format!("vfnode-payer-{}.json", Uuid::new_v4()));
+        let payer = Keypair::new();
+        std::fs::write(&path, serde_json::to_string(&payer.to_bytes().to_vec()).unwrap()).unwrap();
+        let settler = SolanaSettler::from_keypair_file("http://127.0.0.1:8899", &path, ConfirmationLevel::default()).unwrap();
+        assert_eq!(settler.payer(), payer.pubkey());
+
+        std::fs::write(&path, "not a keypair").unwrap();
+        let err = SolanaSettler::from_keypair_file("http://127.0.0.1:8899", &path, ConfirmationLevel::default()).err().unwrap();
+        assert!(err.contains("is not a valid keypair"), "{}", err);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
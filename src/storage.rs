@@ -1,35 +1,281 @@
-use crate::types::{CoinflipRequest, CoinflipResponse, VfError};
-use sqlx::{SqlitePool, sqlite::SqliteConnectOptions};
+use crate::types::{
+    BatchRecord, BatchReplay, BetStatus, CoinSide, CoinflipRequest, CoinflipResponse, DeadLetterBet, InclusionProof, LedgerEntry, LedgerEntryKind, OutboxEntry, OutboxStatus, OutcomeBit,
+    PlayerLedger, ProofBackfillReport, ProofBundle, ProofStatus, RealizedEdge, ReplayMismatch, SeedEncoding, SettlementReceipt, SettlementStatus, VfError, VrfProof,
+};
+use crate::merkle;
+use crate::vrf_engine::VrfEngine;
+use futures_util::Stream;
+use sqlx::{SqlitePool, sqlite::{SqliteConnectOptions, SqlitePoolOptions}};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::{info, error};
+use uuid::Uuid;
+
+pub use sqlx::sqlite::SqliteJournalMode;
+
+/// Where a `sqlite:` database URL points
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatabaseLocation {
+    Memory,
+    File(PathBuf),
+}
+
+impl DatabaseLocation {
+    /// Accepts `sqlite::memory:`, `sqlite://path`, `sqlite:path` or a bare path
+    pub fn parse(database_url: &str) -> Self {
+        let path = database_url
+            .strip_prefix("sqlite://")
+            .or_else(|| database_url.strip_prefix("sqlite:"))
+            .unwrap_or(database_url);
+        let path = path.split('?').next().unwrap_or(path);
+
+        if path == ":memory:" || path.is_empty() {
+            DatabaseLocation::Memory
+        } else {
+            DatabaseLocation::File(PathBuf::from(path))
+        }
+    }
+}
+
+/// How a file database is opened. In-memory databases ignore these and always use a
+/// single connection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageOptions {
+    /// Connections the pool keeps open at most
+    pub max_connections: u32,
+    /// WAL lets status reads run while the drainer writes a batch
+    pub journal_mode: SqliteJournalMode,
+    /// How long a connection waits on a locked database before failing with `SQLITE_BUSY`
+    pub busy_timeout: Duration,
+}
+
+impl StorageOptions {
+    pub const DEFAULT_MAX_CONNECTIONS: u32 = 10;
+    pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+}
+
+impl Default for StorageOptions {
+    fn default() -> Self {
+        Self {
+            max_connections: Self::DEFAULT_MAX_CONNECTIONS,
+            journal_mode: SqliteJournalMode::Wal,
+            busy_timeout: Self::DEFAULT_BUSY_TIMEOUT,
+        }
+    }
+}
+
+/// Tables and columns the node queries. Keep in step with `run_migrations`.
+pub(crate) const EXPECTED_SCHEMA: &[(&str, &[&str])] = &[
+    ("pending_bets", &[
+        "bet_id", "user_seed", "timestamp", "node_id", "heads", "vrf_proof", "processing_time_ms",
+        "processed_at", "retry_count", "status", "tx_signature", "settled_at", "failed_at",
+        "error_message", "created_at", "batch_id", "token_mint", "wager_lamports", "payout_lamports",
+        "proof_status", "player_pubkey", "client_seed", "player_choice",
+        "win_probability_bps", "outcome_bit", "proof_json", "seed_encoding", "game",
+    ]),
+    ("settlement_batches", &[
+        "batch_id", "bet_count", "processing_time_ms", "tx_signature", "success", "created_at", "merkle_root",
+    ]),
+    ("settlement_lock", &["id", "owner", "heartbeat_at"]),
+    ("settlement_outbox", &[
+        "batch_id", "bet_count", "payout_lamports", "merkle_root", "status", "tx_signature", "error_message",
+        "created_at", "updated_at",
+    ]),
+    ("dead_letter_bets", &[
+        "bet_id", "user_seed", "client_seed", "timestamp", "node_id", "heads", "vrf_proof", "proof_json",
+        "proof_status", "processing_time_ms", "processed_at", "token_mint", "wager_lamports", "payout_lamports",
+        "player_pubkey", "player_choice", "win_probability_bps", "outcome_bit", "retry_count", "error_message",
+        "failed_at", "seed_encoding", "game",
+    ]),
+    ("bet_results", &[
+        "id", "bet_id", "user_seed", "seed_encoding", "client_seed", "timestamp", "node_id", "player_pubkey",
+        "heads", "vrf_proof", "proof_json", "processing_time_ms", "created_at",
+    ]),
+];
 
 pub struct Storage {
     pool: SqlitePool,
 }
 
+/// A stored bet's columns, as read from `pending_bets` or `dead_letter_bets`
+struct StoredBet {
+    bet_id: String,
+    user_seed: String,
+    seed_encoding: Option<String>,
+    client_seed: Option<String>,
+    player_choice: Option<String>,
+    timestamp: i64,
+    node_id: String,
+    heads: bool,
+    vrf_proof: String,
+    processing_time_ms: i64,
+    processed_at: String,
+    proof_status: Option<String>,
+    win_probability_bps: Option<i64>,
+    outcome_bit: Option<String>,
+    token_mint: Option<String>,
+    wager_lamports: Option<i64>,
+    payout_lamports: Option<i64>,
+    player_pubkey: Option<String>,
+    proof_json: Option<String>,
+}
+
+impl StoredBet {
+    /// Everything an offline verifier needs for the bet, preferring the stored full proof
+    /// and otherwise rebuilding it from the signature
+    fn into_bundle(self) -> Result<ProofBundle, VfError> {
+        let processed_at = time::OffsetDateTime::parse(
+            &self.processed_at,
+            &time::format_description::well_known::Rfc3339,
+        )?;
+        let stored_status = self.proof_status.as_deref().and_then(ProofStatus::parse);
+        let (proof, proof_status) = match self.proof_json.as_deref().map(serde_json::from_str::<VrfProof>) {
+            Some(Ok(proof)) => (proof, ProofStatus::Full),
+            _ => {
+                let (mut proof, proof_status) = Storage::rebuild_proof(&self.node_id, &self.vrf_proof, stored_status);
+                proof.win_probability_bps = self.win_probability_bps.map(|bps| bps as u16);
+                proof.outcome_bit = self.outcome_bit.as_deref().and_then(OutcomeBit::parse);
+                (proof, proof_status)
+            }
+        };
+        let random_value = VrfEngine::random_value_from_proof(&proof).ok();
+        let player_choice = self.player_choice.as_deref().and_then(CoinSide::parse).unwrap_or_default();
+
+        let bet_id = Uuid::parse_str(&self.bet_id)?;
+
+        Ok(ProofBundle {
+            bet_id,
+            request: CoinflipRequest {
+                user_seed: self.user_seed,
+                seed_encoding: self.seed_encoding.as_deref().and_then(SeedEncoding::parse).unwrap_or_default(),
+                client_seed: self.client_seed,
+                timestamp: self.timestamp as u64,
+                player_choice,
+                player_pubkey: self.player_pubkey,
+                wager_lamports: self.wager_lamports.map(|w| w as u64),
+                token_mint: self.token_mint,
+                // Only a client-chosen id was part of the request the proof answers
+                bet_id: proof.bet_id,
+                ..Default::default()
+            },
+            response: CoinflipResponse {
+                proof,
+                node_id: self.node_id.clone(),
+                heads: self.heads,
+                win: player_choice.wins(self.heads),
+                timestamp: processed_at.unix_timestamp() as u64,
+                processing_time_ms: self.processing_time_ms as u64,
+                free_play: false, // Free-play flips are never stored
+                bet_id: Some(bet_id),
+                payout_lamports: self.payout_lamports.map(|p| p as u64),
+                random_value,
+            },
+            node_pubkey: self.node_id,
+            proof_status,
+        })
+    }
+}
+
+/// Recombine a lamport sum taken by 32-bit halves.
+///
+/// Lamports are u64s stored bit-for-bit in i64 columns, so a plain `SUM` can overflow, and
+/// an amount past `i64::MAX` reads back negative. Queries instead sum each half separately,
+/// `SUM((x >> 32) & 0xFFFFFFFF)` and `SUM(x & 0xFFFFFFFF)`, which can't overflow below 2^31
+/// rows, and pass both sums here. Differences of halves work the same way.
+fn lamports_from_halves(high: i64, low: i64) -> i128 {
+    ((high as i128) << 32) + low as i128
+}
+
 impl Storage {
     pub async fn new(database_url: &str) -> Result<Self, VfError> {
+        Self::open(database_url, true).await
+    }
+
+    /// Connect, running migrations only when `migrate` is set (`AUTO_MIGRATE`). Without
+    /// them, follow up with [`Storage::check_schema`] before serving.
+    pub async fn open(database_url: &str, migrate: bool) -> Result<Self, VfError> {
+        Self::open_with(database_url, migrate, &StorageOptions::default()).await
+    }
+
+    /// [`Storage::open`] with explicit pool and journal settings
+    pub async fn open_with(database_url: &str, migrate: bool, options: &StorageOptions) -> Result<Self, VfError> {
         info!("🗄️  Initializing database connection: {}", database_url);
 
-        // Configure SQLite connection
-        let options = SqliteConnectOptions::new()
-            .filename(database_url.strip_prefix("sqlite:").unwrap_or(database_url))
-            .create_if_missing(true);
+        let pool = match DatabaseLocation::parse(database_url) {
+            // Every connection to :memory: is a separate database, so pin a single
+            // connection for the lifetime of the pool
+            DatabaseLocation::Memory => {
+                SqlitePoolOptions::new()
+                    .max_connections(1)
+                    .min_connections(1)
+                    .idle_timeout(None)
+                    .max_lifetime(None)
+                    .connect_with(SqliteConnectOptions::from_str("sqlite::memory:")?)
+                    .await?
+            }
+            DatabaseLocation::File(path) => {
+                Self::ensure_parent_dir(&path)?;
+
+                // Configure SQLite connection
+                let connect = SqliteConnectOptions::new()
+                    .filename(&path)
+                    .create_if_missing(true)
+                    .journal_mode(options.journal_mode)
+                    .busy_timeout(options.busy_timeout);
 
-        let pool = SqlitePool::connect_with(options).await?;
+                SqlitePoolOptions::new()
+                    .max_connections(options.max_connections)
+                    .connect_with(connect)
+                    .await
+                    .map_err(|e| {
+                        VfError::InvalidInput(format!(
+                            "Database error: cannot open {}: {}",
+                            path.display(),
+                            e
+                        ))
+                    })?
+            }
+        };
 
-        // Run migrations
-        Self::run_migrations(&pool).await?;
+        if migrate {
+            Self::run_migrations(&pool).await?;
+        }
 
         info!("✅ Database initialized successfully");
 
         Ok(Self { pool })
     }
 
+    /// Create the database file's parent directory so first runs don't fail on a fresh checkout
+    fn ensure_parent_dir(path: &Path) -> Result<(), VfError> {
+        let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) else {
+            return Ok(());
+        };
+
+        std::fs::create_dir_all(dir).map_err(|e| {
+            let reason = match e.kind() {
+                std::io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+                _ => e.to_string(),
+            };
+            VfError::InvalidInput(format!(
+                "Database error: cannot create directory {}: {}",
+                dir.display(),
+                reason
+            ))
+        })
+    }
+
     pub fn pool(&self) -> Arc<SqlitePool> {
         Arc::new(self.pool.clone())
     }
 
+    /// Share an already-open pool, e.g. the settlement engine's
+    pub fn from_pool(pool: &SqlitePool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
     async fn run_migrations(pool: &SqlitePool) -> Result<(), VfError> {
         info!("🔄 Running database migrations...");
 
@@ -51,13 +297,41 @@ impl Storage {
                 settled_at TEXT NULL,
                 failed_at TEXT NULL,
                 error_message TEXT NULL,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                batch_id TEXT NULL,
+                token_mint TEXT NULL,
+                wager_lamports INTEGER NULL,
+                payout_lamports INTEGER NULL,
+                proof_status TEXT NULL,
+                player_pubkey TEXT NULL,
+                client_seed TEXT NULL,
+                player_choice TEXT NULL,
+                win_probability_bps INTEGER NULL,
+                outcome_bit TEXT NULL,
+                proof_json TEXT NULL,
+                seed_encoding TEXT NULL,
+                game TEXT NULL
             )
             "#
         )
         .execute(pool)
         .await?;
 
+        // Columns added after the initial schema
+        Self::add_column_if_missing(pool, "pending_bets", "batch_id", "TEXT NULL").await?;
+        Self::add_column_if_missing(pool, "pending_bets", "token_mint", "TEXT NULL").await?;
+        Self::add_column_if_missing(pool, "pending_bets", "wager_lamports", "INTEGER NULL").await?;
+        Self::add_column_if_missing(pool, "pending_bets", "payout_lamports", "INTEGER NULL").await?;
+        Self::add_column_if_missing(pool, "pending_bets", "proof_status", "TEXT NULL").await?;
+        Self::add_column_if_missing(pool, "pending_bets", "player_pubkey", "TEXT NULL").await?;
+        Self::add_column_if_missing(pool, "pending_bets", "client_seed", "TEXT NULL").await?;
+        Self::add_column_if_missing(pool, "pending_bets", "player_choice", "TEXT NULL").await?;
+        Self::add_column_if_missing(pool, "pending_bets", "win_probability_bps", "INTEGER NULL").await?;
+        Self::add_column_if_missing(pool, "pending_bets", "outcome_bit", "TEXT NULL").await?;
+        Self::add_column_if_missing(pool, "pending_bets", "proof_json", "TEXT NULL").await?;
+        Self::add_column_if_missing(pool, "pending_bets", "seed_encoding", "TEXT NULL").await?;
+        Self::add_column_if_missing(pool, "pending_bets", "game", "TEXT NULL").await?;
+
         // Create settlement_batches table
         sqlx::query!(
             r#"
@@ -67,6 +341,101 @@ impl Storage {
                 processing_time_ms INTEGER NOT NULL,
                 tx_signature TEXT NOT NULL,
                 success BOOLEAN NOT NULL,
+                created_at TEXT NOT NULL,
+                merkle_root TEXT NULL
+            )
+            "#
+        )
+        .execute(pool)
+        .await?;
+
+        Self::add_column_if_missing(pool, "settlement_batches", "merkle_root", "TEXT NULL").await?;
+
+        // Single-row advisory lock held by the active settlement loop
+        sqlx::query!(
+            r#"
+            CREATE TABLE IF NOT EXISTS settlement_lock (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                owner TEXT NOT NULL,
+                heartbeat_at INTEGER NOT NULL
+            )
+            "#
+        )
+        .execute(pool)
+        .await?;
+
+        // Each batch's settlement transaction, recorded before submission and marked with its
+        // outcome after, so chain state can be reconciled with the database
+        sqlx::query!(
+            r#"
+            CREATE TABLE IF NOT EXISTS settlement_outbox (
+                batch_id TEXT PRIMARY KEY,
+                bet_count INTEGER NOT NULL,
+                payout_lamports INTEGER NOT NULL,
+                merkle_root TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                tx_signature TEXT NULL,
+                error_message TEXT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+            "#
+        )
+        .execute(pool)
+        .await?;
+
+        // Bets that exhausted their settlement retries, moved out of the working set
+        sqlx::query!(
+            r#"
+            CREATE TABLE IF NOT EXISTS dead_letter_bets (
+                bet_id TEXT PRIMARY KEY,
+                user_seed TEXT NOT NULL,
+                client_seed TEXT NULL,
+                timestamp INTEGER NOT NULL,
+                node_id TEXT NOT NULL,
+                heads BOOLEAN NOT NULL,
+                vrf_proof TEXT NOT NULL,
+                proof_json TEXT NULL,
+                proof_status TEXT NULL,
+                processing_time_ms INTEGER NOT NULL,
+                processed_at TEXT NOT NULL,
+                token_mint TEXT NULL,
+                wager_lamports INTEGER NULL,
+                payout_lamports INTEGER NULL,
+                player_pubkey TEXT NULL,
+                player_choice TEXT NULL,
+                win_probability_bps INTEGER NULL,
+                outcome_bit TEXT NULL,
+                retry_count INTEGER NOT NULL,
+                error_message TEXT NOT NULL,
+                failed_at TEXT NOT NULL,
+                seed_encoding TEXT NULL,
+                game TEXT NULL
+            )
+            "#
+        )
+        .execute(pool)
+        .await?;
+
+        Self::add_column_if_missing(pool, "dead_letter_bets", "seed_encoding", "TEXT NULL").await?;
+        Self::add_column_if_missing(pool, "dead_letter_bets", "game", "TEXT NULL").await?;
+
+        // Every served outcome, written when AUDIT_TRAIL is on
+        sqlx::query!(
+            r#"
+            CREATE TABLE IF NOT EXISTS bet_results (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                bet_id TEXT NULL,
+                user_seed TEXT NOT NULL,
+                seed_encoding TEXT NULL,
+                client_seed TEXT NULL,
+                timestamp INTEGER NOT NULL,
+                node_id TEXT NOT NULL,
+                player_pubkey TEXT NULL,
+                heads BOOLEAN NOT NULL,
+                vrf_proof TEXT NOT NULL,
+                proof_json TEXT NULL,
+                processing_time_ms INTEGER NOT NULL,
                 created_at TEXT NOT NULL
             )
             "#
@@ -74,6 +443,30 @@ impl Storage {
         .execute(pool)
         .await?;
 
+        // Bets failed in place before the dead-letter table existed
+        let mut tx = pool.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO dead_letter_bets (
+                bet_id, user_seed, client_seed, timestamp, node_id, heads, vrf_proof, proof_json, proof_status,
+                processing_time_ms, processed_at, token_mint, wager_lamports, payout_lamports, player_pubkey,
+                player_choice, win_probability_bps, outcome_bit, seed_encoding, game, retry_count, error_message, failed_at
+            )
+            SELECT bet_id, user_seed, client_seed, timestamp, node_id, heads, vrf_proof, proof_json, proof_status,
+                   processing_time_ms, processed_at, token_mint, wager_lamports, payout_lamports, player_pubkey,
+                   player_choice, win_probability_bps, outcome_bit, seed_encoding, game, COALESCE(retry_count, 0),
+                   COALESCE(error_message, 'unknown'), COALESCE(failed_at, processed_at)
+            FROM pending_bets
+            WHERE status = 'failed'
+            "#
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!("DELETE FROM pending_bets WHERE status = 'failed'")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
         // Create indexes
         sqlx::query!("CREATE INDEX IF NOT EXISTS idx_pending_bets_status ON pending_bets(status)")
             .execute(pool)
@@ -83,10 +476,31 @@ impl Storage {
             .execute(pool)
             .await?;
 
+        // Batch collection filters on status and takes the oldest first
+        sqlx::query!("CREATE INDEX IF NOT EXISTS idx_pending_bets_status_processed_at ON pending_bets(status, processed_at)")
+            .execute(pool)
+            .await?;
+
         sqlx::query!("CREATE INDEX IF NOT EXISTS idx_pending_bets_retry_count ON pending_bets(retry_count)")
             .execute(pool)
             .await?;
 
+        sqlx::query!("CREATE INDEX IF NOT EXISTS idx_pending_bets_batch_id ON pending_bets(batch_id)")
+            .execute(pool)
+            .await?;
+
+        sqlx::query!("CREATE INDEX IF NOT EXISTS idx_pending_bets_settled_at ON pending_bets(settled_at)")
+            .execute(pool)
+            .await?;
+
+        sqlx::query!("CREATE INDEX IF NOT EXISTS idx_settlement_outbox_status ON settlement_outbox(status)")
+            .execute(pool)
+            .await?;
+
+        sqlx::query!("CREATE INDEX IF NOT EXISTS idx_pending_bets_player ON pending_bets(player_pubkey, settled_at)")
+            .execute(pool)
+            .await?;
+
         sqlx::query!("CREATE INDEX IF NOT EXISTS idx_settlement_batches_created_at ON settlement_batches(created_at)")
             .execute(pool)
             .await?;
@@ -95,29 +509,115 @@ impl Storage {
             .execute(pool)
             .await?;
 
+        sqlx::query!("CREATE INDEX IF NOT EXISTS idx_dead_letter_bets_failed_at ON dead_letter_bets(failed_at)")
+            .execute(pool)
+            .await?;
+
+        sqlx::query!("CREATE INDEX IF NOT EXISTS idx_bet_results_created_at ON bet_results(created_at)")
+            .execute(pool)
+            .await?;
+
+        sqlx::query!("CREATE INDEX IF NOT EXISTS idx_bet_results_bet_id ON bet_results(bet_id)")
+            .execute(pool)
+            .await?;
+
+        sqlx::query!("CREATE INDEX IF NOT EXISTS idx_bet_results_player ON bet_results(player_pubkey, created_at)")
+            .execute(pool)
+            .await?;
+
         info!("✅ Database migrations completed");
         Ok(())
     }
 
-    /// Store bet result (optional - for audit trail)
+    /// Fail fast, naming every missing table and column, when the database predates the
+    /// schema this build queries, instead of failing later on the first query that needs it
+    pub async fn check_schema(&self) -> Result<(), VfError> {
+        let mut missing = Vec::new();
+        for &(table, columns) in EXPECTED_SCHEMA {
+            let present: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+                .bind(table)
+                .fetch_all(&self.pool)
+                .await?;
+
+            if present.is_empty() {
+                missing.push(format!("table {}", table));
+                continue;
+            }
+            missing.extend(
+                columns
+                    .iter()
+                    .filter(|column| !present.iter().any(|p| p == *column))
+                    .map(|column| format!("{}.{}", table, column)),
+            );
+        }
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(VfError::InvalidInput(format!(
+            "Database error: schema is out of date, run migrations (start once with AUTO_MIGRATE=1). Missing: {}",
+            missing.join(", ")
+        )))
+    }
+
+    /// SQLite has no `ADD COLUMN IF NOT EXISTS`, so check `table_info` first to keep
+    /// migrations idempotent on databases created by older versions
+    async fn add_column_if_missing(
+        pool: &SqlitePool,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<(), VfError> {
+        let exists: Option<i64> = sqlx::query_scalar("SELECT 1 FROM pragma_table_info(?) WHERE name = ?")
+            .bind(table)
+            .bind(column)
+            .fetch_optional(pool)
+            .await?;
+
+        if exists.is_none() {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(pool)
+                .await?;
+            info!(table, column, "➕ Added missing column");
+        }
+
+        Ok(())
+    }
+
+    /// Record a served outcome in `bet_results` (the `AUDIT_TRAIL` log). Free-play flips
+    /// are recorded too, with no bet id.
     pub async fn store_bet(
         &self,
         request: &CoinflipRequest,
         response: &CoinflipResponse,
     ) -> Result<(), VfError> {
+        let bet_id = response.bet_id.map(|id| id.to_string());
+        let seed_encoding = (!request.seed_encoding.is_raw()).then(|| request.seed_encoding.as_str());
+        let timestamp = request.timestamp as i64;
+        let proof_json = serde_json::to_string(&response.proof)
+            .map_err(|e| VfError::InvalidInput(format!("Cannot encode proof: {}", e)))?;
+        let processing_time_ms = response.processing_time_ms as i64;
+        let created_at = time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap();
+
         sqlx::query!(
             r#"
             INSERT INTO bet_results (
-                user_seed, timestamp, node_id, heads, 
-                vrf_proof, processing_time_ms, created_at
-            ) VALUES (?, ?, ?, ?, ?, ?, datetime('now'))
+                bet_id, user_seed, seed_encoding, client_seed, timestamp, node_id, player_pubkey,
+                heads, vrf_proof, proof_json, processing_time_ms, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
+            bet_id,
             request.user_seed,
-            request.timestamp as i64,
+            seed_encoding,
+            request.client_seed,
+            timestamp,
             response.node_id,
+            request.player_pubkey,
             response.heads,
             response.proof.signature,
-            response.processing_time_ms as i64
+            proof_json,
+            processing_time_ms,
+            created_at
         )
         .execute(&self.pool)
         .await
@@ -129,6 +629,756 @@ impl Storage {
         Ok(())
     }
 
+    pub async fn batch_exists(&self, batch_id: &Uuid) -> Result<bool, VfError> {
+        let batch_id = batch_id.to_string();
+        let row = sqlx::query!("SELECT batch_id FROM settlement_batches WHERE batch_id = ?", batch_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.is_some())
+    }
+
+    /// One page of recorded settlement batches, newest first
+    pub async fn list_batches(&self, limit: i64, offset: i64) -> Result<Vec<BatchRecord>, VfError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT batch_id as "batch_id!", bet_count, processing_time_ms, tx_signature, success, created_at, merkle_root
+            FROM settlement_batches
+            ORDER BY created_at DESC, batch_id DESC
+            LIMIT ? OFFSET ?
+            "#,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(BatchRecord {
+                    batch_id: Uuid::parse_str(&row.batch_id)?,
+                    bet_count: row.bet_count as u64,
+                    processing_time_ms: row.processing_time_ms as u64,
+                    tx_signature: row.tx_signature,
+                    success: row.success,
+                    created_at: row.created_at,
+                    merkle_root: row.merkle_root,
+                })
+            })
+            .collect()
+    }
+
+    /// Record a batch's settlement transaction in the outbox before it is submitted. A batch
+    /// already recorded, e.g. one resumed after a crash, keeps its entry; either way the
+    /// stored entry is returned.
+    pub async fn insert_outbox_tx(
+        &self,
+        batch_id: &Uuid,
+        bet_count: u64,
+        payout_lamports: u64,
+        merkle_root: &str,
+    ) -> Result<OutboxEntry, VfError> {
+        let id = batch_id.to_string();
+        let bet_count = bet_count as i64;
+        let payout_lamports = payout_lamports as i64;
+        let now = time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO settlement_outbox (batch_id, bet_count, payout_lamports, merkle_root, status, created_at, updated_at)
+            VALUES (?, ?, ?, ?, 'pending', ?, ?)
+            ON CONFLICT(batch_id) DO NOTHING
+            "#,
+            id,
+            bet_count,
+            payout_lamports,
+            merkle_root,
+            now,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        self.get_outbox_entry(batch_id)
+            .await?
+            .ok_or_else(|| VfError::InvalidInput(format!("Outbox entry for batch {} was not stored", batch_id)))
+    }
+
+    /// Mark a batch's outbox transaction submitted under `tx_signature`, to be confirmed by
+    /// polling. A confirmed transaction stays confirmed. False if nothing was updated.
+    pub async fn submit_outbox_tx(&self, batch_id: &Uuid, tx_signature: &str) -> Result<bool, VfError> {
+        let id = batch_id.to_string();
+        let now = time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap();
+        let updated = sqlx::query!(
+            "UPDATE settlement_outbox SET status = 'submitted', tx_signature = ?, error_message = NULL, updated_at = ? WHERE batch_id = ? AND status != 'confirmed'",
+            tx_signature,
+            now,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    /// Outbox transactions awaiting confirmation, oldest first
+    pub async fn submitted_outbox_txs(&self) -> Result<Vec<OutboxEntry>, VfError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT batch_id as "batch_id!", bet_count, payout_lamports, merkle_root, status,
+                   tx_signature, error_message, created_at, updated_at
+            FROM settlement_outbox
+            WHERE status = 'submitted'
+            ORDER BY created_at ASC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(OutboxEntry {
+                    batch_id: Uuid::parse_str(&row.batch_id)?,
+                    bet_count: row.bet_count as u64,
+                    payout_lamports: row.payout_lamports as u64,
+                    merkle_root: row.merkle_root,
+                    status: OutboxStatus::Submitted,
+                    tx_signature: row.tx_signature,
+                    error_message: row.error_message,
+                    created_at: row.created_at,
+                    updated_at: row.updated_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Mark a batch's outbox transaction confirmed under `tx_signature`. False if the batch
+    /// has no outbox entry.
+    pub async fn confirm_outbox_tx(&self, batch_id: &Uuid, tx_signature: &str) -> Result<bool, VfError> {
+        let id = batch_id.to_string();
+        let now = time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap();
+        let updated = sqlx::query!(
+            "UPDATE settlement_outbox SET status = 'confirmed', tx_signature = ?, error_message = NULL, updated_at = ? WHERE batch_id = ?",
+            tx_signature,
+            now,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    /// Mark a batch's outbox transaction failed with `error`. A confirmed transaction stays
+    /// confirmed. False if nothing was updated.
+    pub async fn fail_outbox_tx(&self, batch_id: &Uuid, error: &str) -> Result<bool, VfError> {
+        let id = batch_id.to_string();
+        let now = time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap();
+        let updated = sqlx::query!(
+            "UPDATE settlement_outbox SET status = 'failed', error_message = ?, updated_at = ? WHERE batch_id = ? AND status != 'confirmed'",
+            error,
+            now,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    /// A batch's outbox entry; `None` if its transaction was never recorded
+    pub async fn get_outbox_entry(&self, batch_id: &Uuid) -> Result<Option<OutboxEntry>, VfError> {
+        let id = batch_id.to_string();
+        let row = sqlx::query!(
+            r#"
+            SELECT batch_id as "batch_id!", bet_count, payout_lamports, merkle_root, status,
+                   tx_signature, error_message, created_at, updated_at
+            FROM settlement_outbox
+            WHERE batch_id = ?
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(|row| {
+            Ok(OutboxEntry {
+                batch_id: Uuid::parse_str(&row.batch_id)?,
+                bet_count: row.bet_count as u64,
+                payout_lamports: row.payout_lamports as u64,
+                merkle_root: row.merkle_root,
+                status: OutboxStatus::parse(&row.status)
+                    .ok_or_else(|| VfError::InvalidInput(format!("Unknown outbox status '{}'", row.status)))?,
+                tx_signature: row.tx_signature,
+                error_message: row.error_message,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+            })
+        })
+        .transpose()
+    }
+
+    /// One page of proof bundles for the bets settled in a batch, in processing order
+    pub async fn get_batch_proofs(
+        &self,
+        batch_id: &Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ProofBundle>, VfError> {
+        let batch_id = batch_id.to_string();
+        let rows = sqlx::query!(
+            r#"
+            SELECT bet_id, user_seed, seed_encoding, client_seed, player_choice, timestamp, node_id, heads,
+                   vrf_proof, processing_time_ms, processed_at, proof_status, win_probability_bps, outcome_bit,
+                   token_mint, wager_lamports, payout_lamports, player_pubkey, proof_json
+            FROM pending_bets
+            WHERE batch_id = ?
+            ORDER BY processed_at ASC, bet_id ASC
+            LIMIT ? OFFSET ?
+            "#,
+            batch_id,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                StoredBet {
+                    bet_id: row.bet_id.unwrap_or_default(),
+                    user_seed: row.user_seed,
+                    seed_encoding: row.seed_encoding,
+                    client_seed: row.client_seed,
+                    player_choice: row.player_choice,
+                    timestamp: row.timestamp,
+                    node_id: row.node_id,
+                    heads: row.heads,
+                    vrf_proof: row.vrf_proof,
+                    processing_time_ms: row.processing_time_ms,
+                    processed_at: row.processed_at,
+                    proof_status: row.proof_status,
+                    win_probability_bps: row.win_probability_bps,
+                    outcome_bit: row.outcome_bit,
+                    token_mint: row.token_mint,
+                    wager_lamports: row.wager_lamports,
+                    payout_lamports: row.payout_lamports,
+                    player_pubkey: row.player_pubkey,
+                    proof_json: row.proof_json,
+                }
+                .into_bundle()
+            })
+            .collect()
+    }
+
+    /// Delete bets settled before `cutoff`, and dead-lettered bets that failed before it.
+    /// Pending bets are never touched. Returns how many rows went.
+    ///
+    /// A batch's bets share one `settled_at`, so a batch is purged whole and inclusion
+    /// proofs for the batches that remain stay valid.
+    pub async fn purge_settled_before(&self, cutoff: time::OffsetDateTime) -> Result<u64, VfError> {
+        let cutoff = cutoff
+            .format(&time::format_description::well_known::Rfc3339)
+            .map_err(|e| VfError::InvalidInput(format!("Invalid retention cutoff: {}", e)))?;
+
+        let mut tx = self.pool.begin().await?;
+        let settled = sqlx::query!(
+            "DELETE FROM pending_bets WHERE status = 'settled' AND settled_at < ?",
+            cutoff
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        let failed = sqlx::query!("DELETE FROM dead_letter_bets WHERE failed_at < ?", cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+
+        Ok(settled + failed)
+    }
+
+    /// One page of dead-lettered bets, most recently failed first
+    pub async fn list_dead_letters(&self, limit: i64, offset: i64) -> Result<Vec<DeadLetterBet>, VfError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT bet_id, user_seed, seed_encoding, client_seed, player_choice, timestamp, node_id, heads,
+                   vrf_proof, processing_time_ms, processed_at, proof_status, win_probability_bps, outcome_bit,
+                   token_mint, wager_lamports, payout_lamports, player_pubkey, proof_json,
+                   retry_count, error_message, failed_at
+            FROM dead_letter_bets
+            ORDER BY failed_at DESC, bet_id DESC
+            LIMIT ? OFFSET ?
+            "#,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let bundle = StoredBet {
+                    bet_id: row.bet_id.unwrap_or_default(),
+                    user_seed: row.user_seed,
+                    seed_encoding: row.seed_encoding,
+                    client_seed: row.client_seed,
+                    player_choice: row.player_choice,
+                    timestamp: row.timestamp,
+                    node_id: row.node_id,
+                    heads: row.heads,
+                    vrf_proof: row.vrf_proof,
+                    processing_time_ms: row.processing_time_ms,
+                    processed_at: row.processed_at,
+                    proof_status: row.proof_status,
+                    win_probability_bps: row.win_probability_bps,
+                    outcome_bit: row.outcome_bit,
+                    token_mint: row.token_mint,
+                    wager_lamports: row.wager_lamports,
+                    payout_lamports: row.payout_lamports,
+                    player_pubkey: row.player_pubkey,
+                    proof_json: row.proof_json,
+                }
+                .into_bundle()?;
+                Ok(DeadLetterBet {
+                    bundle,
+                    retry_count: row.retry_count as u32,
+                    error_message: row.error_message,
+                    failed_at: row.failed_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Settlement status and timestamps of a stored bet; `None` if no bet has this id
+    pub async fn get_bet_by_id(&self, bet_id: &Uuid) -> Result<Option<BetStatus>, VfError> {
+        let bet_id_str = bet_id.to_string();
+        let row = sqlx::query!(
+            r#"
+            SELECT status, tx_signature, processed_at, settled_at, failed_at
+            FROM pending_bets
+            WHERE bet_id = ?
+            "#,
+            bet_id_str
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            // Bets that exhausted their retries live in the dead-letter table
+            let dead = sqlx::query!(
+                "SELECT processed_at, failed_at FROM dead_letter_bets WHERE bet_id = ?",
+                bet_id_str
+            )
+            .fetch_optional(&self.pool)
+            .await?;
+            return Ok(dead.map(|dead| BetStatus {
+                bet_id: *bet_id,
+                status: SettlementStatus::Failed,
+                tx_signature: None,
+                processed_at: dead.processed_at,
+                settled_at: None,
+                failed_at: Some(dead.failed_at),
+            }));
+        };
+        let status = match row.status.as_deref() {
+            None => SettlementStatus::default(),
+            Some(status) => SettlementStatus::parse(status)
+                .ok_or_else(|| VfError::InvalidInput(format!("Unknown settlement status '{}'", status)))?,
+        };
+
+        Ok(Some(BetStatus {
+            bet_id: *bet_id,
+            status,
+            tx_signature: row.tx_signature,
+            processed_at: row.processed_at,
+            settled_at: row.settled_at,
+            failed_at: row.failed_at,
+        }))
+    }
+
+    /// Settlement facts for a settled bet, as an unsigned receipt (`node_pubkey` and
+    /// `signature` are empty until `VrfEngine::sign_receipt`)
+    pub async fn get_settlement_receipt(&self, bet_id: &Uuid) -> Result<Option<SettlementReceipt>, VfError> {
+        let bet_id_str = bet_id.to_string();
+        let row = sqlx::query!(
+            r#"
+            SELECT batch_id as "batch_id!", tx_signature as "tx_signature!", heads, payout_lamports,
+                   settled_at as "settled_at!"
+            FROM pending_bets
+            WHERE bet_id = ? AND status = 'settled'
+              AND batch_id IS NOT NULL AND tx_signature IS NOT NULL AND settled_at IS NOT NULL
+            "#,
+            bet_id_str
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        Ok(Some(SettlementReceipt {
+            bet_id: *bet_id,
+            batch_id: Uuid::parse_str(&row.batch_id)?,
+            tx_signature: row.tx_signature,
+            heads: row.heads,
+            payout_lamports: row.payout_lamports.map(|p| p as u64),
+            settled_at: row.settled_at,
+            node_pubkey: String::new(),
+            signature: String::new(),
+        }))
+    }
+
+    /// Merkle inclusion proof tying a settled bet to its batch root and settlement tx.
+    /// `None` if the bet isn't settled or its batch predates Merkle roots.
+    pub async fn get_inclusion_proof(&self, bet_id: &Uuid) -> Result<Option<InclusionProof>, VfError> {
+        let bet_id_str = bet_id.to_string();
+        let batch = sqlx::query!(
+            r#"
+            SELECT b.batch_id as "batch_id!", b.tx_signature, b.merkle_root as "merkle_root!"
+            FROM pending_bets p
+            JOIN settlement_batches b ON b.batch_id = p.batch_id
+            WHERE p.bet_id = ? AND p.status = 'settled' AND b.merkle_root IS NOT NULL
+            "#,
+            bet_id_str
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(batch) = batch else {
+            return Ok(None);
+        };
+
+        let rows = sqlx::query!(
+            "SELECT bet_id, vrf_proof, heads FROM pending_bets WHERE batch_id = ?",
+            batch.batch_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let bets = rows
+            .iter()
+            .map(|row| Ok((Uuid::parse_str(row.bet_id.as_deref().unwrap_or_default())?, row.vrf_proof.as_str(), row.heads)))
+            .collect::<Result<Vec<_>, VfError>>()?;
+        let (ids, tree) = merkle::batch_tree(bets.iter().copied());
+
+        if hex::encode(tree.root()) != batch.merkle_root {
+            error!(batch_id = %batch.batch_id, "🚨 Stored batch rows no longer match the recorded Merkle root");
+            return Err(VfError::InvalidProof("Batch contents do not match the recorded Merkle root".to_string()));
+        }
+
+        let index = ids.iter().position(|id| id == bet_id);
+        let bet = bets.iter().find(|(id, _, _)| id == bet_id);
+        let (Some(index), Some(&(_, vrf_signature, heads))) = (index, bet) else {
+            return Ok(None);
+        };
+
+        Ok(Some(InclusionProof {
+            bet_id: *bet_id,
+            vrf_signature: vrf_signature.to_string(),
+            heads,
+            batch_id: Uuid::parse_str(&batch.batch_id)?,
+            tx_signature: batch.tx_signature,
+            merkle_root: batch.merkle_root,
+            path: tree.proof(index).unwrap_or_default(),
+        }))
+    }
+
+    /// Re-derive every bet's outcome in a batch from its stored inputs and compare it with
+    /// the stored outcome. Catches rows corrupted or mis-written between flip and storage.
+    pub async fn replay_batch(&self, batch_id: &Uuid) -> Result<BatchReplay, VfError> {
+        const PAGE_SIZE: i64 = 500;
+        let mut replay = BatchReplay { batch_id: *batch_id, bet_count: 0, mismatches: Vec::new() };
+        let mut offset = 0;
+
+        loop {
+            let page = self.get_batch_proofs(batch_id, PAGE_SIZE, offset).await?;
+            replay.bet_count += page.len() as u64;
+
+            for bundle in &page {
+                if let Some(mismatch) = Self::replay_bet(bundle) {
+                    replay.mismatches.push(mismatch);
+                }
+            }
+
+            if (page.len() as i64) < PAGE_SIZE {
+                break;
+            }
+            offset += PAGE_SIZE;
+        }
+
+        if !replay.mismatches.is_empty() {
+            error!(
+                batch_id = %batch_id,
+                mismatches = replay.mismatches.len(),
+                "🚨 Settlement batch replay found mismatches"
+            );
+        }
+        Ok(replay)
+    }
+
+    fn replay_bet(bundle: &ProofBundle) -> Option<ReplayMismatch> {
+        let stored_heads = bundle.response.heads;
+        let mismatch = |derived_heads, problem: &str| ReplayMismatch {
+            bet_id: bundle.bet_id,
+            stored_heads,
+            derived_heads,
+            problem: problem.to_string(),
+        };
+
+        if bundle.proof_status == ProofStatus::Unreconstructable {
+            return Some(mismatch(None, "stored proof cannot be rebuilt"));
+        }
+        let Ok(derived_heads) = VrfEngine::outcome_from_proof(&bundle.response.proof, bundle.request.player_choice) else {
+            return Some(mismatch(None, "stored proof cannot be rebuilt"));
+        };
+        if VrfEngine::verify_with_pubkey(&bundle.node_pubkey, &bundle.request, &bundle.response.proof).is_err() {
+            return Some(mismatch(Some(derived_heads), "signature does not match the stored inputs"));
+        }
+        if derived_heads != stored_heads {
+            return Some(mismatch(Some(derived_heads), "stored outcome differs from the derived outcome"));
+        }
+        None
+    }
+
+    /// Rebuild a stored proof, never failing: rows that can't be rebuilt come back as
+    /// `Unreconstructable` carrying just the raw signature.
+    fn rebuild_proof(node_pubkey: &str, signature: &str, stored: Option<ProofStatus>) -> (VrfProof, ProofStatus) {
+        let rebuilt = match stored {
+            Some(ProofStatus::Unreconstructable) => None,
+            _ => VrfEngine::proof_from_signature(node_pubkey, signature).ok(),
+        };
+
+        match rebuilt {
+            Some(proof) => (proof, stored.unwrap_or(ProofStatus::Legacy)),
+            None => {
+                let raw = VrfProof {
+                    seed_commitment: String::new(),
+                    vrf_output: String::new(),
+                    signature: signature.to_string(),
+                    prev_output: None,
+                    beacon_round: None,
+                    beacon_randomness: None,
+                    output_hash: None,
+                    win_probability_bps: None,
+                    outcome_bit: None,
+                    challenge_len: None,
+                    node_seed: None,
+                    bet_id: None,
+                    challenge: String::new(),
+                };
+                (raw, ProofStatus::Unreconstructable)
+            }
+        }
+    }
+
+    /// Flag rows written before proof status was tracked. Rows whose signature still
+    /// rebuilds into a proof are marked `legacy`, the rest `unreconstructable`. Only
+    /// unflagged rows are touched, so rerunning is cheap.
+    pub async fn backfill_legacy_proofs(&self) -> Result<ProofBackfillReport, VfError> {
+        const PAGE_SIZE: i64 = 500;
+        let mut report = ProofBackfillReport::default();
+
+        loop {
+            let rows = sqlx::query!(
+                "SELECT bet_id, node_id, vrf_proof FROM pending_bets WHERE proof_status IS NULL LIMIT ?",
+                PAGE_SIZE
+            )
+            .fetch_all(&self.pool)
+            .await?;
+
+            if rows.is_empty() {
+                break;
+            }
+
+            let mut tx = self.pool.begin().await?;
+            for row in &rows {
+                let (_, status) = Self::rebuild_proof(&row.node_id, &row.vrf_proof, None);
+                match status {
+                    ProofStatus::Unreconstructable => report.unreconstructable += 1,
+                    _ => report.legacy += 1,
+                }
+
+                let status = status.as_str();
+                sqlx::query!(
+                    "UPDATE pending_bets SET proof_status = ? WHERE bet_id = ?",
+                    status,
+                    row.bet_id
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await?;
+        }
+
+        if report.unreconstructable > 0 {
+            error!(
+                legacy = report.legacy,
+                unreconstructable = report.unreconstructable,
+                "⚠️ Some stored proofs cannot be rebuilt"
+            );
+        } else if report.legacy > 0 {
+            info!(legacy = report.legacy, "🏷️ Flagged legacy proof rows");
+        }
+
+        Ok(report)
+    }
+
+    /// Page through a batch's proofs without loading the whole batch into memory
+    pub fn stream_batch_proofs(
+        self: &Arc<Self>,
+        batch_id: Uuid,
+        page_size: i64,
+    ) -> impl Stream<Item = Result<Vec<ProofBundle>, VfError>> + Send + 'static {
+        let storage = self.clone();
+
+        futures_util::stream::unfold(Some(0i64), move |offset| {
+            let storage = storage.clone();
+            async move {
+                let offset = offset?;
+                match storage.get_batch_proofs(&batch_id, page_size, offset).await {
+                    Ok(page) if page.is_empty() && offset > 0 => None,
+                    Ok(page) => {
+                        let next = (page.len() as i64 == page_size).then_some(offset + page_size);
+                        Some((Ok(page), next))
+                    }
+                    Err(e) => Some((Err(e), None)),
+                }
+            }
+        })
+    }
+
+    /// Realized house edge per token over bets settled in `[from, to)`. Bets recorded
+    /// before wagers were persisted are left out.
+    pub async fn get_realized_edge(
+        &self,
+        from: Option<time::OffsetDateTime>,
+        to: Option<time::OffsetDateTime>,
+    ) -> Result<Vec<RealizedEdge>, VfError> {
+        let rfc3339 = |t: time::OffsetDateTime| {
+            t.format(&time::format_description::well_known::Rfc3339)
+                .map_err(|e| VfError::InvalidInput(format!("Invalid time window: {}", e)))
+        };
+        let from = from.map(rfc3339).transpose()?;
+        let to = to.map(rfc3339).transpose()?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                token_mint as "token_mint!",
+                COUNT(*) as "bet_count!: i64",
+                SUM((wager_lamports >> 32) & 0xFFFFFFFF) as "wagered_high!: i64",
+                SUM(wager_lamports & 0xFFFFFFFF) as "wagered_low!: i64",
+                SUM((payout_lamports >> 32) & 0xFFFFFFFF) as "paid_high!: i64",
+                SUM(payout_lamports & 0xFFFFFFFF) as "paid_low!: i64"
+            FROM pending_bets
+            WHERE status = 'settled'
+              AND wager_lamports IS NOT NULL
+              AND token_mint IS NOT NULL
+              AND (?1 IS NULL OR julianday(settled_at) >= julianday(?1))
+              AND (?2 IS NULL OR julianday(settled_at) < julianday(?2))
+            GROUP BY token_mint
+            ORDER BY token_mint
+            "#,
+            from,
+            to
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Totals saturate at u64::MAX
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let wagered = lamports_from_halves(row.wagered_high, row.wagered_low);
+                let paid = lamports_from_halves(row.paid_high, row.paid_low);
+                RealizedEdge {
+                    token_mint: row.token_mint,
+                    bet_count: row.bet_count as u64,
+                    total_wagered_lamports: u64::try_from(wagered).unwrap_or(u64::MAX),
+                    total_paid_lamports: u64::try_from(paid).unwrap_or(u64::MAX),
+                    realized_edge: if wagered == 0 { 0.0 } else { (wagered as f64 - paid as f64) / wagered as f64 },
+                }
+            })
+            .collect())
+    }
+
+    /// A player's settled bets as ledger entries, oldest first: a debit for each wager and a
+    /// credit for each non-zero payout, with the running balance per token. Pages count
+    /// bets, not entries, and balances carry over from earlier pages.
+    pub async fn get_player_ledger(&self, player_pubkey: &str, offset: u64, limit: u64) -> Result<PlayerLedger, VfError> {
+        let offset = offset as i64;
+        let fetch = limit as i64 + 1; // One extra row tells us whether another page exists
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                bet_id as "bet_id!",
+                settled_at as "settled_at!",
+                token_mint as "token_mint!",
+                wager_lamports as "wager_lamports!",
+                payout_lamports as "payout_lamports!",
+                SUM(((payout_lamports >> 32) & 0xFFFFFFFF) - ((wager_lamports >> 32) & 0xFFFFFFFF)) OVER (
+                    PARTITION BY token_mint
+                    ORDER BY settled_at, processed_at, bet_id
+                    ROWS UNBOUNDED PRECEDING
+                ) as "balance_high!: i64",
+                SUM((payout_lamports & 0xFFFFFFFF) - (wager_lamports & 0xFFFFFFFF)) OVER (
+                    PARTITION BY token_mint
+                    ORDER BY settled_at, processed_at, bet_id
+                    ROWS UNBOUNDED PRECEDING
+                ) as "balance_low!: i64"
+            FROM pending_bets
+            WHERE player_pubkey = ?1
+              AND status = 'settled'
+              AND wager_lamports IS NOT NULL
+              AND payout_lamports IS NOT NULL
+              AND token_mint IS NOT NULL
+            ORDER BY settled_at, processed_at, bet_id
+            LIMIT ?2 OFFSET ?3
+            "#,
+            player_pubkey,
+            fetch,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Balances saturate at the i64 range
+        let saturate = |balance: i128| balance.clamp(i64::MIN.into(), i64::MAX.into()) as i64;
+        let has_more = rows.len() as u64 > limit;
+        let mut entries = Vec::new();
+        for row in rows.into_iter().take(limit as usize) {
+            let bet_id = Uuid::parse_str(&row.bet_id)?;
+            let wager = row.wager_lamports as u64;
+            let payout = row.payout_lamports as u64;
+            let balance = lamports_from_halves(row.balance_high, row.balance_low);
+
+            entries.push(LedgerEntry {
+                bet_id,
+                settled_at: row.settled_at.clone(),
+                token_mint: row.token_mint.clone(),
+                kind: LedgerEntryKind::Debit,
+                amount_lamports: wager,
+                balance_lamports: saturate(balance - payout as i128),
+            });
+            if payout > 0 {
+                entries.push(LedgerEntry {
+                    bet_id,
+                    settled_at: row.settled_at,
+                    token_mint: row.token_mint,
+                    kind: LedgerEntryKind::Credit,
+                    amount_lamports: payout,
+                    balance_lamports: saturate(balance),
+                });
+            }
+        }
+
+        Ok(PlayerLedger {
+            player_pubkey: player_pubkey.to_string(),
+            entries,
+            next_offset: has_more.then_some(offset as u64 + limit),
+        })
+    }
+
     /// Get settlement statistics from database
     pub async fn get_settlement_summary(&self) -> Result<serde_json::Value, VfError> {
         let stats = sqlx::query!(
@@ -137,8 +1387,10 @@ impl Storage {
                 COUNT(*) as total_bets,
                 SUM(CASE WHEN status = 'settled' THEN 1 ELSE 0 END) as settled_bets,
                 SUM(CASE WHEN status = 'pending' THEN 1 ELSE 0 END) as pending_bets,
-                SUM(CASE WHEN status = 'failed' THEN 1 ELSE 0 END) as failed_bets,
-                AVG(CASE WHEN status = 'settled' THEN processing_time_ms END) as avg_processing_time
+                (SELECT COUNT(*) FROM dead_letter_bets) as failed_bets,
+                AVG(CASE WHEN status = 'settled' THEN processing_time_ms END) as avg_processing_time,
+                SUM(CASE WHEN proof_status = 'legacy' THEN 1 ELSE 0 END) as legacy_proofs,
+                SUM(CASE WHEN proof_status = 'unreconstructable' THEN 1 ELSE 0 END) as unreconstructable_proofs
             FROM pending_bets
             "#
         )
@@ -158,6 +1410,29 @@ impl Storage {
         .fetch_one(&self.pool)
         .await?;
 
+        // Bets stored before bets named their game are coinflips
+        let game_stats = sqlx::query!(
+            r#"
+            SELECT
+                COALESCE(game, 'coinflip') as "game!: String",
+                COUNT(*) as "total_bets!: i64",
+                SUM(CASE WHEN status = 'settled' THEN 1 ELSE 0 END) as "settled_bets!: i64",
+                SUM(CASE WHEN status = 'pending' THEN 1 ELSE 0 END) as "pending_bets!: i64",
+                SUM(CASE WHEN heads = (COALESCE(player_choice, 'heads') = 'heads') THEN 1 ELSE 0 END) as "wins!: i64"
+            FROM pending_bets
+            GROUP BY COALESCE(game, 'coinflip')
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let games: serde_json::Map<_, _> = game_stats
+            .into_iter()
+            .map(|row| {
+                let summary = game_summary(row.total_bets, row.settled_bets, row.pending_bets, row.wins);
+                (row.game, summary)
+            })
+            .collect();
+
         Ok(serde_json::json!({
             "bets": {
                 "total": stats.total_bets,
@@ -166,16 +1441,33 @@ impl Storage {
                 "failed": stats.failed_bets,
                 "avg_processing_time_ms": stats.avg_processing_time
             },
+            "proofs": {
+                "legacy": stats.legacy_proofs,
+                "unreconstructable": stats.unreconstructable_proofs
+            },
             "batches": {
                 "total": batch_stats.total_batches,
                 "successful": batch_stats.successful_batches,
                 "avg_size": batch_stats.avg_batch_size,
                 "avg_processing_time_ms": batch_stats.avg_batch_processing_time
-            }
+            },
+            "games": games
         }))
     }
 }
 
+/// One game's entry under `games` in the settlement summary. `win_rate` is the share of the
+/// game's stored bets the player won.
+pub(crate) fn game_summary(total: i64, settled: i64, pending: i64, wins: i64) -> serde_json::Value {
+    serde_json::json!({
+        "total": total,
+        "settled": settled,
+        "pending": pending,
+        "wins": wins,
+        "win_rate": if total > 0 { wins as f64 / total as f64 } else { 0.0 }
+    })
+}
+
 impl From<sqlx::Error> for VfError {
     fn from(err: sqlx::Error) -> Self {
         VfError::InvalidInput(format!("Database error: {}", err))
@@ -192,4 +1484,717 @@ impl From<time::error::Parse> for VfError {
     fn from(err: time::error::Parse) -> Self {
         VfError::InvalidInput(format!("Time parsing error: {}", err))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    /// Insert bets as the settlement engine would after settling them in `batch_id`
+    async fn insert_settled_batch(storage: &Storage, engine: &VrfEngine, batch_id: Uuid, count: usize) {
+        let now = time::OffsetDateTime::now_utc();
+        let rfc3339 = |t: time::OffsetDateTime| t.format(&time::format_description::well_known::Rfc3339).unwrap();
+
+        sqlx::query(
+            "INSERT INTO settlement_batches (batch_id, bet_count, processing_time_ms, tx_signature, success, created_at)
+             VALUES (?, ?, 0, 'mock_tx', 1, ?)",
+        )
+        .bind(batch_id.to_string())
+        .bind(count as i64)
+        .bind(rfc3339(now))
+        .execute(&storage.pool)
+        .await
+        .unwrap();
+
+        for i in 0..count {
+            let req = CoinflipRequest {
+                user_seed: format!("seed_{}", i),
+                timestamp: 1234567890 + i as u64,
+                ..Default::default()
+            };
+            let response = engine.process_coinflip(&req).unwrap();
+
+            sqlx::query(
+                "INSERT INTO pending_bets (bet_id, user_seed, timestamp, node_id, heads, vrf_proof,
+                                           processing_time_ms, processed_at, status, batch_id, proof_status, proof_json)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, 'settled', ?, 'full', ?)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(&req.user_seed)
+            .bind(req.timestamp as i64)
+            .bind(&response.node_id)
+            .bind(response.heads)
+            .bind(&response.proof.signature)
+            .bind(response.processing_time_ms as i64)
+            .bind(rfc3339(now + time::Duration::milliseconds(i as i64)))
+            .bind(batch_id.to_string())
+            .bind(serde_json::to_string(&response.proof).unwrap())
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_parse_database_location() {
+        assert_eq!(DatabaseLocation::parse("sqlite::memory:"), DatabaseLocation::Memory);
+        assert_eq!(
+            DatabaseLocation::parse("sqlite:./data/vfnode.db"),
+            DatabaseLocation::File(PathBuf::from("./data/vfnode.db"))
+        );
+        assert_eq!(
+            DatabaseLocation::parse("sqlite:///var/lib/vfnode.db?mode=rwc"),
+            DatabaseLocation::File(PathBuf::from("/var/lib/vfnode.db"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bet_results_audit_trail() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+        let engine = VrfEngine::new();
+        let req = CoinflipRequest {
+            user_seed: "audited".to_string(),
+            client_seed: Some("client".to_string()),
+            player_pubkey: Some("player_a".to_string()),
+            ..Default::default()
+        };
+        let mut response = engine.process_coinflip(&req).unwrap();
+        response.bet_id = Some(Uuid::new_v4());
+
+        storage.store_bet(&req, &response).await.unwrap();
+
+        let (bet_id, user_seed, client_seed, heads, vrf_proof, proof_json): (String, String, Option<String>, bool, String, String) =
+            sqlx::query_as("SELECT bet_id, user_seed, client_seed, heads, vrf_proof, proof_json FROM bet_results")
+                .fetch_one(&storage.pool)
+                .await
+                .unwrap();
+        assert_eq!(bet_id, response.bet_id.unwrap().to_string());
+        assert_eq!((user_seed.as_str(), client_seed.as_deref()), ("audited", Some("client")));
+        assert_eq!(heads, response.heads);
+        assert_eq!(vrf_proof, response.proof.signature);
+        let proof: VrfProof = serde_json::from_str(&proof_json).unwrap();
+        assert!(engine.verify_proof(&proof, &req).unwrap());
+        assert!(storage.check_schema().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_nested_path_is_created() {
+        let base = std::env::temp_dir().join(format!("vfnode-test-{}", uuid::Uuid::new_v4()));
+        let db_path = base.join("data").join("nested").join("vfnode.db");
+
+        let storage = Storage::new(&format!("sqlite:{}", db_path.display())).await.unwrap();
+        assert!(db_path.exists());
+        assert!(storage.get_settlement_summary().await.is_ok());
+
+        drop(storage);
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[tokio::test]
+    async fn test_file_database_uses_pool_options() {
+        let base = std::env::temp_dir().join(format!("vfnode-test-{}", uuid::Uuid::new_v4()));
+        let url = format!("sqlite:{}", base.join("vfnode.db").display());
+
+        let storage = Storage::new(&url).await.unwrap();
+        let mode: String = sqlx::query_scalar("PRAGMA journal_mode").fetch_one(&storage.pool).await.unwrap();
+        assert_eq!(mode, "wal");
+        let busy_ms: i64 = sqlx::query_scalar("PRAGMA busy_timeout").fetch_one(&storage.pool).await.unwrap();
+        assert_eq!(busy_ms, 5_000);
+
+        let options = StorageOptions {
+            max_connections: 2,
+            journal_mode: SqliteJournalMode::Delete,
+            busy_timeout: Duration::from_millis(250),
+        };
+        let url = format!("sqlite:{}", base.join("rollback.db").display());
+        let storage = Storage::open_with(&url, true, &options).await.unwrap();
+        let mode: String = sqlx::query_scalar("PRAGMA journal_mode").fetch_one(&storage.pool).await.unwrap();
+        assert_eq!(mode, "delete");
+        let busy_ms: i64 = sqlx::query_scalar("PRAGMA busy_timeout").fetch_one(&storage.pool).await.unwrap();
+        assert_eq!(busy_ms, 250);
+        assert_eq!(storage.pool.options().get_max_connections(), 2);
+
+        drop(storage);
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[tokio::test]
+    async fn test_stale_schema_is_detected() {
+        let base = std::env::temp_dir().join(format!("vfnode-test-{}", uuid::Uuid::new_v4()));
+        let url = format!("sqlite:{}", base.join("vfnode.db").display());
+
+        // A database from before wagers, proofs, players and the settlement lock
+        let storage = Storage::open(&url, false).await.unwrap();
+        sqlx::query(
+            "CREATE TABLE pending_bets (
+                bet_id TEXT PRIMARY KEY, user_seed TEXT NOT NULL, timestamp INTEGER NOT NULL,
+                node_id TEXT NOT NULL, heads BOOLEAN NOT NULL, vrf_proof TEXT NOT NULL,
+                processing_time_ms INTEGER NOT NULL, processed_at TEXT NOT NULL,
+                retry_count INTEGER DEFAULT 0, status TEXT DEFAULT 'pending', tx_signature TEXT NULL,
+                settled_at TEXT NULL, failed_at TEXT NULL, error_message TEXT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP, batch_id TEXT NULL
+            )",
+        )
+        .execute(&storage.pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO pending_bets (bet_id, user_seed, timestamp, node_id, heads, vrf_proof, processing_time_ms, processed_at, status)
+             VALUES ('old_bet', 'old_seed', 0, 'node', 1, 'sig', 0, '2024-01-01T00:00:00Z', 'pending'),
+                    ('old_failed', 'old_seed', 0, 'node', 1, 'sig', 0, '2024-01-01T00:00:00Z', 'failed')",
+        )
+        .execute(&storage.pool)
+        .await
+        .unwrap();
+
+        let error = storage.check_schema().await.unwrap_err().to_string();
+        assert!(error.contains("run migrations"), "{}", error);
+        for missing in ["pending_bets.token_mint", "pending_bets.player_pubkey", "table settlement_batches", "table settlement_lock"] {
+            assert!(error.contains(missing), "{} not reported in: {}", missing, error);
+        }
+        assert!(!error.contains("pending_bets.batch_id"));
+        drop(storage);
+
+        // Migrating brings it up to date, keeping existing rows
+        let storage = Storage::new(&url).await.unwrap();
+        storage.check_schema().await.unwrap();
+        let old_rows: Vec<(String, Option<String>)> = sqlx::query_as("SELECT bet_id, proof_json FROM pending_bets")
+            .fetch_all(&storage.pool)
+            .await
+            .unwrap();
+        assert_eq!(old_rows, vec![("old_bet".to_string(), None)]);
+        // Bets failed in place are moved to the dead-letter table
+        let dead: Vec<(String, String)> = sqlx::query_as("SELECT bet_id, failed_at FROM dead_letter_bets")
+            .fetch_all(&storage.pool)
+            .await
+            .unwrap();
+        assert_eq!(dead, vec![("old_failed".to_string(), "2024-01-01T00:00:00Z".to_string())]);
+
+        drop(storage);
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_database() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+
+        // Tables created by migrations must be visible on the pooled connection
+        let summary = storage.get_settlement_summary().await.unwrap();
+        assert_eq!(summary["bets"]["total"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_summary_groups_bets_by_game() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+        // (game, heads, player_choice, status); a NULL game is a legacy coinflip
+        for (game, heads, player_choice, status) in [
+            (None, true, None, "settled"),
+            (Some("coinflip"), false, Some("heads"), "settled"),
+            (Some("coinflip"), false, Some("tails"), "pending"),
+            (Some("dice"), true, Some("heads"), "pending"),
+        ] {
+            sqlx::query(
+                "INSERT INTO pending_bets (bet_id, user_seed, timestamp, node_id, heads, vrf_proof,
+                                           processing_time_ms, processed_at, status, player_choice, game)
+                 VALUES (?, 'seed', 0, 'node', ?, 'sig', 0, '2024-01-01T00:00:00Z', ?, ?, ?)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(heads)
+            .bind(status)
+            .bind(player_choice)
+            .bind(game)
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        }
+
+        let summary = storage.get_settlement_summary().await.unwrap();
+        assert_eq!(summary["bets"]["total"], 4);
+        assert_eq!(summary["games"]["coinflip"], serde_json::json!({
+            "total": 3, "settled": 2, "pending": 1, "wins": 2, "win_rate": 2.0 / 3.0
+        }));
+        assert_eq!(summary["games"]["dice"], serde_json::json!({
+            "total": 1, "settled": 0, "pending": 1, "wins": 1, "win_rate": 1.0
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_outbox_entry_lifecycle() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+        let batch_id = Uuid::new_v4();
+        assert_eq!(storage.get_outbox_entry(&batch_id).await.unwrap(), None);
+        assert!(!storage.confirm_outbox_tx(&batch_id, "tx").await.unwrap());
+
+        let entry = storage.insert_outbox_tx(&batch_id, 3, 6_000, "root").await.unwrap();
+        assert_eq!((entry.status, entry.bet_count, entry.payout_lamports), (OutboxStatus::Pending, 3, 6_000));
+        assert!(storage.fail_outbox_tx(&batch_id, "rpc timeout").await.unwrap());
+        assert!(storage.confirm_outbox_tx(&batch_id, "tx_sig").await.unwrap());
+
+        // Recording the batch again keeps the entry, and a confirmed transaction can't fail
+        let entry = storage.insert_outbox_tx(&batch_id, 1, 0, "other_root").await.unwrap();
+        assert_eq!((entry.bet_count, entry.merkle_root.as_str()), (3, "root"));
+        assert!(!storage.fail_outbox_tx(&batch_id, "late error").await.unwrap());
+        let entry = storage.get_outbox_entry(&batch_id).await.unwrap().unwrap();
+        assert_eq!(entry.status, OutboxStatus::Confirmed);
+        assert_eq!(entry.tx_signature.as_deref(), Some("tx_sig"));
+        assert_eq!(entry.error_message, None);
+    }
+
+    #[tokio::test]
+    async fn test_submitted_outbox_txs_await_confirmation() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        assert!(!storage.submit_outbox_tx(&first, "tx_1").await.unwrap());
+        storage.insert_outbox_tx(&first, 1, 0, "root_1").await.unwrap();
+        storage.insert_outbox_tx(&second, 2, 0, "root_2").await.unwrap();
+
+        assert!(storage.submit_outbox_tx(&first, "tx_1").await.unwrap());
+        assert!(storage.submit_outbox_tx(&second, "tx_2").await.unwrap());
+        let submitted = storage.submitted_outbox_txs().await.unwrap();
+        assert_eq!(submitted.iter().map(|e| e.batch_id).collect::<Vec<_>>(), vec![first, second]);
+        assert_eq!(submitted[0].status, OutboxStatus::Submitted);
+        assert_eq!(submitted[0].tx_signature.as_deref(), Some("tx_1"));
+
+        // Confirmed and failed transactions leave the submitted set; a confirmed one stays put
+        storage.confirm_outbox_tx(&first, "tx_1").await.unwrap();
+        storage.fail_outbox_tx(&second, "expired").await.unwrap();
+        assert!(storage.submitted_outbox_txs().await.unwrap().is_empty());
+        assert!(!storage.submit_outbox_tx(&first, "tx_3").await.unwrap());
+        assert_eq!(storage.get_outbox_entry(&second).await.unwrap().unwrap().tx_signature.as_deref(), Some("tx_2"));
+    }
+
+    #[tokio::test]
+    async fn test_bet_status_by_id() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+        let (pending, settled) = (Uuid::new_v4(), Uuid::new_v4());
+        for (bet_id, status, tx_signature, settled_at) in [
+            (pending, "pending", None, None),
+            (settled, "settled", Some("tx_1"), Some("2024-01-01T00:00:10Z")),
+        ] {
+            sqlx::query(
+                "INSERT INTO pending_bets (bet_id, user_seed, timestamp, node_id, heads, vrf_proof,
+                                           processing_time_ms, processed_at, status, tx_signature, settled_at)
+                 VALUES (?, 'seed', 0, 'node', 1, 'sig', 0, '2024-01-01T00:00:00Z', ?, ?, ?)",
+            )
+            .bind(bet_id.to_string())
+            .bind(status)
+            .bind(tx_signature)
+            .bind(settled_at)
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        }
+
+        let bet = storage.get_bet_by_id(&pending).await.unwrap().unwrap();
+        assert_eq!(bet.status, SettlementStatus::Pending);
+        assert_eq!(bet.tx_signature, None);
+        assert_eq!(bet.processed_at, "2024-01-01T00:00:00Z");
+
+        let bet = storage.get_bet_by_id(&settled).await.unwrap().unwrap();
+        assert_eq!(bet.status, SettlementStatus::Settled);
+        assert_eq!(bet.tx_signature.as_deref(), Some("tx_1"));
+        assert_eq!(bet.settled_at.as_deref(), Some("2024-01-01T00:00:10Z"));
+
+        assert!(storage.get_bet_by_id(&Uuid::new_v4()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_batches_newest_first() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+        let engine = VrfEngine::new();
+        let batch_ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        for (i, batch_id) in batch_ids.iter().enumerate() {
+            insert_settled_batch(&storage, &engine, *batch_id, i + 1).await;
+            sqlx::query("UPDATE settlement_batches SET created_at = ? WHERE batch_id = ?")
+                .bind(format!("2024-01-0{}T00:00:00Z", i + 1))
+                .bind(batch_id.to_string())
+                .execute(&storage.pool)
+                .await
+                .unwrap();
+        }
+
+        let page = storage.list_batches(2, 0).await.unwrap();
+        assert_eq!(page.iter().map(|b| b.batch_id).collect::<Vec<_>>(), vec![batch_ids[2], batch_ids[1]]);
+        assert_eq!(page[0].bet_count, 3);
+        assert_eq!(page[0].tx_signature, "mock_tx");
+        assert!(page[0].success);
+
+        let rest = storage.list_batches(2, 2).await.unwrap();
+        assert_eq!(rest.iter().map(|b| b.batch_id).collect::<Vec<_>>(), vec![batch_ids[0]]);
+        assert!(storage.list_batches(2, 3).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_exported_batch_proofs_verify() {
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let engine = VrfEngine::new();
+        let batch_id = Uuid::new_v4();
+        insert_settled_batch(&storage, &engine, batch_id, 7).await;
+        insert_settled_batch(&storage, &engine, Uuid::new_v4(), 2).await;
+
+        assert!(storage.batch_exists(&batch_id).await.unwrap());
+        assert!(!storage.batch_exists(&Uuid::new_v4()).await.unwrap());
+
+        let pages: Vec<_> = storage.stream_batch_proofs(batch_id, 3).collect().await;
+        assert_eq!(pages.len(), 3);
+
+        let bundles: Vec<ProofBundle> = pages.into_iter().flat_map(|p| p.unwrap()).collect();
+        assert_eq!(bundles.len(), 7);
+        for bundle in &bundles {
+            assert_eq!(bundle.node_pubkey, engine.node_pubkey());
+            assert!(VrfEngine::verify_with_pubkey(&bundle.node_pubkey, &bundle.request, &bundle.response.proof).unwrap());
+        }
+
+        // Reconstructed proofs match what the engine originally returned
+        let original = engine.process_coinflip(&bundles[0].request).unwrap();
+        assert_eq!(bundles[0].response.proof.seed_commitment, original.proof.seed_commitment);
+        assert_eq!(bundles[0].response.proof.vrf_output, original.proof.vrf_output);
+    }
+
+    #[tokio::test]
+    async fn test_player_ledger_running_balance() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+        let minute = |m: i64| {
+            (time::OffsetDateTime::UNIX_EPOCH + time::Duration::minutes(m))
+                .format(&time::format_description::well_known::Rfc3339)
+                .unwrap()
+        };
+
+        // (player, token, wager, payout, status, settled minute)
+        type Bet = (&'static str, &'static str, i64, i64, &'static str, i64);
+        let bets: &[Bet] = &[
+            ("alice", "SOL", 100, 200, "settled", 1),
+            ("alice", "SOL", 300, 0, "settled", 2),
+            ("alice", "USDC", 50, 100, "settled", 3),
+            ("alice", "SOL", 500, 1000, "settled", 4),
+            ("alice", "SOL", 700, 0, "pending", 5), // not settled
+            ("bob", "SOL", 900, 0, "settled", 1),
+        ];
+        for &(player, token, wager, payout, status, settled) in bets {
+            sqlx::query(
+                "INSERT INTO pending_bets (bet_id, user_seed, timestamp, node_id, heads, vrf_proof,
+                                           processing_time_ms, processed_at, status, settled_at,
+                                           token_mint, wager_lamports, payout_lamports, player_pubkey)
+                 VALUES (?, 'seed', 0, 'node', 1, 'sig', 0, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(minute(settled))
+            .bind(status)
+            .bind(minute(settled))
+            .bind(token)
+            .bind(wager)
+            .bind(payout)
+            .bind(player)
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        }
+
+        let summarize = |ledger: &PlayerLedger| {
+            ledger
+                .entries
+                .iter()
+                .map(|e| (e.token_mint.clone(), e.kind, e.amount_lamports, e.balance_lamports))
+                .collect::<Vec<_>>()
+        };
+        let sol = |kind, amount, balance| ("SOL".to_string(), kind, amount, balance);
+
+        let first = storage.get_player_ledger("alice", 0, 2).await.unwrap();
+        assert_eq!(summarize(&first), vec![
+            sol(LedgerEntryKind::Debit, 100, -100),
+            sol(LedgerEntryKind::Credit, 200, 100),
+            sol(LedgerEntryKind::Debit, 300, -200),
+        ]);
+        assert_eq!(first.next_offset, Some(2));
+
+        // Balances carry over into later pages and are tracked per token
+        let second = storage.get_player_ledger("alice", 2, 2).await.unwrap();
+        assert_eq!(summarize(&second), vec![
+            ("USDC".to_string(), LedgerEntryKind::Debit, 50, -50),
+            ("USDC".to_string(), LedgerEntryKind::Credit, 100, 50),
+            sol(LedgerEntryKind::Debit, 500, -700),
+            sol(LedgerEntryKind::Credit, 1000, 300),
+        ]);
+        assert_eq!(second.next_offset, None);
+
+        assert!(storage.get_player_ledger("carol", 0, 10).await.unwrap().entries.is_empty());
+
+        // Wagers near u64::MAX don't overflow the running sum; the balance saturates instead
+        for (wager, payout, settled) in [(u64::MAX, 0, 1), (u64::MAX, 0, 2), (1, u64::MAX, 3)] {
+            sqlx::query(
+                "INSERT INTO pending_bets (bet_id, user_seed, timestamp, node_id, heads, vrf_proof,
+                                           processing_time_ms, processed_at, status, settled_at,
+                                           token_mint, wager_lamports, payout_lamports, player_pubkey)
+                 VALUES (?, 'seed', 0, 'node', 1, 'sig', 0, ?, 'settled', ?, 'SOL', ?, ?, 'dave')",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(minute(settled))
+            .bind(minute(settled))
+            .bind(wager as i64)
+            .bind(payout as i64)
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        }
+        let whale = storage.get_player_ledger("dave", 0, 10).await.unwrap();
+        assert_eq!(summarize(&whale), vec![
+            sol(LedgerEntryKind::Debit, u64::MAX, i64::MIN),
+            sol(LedgerEntryKind::Debit, u64::MAX, i64::MIN),
+            sol(LedgerEntryKind::Debit, 1, i64::MIN),
+            sol(LedgerEntryKind::Credit, u64::MAX, i64::MIN),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_realized_edge_per_token() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+        let day = |d: u8| time::Date::from_calendar_date(2025, time::Month::March, d).unwrap().midnight().assume_utc();
+
+        // (token, wager, payout, status, settled_at)
+        type Bet = (Option<&'static str>, Option<i64>, Option<i64>, &'static str, u8);
+        let bets: &[Bet] = &[
+            (Some("SOL"), Some(100), Some(200), "settled", 2),
+            (Some("SOL"), Some(200), Some(0), "settled", 2),
+            (Some("SOL"), Some(300), Some(0), "settled", 3),
+            (Some("SOL"), Some(400), Some(0), "settled", 4),
+            (Some("USDC"), Some(1000), Some(2000), "settled", 3),
+            (Some("USDC"), Some(1000), Some(0), "settled", 3),
+            (Some("SOL"), Some(5000), Some(0), "pending", 3), // not settled
+            (Some("SOL"), Some(5000), Some(10000), "settled", 1), // before the window
+            (None, None, None, "settled", 3),                   // legacy row without a wager
+        ];
+        for &(token, wager, payout, status, settled_day) in bets {
+            sqlx::query(
+                "INSERT INTO pending_bets (bet_id, user_seed, timestamp, node_id, heads, vrf_proof,
+                                           processing_time_ms, processed_at, status, settled_at,
+                                           token_mint, wager_lamports, payout_lamports)
+                 VALUES (?, 'seed', 0, 'node', 1, 'sig', 0, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(day(settled_day).format(&time::format_description::well_known::Rfc3339).unwrap())
+            .bind(status)
+            .bind(day(settled_day).format(&time::format_description::well_known::Rfc3339).unwrap())
+            .bind(token)
+            .bind(wager)
+            .bind(payout)
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        }
+
+        let edges = storage.get_realized_edge(Some(day(2)), Some(day(5))).await.unwrap();
+        assert_eq!(edges, vec![
+            RealizedEdge {
+                token_mint: "SOL".to_string(),
+                bet_count: 4,
+                total_wagered_lamports: 1000,
+                total_paid_lamports: 200,
+                realized_edge: 0.8,
+            },
+            RealizedEdge {
+                token_mint: "USDC".to_string(),
+                bet_count: 2,
+                total_wagered_lamports: 2000,
+                total_paid_lamports: 2000,
+                realized_edge: 0.0,
+            },
+        ]);
+
+        // Upper bound is exclusive
+        let edges = storage.get_realized_edge(Some(day(2)), Some(day(3))).await.unwrap();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].total_wagered_lamports, 300);
+
+        // Unbounded window picks up the earlier bet too
+        let edges = storage.get_realized_edge(None, None).await.unwrap();
+        assert_eq!(edges[0].total_wagered_lamports, 6000);
+        assert!((edges[0].realized_edge - (-0.7)).abs() < 1e-9);
+
+        // Wagers near u64::MAX neither overflow the sum nor wrap negative; totals saturate
+        for (wager, payout) in [(u64::MAX, 0), (u64::MAX, u64::MAX), (1 << 63, 0)] {
+            sqlx::query(
+                "INSERT INTO pending_bets (bet_id, user_seed, timestamp, node_id, heads, vrf_proof,
+                                           processing_time_ms, processed_at, status, settled_at,
+                                           token_mint, wager_lamports, payout_lamports)
+                 VALUES (?, 'seed', 0, 'node', 1, 'sig', 0, ?, 'settled', ?, 'WHALE', ?, ?)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(day(3).format(&time::format_description::well_known::Rfc3339).unwrap())
+            .bind(day(3).format(&time::format_description::well_known::Rfc3339).unwrap())
+            .bind(wager as i64)
+            .bind(payout as i64)
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        }
+        let edges = storage.get_realized_edge(None, None).await.unwrap();
+        let whale = edges.iter().find(|edge| edge.token_mint == "WHALE").unwrap();
+        assert_eq!(whale.bet_count, 3);
+        assert_eq!(whale.total_wagered_lamports, u64::MAX);
+        assert_eq!(whale.total_paid_lamports, u64::MAX);
+        let wagered = 2.0 * u64::MAX as f64 + (1u64 << 63) as f64;
+        assert!((whale.realized_edge - (wagered - u64::MAX as f64) / wagered).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_lamport_sums_past_i64_max_are_exact() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+        let settled = |m: i64| {
+            (time::OffsetDateTime::UNIX_EPOCH + time::Duration::minutes(m))
+                .format(&time::format_description::well_known::Rfc3339)
+                .unwrap()
+        };
+        // (token, wager, payout), in settlement order
+        let bets: [(&str, u64, u64); 5] = [
+            ("EDGE", 1 << 62, 5),
+            ("EDGE", 1 << 62, (1 << 63) + 7),
+            ("EDGE", (1 << 62) + 3, 0),
+            ("LEDGER", 0, (1 << 63) + 10),
+            ("LEDGER", 1 << 62, 0),
+        ];
+        for (i, (token, wager, payout)) in bets.into_iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO pending_bets (bet_id, user_seed, timestamp, node_id, heads, vrf_proof,
+                                           processing_time_ms, processed_at, status, settled_at,
+                                           token_mint, wager_lamports, payout_lamports, player_pubkey)
+                 VALUES (?, 'seed', 0, 'node', 1, 'sig', 0, ?, 'settled', ?, ?, ?, ?, ?)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(settled(i as i64))
+            .bind(settled(i as i64))
+            .bind(token)
+            .bind(wager as i64)
+            .bind(payout as i64)
+            .bind(token.to_lowercase())
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        }
+
+        // Both totals pass i64::MAX, and one payout is stored as a negative i64
+        let edges = storage.get_realized_edge(None, None).await.unwrap();
+        let edge = edges.iter().find(|edge| edge.token_mint == "EDGE").unwrap();
+        assert_eq!(edge.total_wagered_lamports, 3 * (1 << 62) + 3);
+        assert_eq!(edge.total_paid_lamports, (1 << 63) + 12);
+
+        // A running balance past i64::MAX saturates, then comes back into range exactly
+        let ledger = storage.get_player_ledger("ledger", 0, 10).await.unwrap();
+        let balances: Vec<i64> = ledger.entries.iter().map(|entry| entry.balance_lamports).collect();
+        assert_eq!(balances, vec![0, i64::MAX, (1 << 62) + 10]);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_proof_rows_are_flagged() {
+        let storage = Arc::new(Storage::new("sqlite::memory:").await.unwrap());
+        let engine = VrfEngine::new();
+        let batch_id = Uuid::new_v4();
+        insert_settled_batch(&storage, &engine, batch_id, 0).await;
+
+        // Rows from before full proofs were stored, signed under the key-only seed commitment
+        for i in 0..3 {
+            let req = CoinflipRequest { user_seed: format!("legacy_{}", i), timestamp: 1234567890, ..Default::default() };
+            let signature = engine.legacy_signature(&req);
+            let proof = VrfEngine::proof_from_signature(&engine.node_pubkey(), &signature).unwrap();
+            sqlx::query(
+                "INSERT INTO pending_bets (bet_id, user_seed, timestamp, node_id, heads, vrf_proof,
+                                           processing_time_ms, processed_at, status, batch_id)
+                 VALUES (?, ?, ?, ?, ?, ?, 0, '2099-01-01T00:00:00Z', 'settled', ?)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(&req.user_seed)
+            .bind(req.timestamp as i64)
+            .bind(engine.node_pubkey())
+            .bind(VrfEngine::outcome_from_proof(&proof, req.player_choice).unwrap())
+            .bind(&signature)
+            .bind(batch_id.to_string())
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        }
+
+        // Older rows whose key or signature no longer decodes
+        for (node_id, signature) in [("node-1", "c2lnbmF0dXJl"), (engine.node_pubkey().as_str(), "%%not-base64%%")] {
+            sqlx::query(
+                "INSERT INTO pending_bets (bet_id, user_seed, timestamp, node_id, heads, vrf_proof,
+                                           processing_time_ms, processed_at, status, batch_id)
+                 VALUES (?, 'old_seed', 0, ?, 1, ?, 0, '2099-01-01T00:00:00Z', 'settled', ?)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(node_id)
+            .bind(signature)
+            .bind(batch_id.to_string())
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        }
+
+        // Export copes before the backfill has run
+        let bundles = storage.get_batch_proofs(&batch_id, 100, 0).await.unwrap();
+        assert_eq!(bundles.len(), 5);
+
+        let report = storage.backfill_legacy_proofs().await.unwrap();
+        assert_eq!(report, ProofBackfillReport { legacy: 3, unreconstructable: 2 });
+        assert_eq!(storage.backfill_legacy_proofs().await.unwrap(), ProofBackfillReport::default());
+
+        let bundles: Vec<ProofBundle> = storage
+            .stream_batch_proofs(batch_id, 2)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .flat_map(|page| page.unwrap())
+            .collect();
+        assert_eq!(bundles.len(), 5);
+
+        let (legacy, broken): (Vec<_>, Vec<_>) =
+            bundles.iter().partition(|b| b.proof_status == ProofStatus::Legacy);
+        assert_eq!(legacy.len(), 3);
+        for bundle in legacy {
+            assert!(VrfEngine::verify_with_pubkey(&bundle.node_pubkey, &bundle.request, &bundle.response.proof).unwrap());
+        }
+        assert_eq!(broken.len(), 2);
+        assert!(broken.iter().all(|b| b.proof_status == ProofStatus::Unreconstructable));
+        assert!(broken.iter().any(|b| b.response.proof.signature == "%%not-base64%%"));
+
+        let summary = storage.get_settlement_summary().await.unwrap();
+        assert_eq!(summary["proofs"]["legacy"], 3);
+        assert_eq!(summary["proofs"]["unreconstructable"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_replay_flags_tampered_outcome() {
+        let storage = Storage::new("sqlite::memory:").await.unwrap();
+        let engine = VrfEngine::new();
+        let batch_id = Uuid::new_v4();
+        insert_settled_batch(&storage, &engine, batch_id, 5).await;
+
+        let replay = storage.replay_batch(&batch_id).await.unwrap();
+        assert_eq!(replay.bet_count, 5);
+        assert!(replay.mismatches.is_empty(), "{:?}", replay.mismatches);
+
+        // Flip one stored outcome and corrupt another bet's input
+        let bets = storage.get_batch_proofs(&batch_id, 5, 0).await.unwrap();
+        sqlx::query("UPDATE pending_bets SET heads = NOT heads WHERE bet_id = ?")
+            .bind(bets[1].bet_id.to_string())
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE pending_bets SET user_seed = 'edited' WHERE bet_id = ?")
+            .bind(bets[3].bet_id.to_string())
+            .execute(&storage.pool)
+            .await
+            .unwrap();
+
+        let replay = storage.replay_batch(&batch_id).await.unwrap();
+        assert_eq!(replay.mismatches.len(), 2);
+
+        let flipped = &replay.mismatches[0];
+        assert_eq!(flipped.bet_id, bets[1].bet_id);
+        assert_eq!(flipped.derived_heads, Some(bets[1].response.heads));
+        assert_ne!(flipped.stored_heads, bets[1].response.heads);
+        assert!(flipped.problem.contains("outcome"));
+
+        assert_eq!(replay.mismatches[1].bet_id, bets[3].bet_id);
+        assert!(replay.mismatches[1].problem.contains("signature"));
+    }
+}
@@ -0,0 +1,224 @@
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Token bucket used to shed load once a node exceeds its tested capacity.
+///
+/// Refills continuously at `rate_per_sec` up to `capacity` tokens; each admitted
+/// request takes one token.
+pub struct TokenBucket {
+    capacity: f64,
+    rate_per_sec: f64,
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate_per_sec: u32, capacity: u32) -> Self {
+        Self {
+            capacity: capacity.max(1) as f64,
+            rate_per_sec: rate_per_sec as f64,
+            state: Mutex::new(BucketState {
+                tokens: capacity.max(1) as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Bucket that allows `rate_per_sec` sustained with a one-second burst.
+    pub fn per_second(rate_per_sec: u32) -> Self {
+        Self::new(rate_per_sec, rate_per_sec)
+    }
+
+    #[inline]
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    pub fn try_acquire_at(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.refill(now, self.rate_per_sec, self.capacity);
+        state.try_take()
+    }
+}
+
+impl BucketState {
+    fn refill(&mut self, now: Instant, rate_per_sec: f64, capacity: f64) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate_per_sec).min(capacity);
+        self.last_refill = self.last_refill.max(now);
+    }
+
+    fn try_take(&mut self) -> bool {
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Clients tracked before idle (full) buckets are swept out
+const CLIENT_SWEEP_THRESHOLD: usize = 10_000;
+
+/// One token bucket per client IP, so a single client can't take the whole node's
+/// throughput. A client with no bucket yet starts with a full burst.
+pub struct ClientRateLimiter {
+    capacity: f64,
+    rate_per_sec: f64,
+    clients: Mutex<ClientBuckets>,
+}
+
+struct ClientBuckets {
+    buckets: HashMap<IpAddr, BucketState>,
+    sweep_at: usize,
+}
+
+impl ClientRateLimiter {
+    pub fn new(rate_per_sec: u32, burst: u32) -> Self {
+        Self {
+            capacity: burst.max(1) as f64,
+            rate_per_sec: rate_per_sec.max(1) as f64,
+            clients: Mutex::new(ClientBuckets { buckets: HashMap::new(), sweep_at: CLIENT_SWEEP_THRESHOLD }),
+        }
+    }
+
+    #[inline]
+    pub fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        self.check_at(client, Instant::now())
+    }
+
+    /// Take a token for `client`, or return how long until one is available
+    pub fn check_at(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Buckets that have refilled completely carry no state worth keeping
+        if clients.buckets.len() >= clients.sweep_at && !clients.buckets.contains_key(&client) {
+            let (rate, capacity) = (self.rate_per_sec, self.capacity);
+            clients.buckets.retain(|_, bucket| {
+                bucket.refill(now, rate, capacity);
+                bucket.tokens < capacity
+            });
+            clients.sweep_at = (clients.buckets.len() * 2).max(CLIENT_SWEEP_THRESHOLD);
+        }
+
+        let bucket = clients
+            .buckets
+            .entry(client)
+            .or_insert(BucketState { tokens: self.capacity, last_refill: now });
+        bucket.refill(now, self.rate_per_sec, self.capacity);
+        if bucket.try_take() {
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate_per_sec))
+        }
+    }
+
+    pub fn tracked_clients(&self) -> usize {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner()).buckets.len()
+    }
+}
+
+/// Middleware answering `429` with `Retry-After` once the connecting IP runs out of
+/// tokens. Requests without a peer address (Unix socket) are not limited.
+pub async fn limit_clients(
+    State(limiter): State<Arc<ClientRateLimiter>>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(ConnectInfo(peer)) = peer else {
+        return next.run(request).await;
+    };
+    match limiter.check(peer.ip()) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                "Too many requests from this client",
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sheds_above_ceiling() {
+        let bucket = TokenBucket::per_second(10);
+        let start = Instant::now();
+
+        // 20 req/s sustained for 5 seconds against a 10 req/s ceiling
+        let mut admitted = 0;
+        for i in 0..100 {
+            let now = start + Duration::from_millis(i * 50);
+            if bucket.try_acquire_at(now) {
+                admitted += 1;
+            }
+        }
+
+        // initial burst of 10 plus ~10/s refill
+        assert!(admitted < 100);
+        assert!((55..=62).contains(&admitted), "admitted {}", admitted);
+    }
+
+    #[test]
+    fn test_under_ceiling_passes() {
+        let bucket = TokenBucket::per_second(10);
+        let start = Instant::now();
+
+        // 5 req/s sustained for 10 seconds
+        for i in 0..50 {
+            let now = start + Duration::from_millis(i * 200);
+            assert!(bucket.try_acquire_at(now), "request {} shed", i);
+        }
+    }
+
+    #[test]
+    fn test_clients_are_limited_independently() {
+        let limiter = ClientRateLimiter::new(2, 4);
+        let start = Instant::now();
+        let (a, b): (IpAddr, IpAddr) = ([10, 0, 0, 1].into(), [10, 0, 0, 2].into());
+
+        // A full burst, then the next request waits for half a second's refill
+        for _ in 0..4 {
+            assert!(limiter.check_at(a, start).is_ok());
+        }
+        assert_eq!(limiter.check_at(a, start), Err(Duration::from_millis(500)));
+
+        // Another client still has its own burst
+        assert!(limiter.check_at(b, start).is_ok());
+
+        assert!(limiter.check_at(a, start + Duration::from_millis(500)).is_ok());
+        assert!(limiter.check_at(a, start + Duration::from_millis(500)).is_err());
+    }
+
+    #[test]
+    fn test_idle_clients_are_swept() {
+        let limiter = ClientRateLimiter::new(1, 1);
+        let start = Instant::now();
+        for i in 0..CLIENT_SWEEP_THRESHOLD as u32 {
+            assert!(limiter.check_at(IpAddr::from(i.to_be_bytes()), start).is_ok());
+        }
+        assert_eq!(limiter.tracked_clients(), CLIENT_SWEEP_THRESHOLD);
+
+        // A second later every bucket has refilled, so the next new client sweeps them
+        assert!(limiter.check_at([192, 168, 0, 1].into(), start + Duration::from_secs(1)).is_ok());
+        assert_eq!(limiter.tracked_clients(), 1);
+    }
+}
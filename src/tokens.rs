@@ -0,0 +1,164 @@
+use crate::types::{CoinflipRequest, VfError, NATIVE_TOKEN_MINT};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Betting limits for one accepted token.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenConfig {
+    pub mint: String,
+    pub decimals: u8,
+    pub min_wager_lamports: u64,
+    pub max_wager_lamports: u64,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Tokens the node accepts, in configured order.
+///
+/// Configured with `TOKENS` as a JSON array of [`TokenConfig`]; defaults to the native
+/// token with no wager limits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenRegistry {
+    tokens: Vec<TokenConfig>,
+}
+
+impl Default for TokenRegistry {
+    fn default() -> Self {
+        Self {
+            tokens: vec![TokenConfig {
+                mint: NATIVE_TOKEN_MINT.to_string(),
+                decimals: 9,
+                min_wager_lamports: 1,
+                max_wager_lamports: u64::MAX,
+                enabled: true,
+            }],
+        }
+    }
+}
+
+impl TokenRegistry {
+    /// Parse and validate a registry, returning every problem found
+    pub fn from_json(json: &str) -> Result<Self, Vec<String>> {
+        let tokens: Vec<TokenConfig> = serde_json::from_str(json)
+            .map_err(|e| vec![format!("TOKENS must be a JSON array of token configs: {}", e)])?;
+        let registry = Self { tokens };
+        let problems = registry.validate();
+        if problems.is_empty() { Ok(registry) } else { Err(problems) }
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut seen = HashSet::new();
+
+        if self.tokens.is_empty() {
+            problems.push("TOKENS must list at least one token".to_string());
+        }
+        for token in &self.tokens {
+            if token.mint.trim().is_empty() {
+                problems.push("TOKENS entry has an empty mint".to_string());
+            } else if !seen.insert(token.mint.as_str()) {
+                problems.push(format!("TOKENS lists mint '{}' more than once", token.mint));
+            }
+            if token.min_wager_lamports == 0 {
+                problems.push(format!("TOKENS '{}': min_wager_lamports must be at least 1", token.mint));
+            }
+            if token.min_wager_lamports > token.max_wager_lamports {
+                problems.push(format!(
+                    "TOKENS '{}': min_wager_lamports ({}) exceeds max_wager_lamports ({})",
+                    token.mint, token.min_wager_lamports, token.max_wager_lamports
+                ));
+            }
+        }
+        problems
+    }
+
+    pub fn tokens(&self) -> &[TokenConfig] {
+        &self.tokens
+    }
+
+    pub fn get(&self, mint: &str) -> Option<&TokenConfig> {
+        self.tokens.iter().find(|t| t.mint == mint)
+    }
+
+    /// Smallest and largest wager any enabled token accepts: the engine-wide bounds every
+    /// per-token limit falls within
+    pub fn wager_bounds(&self) -> (u64, u64) {
+        self.tokens
+            .iter()
+            .filter(|t| t.enabled)
+            .fold((u64::MAX, 0), |(min, max), t| (min.min(t.min_wager_lamports), max.max(t.max_wager_lamports)))
+    }
+
+    /// Mints of the enabled tokens
+    pub fn enabled_mints(&self) -> HashSet<String> {
+        self.tokens.iter().filter(|t| t.enabled).map(|t| t.mint.clone()).collect()
+    }
+
+    /// Check a request's wager against its token's limits. Requests without a wager
+    /// aren't settled, so they always pass.
+    pub fn check_wager(&self, req: &CoinflipRequest) -> Result<(), VfError> {
+        let Some(wager) = req.wager_lamports else {
+            return Ok(());
+        };
+        let mint = req.token_mint.as_deref().unwrap_or(NATIVE_TOKEN_MINT);
+
+        match self.get(mint) {
+            None => Err(VfError::InvalidInput(format!("Unsupported token mint '{}'", mint))),
+            Some(token) if !token.enabled => Err(VfError::InvalidInput(format!("Token '{}' is disabled", mint))),
+            Some(token) if wager < token.min_wager_lamports || wager > token.max_wager_lamports => {
+                Err(VfError::InvalidInput(format!(
+                    "Wager {} outside [{}, {}] for token '{}'",
+                    wager, token.min_wager_lamports, token.max_wager_lamports, mint
+                )))
+            }
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_registry_lists_every_problem() {
+        let problems = TokenRegistry::from_json(
+            r#"[
+                {"mint": "SOL", "decimals": 9, "min_wager_lamports": 10, "max_wager_lamports": 5},
+                {"mint": "SOL", "decimals": 9, "min_wager_lamports": 0, "max_wager_lamports": 5}
+            ]"#,
+        )
+        .unwrap_err();
+
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+    }
+
+    #[test]
+    fn test_check_wager() {
+        let registry = TokenRegistry::from_json(
+            r#"[
+                {"mint": "SOL", "decimals": 9, "min_wager_lamports": 1000, "max_wager_lamports": 5000},
+                {"mint": "BONK", "decimals": 5, "min_wager_lamports": 1, "max_wager_lamports": 10, "enabled": false}
+            ]"#,
+        )
+        .unwrap();
+
+        let bet = |wager: u64, mint: Option<&str>| CoinflipRequest {
+            wager_lamports: Some(wager),
+            token_mint: mint.map(str::to_string),
+            ..Default::default()
+        };
+
+        assert!(registry.check_wager(&CoinflipRequest::default()).is_ok());
+        assert!(registry.check_wager(&bet(1000, None)).is_ok());
+        assert!(registry.check_wager(&bet(5000, Some("SOL"))).is_ok());
+        assert!(registry.check_wager(&bet(999, Some("SOL"))).is_err());
+        assert!(registry.check_wager(&bet(5001, Some("SOL"))).is_err());
+        assert!(registry.check_wager(&bet(5, Some("BONK"))).is_err());
+        assert!(registry.check_wager(&bet(5, Some("USDC"))).is_err());
+    }
+}
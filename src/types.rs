@@ -1,13 +1,180 @@
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct CoinflipRequest {
     #[serde(alias = "seed")]
     pub user_seed: String,
     #[serde(default = "default_timestamp")]
     pub timestamp: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_pubkey: Option<String>, // Identifies the player's chain in seed-chaining mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wager_lamports: Option<u64>, // Stake recorded for settlement and PnL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_mint: Option<String>, // Defaults to NATIVE_TOKEN_MINT
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub free_play: bool, // Outcome and proof only: never settled, never paid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_seed: Option<String>, // Player entropy committed to the transcript alongside user_seed
+    #[serde(default)]
+    pub player_choice: CoinSide, // Side the player backs; older clients implicitly back heads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bet_id: Option<Uuid>, // Client-chosen settlement id; the node assigns one when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet_sig: Option<String>, // Base58 ed25519 signature by player_pubkey over wallet_message()
+    #[serde(default, skip_serializing_if = "SeedEncoding::is_raw")]
+    pub seed_encoding: SeedEncoding, // How user_seed decodes to the bytes committed to the transcript
+}
+
+impl CoinflipRequest {
+    /// The bytes a player's wallet signs to authorize this bet: canonical JSON of every
+    /// request field except `wallet_sig`, keys sorted, no whitespace, absent optional
+    /// fields omitted. E.g. `{"player_choice":"heads","player_pubkey":"…","timestamp":…,"user_seed":"…"}`
+    pub fn wallet_message(&self) -> Vec<u8> {
+        let unsigned = CoinflipRequest { wallet_sig: None, ..self.clone() };
+        // serde_json::Value keeps object keys in a BTreeMap, so they serialize sorted
+        let value = serde_json::to_value(&unsigned).expect("request serializes to JSON");
+        serde_json::to_vec(&value).expect("JSON value serializes")
+    }
+
+    /// `user_seed` as the bytes committed to the transcript, decoded per `seed_encoding`
+    pub fn seed_bytes(&self) -> Result<Cow<'_, [u8]>, VfError> {
+        self.seed_encoding.decode(&self.user_seed)
+    }
+}
+
+/// How `user_seed` turns into transcript bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[serde(rename_all = "snake_case")]
+pub enum SeedEncoding {
+    /// The UTF-8 bytes of the string, as before encodings existed
+    #[default]
+    Raw,
+    /// Hex digits, either case
+    Hex,
+    /// Standard base64 with padding
+    Base64,
+}
+
+impl SeedEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SeedEncoding::Raw => "raw",
+            SeedEncoding::Hex => "hex",
+            SeedEncoding::Base64 => "base64",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "raw" => Some(SeedEncoding::Raw),
+            "hex" => Some(SeedEncoding::Hex),
+            "base64" => Some(SeedEncoding::Base64),
+            _ => None,
+        }
+    }
+
+    pub fn is_raw(&self) -> bool {
+        *self == SeedEncoding::Raw
+    }
+
+    pub fn decode<'a>(&self, seed: &'a str) -> Result<Cow<'a, [u8]>, VfError> {
+        use base64::{engine::general_purpose::STANDARD as Base64Engine, Engine as _};
+        match self {
+            SeedEncoding::Raw => Ok(Cow::Borrowed(seed.as_bytes())),
+            SeedEncoding::Hex => hex::decode(seed)
+                .map(Cow::Owned)
+                .map_err(|e| VfError::InvalidInput(format!("user_seed is not valid hex: {}", e))),
+            SeedEncoding::Base64 => Base64Engine
+                .decode(seed)
+                .map(Cow::Owned)
+                .map_err(|e| VfError::InvalidInput(format!("user_seed is not valid base64: {}", e))),
+        }
+    }
+}
+
+/// A coin side, as picked by the player
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[serde(rename_all = "snake_case")]
+pub enum CoinSide {
+    #[default]
+    Heads,
+    Tails,
+}
+
+impl CoinSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoinSide::Heads => "heads",
+            CoinSide::Tails => "tails",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "heads" => Some(CoinSide::Heads),
+            "tails" => Some(CoinSide::Tails),
+            _ => None,
+        }
+    }
+
+    /// Whether a player backing this side wins the flip
+    pub fn wins(&self, heads: bool) -> bool {
+        heads == (*self == CoinSide::Heads)
+    }
 }
 
+/// Which bits of the 8-byte random value decide a fair flip. The value is the first 8
+/// bytes of `SHA-256(signature)` read as a little-endian `u64`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+#[serde(rename_all = "snake_case")]
+pub enum OutcomeBit {
+    /// Heads when the least significant bit (bit 0 of the first byte) is 0, i.e. the value is even
+    #[default]
+    Lsb,
+    /// Heads when the most significant bit (bit 7 of the eighth byte) is 0
+    Msb,
+    /// Heads when all 64 bits together have an even number of ones
+    Parity,
+}
+
+impl OutcomeBit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutcomeBit::Lsb => "lsb",
+            OutcomeBit::Msb => "msb",
+            OutcomeBit::Parity => "parity",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "lsb" => Some(OutcomeBit::Lsb),
+            "msb" => Some(OutcomeBit::Msb),
+            "parity" => Some(OutcomeBit::Parity),
+            _ => None,
+        }
+    }
+
+    /// Coin side this strategy reads from a random value
+    pub fn heads(&self, random_value: u64) -> bool {
+        match self {
+            OutcomeBit::Lsb => random_value & 1 == 0,
+            OutcomeBit::Msb => random_value >> 63 == 0,
+            OutcomeBit::Parity => random_value.count_ones().is_multiple_of(2),
+        }
+    }
+}
+
+/// Token assumed for wagers that don't name a mint
+pub const NATIVE_TOKEN_MINT: &str = "SOL";
+
 fn default_timestamp() -> u64 {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -16,19 +183,581 @@ fn default_timestamp() -> u64 {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct CoinflipResponse {
     pub node_id: String,
     pub heads: bool,
+    #[serde(default)]
+    pub win: bool, // Whether the flip landed on the player's choice
     pub proof: VrfProof,
     pub timestamp: u64, // Unix timestamp
     pub processing_time_ms: u64, // Performance metric
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub free_play: bool, // No settlement or payout happens for this flip
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bet_id: Option<Uuid>, // Id the bet settles under; None for free play unless the client chose one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payout_lamports: Option<u64>, // Owed to the player; None without a wager or in free play
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random_value: Option<u64>, // Integer behind the outcome, read by the proof's outcome_bit on a fair flip. None in older exports
+}
+
+/// Faces on the die rolled by [`DiceRequest`]
+pub const DICE_FACES: u8 = 6;
+
+/// A die roll; the player wins when the roll lands on `target`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiceRequest {
+    #[serde(alias = "seed")]
+    pub user_seed: String,
+    #[serde(default = "default_timestamp")]
+    pub timestamp: u64,
+    pub target: u8, // Face the player backs, 1..=DICE_FACES
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiceResponse {
+    pub node_id: String,
+    pub roll: u8, // 1..=DICE_FACES
+    pub win: bool, // Whether the roll landed on the target
+    pub proof: VrfProof,
+    pub timestamp: u64, // Unix timestamp
+    pub processing_time_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VrfProof {
-    pub seed_commitment: String, // Base64 seed commitment
+    pub seed_commitment: String, // Base64 commitment to the request under the node key
     pub vrf_output: String,      // Base64 VRF output
     pub signature: String,       // Base64 signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_output: Option<String>, // Hex output hash of the player's previous round (chain mode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beacon_round: Option<u64>, // External beacon round mixed into the transcript
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beacon_randomness: Option<String>, // Hex beacon value for that round
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_hash: Option<String>, // Hex SHA-256 of the signature; vrf_output is its first 8 bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub win_probability_bps: Option<u16>, // Player's win chance under a house edge; absent for a fair flip
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome_bit: Option<OutcomeBit>, // Strategy a fair flip was decided with; absent means lsb
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge_len: Option<u8>, // Bytes of challenge the signature covers; absent means 64
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_seed: Option<String>, // Hex node seed revealed by a commit-reveal flip
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bet_id: Option<Uuid>, // Client-chosen bet id committed to the transcript
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub challenge: String, // Hex transcript challenge the signature covers; empty on proofs that predate it
+}
+
+/// Borsh form of [`VrfProof`]: the base64 and hex fields as their raw bytes, so the
+/// encoding stays compact enough for instruction data. Converting back re-encodes them,
+/// so a proof round-trips to the same strings.
+#[cfg(feature = "borsh")]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
+struct VrfProofBytes {
+    seed_commitment: [u8; 32],
+    vrf_output: [u8; 8],
+    signature: [u8; 64],
+    prev_output: Option<[u8; 32]>,
+    beacon_round: Option<u64>,
+    beacon_randomness: Option<Vec<u8>>,
+    output_hash: Option<[u8; 32]>,
+    win_probability_bps: Option<u16>,
+    outcome_bit: Option<OutcomeBit>,
+    challenge: Option<Vec<u8>>,
+    challenge_len: Option<u8>,
+    node_seed: Option<[u8; 32]>,
+    bet_id: Option<[u8; 16]>,
+}
+
+#[cfg(feature = "borsh")]
+mod proof_bytes {
+    use base64::{engine::general_purpose::STANDARD as Base64Engine, Engine as _};
+    use std::io;
+
+    fn invalid(field: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, format!("Invalid {} in proof", field))
+    }
+
+    pub fn base64<const N: usize>(field: &str, value: &str) -> io::Result<[u8; N]> {
+        Base64Engine.decode(value).ok().and_then(|bytes| bytes.try_into().ok()).ok_or_else(|| invalid(field))
+    }
+
+    pub fn hex_vec(field: &str, value: &str) -> io::Result<Vec<u8>> {
+        hex::decode(value).map_err(|_| invalid(field))
+    }
+
+    pub fn hex<const N: usize>(field: &str, value: &str) -> io::Result<[u8; N]> {
+        hex_vec(field, value)?.try_into().map_err(|_| invalid(field))
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl borsh::BorshSerialize for VrfProof {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let bytes = VrfProofBytes {
+            seed_commitment: proof_bytes::base64("seed_commitment", &self.seed_commitment)?,
+            vrf_output: proof_bytes::base64("vrf_output", &self.vrf_output)?,
+            signature: proof_bytes::base64("signature", &self.signature)?,
+            prev_output: self.prev_output.as_deref().map(|p| proof_bytes::hex("prev_output", p)).transpose()?,
+            beacon_round: self.beacon_round,
+            beacon_randomness: self.beacon_randomness.as_deref().map(|r| proof_bytes::hex_vec("beacon_randomness", r)).transpose()?,
+            output_hash: self.output_hash.as_deref().map(|h| proof_bytes::hex("output_hash", h)).transpose()?,
+            win_probability_bps: self.win_probability_bps,
+            outcome_bit: self.outcome_bit,
+            challenge: (!self.challenge.is_empty()).then(|| proof_bytes::hex_vec("challenge", &self.challenge)).transpose()?,
+            challenge_len: self.challenge_len,
+            node_seed: self.node_seed.as_deref().map(|s| proof_bytes::hex("node_seed", s)).transpose()?,
+            bet_id: self.bet_id.map(|id| id.into_bytes()),
+        };
+        bytes.serialize(writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl borsh::BorshDeserialize for VrfProof {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        use base64::{engine::general_purpose::STANDARD as Base64Engine, Engine as _};
+
+        let bytes = VrfProofBytes::deserialize_reader(reader)?;
+        Ok(VrfProof {
+            seed_commitment: Base64Engine.encode(bytes.seed_commitment),
+            vrf_output: Base64Engine.encode(bytes.vrf_output),
+            signature: Base64Engine.encode(bytes.signature),
+            prev_output: bytes.prev_output.map(hex::encode),
+            beacon_round: bytes.beacon_round,
+            beacon_randomness: bytes.beacon_randomness.map(hex::encode),
+            output_hash: bytes.output_hash.map(hex::encode),
+            win_probability_bps: bytes.win_probability_bps,
+            outcome_bit: bytes.outcome_bit,
+            challenge_len: bytes.challenge_len,
+            node_seed: bytes.node_seed.map(hex::encode),
+            bet_id: bytes.bet_id.map(Uuid::from_bytes),
+            challenge: bytes.challenge.map(hex::encode).unwrap_or_default(),
+        })
+    }
+}
+
+impl VrfProof {
+    /// This proof as fixed-size byte arrays for an on-chain verifier (see [`OnchainProof`]),
+    /// given the request it answers and the base64 key that signed it
+    pub fn to_onchain_bytes(&self, node_pubkey: &str, req: &CoinflipRequest) -> Result<OnchainProof, VfError> {
+        crate::vrf_engine::VrfEngine::onchain_proof(node_pubkey, req, self)
+    }
+}
+
+/// A coinflip proof in the form a Solana program checks it: an Ed25519 signature by
+/// `node_pubkey` over `challenge`, the same 64 bytes `verify_proof` checks.
+///
+/// Borsh-encoded, fixed-size arrays carry no length prefix, so the encoding is exactly
+/// 160 bytes:
+///
+/// | offset | len | field         |
+/// |--------|-----|---------------|
+/// | 0      | 32  | `node_pubkey` |
+/// | 32     | 64  | `signature`   |
+/// | 96     | 64  | `challenge`   |
+///
+/// `challenge` is the Merlin challenge (label `challenge`) of the request's canonical
+/// transcript with the seed commitment (`seed_commit`) appended; see
+/// `VrfEngine::signing_payload`. A program that only checks the signature trusts the
+/// exporter for that binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
+pub struct OnchainProof {
+    pub node_pubkey: [u8; 32],
+    pub signature: [u8; 64],
+    pub challenge: [u8; 64],
+}
+
+#[cfg(feature = "borsh")]
+impl OnchainProof {
+    pub const ENCODED_LEN: usize = 160;
+
+    pub fn to_borsh(&self) -> Vec<u8> {
+        borsh::to_vec(self).expect("fixed-size arrays always encode")
+    }
+
+    pub fn from_borsh(bytes: &[u8]) -> Result<Self, VfError> {
+        borsh::from_slice(bytes).map_err(|e| VfError::InvalidProof(format!("Invalid on-chain proof: {}", e)))
+    }
+}
+
+/// Everything an offline verifier needs for one bet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofBundle {
+    pub bet_id: Uuid,
+    pub request: CoinflipRequest,
+    pub response: CoinflipResponse,
+    pub node_pubkey: String, // Base64 key the proof must verify against
+    #[serde(default)]
+    pub proof_status: ProofStatus,
+}
+
+/// A bet that exhausted its settlement retries, as kept in `dead_letter_bets`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterBet {
+    pub bundle: ProofBundle,
+    pub retry_count: u32,
+    pub error_message: String, // Error from the final settlement attempt
+    pub failed_at: String, // RFC 3339
+}
+
+/// How much of a stored bet's proof survived
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofStatus {
+    /// Every proof component was stored
+    #[default]
+    Full,
+    /// Only the signature was stored; the rest is rebuilt from it. Chain and beacon
+    /// context were never recorded, so such proofs only verify for plain rounds.
+    Legacy,
+    /// The stored signature or node key is unusable; the raw signature is passed through
+    Unreconstructable,
+}
+
+impl ProofStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProofStatus::Full => "full",
+            ProofStatus::Legacy => "legacy",
+            ProofStatus::Unreconstructable => "unreconstructable",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "full" => Some(ProofStatus::Full),
+            "legacy" => Some(ProofStatus::Legacy),
+            "unreconstructable" => Some(ProofStatus::Unreconstructable),
+            _ => None,
+        }
+    }
+}
+
+/// Where a stored bet is in settlement
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementStatus {
+    /// Queued for the next settlement batch (or awaiting a retry)
+    #[default]
+    Pending,
+    /// Claimed by a batch that is being settled
+    Settling,
+    Settled,
+    /// Gave up after exhausting retries
+    Failed,
+}
+
+impl SettlementStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SettlementStatus::Pending => "pending",
+            SettlementStatus::Settling => "settling",
+            SettlementStatus::Settled => "settled",
+            SettlementStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(SettlementStatus::Pending),
+            "settling" => Some(SettlementStatus::Settling),
+            "settled" => Some(SettlementStatus::Settled),
+            "failed" => Some(SettlementStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// A bet's settlement progress, for players polling whether their flip settled
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BetStatus {
+    pub bet_id: Uuid,
+    pub status: SettlementStatus,
+    pub tx_signature: Option<String>, // Settlement transaction, once settled
+    pub processed_at: String, // RFC 3339; when the flip was made
+    pub settled_at: Option<String>,
+    pub failed_at: Option<String>,
+}
+
+/// One settlement batch as recorded in `settlement_batches`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchRecord {
+    pub batch_id: Uuid,
+    pub bet_count: u64,
+    pub processing_time_ms: u64,
+    pub tx_signature: String,
+    pub success: bool,
+    pub created_at: String, // RFC 3339
+    pub merkle_root: Option<String>, // Hex; None for batches settled before roots were recorded
+}
+
+/// Where a settlement transaction recorded in `settlement_outbox` stands
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    /// Recorded, and submitted or about to be
+    #[default]
+    Pending,
+    /// Submitted under `tx_signature` by a settler that confirms separately, awaiting finality
+    Submitted,
+    Confirmed,
+    Failed,
+}
+
+impl OutboxStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutboxStatus::Pending => "pending",
+            OutboxStatus::Submitted => "submitted",
+            OutboxStatus::Confirmed => "confirmed",
+            OutboxStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(OutboxStatus::Pending),
+            "submitted" => Some(OutboxStatus::Submitted),
+            "confirmed" => Some(OutboxStatus::Confirmed),
+            "failed" => Some(OutboxStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// A batch's settlement transaction as recorded in `settlement_outbox`: written before it is
+/// submitted, then marked with the signature it confirmed under or the error it failed with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub batch_id: Uuid,
+    pub bet_count: u64,
+    pub payout_lamports: u64,
+    pub merkle_root: String, // Hex root the transaction commits to
+    pub status: OutboxStatus,
+    pub tx_signature: Option<String>,
+    pub error_message: Option<String>,
+    pub created_at: String, // RFC 3339
+    pub updated_at: String, // RFC 3339
+}
+
+/// A bet whose stored outcome could not be reproduced on replay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayMismatch {
+    pub bet_id: Uuid,
+    pub stored_heads: bool,
+    pub derived_heads: Option<bool>, // None when the proof couldn't be rebuilt
+    pub problem: String,
+}
+
+/// Result of re-deriving every outcome in a settlement batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchReplay {
+    pub batch_id: Uuid,
+    pub bet_count: u64,
+    pub mismatches: Vec<ReplayMismatch>,
+}
+
+/// Proof that a bet was settled in a given batch: the bet's leaf, its Merkle path to the
+/// batch root, and the settlement transaction that committed that root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub bet_id: Uuid,
+    pub vrf_signature: String, // Base64 VRF signature the leaf commits to
+    pub heads: bool,
+    pub batch_id: Uuid,
+    pub tx_signature: String,
+    pub merkle_root: String, // Hex
+    pub path: Vec<crate::merkle::MerkleStep>,
+}
+
+impl InclusionProof {
+    /// Recompute the leaf from the bet's own data and fold it up to the batch root
+    pub fn verify(&self) -> bool {
+        let Some(root) = hex::decode(&self.merkle_root).ok().and_then(|r| <[u8; 32]>::try_from(r).ok()) else {
+            return false;
+        };
+        let leaf = crate::merkle::leaf_hash(&self.bet_id, &self.vrf_signature, self.heads);
+        crate::merkle::verify_path(leaf, &self.path, &root)
+    }
+}
+
+/// Node-signed statement that a bet was paid out in a settlement transaction, so the
+/// player holds proof of payment without querying the chain.
+///
+/// The signature is Ed25519 by `node_pubkey` over [`SettlementReceipt::payload`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettlementReceipt {
+    pub bet_id: Uuid,
+    pub batch_id: Uuid,
+    pub tx_signature: String,
+    pub heads: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payout_lamports: Option<u64>, // None for bets recorded without a wager
+    pub settled_at: String, // RFC 3339
+    pub node_pubkey: String, // Base64 key that signed the receipt
+    pub signature: String,   // Base64
+}
+
+impl SettlementReceipt {
+    /// Canonical bytes the node signs
+    pub fn payload(&self) -> crate::canonical::CanonicalPayload<'_> {
+        let mut payload = crate::canonical::CanonicalPayload::new(b"vf_settlement_receipt")
+            .bytes(b"bet_id", self.bet_id.as_bytes())
+            .bytes(b"batch_id", self.batch_id.as_bytes())
+            .bytes(b"tx_signature", self.tx_signature.as_bytes())
+            .u64(b"heads", self.heads as u64);
+        if let Some(payout) = self.payout_lamports {
+            payload = payload.u64(b"payout_lamports", payout);
+        }
+        payload
+            .bytes(b"settled_at", self.settled_at.as_bytes())
+            .bytes(b"node_pubkey", self.node_pubkey.as_bytes())
+    }
+
+    pub fn verify(&self) -> bool {
+        crate::vrf_engine::VrfEngine::verify_receipt(self).unwrap_or(false)
+    }
+}
+
+/// Outcome of flagging legacy proof rows
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ProofBackfillReport {
+    pub legacy: u64,
+    pub unreconstructable: u64,
+}
+
+/// House edge actually realized on one token's settled bets over a window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RealizedEdge {
+    pub token_mint: String,
+    pub bet_count: u64,
+    pub total_wagered_lamports: u64,
+    pub total_paid_lamports: u64,
+    pub realized_edge: f64, // (wagered - paid) / wagered
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerEntryKind {
+    Debit,  // Wager taken from the player
+    Credit, // Payout returned to the player
+}
+
+/// One movement on a player's ledger
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub bet_id: Uuid,
+    pub settled_at: String, // RFC 3339
+    pub token_mint: String,
+    pub kind: LedgerEntryKind,
+    pub amount_lamports: u64,
+    pub balance_lamports: i64, // Player's running balance in this token after the entry
+}
+
+/// A page of a player's ledger
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerLedger {
+    pub player_pubkey: String,
+    pub entries: Vec<LedgerEntry>,
+    pub next_offset: Option<u64>, // Offset of the next page, if there is one
+}
+
+/// Exact transcript bytes for a request, for verifier implementers to diff against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptDebug {
+    pub canonical_payload: String, // Hex canonical VRF inputs
+    pub seed_commitment: String,   // Base64 seed commitment appended before the challenge
+    pub challenge: String,         // Hex 64-byte challenge that gets signed
+}
+
+/// A single proof submitted to `/verify`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_pubkey: Option<String>, // Base64 key to verify against; defaults to this node's key
+    pub request: CoinflipRequest,
+    pub proof: VrfProof,
+}
+
+/// A stored bet and the proof it was served with, submitted to `/replay`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayRequest {
+    pub request: CoinflipRequest,
+    pub proof: VrfProof,
+}
+
+/// Why a replayed flip doesn't reproduce the stored proof
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayDivergence {
+    KeyRotated, // The stored proof wasn't signed by the active node key
+    Tampered,   // Signed by the active key, but the stored proof doesn't match the stored request
+}
+
+/// `/replay` reply: the recomputed flip and whether it reproduces the stored proof
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayResult {
+    pub response: CoinflipResponse,
+    pub matches_stored: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<ReplayDivergence>,
+}
+
+/// `/commit` reply: the node's commitment to a seed it reveals with the flip
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitResponse {
+    pub commit_id: Uuid,
+    pub commitment: String, // Hex SHA-256 of the node seed
+    pub expires_in_secs: u64,
+}
+
+/// `/reveal` body: a coinflip request plus the commitment it is played against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevealRequest {
+    pub commit_id: Uuid,
+    #[serde(flatten)]
+    pub bet: CoinflipRequest,
+}
+
+/// `/reveal` reply: the flip plus the revealed node seed, which hashes to `commitment`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevealResponse {
+    #[serde(flatten)]
+    pub response: CoinflipResponse,
+    pub commit_id: Uuid,
+    pub commitment: String,
+    pub node_seed: String, // Hex
+}
+
+/// One entry of a `/coinflip/batch` reply: the flip, or why that request failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoinflipBatchResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<CoinflipResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyResult {
+    pub valid: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Offline verdict for one exported [`ProofBundle`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleVerdict {
+    pub bet_id: Uuid,
+    pub valid: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -39,4 +768,78 @@ pub enum VfError {
     InvalidProof(String),
     #[error("VRF generation failed: {0}")]
     VrfFailed(String),
-}
\ No newline at end of file
+    #[error("Invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+    #[error("Duplicate request: {0}")]
+    DuplicateRequest(String),
+    #[error("Duplicate bet: {0}")]
+    DuplicateBet(String),
+    #[error("Settlement backpressure: {0}")]
+    SettlementBackpressure(String),
+    #[error("Settlement failed: {0}")]
+    Settlement(String),
+    #[error("Unavailable: {0}")]
+    Unavailable(String),
+    #[error("Compute budget exceeded: {0}")]
+    ComputeBudgetExceeded(String),
+    #[error("Bad wallet signature: {0}")]
+    BadWalletSignature(String),
+    #[error("Outcome hook failed: {0}")]
+    Hook(String),
+}
+
+#[cfg(all(test, feature = "borsh"))]
+mod tests {
+    use super::*;
+    use crate::VrfEngine;
+    use base64::{engine::general_purpose::STANDARD as Base64Engine, Engine as _};
+
+    fn json<T: Serialize>(value: &T) -> serde_json::Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_borsh_round_trips_wire_types() {
+        let engine = VrfEngine::new();
+        let req = CoinflipRequest {
+            user_seed: "borsh".to_string(),
+            timestamp: 1234567890,
+            player_pubkey: Some("player".to_string()),
+            wager_lamports: Some(1000),
+            client_seed: Some("client".to_string()),
+            player_choice: CoinSide::Tails,
+            bet_id: Some(Uuid::new_v4()),
+            ..Default::default()
+        };
+        let response = engine.process_coinflip(&req).unwrap();
+
+        let decoded: CoinflipRequest = borsh::from_slice(&borsh::to_vec(&req).unwrap()).unwrap();
+        assert_eq!(json(&decoded), json(&req));
+        let decoded: CoinflipResponse = borsh::from_slice(&borsh::to_vec(&response).unwrap()).unwrap();
+        assert_eq!(json(&decoded), json(&response));
+        assert!(engine.verify_proof(&decoded.proof, &req).unwrap());
+    }
+
+    #[test]
+    fn test_borsh_proof_stores_raw_bytes() {
+        let engine = VrfEngine::new();
+        let req = CoinflipRequest { user_seed: "compact".to_string(), ..Default::default() };
+        let mut proof = engine.process_coinflip(&req).unwrap().proof;
+        proof.beacon_round = Some(7);
+        proof.beacon_randomness = Some(hex::encode([9u8; 48]));
+
+        let encoded = borsh::to_vec(&proof).unwrap();
+        let decoded: VrfProof = borsh::from_slice(&encoded).unwrap();
+        assert_eq!(json(&decoded), json(&proof));
+
+        // Commitment, output and signature lead as raw bytes, with no length prefixes
+        let raw = |value: &str| Base64Engine.decode(value).unwrap();
+        assert_eq!(encoded[..32], raw(&proof.seed_commitment)[..]);
+        assert_eq!(encoded[32..40], raw(&proof.vrf_output)[..]);
+        assert_eq!(encoded[40..104], raw(&proof.signature)[..]);
+        assert!(encoded.len() < serde_json::to_vec(&proof).unwrap().len());
+
+        proof.signature = "not base64".to_string();
+        assert!(borsh::to_vec(&proof).is_err());
+    }
+}
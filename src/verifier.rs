@@ -0,0 +1,240 @@
+use crate::types::{BundleVerdict, ProofBundle, ReplayRequest, ReplayResult, VerifyRequest, VerifyResult, VfError};
+use crate::vrf_engine::VrfEngine;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Runs proof verification on the blocking pool behind its own concurrency limit, so an
+/// audit storm queues here instead of competing with coinflips for CPU.
+///
+/// A batch holds a single permit for its whole run.
+pub struct VerifierPool {
+    permits: Arc<Semaphore>,
+}
+
+impl VerifierPool {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self { permits: Arc::new(Semaphore::new(max_concurrent.max(1))) }
+    }
+
+    /// Default limit: half the cores, leaving the rest for flips
+    pub fn default_limit() -> usize {
+        (num_cpus::get() / 2).max(1)
+    }
+
+    pub fn available(&self) -> usize {
+        self.permits.available_permits()
+    }
+
+    pub async fn verify(&self, default_pubkey: &str, item: VerifyRequest) -> Result<VerifyResult, VfError> {
+        let mut results = self.verify_batch(default_pubkey, vec![item]).await?;
+        Ok(results.remove(0))
+    }
+
+    pub async fn verify_batch(
+        &self,
+        default_pubkey: &str,
+        items: Vec<VerifyRequest>,
+    ) -> Result<Vec<VerifyResult>, VfError> {
+        let _permit = self.permits.clone().acquire_owned().await
+            .map_err(|_| VfError::VrfFailed("Verifier pool closed".to_string()))?;
+
+        let default_pubkey = default_pubkey.to_string();
+        tokio::task::spawn_blocking(move || {
+            items.iter().map(|item| verify_one(&default_pubkey, item)).collect()
+        })
+        .await
+        .map_err(|e| VfError::VrfFailed(format!("Verification task failed: {}", e)))
+    }
+
+    /// Recompute a disputed flip (see [`VrfEngine::replay_coinflip`]); dispute tooling is
+    /// audit traffic, so it queues behind the same limit as verification
+    pub async fn replay(&self, engine: Arc<VrfEngine>, item: ReplayRequest) -> Result<ReplayResult, VfError> {
+        let _permit = self.permits.clone().acquire_owned().await
+            .map_err(|_| VfError::VrfFailed("Verifier pool closed".to_string()))?;
+
+        tokio::task::spawn_blocking(move || engine.replay_coinflip(&item.request, &item.proof))
+            .await
+            .map_err(|e| VfError::VrfFailed(format!("Replay task failed: {}", e)))?
+    }
+}
+
+fn verify_one(default_pubkey: &str, item: &VerifyRequest) -> VerifyResult {
+    let node_pubkey = item.node_pubkey.as_deref().unwrap_or(default_pubkey);
+    match VrfEngine::verify_with_pubkey(node_pubkey, &item.request, &item.proof) {
+        Ok(valid) => VerifyResult { valid, error: None },
+        Err(e) => VerifyResult { valid: false, error: Some(e.to_string()) },
+    }
+}
+
+/// Check exported proof bundles with nothing but the bundles themselves: each proof must
+/// verify against the bundle's node key and derive the outcome the response claims.
+pub fn verify_bundles(bundles: &[ProofBundle]) -> Vec<BundleVerdict> {
+    bundles.iter().map(verify_bundle).collect()
+}
+
+/// Read a JSON array of bundles, as served by `/settlement/batch/:id/proofs`, and verify it
+pub fn verify_bundle_file(path: &Path) -> Result<Vec<BundleVerdict>, VfError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| VfError::InvalidInput(format!("Cannot read {}: {}", path.display(), e)))?;
+    let bundles: Vec<ProofBundle> = serde_json::from_str(&contents)
+        .map_err(|e| VfError::InvalidInput(format!("{} is not a proof bundle export: {}", path.display(), e)))?;
+    Ok(verify_bundles(&bundles))
+}
+
+fn verify_bundle(bundle: &ProofBundle) -> BundleVerdict {
+    let proof = &bundle.response.proof;
+    let error = match VrfEngine::verify_with_pubkey(&bundle.node_pubkey, &bundle.request, proof) {
+        Ok(true) => match outcome_mismatch(bundle) {
+            Ok(mismatch) => mismatch,
+            Err(e) => Some(e.to_string()),
+        },
+        Ok(false) => Some("Proof does not verify against the node key".to_string()),
+        Err(e) => Some(e.to_string()),
+    };
+    BundleVerdict { bet_id: bundle.bet_id, valid: error.is_none(), error }
+}
+
+/// Compare the response's claimed outcome (and raw value, when present) with the proof's
+fn outcome_mismatch(bundle: &ProofBundle) -> Result<Option<String>, VfError> {
+    let (proof, response) = (&bundle.response.proof, &bundle.response);
+    if let Some(claimed) = response.random_value {
+        // Under a house edge the side comes from a re-drawn roll, not the raw value's bits
+        if proof.win_probability_bps.is_none() && proof.outcome_bit.unwrap_or_default().heads(claimed) != response.heads {
+            return Ok(Some(format!("Response random_value={} contradicts its heads={}", claimed, response.heads)));
+        }
+        let derived = VrfEngine::random_value_from_proof(proof)?;
+        if claimed != derived {
+            return Ok(Some(format!("Proof derives random_value={} but the response claims {}", derived, claimed)));
+        }
+    }
+    let heads = VrfEngine::outcome_from_proof(proof, bundle.request.player_choice)?;
+    if heads != response.heads {
+        return Ok(Some(format!("Proof derives heads={} but the response claims heads={}", heads, response.heads)));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CoinflipRequest, CoinflipResponse, ProofStatus};
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_verify_roundtrip() {
+        let engine = VrfEngine::from_seed([3u8; 32]);
+        let req = CoinflipRequest { user_seed: "audit".to_string(), timestamp: 1_700_000_000, ..Default::default() };
+        let response = engine.process_coinflip(&req).unwrap();
+
+        let pool = VerifierPool::new(2);
+        let mut tampered = req.clone();
+        tampered.user_seed = "other".to_string();
+        let results = pool
+            .verify_batch(&engine.node_pubkey(), vec![
+                VerifyRequest { node_pubkey: None, request: req, proof: response.proof.clone() },
+                VerifyRequest { node_pubkey: None, request: tampered, proof: response.proof },
+            ])
+            .await
+            .unwrap();
+
+        assert!(results[0].valid);
+        assert!(!results[1].valid);
+        assert!(results[1].error.is_some());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_saturated_verifier_does_not_block_flips() {
+        let engine = Arc::new(VrfEngine::from_seed([4u8; 32]));
+        let req = CoinflipRequest { user_seed: "audit".to_string(), timestamp: 1_700_000_000, ..Default::default() };
+        let proof = engine.process_coinflip(&req).unwrap().proof;
+
+        let pool = Arc::new(VerifierPool::new(1));
+        let held = pool.permits.clone().acquire_owned().await.unwrap();
+        assert_eq!(pool.available(), 0);
+
+        // Queued verification waits for a permit
+        let queued = {
+            let pool = pool.clone();
+            let pubkey = engine.node_pubkey();
+            tokio::spawn(async move {
+                pool.verify(&pubkey, VerifyRequest { node_pubkey: None, request: req, proof }).await
+            })
+        };
+
+        // Flips keep completing while verification is saturated
+        for i in 0..20 {
+            let engine = engine.clone();
+            let flip = tokio::task::spawn_blocking(move || {
+                engine.process_coinflip(&CoinflipRequest { user_seed: format!("flip_{}", i), ..Default::default() })
+            });
+            let result = tokio::time::timeout(Duration::from_secs(1), flip).await;
+            assert!(result.is_ok_and(|r| r.unwrap().is_ok()), "flip {} blocked", i);
+        }
+        assert!(!queued.is_finished());
+
+        drop(held);
+        let result = tokio::time::timeout(Duration::from_secs(5), queued).await.unwrap().unwrap().unwrap();
+        assert!(result.valid);
+    }
+
+    #[test]
+    fn test_bundle_file_flags_tampered_entry() {
+        let engine = VrfEngine::from_seed([5u8; 32]);
+        let mut bundles: Vec<ProofBundle> = (0..4)
+            .map(|i| {
+                let request = CoinflipRequest { user_seed: format!("export_{}", i), timestamp: 1_700_000_000, ..Default::default() };
+                let response = engine.process_coinflip(&request).unwrap();
+                ProofBundle {
+                    bet_id: Uuid::new_v4(),
+                    request,
+                    response,
+                    node_pubkey: engine.node_pubkey(),
+                    proof_status: ProofStatus::Full,
+                }
+            })
+            .collect();
+        bundles[2].response.heads = !bundles[2].response.heads;
+
+        let path = std::env::temp_dir().join(format!("vfnode-bundles-{}.json", Uuid::new_v4()));
+        std::fs::write(&path, serde_json::to_vec(&bundles).unwrap()).unwrap();
+        let verdicts = verify_bundle_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(verdicts.len(), 4);
+        for (i, verdict) in verdicts.iter().enumerate() {
+            assert_eq!(verdict.bet_id, bundles[i].bet_id);
+            assert_eq!(verdict.valid, i != 2, "bundle {}: {:?}", i, verdict.error);
+        }
+        assert!(verdicts[2].error.is_some());
+    }
+
+    #[test]
+    fn test_random_value_must_match_outcome() {
+        let engine = VrfEngine::from_seed([6u8; 32]);
+        let request = CoinflipRequest { user_seed: "raw_value".to_string(), timestamp: 1_700_000_000, ..Default::default() };
+        let response = engine.process_coinflip(&request).unwrap();
+        let value = response.random_value.expect("node reports the raw value");
+        assert_eq!(value & 1 == 0, response.heads);
+        assert_eq!(VrfEngine::random_value_from_proof(&response.proof).unwrap(), value);
+
+        let bundle = |random_value| ProofBundle {
+            bet_id: Uuid::new_v4(),
+            request: request.clone(),
+            response: CoinflipResponse { random_value, ..response.clone() },
+            node_pubkey: engine.node_pubkey(),
+            proof_status: ProofStatus::Full,
+        };
+        let verdicts = verify_bundles(&[
+            bundle(Some(value)),
+            bundle(None), // Exported before the field existed
+            bundle(Some(value ^ 1)),
+            bundle(Some(value ^ 2)),
+        ]);
+
+        assert!(verdicts[0].valid && verdicts[1].valid);
+        assert!(verdicts[2].error.as_deref().unwrap().contains("contradicts"));
+        assert!(verdicts[3].error.as_deref().unwrap().contains("derives random_value"));
+    }
+}
@@ -1,183 +1,1316 @@
-use crate::types::{CoinflipRequest, CoinflipResponse, VrfProof, VfError};
+use crate::beacon::EntropyBeacon;
+use crate::budget::{sample_below, ComputeBudget};
+use crate::canonical::CanonicalPayload;
+use crate::chain::ChainHeads;
+use crate::dedup::{RecentTuples, SeenBetIds};
+use crate::drift::{ClockDrift, ClockDriftStats};
+use crate::hooks::OutcomeHooks;
+use crate::metrics::METRICS;
+use crate::types::{CoinSide, CoinflipRequest, CoinflipResponse, DiceRequest, DiceResponse, OnchainProof, OutcomeBit, ReplayDivergence, ReplayResult, SettlementReceipt, TranscriptDebug, VfError, VrfProof, DICE_FACES, NATIVE_TOKEN_MINT};
 use ed25519_dalek::{SigningKey, Signature, Signer, VerifyingKey, Verifier};
 use merlin::Transcript;
 use rand::{thread_rng, RngCore};
 use base64::{Engine as _, engine::general_purpose::STANDARD as Base64Engine};
 use sha2::{Sha256, Digest};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-pub struct VrfEngine {
+/// Win probability of a fair even-money flip, in basis points
+pub const FAIR_WIN_PROBABILITY_BPS: u16 = 5_000;
+
+/// Basis points in 100%
+pub const BPS_SCALE: u64 = 10_000;
+
+/// Payout on a win, in basis points of the wager: double the wager back
+pub const DEFAULT_PAYOUT_MULTIPLIER_BPS: u32 = 20_000;
+
+/// How far ahead of the node clock a request timestamp may be, when freshness is checked
+pub const TIMESTAMP_SKEW_TOLERANCE: Duration = Duration::from_secs(5);
+
+/// Bytes of transcript challenge a proof signs unless configured otherwise
+pub const DEFAULT_CHALLENGE_LEN: usize = 64;
+
+/// Challenge lengths a node may sign and a verifier accepts
+pub const CHALLENGE_LENS: [usize; 2] = [32, 64];
+
+/// Request timestamp (Unix seconds, 2026-10-16T00:00:00Z) from which proofs must carry the
+/// request-bound seed commitment. Only requests timestamped earlier may verify under the
+/// legacy key-only commitment, so a node can't fall back to it for new bets.
+pub const LEGACY_COMMITMENT_CUTOVER: u64 = 1_792_108_800;
+
+/// Transcript domain of coinflip proofs. Every game signs under its own label, so a proof
+/// made for one game can never verify as another's.
+pub const COINFLIP_DOMAIN: &[u8] = b"vf_coinflip";
+
+/// Transcript domain of dice proofs
+pub const DICE_DOMAIN: &[u8] = b"vf_dice";
+
+/// Tunables for the VRF engine. `Default` reproduces the original behavior.
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    /// Pad every successful flip to at least this duration so response timing can't
+    /// correlate with the outcome (off by default)
+    pub latency_floor: Option<Duration>,
+    /// Chain each player's rounds: the transcript commits to the output hash of the
+    /// player's previous round, so rounds can't be reordered or dropped undetected
+    pub seed_chaining: bool,
+    /// Players whose chain heads are kept in seed-chaining mode; past this the least
+    /// recently used are dropped and their chains restart
+    pub seed_chain_capacity: usize,
+    /// A chain unused for this long restarts
+    pub seed_chain_ttl: Duration,
+    /// Reject a repeat of the exact same (player, seed, timestamp) tuple within this window;
+    /// it would deterministically reproduce an already-revealed outcome
+    pub duplicate_window: Option<Duration>,
+    /// Reject a second request carrying an already-flipped `bet_id`, remembering up to
+    /// this many ids (None = off)
+    pub seen_bet_ids: Option<usize>,
+    /// Warn about and count requests whose timestamp is further than this from the node
+    /// clock. Diagnostic only; such requests are still processed
+    pub max_clock_drift: Option<Duration>,
+    /// Reject requests whose timestamp is older than this, or further than
+    /// `TIMESTAMP_SKEW_TOLERANCE` in the future (None = accept any timestamp)
+    pub max_request_age: Option<Duration>,
+    /// Iterations a single request may spend in data-dependent loops such as rejection
+    /// sampling (None = `ComputeBudget::DEFAULT_LIMIT`)
+    pub compute_budget: Option<u32>,
+    /// Include the full 32-byte output hash in proofs next to the truncated `vrf_output`
+    pub full_output_hash: bool,
+    /// Chance the player wins a flip, in basis points (at most `BPS_SCALE`). Anything but
+    /// `FAIR_WIN_PROBABILITY_BPS` is committed to the transcript and reported in the proof
+    pub win_probability_bps: u16,
+    /// Bits of the random value that decide a fair flip. Anything but `Lsb` is committed to
+    /// the transcript and reported in the proof
+    pub outcome_bit: OutcomeBit,
+    /// Bytes of transcript challenge each proof signs, one of `CHALLENGE_LENS`. Anything but
+    /// `DEFAULT_CHALLENGE_LEN` is committed to the transcript and reported in the proof
+    pub challenge_len: usize,
+    /// A winning wager is paid `wager * payout_multiplier_bps / BPS_SCALE`
+    pub payout_multiplier_bps: u32,
+    /// Smallest accepted `wager_lamports`, across all tokens
+    pub min_wager_lamports: u64,
+    /// Largest accepted `wager_lamports`, capping the exposure of any one bet
+    pub max_wager_lamports: u64,
+    /// Token mints a wagered bet may use, absent `token_mint` meaning `NATIVE_TOKEN_MINT`
+    /// (empty = any mint)
+    pub allowed_mints: HashSet<String>,
+    /// Reject wagered, non-free bets that carry no `wallet_sig`. A signature that is
+    /// present is always verified
+    pub require_wallet_sig: bool,
+    /// Shortest accepted `user_seed`, in bytes
+    pub min_seed_len: usize,
+    /// Longest accepted `user_seed`, in bytes
+    pub max_seed_len: usize,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            latency_floor: None,
+            seed_chaining: false,
+            seed_chain_capacity: ChainHeads::DEFAULT_CAPACITY,
+            seed_chain_ttl: ChainHeads::DEFAULT_TTL,
+            duplicate_window: None,
+            seen_bet_ids: None,
+            max_clock_drift: None,
+            max_request_age: None,
+            compute_budget: None,
+            full_output_hash: false,
+            win_probability_bps: FAIR_WIN_PROBABILITY_BPS,
+            outcome_bit: OutcomeBit::Lsb,
+            challenge_len: DEFAULT_CHALLENGE_LEN,
+            payout_multiplier_bps: DEFAULT_PAYOUT_MULTIPLIER_BPS,
+            min_wager_lamports: 1,
+            max_wager_lamports: u64::MAX,
+            allowed_mints: HashSet::new(),
+            require_wallet_sig: false,
+            min_seed_len: 1,
+            max_seed_len: 1024,
+        }
+    }
+}
+
+/// Per-flip transcript inputs that come from node state rather than the request
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TranscriptContext<'a> {
+    pub prev_output: Option<&'a [u8]>,
+    pub beacon: Option<(u64, &'a [u8])>,
+    pub win_probability_bps: Option<u16>, // None for a fair flip
+    pub outcome_bit: Option<OutcomeBit>, // None for the original lsb rule
+    pub challenge_len: Option<u8>, // None for the original 64 bytes
+    pub node_seed: Option<&'a [u8]>, // Committed node seed, for commit-reveal flips only
+    pub bet_id: Option<&'a uuid::Uuid>, // Client-chosen bet id the proof is bound to
+}
+
+/// What a proof's challenge is drawn from: the request transcript and the seed commitment
+/// appended to it
+struct SigningInput {
+    transcript: Transcript,
+    seed_commit: [u8; 32],
+}
+
+struct VrfOutput {
+    random_value: u64,
+    signature: [u8; 64],
+    output_hash: [u8; 32],
+    seed_commit: String,
+    challenge: Vec<u8>,
+}
+
+/// Signing keypair; swapped as a unit on rotation so signer and reported node_id never tear
+struct NodeKey {
     signing_key: SigningKey,
     verifying_key: VerifyingKey,
 }
 
-impl VrfEngine {
-    pub fn new() -> Self {
+impl NodeKey {
+    fn from_secret(secret_bytes: &[u8; 32]) -> Self {
+        let signing_key = SigningKey::from_bytes(secret_bytes);
+        let verifying_key = signing_key.verifying_key();
+        Self { signing_key, verifying_key }
+    }
+
+    fn random() -> Self {
         let mut csprng = thread_rng();
         let mut secret_bytes = [0u8; 32];
         csprng.fill_bytes(&mut secret_bytes);
-        let signing_key = SigningKey::from_bytes(&secret_bytes);
-        let verifying_key = signing_key.verifying_key();
-        
-        Self { signing_key, verifying_key }
+        Self::from_secret(&secret_bytes)
+    }
+}
+
+pub struct VrfEngine {
+    key: RwLock<Arc<NodeKey>>,
+    config: EngineConfig,
+    // Last output hash per player (seed-chaining mode only)
+    chains: ChainHeads,
+    // Optional external entropy mixed into every transcript
+    beacon: Option<Arc<dyn EntropyBeacon>>,
+    // Recently flipped tuples (only when duplicate_window is set)
+    recent: Option<RecentTuples>,
+    // Flipped bet ids (only when seen_bet_ids is set)
+    seen_bets: Option<SeenBetIds>,
+    // Client clock drift tracking (only when max_clock_drift is set)
+    drift: Option<ClockDrift>,
+    // Operator post-outcome hooks, run off the flip path
+    hooks: Option<OutcomeHooks>,
+}
+
+impl VrfEngine {
+    pub fn new() -> Self {
+        Self::with_config(EngineConfig::default())
+    }
+
+    pub fn with_config(config: EngineConfig) -> Self {
+        Self::from_key(NodeKey::random(), config)
     }
 
     /// Create VRF engine with deterministic keypair (for testing)
     pub fn from_seed(seed: [u8; 32]) -> Self {
-        let signing_key = SigningKey::from_bytes(&seed);
-        let verifying_key = signing_key.verifying_key();
-        Self { signing_key, verifying_key }
+        Self::from_seed_with_config(seed, EngineConfig::default())
+    }
+
+    pub fn from_seed_with_config(seed: [u8; 32], config: EngineConfig) -> Self {
+        Self::from_key(NodeKey::from_secret(&seed), config)
+    }
+
+    fn from_key(key: NodeKey, config: EngineConfig) -> Self {
+        let recent = config
+            .duplicate_window
+            .map(|window| RecentTuples::new(window, RecentTuples::DEFAULT_CAPACITY));
+        let seen_bets = config.seen_bet_ids.map(SeenBetIds::new);
+        let drift = config.max_clock_drift.map(ClockDrift::new);
+        let chains = ChainHeads::new(config.seed_chain_capacity, config.seed_chain_ttl);
+
+        Self {
+            key: RwLock::new(Arc::new(key)),
+            config,
+            chains,
+            beacon: None,
+            recent,
+            seen_bets,
+            drift,
+            hooks: None,
+        }
+    }
+
+    /// Mix the latest round of an external randomness beacon into every flip
+    pub fn with_beacon(mut self, beacon: Arc<dyn EntropyBeacon>) -> Self {
+        self.beacon = Some(beacon);
+        self
+    }
+
+    /// Hand every successful flip to operator hooks (see [`crate::hooks::PostOutcomeHook`])
+    pub fn with_hooks(mut self, hooks: OutcomeHooks) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
+
+    /// Fresh iteration budget for one request
+    pub fn compute_budget(&self) -> ComputeBudget {
+        ComputeBudget::new(self.config.compute_budget.unwrap_or(ComputeBudget::DEFAULT_LIMIT))
+    }
+
+    /// Client clock drift counters, when drift tracking is enabled
+    pub fn clock_drift(&self) -> Option<ClockDriftStats> {
+        self.drift.as_ref().map(ClockDrift::stats)
+    }
+
+    /// Snapshot of the active key; hold it for the whole flip so signing and node_id agree
+    #[inline]
+    fn current_key(&self) -> Arc<NodeKey> {
+        self.key.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Replace the node key. In-flight flips finish with the key they started with.
+    pub fn rotate_key(&self) -> String {
+        let new_key = Arc::new(NodeKey::random());
+        let pubkey = Base64Engine.encode(new_key.verifying_key.as_bytes());
+        *self.key.write().unwrap_or_else(|e| e.into_inner()) = new_key;
+        pubkey
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.current_key().verifying_key
     }
 
     pub fn node_pubkey(&self) -> String {
-        Base64Engine.encode(self.verifying_key.as_bytes())
+        Base64Engine.encode(self.current_key().verifying_key.as_bytes())
+    }
+
+    /// The node public key in base58, the encoding Solana tooling uses for account keys.
+    pub fn node_pubkey_base58(&self) -> String {
+        bs58::encode(self.current_key().verifying_key.as_bytes()).into_string()
+    }
+
+    /// The node public key as 64 lowercase hex characters.
+    pub fn node_pubkey_hex(&self) -> String {
+        hex::encode(self.current_key().verifying_key.as_bytes())
     }
 
     // Optimized for high performance - no async overhead for CPU-bound work
     #[inline]
     pub fn process_coinflip(&self, req: &CoinflipRequest) -> Result<CoinflipResponse, VfError> {
-        let start_time = std::time::Instant::now();
+        self.process(req, None)
+    }
 
+    /// Flip with a node seed the node committed to before it saw the request (see
+    /// [`CommitStore`](crate::commit::CommitStore)). The seed is mixed into the transcript and
+    /// revealed in the proof as `node_seed`, so the player can check it against the commitment.
+    pub fn process_committed_coinflip(&self, req: &CoinflipRequest, node_seed: &[u8; 32]) -> Result<CoinflipResponse, VfError> {
+        self.process(req, Some(node_seed))
+    }
+
+    fn process(&self, req: &CoinflipRequest, node_seed: Option<&[u8; 32]>) -> Result<CoinflipResponse, VfError> {
         // 1. Fast validation
         self.validate_request(req)?;
 
+        let response = self.flip_new_bet(req, node_seed)?;
+        METRICS.record_flip(response.heads, response.processing_time_ms);
+        if let Some(drift) = &self.drift {
+            drift.record(req.timestamp, response.timestamp);
+        }
+        if let Some(hooks) = &self.hooks {
+            hooks.dispatch(req, &response);
+        }
+        Ok(response)
+    }
+
+    /// Flip a slice of bets in order on the calling thread, one result per bet.
+    ///
+    /// Blocks for the whole slice: wrap it in a single `spawn_blocking` to pay the task
+    /// spawn once per slice instead of once per bet. Each bet goes through
+    /// [`process_coinflip`](Self::process_coinflip) unchanged, latency floor included.
+    pub fn process_coinflip_batch_blocking(&self, reqs: &[CoinflipRequest]) -> Vec<Result<CoinflipResponse, VfError>> {
+        reqs.iter().map(|req| self.process_coinflip(req)).collect()
+    }
+
+    /// Flip, refusing a bet id that was already flipped
+    fn flip_new_bet(&self, req: &CoinflipRequest, node_seed: Option<&[u8; 32]>) -> Result<CoinflipResponse, VfError> {
+        let (Some(seen), Some(bet_id)) = (&self.seen_bets, req.bet_id) else {
+            return self.flip_once(req, node_seed);
+        };
+        if !seen.insert(bet_id) {
+            return Err(VfError::DuplicateBet(format!("bet {} was already flipped", bet_id)));
+        }
+
+        let result = self.flip_once(req, node_seed);
+        if result.is_err() {
+            seen.remove(&bet_id);
+        }
+        result
+    }
+
+    /// Flip, refusing tuples already flipped within the duplicate window
+    fn flip_once(&self, req: &CoinflipRequest, node_seed: Option<&[u8; 32]>) -> Result<CoinflipResponse, VfError> {
+        let Some(recent) = &self.recent else {
+            return self.flip(req, node_seed);
+        };
+        let key = RecentTuples::key(req, node_seed)?;
+        if !recent.insert(key) {
+            return Err(VfError::DuplicateRequest(
+                "this (seed, timestamp) was already flipped; use a fresh seed or timestamp".to_string(),
+            ));
+        }
+
+        let result = self.flip(req, node_seed);
+        if result.is_err() {
+            recent.remove(&key);
+        }
+        result
+    }
+
+    fn flip(&self, req: &CoinflipRequest, node_seed: Option<&[u8; 32]>) -> Result<CoinflipResponse, VfError> {
+        let start_time = std::time::Instant::now();
+
         // 2. Build transcript (optimized)
-        let transcript = self.build_transcript(req);
+        let key = self.current_key();
+
+        // Chained rounds hold the player's chain for the whole flip so it stays linear
+        let player = req.player_pubkey.as_deref().unwrap_or_default();
+        let link = self.config.seed_chaining.then(|| self.chains.link(player));
+        let prev_output = link.as_ref().and_then(|l| l.prev_output());
+
+        // Fail closed: if mixing is configured, never flip without fresh beacon entropy
+        let beacon = self.beacon.as_ref().map(|b| b.latest()).transpose()?;
+
+        let ctx = TranscriptContext {
+            prev_output: prev_output.as_ref().map(|p| &p[..]),
+            beacon: beacon.as_ref().map(|b| (b.round, &b.randomness[..])),
+            win_probability_bps: self.house_edge(),
+            outcome_bit: self.outcome_bit(),
+            challenge_len: self.challenge_len()?,
+            node_seed: node_seed.map(|seed| &seed[..]),
+            bet_id: req.bet_id.as_ref(),
+        };
+        let transcript = Self::build_transcript(COINFLIP_DOMAIN, &key.verifying_key, req, &ctx)?;
 
         // 3. Generate VRF (CPU-intensive, but fast)
-        let (random_value, vrf_proof_bytes, seed_commit) = self.generate_vrf(&transcript)?;
+        let vrf = Self::generate_vrf(&key, &transcript, &ctx)?;
+
+        if let Some(link) = link {
+            link.advance(vrf.output_hash);
+        }
+
+        // 4. Game logic and proof structure
+        let mut response = self.coinflip_response(&key, req, &ctx, vrf)?;
+
+        // 5. Optional constant-time padding (same floor for win and lose paths)
+        if let Some(floor) = self.config.latency_floor {
+            let elapsed = start_time.elapsed();
+            if elapsed < floor {
+                std::thread::sleep(floor - elapsed);
+            }
+        }
+
+        response.processing_time_ms = start_time.elapsed().as_millis() as u64;
+        Ok(response)
+    }
 
-        // 4. Game logic (branchless for speed)
-        let heads = random_value & 1 == 0; // Even = heads, odd = tails
+    /// Decide the flip from its VRF output and assemble the response (processing time unset)
+    fn coinflip_response(
+        &self,
+        key: &NodeKey,
+        req: &CoinflipRequest,
+        ctx: &TranscriptContext,
+        vrf: VrfOutput,
+    ) -> Result<CoinflipResponse, VfError> {
+        let heads = Self::decide_heads(&vrf.output_hash, ctx, req.player_choice, &mut self.compute_budget())?;
 
-        // 5. Create proof structure
         let proof = VrfProof {
-            seed_commitment: seed_commit,
-            vrf_output: Base64Engine.encode(&random_value.to_le_bytes()),
-            signature: Base64Engine.encode(&vrf_proof_bytes),
+            seed_commitment: vrf.seed_commit,
+            vrf_output: Base64Engine.encode(vrf.random_value.to_le_bytes()),
+            signature: Base64Engine.encode(vrf.signature),
+            prev_output: ctx.prev_output.map(hex::encode),
+            beacon_round: ctx.beacon.map(|(round, _)| round),
+            beacon_randomness: ctx.beacon.map(|(_, randomness)| hex::encode(randomness)),
+            output_hash: self.config.full_output_hash.then(|| hex::encode(vrf.output_hash)),
+            win_probability_bps: ctx.win_probability_bps,
+            outcome_bit: ctx.outcome_bit,
+            challenge_len: ctx.challenge_len,
+            node_seed: ctx.node_seed.map(hex::encode),
+            bet_id: ctx.bet_id.copied(),
+            challenge: hex::encode(vrf.challenge),
         };
 
-        let processing_time = start_time.elapsed().as_millis() as u64;
-
+        let win = req.player_choice.wins(heads);
         Ok(CoinflipResponse {
-            node_id: self.node_pubkey(),
+            node_id: Base64Engine.encode(key.verifying_key.as_bytes()),
             heads,
+            win,
+            proof,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            processing_time_ms: 0,
+            free_play: req.free_play,
+            bet_id: req.bet_id,
+            payout_lamports: req.wager_lamports.filter(|_| !req.free_play).map(|w| self.payout_lamports(w, win)).transpose()?,
+            random_value: Some(vrf.random_value),
+        })
+    }
+
+    /// Recompute the flip a stored proof was served for, for dispute resolution.
+    ///
+    /// Runs the same transcript with the current key and the chain, beacon and house-edge
+    /// context the stored proof recorded, but touches no node state: no freshness, duplicate
+    /// or chain tracking, metrics or hooks. Signing is deterministic, so the fresh proof
+    /// matches the stored one unless the node key has rotated since, or the stored proof
+    /// or request was altered.
+    pub fn replay_coinflip(&self, req: &CoinflipRequest, stored: &VrfProof) -> Result<ReplayResult, VfError> {
+        let start_time = std::time::Instant::now();
+        let decode = |field: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(hex::decode)
+                .transpose()
+                .map_err(|_| VfError::InvalidProof(format!("Invalid {} encoding", field)))
+        };
+        let prev_output = decode("prev_output", &stored.prev_output)?;
+        let beacon_randomness = decode("beacon_randomness", &stored.beacon_randomness)?;
+        let node_seed = decode("node_seed", &stored.node_seed)?;
+        let beacon = match (stored.beacon_round, &beacon_randomness) {
+            (Some(round), Some(randomness)) => Some((round, &randomness[..])),
+            (None, None) => None,
+            _ => return Err(VfError::InvalidProof("beacon_round and beacon_randomness must be given together".to_string())),
+        };
+
+        let key = self.current_key();
+        let ctx = TranscriptContext {
+            prev_output: prev_output.as_deref(),
+            beacon,
+            win_probability_bps: stored.win_probability_bps,
+            outcome_bit: stored.outcome_bit,
+            challenge_len: stored.challenge_len,
+            node_seed: node_seed.as_deref(),
+            bet_id: stored.bet_id.as_ref(),
+        };
+        Self::checked_challenge_len(ctx.challenge_len)?;
+        let vrf = Self::generate_vrf(&key, &Self::build_transcript(COINFLIP_DOMAIN, &key.verifying_key, req, &ctx)?, &ctx)?;
+        let output_hash = hex::encode(vrf.output_hash);
+        let mut response = self.coinflip_response(&key, req, &ctx, vrf)?;
+        response.processing_time_ms = start_time.elapsed().as_millis() as u64;
+
+        let fresh = &response.proof;
+        let matches_stored = fresh.seed_commitment == stored.seed_commitment
+            && fresh.signature == stored.signature
+            && fresh.vrf_output == stored.vrf_output
+            && stored.output_hash.as_ref().is_none_or(|hash| *hash == output_hash);
+        let reason = (!matches_stored).then(|| {
+            if Self::signed_by(&key.verifying_key, stored) {
+                ReplayDivergence::Tampered
+            } else {
+                ReplayDivergence::KeyRotated
+            }
+        });
+
+        Ok(ReplayResult { response, matches_stored, reason })
+    }
+
+    /// Signature a fair, unchained flip of `req` carried before seed commitments were bound to
+    /// the request, for tests of stored legacy rows
+    #[cfg(test)]
+    pub(crate) fn legacy_signature(&self, req: &CoinflipRequest) -> String {
+        let key = self.current_key();
+        let mut input = Self::build_transcript(COINFLIP_DOMAIN, &key.verifying_key, req, &TranscriptContext::default()).unwrap();
+        input.seed_commit = Self::legacy_seed_commitment(&key.verifying_key);
+        let vrf = Self::generate_vrf(&key, &input, &TranscriptContext::default()).unwrap();
+        Base64Engine.encode(vrf.signature)
+    }
+
+    /// Whether `key` signed `proof`, judged from the proof alone: its signature over its stated
+    /// challenge, or for proofs that predate the challenge field, a legacy commitment to `key`
+    fn signed_by(key: &VerifyingKey, proof: &VrfProof) -> bool {
+        if proof.challenge.is_empty() {
+            return proof.seed_commitment == Base64Engine.encode(Self::legacy_seed_commitment(key));
+        }
+        let challenge = hex::decode(&proof.challenge).ok();
+        let signature = Base64Engine.decode(&proof.signature).ok().and_then(|s| <[u8; 64]>::try_from(s).ok());
+        match (challenge, signature) {
+            (Some(challenge), Some(signature)) => key.verify(&challenge, &Signature::from_bytes(&signature)).is_ok(),
+            _ => false,
+        }
+    }
+
+    /// Uniform value in `[0, max)` for games with more than two outcomes (dice, roulette).
+    ///
+    /// Derived from the transcript of an unchained round of `req` under the game's `domain`,
+    /// then drawn from its output hash by rejection sampling (see [`sample_below`]) so there
+    /// is no modulo bias.
+    pub fn random_in_range(&self, domain: &'static [u8], req: &CoinflipRequest, max: u64) -> Result<u64, VfError> {
+        self.validate_request(req)?;
+
+        let key = self.current_key();
+        let beacon = self.beacon.as_ref().map(|b| b.latest()).transpose()?;
+        let ctx = TranscriptContext {
+            beacon: beacon.as_ref().map(|b| (b.round, &b.randomness[..])),
+            ..Default::default()
+        };
+        let vrf = Self::generate_vrf(&key, &Self::build_transcript(domain, &key.verifying_key, req, &ctx)?, &ctx)?;
+
+        sample_below(&vrf.output_hash, max, &mut self.compute_budget())
+    }
+
+    /// Roll a die for `req`, drawn from the output hash like
+    /// [`random_in_range`](Self::random_in_range) under [`DICE_DOMAIN`]
+    pub fn process_dice(&self, req: &DiceRequest) -> Result<DiceResponse, VfError> {
+        if !(1..=DICE_FACES).contains(&req.target) {
+            return Err(VfError::InvalidInput(format!("Dice target must be between 1 and {}", DICE_FACES)));
+        }
+        let bet = Self::dice_bet(req);
+        self.validate_request(&bet)?;
+        let start_time = std::time::Instant::now();
+
+        let key = self.current_key();
+        let beacon = self.beacon.as_ref().map(|b| b.latest()).transpose()?;
+        let ctx = TranscriptContext {
+            beacon: beacon.as_ref().map(|b| (b.round, &b.randomness[..])),
+            challenge_len: self.challenge_len()?,
+            ..Default::default()
+        };
+        let vrf = Self::generate_vrf(&key, &Self::build_transcript(DICE_DOMAIN, &key.verifying_key, &bet, &ctx)?, &ctx)?;
+        let roll = Self::roll_die(&vrf.output_hash, &mut self.compute_budget())?;
+
+        let proof = VrfProof {
+            seed_commitment: vrf.seed_commit,
+            vrf_output: Base64Engine.encode(vrf.random_value.to_le_bytes()),
+            signature: Base64Engine.encode(vrf.signature),
+            prev_output: None,
+            beacon_round: beacon.as_ref().map(|b| b.round),
+            beacon_randomness: beacon.as_ref().map(|b| hex::encode(&b.randomness)),
+            output_hash: self.config.full_output_hash.then(|| hex::encode(vrf.output_hash)),
+            win_probability_bps: None,
+            outcome_bit: None,
+            challenge_len: ctx.challenge_len,
+            node_seed: None,
+            bet_id: None,
+            challenge: hex::encode(vrf.challenge),
+        };
+
+        Ok(DiceResponse {
+            node_id: Base64Engine.encode(key.verifying_key.as_bytes()),
+            roll,
+            win: roll == req.target,
             proof,
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
-            processing_time_ms: processing_time,
+            processing_time_ms: start_time.elapsed().as_millis() as u64,
         })
     }
 
+    /// Dice counterpart of [`verify_proof`](Self::verify_proof)
+    pub fn verify_dice_proof(&self, proof: &VrfProof, req: &DiceRequest) -> Result<bool, VfError> {
+        Self::verify_with_key(DICE_DOMAIN, &self.verifying_key(), proof, &Self::dice_bet(req))
+    }
+
+    /// Recompute a dice proof's roll from its signature
+    pub fn dice_roll_from_proof(proof: &VrfProof) -> Result<u8, VfError> {
+        let signature = Base64Engine.decode(&proof.signature)
+            .map_err(|_| VfError::InvalidProof("Invalid signature encoding".to_string()))?;
+        Self::roll_die(&Self::output_hash(&signature), &mut ComputeBudget::new(ComputeBudget::DEFAULT_LIMIT))
+    }
+
+    /// The transcript fields a dice request shares with a coinflip
+    fn dice_bet(req: &DiceRequest) -> CoinflipRequest {
+        CoinflipRequest {
+            user_seed: req.user_seed.clone(),
+            timestamp: req.timestamp,
+            ..Default::default()
+        }
+    }
+
+    /// What a settled flip owes the player: the wager times the payout multiplier on a win,
+    /// nothing on a loss
+    fn payout_lamports(&self, wager_lamports: u64, win: bool) -> Result<u64, VfError> {
+        if !win {
+            return Ok(0);
+        }
+        wager_lamports
+            .checked_mul(u64::from(self.config.payout_multiplier_bps))
+            .map(|scaled| scaled / BPS_SCALE)
+            .ok_or_else(|| VfError::InvalidInput(format!("Wager of {} lamports overflows its payout", wager_lamports)))
+    }
+
+    fn roll_die(output_hash: &[u8; 32], budget: &mut ComputeBudget) -> Result<u8, VfError> {
+        Ok(sample_below(output_hash, DICE_FACES.into(), budget)? as u8 + 1)
+    }
+
     #[inline]
     fn validate_request(&self, req: &CoinflipRequest) -> Result<(), VfError> {
         if req.user_seed.is_empty() {
             return Err(VfError::InvalidInput("User seed cannot be empty".to_string()));
         }
-        if req.user_seed.len() > 1024 {
-            return Err(VfError::InvalidInput("User seed too long".to_string()));
+        // Limits apply to the decoded bytes, the entropy that actually reaches the transcript
+        let seed_len = req.seed_bytes()?.len();
+        if seed_len < self.config.min_seed_len {
+            return Err(VfError::InvalidInput(format!(
+                "User seed too short: {} bytes, minimum is {}",
+                seed_len, self.config.min_seed_len
+            )));
+        }
+        if seed_len > self.config.max_seed_len {
+            return Err(VfError::InvalidInput(format!(
+                "User seed too long: {} bytes, maximum is {}",
+                seed_len, self.config.max_seed_len
+            )));
+        }
+        if req.client_seed.as_ref().is_some_and(|s| s.len() > 1024) {
+            return Err(VfError::InvalidInput("Client seed too long".to_string()));
+        }
+        if req.wager_lamports == Some(0) {
+            return Err(VfError::InvalidInput("Wager must be positive".to_string()));
+        }
+        if let Some(wager) = req.wager_lamports {
+            let mint = req.token_mint.as_deref().unwrap_or(NATIVE_TOKEN_MINT);
+            if !self.config.allowed_mints.is_empty() && !self.config.allowed_mints.contains(mint) {
+                return Err(VfError::InvalidInput(format!("Token mint '{}' is not accepted", mint)));
+            }
+            if wager < self.config.min_wager_lamports {
+                return Err(VfError::InvalidInput(format!(
+                    "Wager too small: {} lamports, minimum is {}",
+                    wager, self.config.min_wager_lamports
+                )));
+            }
+            if wager > self.config.max_wager_lamports {
+                return Err(VfError::InvalidInput(format!(
+                    "Wager too large: {} lamports, maximum is {}",
+                    wager, self.config.max_wager_lamports
+                )));
+            }
+            // Rejected up front whatever the outcome, so a loss can't go through where a win would fail
+            self.payout_lamports(wager, true)?;
+        }
+        if req.wallet_sig.as_deref().is_some_and(str::is_empty) {
+            return Err(VfError::InvalidInput("wallet_sig cannot be empty".to_string()));
+        }
+        if self.config.seed_chaining && req.player_pubkey.as_deref().unwrap_or_default().is_empty() {
+            return Err(VfError::InvalidInput("player_pubkey is required in seed-chaining mode".to_string()));
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        self.check_timestamp(req.timestamp, now)?;
+        self.check_wallet_sig(req)
+    }
+
+    /// Verify `wallet_sig` as the player's ed25519 signature over
+    /// [`CoinflipRequest::wallet_message`], with `player_pubkey` as the base58 public key
+    pub fn check_wallet_sig(&self, req: &CoinflipRequest) -> Result<(), VfError> {
+        let Some(sig) = req.wallet_sig.as_deref() else {
+            if self.config.require_wallet_sig && req.wager_lamports.is_some() && !req.free_play {
+                return Err(VfError::BadWalletSignature("wallet_sig is required for wagered bets".to_string()));
+            }
+            return Ok(());
+        };
+        let pubkey = req
+            .player_pubkey
+            .as_deref()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| VfError::BadWalletSignature("player_pubkey is required with wallet_sig".to_string()))?;
+
+        let pubkey: [u8; 32] = bs58::decode(pubkey)
+            .into_vec()
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| VfError::BadWalletSignature("player_pubkey is not a base58 32-byte key".to_string()))?;
+        let verifying_key = VerifyingKey::from_bytes(&pubkey)
+            .map_err(|_| VfError::BadWalletSignature("player_pubkey is not a valid ed25519 key".to_string()))?;
+        let signature: [u8; 64] = bs58::decode(sig)
+            .into_vec()
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| VfError::BadWalletSignature("wallet_sig is not a base58 64-byte signature".to_string()))?;
+
+        verifying_key
+            .verify_strict(&req.wallet_message(), &Signature::from_bytes(&signature))
+            .map_err(|_| VfError::BadWalletSignature("wallet_sig does not match the bet and player_pubkey".to_string()))
+    }
+
+    /// Check a request timestamp against `now` (both Unix seconds) and the configured
+    /// freshness window
+    pub fn check_timestamp(&self, timestamp: u64, now: u64) -> Result<(), VfError> {
+        let Some(max_age) = self.config.max_request_age else {
+            return Ok(());
+        };
+        if timestamp.saturating_add(max_age.as_secs()) < now {
+            return Err(VfError::InvalidTimestamp(format!(
+                "timestamp {} is more than {}s old",
+                timestamp,
+                max_age.as_secs()
+            )));
+        }
+        if timestamp > now.saturating_add(TIMESTAMP_SKEW_TOLERANCE.as_secs()) {
+            return Err(VfError::InvalidTimestamp(format!(
+                "timestamp {} is more than {}s in the future",
+                timestamp,
+                TIMESTAMP_SKEW_TOLERANCE.as_secs()
+            )));
         }
         Ok(())
     }
 
+    /// Canonical form of the VRF challenge inputs. Both proving and verification build
+    /// their transcript from this, so the signed input is byte-identical on both sides.
+    /// Fails only when `user_seed` doesn't decode under its `seed_encoding`.
+    pub fn signing_payload<'a>(
+        domain: &'static [u8],
+        verifying_key: &'a VerifyingKey,
+        req: &'a CoinflipRequest,
+        ctx: &TranscriptContext<'a>,
+    ) -> Result<CanonicalPayload<'a>, VfError> {
+        let mut payload = CanonicalPayload::new(domain)
+            .decoded_bytes(b"user_seed", req.seed_bytes()?)
+            .bytes(b"node_pubkey", verifying_key.as_bytes())
+            .u64(b"timestamp", req.timestamp);
+
+        // Only labelled when supplied, so proofs from before client seeds existed still verify
+        if let Some(client_seed) = &req.client_seed {
+            payload = payload.bytes(b"client_seed", client_seed.as_bytes());
+        }
+        // Only for a client-chosen bet id, so anonymous proofs keep their original transcript
+        if let Some(bet_id) = ctx.bet_id {
+            payload = payload.bytes(b"bet_id", bet_id.as_bytes());
+        }
+        // Only for commit-reveal flips, so other proofs keep their original transcript
+        if let Some(node_seed) = ctx.node_seed {
+            payload = payload.bytes(b"node_seed", node_seed);
+        }
+        // Only present in chain mode, so unchained proofs keep their original transcript
+        if let Some(prev_output) = ctx.prev_output {
+            payload = payload.bytes(b"prev_output", prev_output);
+        }
+        if let Some((round, randomness)) = ctx.beacon {
+            payload = payload
+                .u64(b"beacon_round", round)
+                .bytes(b"beacon_randomness", randomness);
+        }
+        // Only for a house edge, so fair proofs keep their original transcript
+        if let Some(bps) = ctx.win_probability_bps {
+            payload = payload.u64(b"win_probability_bps", bps.into());
+        }
+        // Only for a non-default strategy, so lsb proofs keep their original transcript
+        if let Some(outcome_bit) = ctx.outcome_bit {
+            payload = payload.bytes(b"outcome_bit", outcome_bit.as_str().as_bytes());
+        }
+        // Only for a non-default length, so 64-byte proofs keep their original transcript
+        if let Some(len) = ctx.challenge_len {
+            payload = payload.u64(b"challenge_len", len.into());
+        }
+        // Free play gets its own outcome, so a demo flip can't preview a real bet's result
+        if req.free_play {
+            payload = payload.u64(b"free_play", 1);
+        }
+        Ok(payload)
+    }
+
     #[inline]
-    fn build_transcript(&self, req: &CoinflipRequest) -> Transcript {
-        let mut transcript = Transcript::new(b"vf_coinflip");
-        transcript.append_message(b"user_seed", req.user_seed.as_bytes());
-        transcript.append_message(b"node_pubkey", self.verifying_key.as_bytes());
-        transcript.append_u64(b"timestamp", req.timestamp);
-        transcript
+    fn build_transcript(
+        domain: &'static [u8],
+        verifying_key: &VerifyingKey,
+        req: &CoinflipRequest,
+        ctx: &TranscriptContext,
+    ) -> Result<SigningInput, VfError> {
+        let payload = Self::signing_payload(domain, verifying_key, req, ctx)?;
+        Ok(SigningInput { transcript: payload.to_transcript(), seed_commit: Self::seed_commitment(&payload) })
     }
 
+    /// Commitment to one request under one node key: a hash of the canonical payload, which
+    /// covers the node key, every request field in the transcript and the node-side context.
+    /// It can be published before the outcome is revealed and checked against the request after.
     #[inline]
-    fn generate_vrf(&self, transcript: &Transcript) -> Result<(u64, Vec<u8>, String), VfError> {
-        let mut hash_transcript = transcript.clone();
-        
-        // Create seed commitment
+    fn seed_commitment(payload: &CanonicalPayload) -> [u8; 32] {
         let mut hasher = Sha256::new();
-        hasher.update(self.verifying_key.as_bytes());
-        let seed_commit = hasher.finalize();
-        let seed_commit_str = Base64Engine.encode(&seed_commit);
-        
-        // Generate random value using VRF-like construction
-        hash_transcript.append_message(b"seed_commit", &seed_commit);
+        hasher.update(b"seed_commit");
+        hasher.update(payload.to_bytes());
+        hasher.finalize().into()
+    }
+
+    /// The commitment proofs carried before it was bound to the request: a hash of the node
+    /// key alone. Still accepted for requests before [`LEGACY_COMMITMENT_CUTOVER`] so stored
+    /// proofs keep verifying.
+    #[inline]
+    fn legacy_seed_commitment(verifying_key: &VerifyingKey) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(verifying_key.as_bytes());
+        hasher.finalize().into()
+    }
+
+    #[inline]
+    fn challenge(transcript: &Transcript, seed_commit: &[u8], len: usize) -> Vec<u8> {
+        let mut hash_transcript = transcript.clone();
+        hash_transcript.append_message(b"seed_commit", seed_commit);
+
+        let mut challenge_bytes = vec![0u8; len];
+        hash_transcript.challenge_bytes(b"challenge", &mut challenge_bytes);
+        challenge_bytes
+    }
+
+    #[inline]
+    fn generate_vrf(key: &NodeKey, input: &SigningInput, ctx: &TranscriptContext) -> Result<VrfOutput, VfError> {
+        let seed_commit_str = Base64Engine.encode(input.seed_commit);
         
         // Challenge
-        let mut challenge_bytes = [0u8; 64];
-        hash_transcript.challenge_bytes(b"challenge", &mut challenge_bytes);
+        let challenge_bytes =
+            Self::challenge(&input.transcript, &input.seed_commit, Self::checked_challenge_len(ctx.challenge_len)?);
         
         // Sign the challenge
-        let signature = self.signing_key.sign(&challenge_bytes);
+        let signature = key.signing_key.sign(&challenge_bytes);
         
         // Derive random value from signature (deterministic)
-        let mut output_hasher = Sha256::new();
-        output_hasher.update(signature.to_bytes());
-        let output_hash = output_hasher.finalize();
+        let output_hash = Self::output_hash(&signature.to_bytes());
         
         // Convert to u64 for game logic
         let mut value_bytes = [0u8; 8];
         value_bytes.copy_from_slice(&output_hash[..8]);
         let random_value = u64::from_le_bytes(value_bytes);
         
-        Ok((random_value, signature.to_bytes().to_vec(), seed_commit_str))
+        Ok(VrfOutput {
+            random_value,
+            signature: signature.to_bytes(),
+            output_hash,
+            seed_commit: seed_commit_str,
+            challenge: challenge_bytes,
+        })
     }
 
-    pub fn verify_proof(&self, proof: &VrfProof, req: &CoinflipRequest) -> Result<bool, VfError> {
-        // Rebuild transcript
-        let transcript = self.build_transcript(req);
-        
-        // Decode proof components
-        let seed_commit = Base64Engine.decode(&proof.seed_commitment)
-            .map_err(|_| VfError::InvalidProof("Invalid seed commitment encoding".to_string()))?;
-        
-        let signature_bytes = Base64Engine.decode(&proof.signature)
-            .map_err(|_| VfError::InvalidProof("Invalid signature encoding".to_string()))?;
-        
-        if signature_bytes.len() != 64 {
-            return Err(VfError::InvalidProof("Invalid signature length".to_string()));
+    #[inline]
+    fn output_hash(signature: &[u8]) -> [u8; 32] {
+        let mut output_hasher = Sha256::new();
+        output_hasher.update(signature);
+        output_hasher.finalize().into()
+    }
+
+    /// Resume seed chains after a restart from stored `(player_pubkey, proof)` pairs, given
+    /// oldest first and covering at least each player's last round. A player's head is the
+    /// round no other round of theirs links back to; with several, the last given wins.
+    /// Players who already flipped since startup keep their chain. Returns how many
+    /// players' heads were restored.
+    pub fn restore_chain_heads(&self, bets: &[(String, VrfProof)]) -> usize {
+        let mut outputs: HashMap<&str, Vec<[u8; 32]>> = HashMap::new();
+        let mut linked: HashSet<(&str, String)> = HashSet::new();
+        for (player, proof) in bets {
+            let Ok(signature) = Base64Engine.decode(&proof.signature) else { continue };
+            outputs.entry(player).or_default().push(Self::output_hash(&signature));
+            if let Some(prev_output) = &proof.prev_output {
+                linked.insert((player, prev_output.clone()));
+            }
         }
-        
-        let mut sig_array = [0u8; 64];
-        sig_array.copy_from_slice(&signature_bytes);
-        
-        let signature = Signature::from_bytes(&sig_array);
-        
-        // Verify signature
-        let mut hash_transcript = transcript.clone();
-        hash_transcript.append_message(b"seed_commit", &seed_commit);
-        
-        let mut challenge_bytes = [0u8; 64];
-        hash_transcript.challenge_bytes(b"challenge", &mut challenge_bytes);
-        
-        self.verifying_key.verify(&challenge_bytes, &signature)
-            .map_err(|_| VfError::InvalidProof("Signature verification failed".to_string()))?;
-        
-        Ok(true)
+
+        let mut restored = 0;
+        for (player, outputs) in outputs {
+            let head = outputs.into_iter().rev().find(|output| !linked.contains(&(player, hex::encode(output))));
+            if let Some(head) = head {
+                self.chains.restore(player, head);
+                restored += 1;
+            }
+        }
+        restored
     }
-}
 
-// Thread-safe: VrfEngine can be shared across threads
-unsafe impl Send for VrfEngine {}
-unsafe impl Sync for VrfEngine {}
+    /// Expose the exact bytes `generate_vrf` signs for a request (debug tooling only)
+    pub fn debug_transcript(&self, req: &CoinflipRequest) -> Result<TranscriptDebug, VfError> {
+        let verifying_key = self.verifying_key();
+        let prev_output = self
+            .config
+            .seed_chaining
+            .then(|| self.chains.head(req.player_pubkey.as_deref().unwrap_or_default()))
+            .flatten();
+        let beacon = self.beacon.as_ref().and_then(|b| b.latest().ok());
+        let ctx = TranscriptContext {
+            prev_output: prev_output.as_ref().map(|p| &p[..]),
+            beacon: beacon.as_ref().map(|b| (b.round, &b.randomness[..])),
+            win_probability_bps: self.house_edge(),
+            outcome_bit: self.outcome_bit(),
+            challenge_len: self.challenge_len()?,
+            ..Default::default()
+        };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let input = Self::build_transcript(COINFLIP_DOMAIN, &verifying_key, req, &ctx)?;
+        let seed_commit = input.seed_commit;
+        let challenge = Self::challenge(&input.transcript, &seed_commit, Self::checked_challenge_len(ctx.challenge_len)?);
 
-    #[test]
-    fn test_vrf_engine_creation() {
-        let engine = VrfEngine::new();
-        assert!(!engine.node_pubkey().is_empty());
+        Ok(TranscriptDebug {
+            canonical_payload: hex::encode(Self::signing_payload(COINFLIP_DOMAIN, &verifying_key, req, &ctx)?.to_bytes()),
+            seed_commitment: Base64Engine.encode(seed_commit),
+            challenge: hex::encode(challenge),
+        })
     }
 
-    #[test]
-    fn test_deterministic_generation() {
-        let seed = [1u8; 32];
-        let engine1 = VrfEngine::from_seed(seed);
-        let engine2 = VrfEngine::from_seed(seed);
-        
-        assert_eq!(engine1.node_pubkey(), engine2.node_pubkey());
+    pub fn verify_proof(&self, proof: &VrfProof, req: &CoinflipRequest) -> Result<bool, VfError> {
+        Self::verify_with_key(COINFLIP_DOMAIN, &self.verifying_key(), proof, req)
+    }
+
+    /// Verify a proof against a base64 node public key (as advertised in `/info`)
+    pub fn verify_with_pubkey(node_pubkey: &str, req: &CoinflipRequest, proof: &VrfProof) -> Result<bool, VfError> {
+        Self::verify_with_key(COINFLIP_DOMAIN, &Self::decode_pubkey(node_pubkey)?, proof, req)
+    }
+
+    /// Sign a settlement receipt with the current node key, stamping in that key
+    pub fn sign_receipt(&self, mut receipt: SettlementReceipt) -> SettlementReceipt {
+        let key = self.current_key();
+        receipt.node_pubkey = Base64Engine.encode(key.verifying_key.as_bytes());
+        let signature = key.signing_key.sign(&receipt.payload().to_bytes());
+        receipt.signature = Base64Engine.encode(signature.to_bytes());
+        receipt
+    }
+
+    /// Check a receipt's signature against the key it names
+    pub fn verify_receipt(receipt: &SettlementReceipt) -> Result<bool, VfError> {
+        let verifying_key = Self::decode_pubkey(&receipt.node_pubkey)?;
+        let signature_bytes: [u8; 64] = Base64Engine.decode(&receipt.signature)
+            .map_err(|_| VfError::InvalidProof("Invalid signature encoding".to_string()))?
+            .try_into()
+            .map_err(|_| VfError::InvalidProof("Invalid signature length".to_string()))?;
+
+        let signature = Signature::from_bytes(&signature_bytes);
+        Ok(verifying_key.verify(&receipt.payload().to_bytes(), &signature).is_ok())
+    }
+
+    fn decode_pubkey(node_pubkey: &str) -> Result<VerifyingKey, VfError> {
+        let key_bytes: [u8; 32] = Base64Engine.decode(node_pubkey)
+            .map_err(|_| VfError::InvalidProof("Invalid node pubkey encoding".to_string()))?
+            .try_into()
+            .map_err(|_| VfError::InvalidProof("Invalid node pubkey length".to_string()))?;
+
+        VerifyingKey::from_bytes(&key_bytes)
+            .map_err(|_| VfError::InvalidProof("Invalid node pubkey".to_string()))
+    }
+
+    /// Rebuild a full proof from the node key and signature, for rows that only stored the
+    /// signature. Every other component is derived from those two; such rows predate
+    /// request-bound commitments, so the seed commitment is the legacy key hash.
+    pub fn proof_from_signature(node_pubkey: &str, signature: &str) -> Result<VrfProof, VfError> {
+        let verifying_key = Self::decode_pubkey(node_pubkey)?;
+        let signature_bytes = Base64Engine.decode(signature)
+            .map_err(|_| VfError::InvalidProof("Invalid signature encoding".to_string()))?;
+        let output_hash = Self::output_hash(&signature_bytes);
+
+        Ok(VrfProof {
+            seed_commitment: Base64Engine.encode(Self::legacy_seed_commitment(&verifying_key)),
+            vrf_output: Base64Engine.encode(&output_hash[..8]),
+            signature: signature.to_string(),
+            prev_output: None,
+            beacon_round: None,
+            beacon_randomness: None,
+            output_hash: None,
+            win_probability_bps: None,
+            outcome_bit: None,
+            challenge_len: None,
+            node_seed: None,
+            bet_id: None,
+            challenge: String::new(),
+        })
+    }
+
+    /// Recompute a proof's raw random value from its signature, the same way `process_coinflip` does
+    pub fn random_value_from_proof(proof: &VrfProof) -> Result<u64, VfError> {
+        let signature = Base64Engine.decode(&proof.signature)
+            .map_err(|_| VfError::InvalidProof("Invalid signature encoding".to_string()))?;
+        let output_hash = Self::output_hash(&signature);
+
+        let mut value_bytes = [0u8; 8];
+        value_bytes.copy_from_slice(&output_hash[..8]);
+        Ok(u64::from_le_bytes(value_bytes))
+    }
+
+    /// Recompute a proof's coin side from its signature and the side the player picked
+    pub fn outcome_from_proof(proof: &VrfProof, player_choice: CoinSide) -> Result<bool, VfError> {
+        let signature = Base64Engine.decode(&proof.signature)
+            .map_err(|_| VfError::InvalidProof("Invalid signature encoding".to_string()))?;
+        let mut budget = ComputeBudget::new(ComputeBudget::DEFAULT_LIMIT);
+        let ctx = TranscriptContext {
+            win_probability_bps: proof.win_probability_bps,
+            outcome_bit: proof.outcome_bit,
+            ..Default::default()
+        };
+        Self::decide_heads(&Self::output_hash(&signature), &ctx, player_choice, &mut budget)
+    }
+
+    /// Win probability to commit to, when it isn't a fair flip
+    fn house_edge(&self) -> Option<u16> {
+        let bps = self.config.win_probability_bps;
+        (bps != FAIR_WIN_PROBABILITY_BPS).then_some(bps)
+    }
+
+    /// Challenge length to commit to, when it isn't the original 64 bytes. A configured length
+    /// no verifier accepts is refused rather than truncated into one.
+    fn challenge_len(&self) -> Result<Option<u8>, VfError> {
+        let len = self.config.challenge_len;
+        let committed = u8::try_from(len)
+            .ok()
+            .filter(|_| CHALLENGE_LENS.contains(&len))
+            .ok_or_else(|| VfError::VrfFailed(format!("Unsupported configured challenge_len {}", len)))?;
+        Ok((len != DEFAULT_CHALLENGE_LEN).then_some(committed))
+    }
+
+    /// Byte length of the challenge a context calls for, rejecting lengths no node signs
+    fn checked_challenge_len(challenge_len: Option<u8>) -> Result<usize, VfError> {
+        let len = challenge_len.map_or(DEFAULT_CHALLENGE_LEN, usize::from);
+        if !CHALLENGE_LENS.contains(&len) {
+            return Err(VfError::InvalidProof(format!("Unsupported challenge_len {}", len)));
+        }
+        Ok(len)
+    }
+
+    /// Outcome strategy to commit to, when it isn't the original lsb rule
+    fn outcome_bit(&self) -> Option<OutcomeBit> {
+        let outcome_bit = self.config.outcome_bit;
+        (outcome_bit != OutcomeBit::Lsb).then_some(outcome_bit)
+    }
+
+    /// A fair flip reads the random value with the committed `OutcomeBit`. With a house edge
+    /// the player wins when a uniform roll in `[0, BPS_SCALE)` lands under the threshold, and
+    /// the coin shows their side exactly when they win.
+    fn decide_heads(
+        output_hash: &[u8; 32],
+        ctx: &TranscriptContext,
+        player_choice: CoinSide,
+        budget: &mut ComputeBudget,
+    ) -> Result<bool, VfError> {
+        let Some(bps) = ctx.win_probability_bps else {
+            let mut value_bytes = [0u8; 8];
+            value_bytes.copy_from_slice(&output_hash[..8]);
+            return Ok(ctx.outcome_bit.unwrap_or_default().heads(u64::from_le_bytes(value_bytes)));
+        };
+        let win = sample_below(output_hash, BPS_SCALE, budget)? < u64::from(bps);
+        Ok(player_choice.wins(win))
+    }
+
+    fn verify_with_key(
+        domain: &'static [u8],
+        verifying_key: &VerifyingKey,
+        proof: &VrfProof,
+        req: &CoinflipRequest,
+    ) -> Result<bool, VfError> {
+        let (challenge_bytes, seed_commit) = Self::proof_challenge(domain, verifying_key, proof, req)?;
+        if !proof.challenge.is_empty() {
+            Self::check_challenge(&proof.challenge, &challenge_bytes)?;
+        }
+
+        // Decode proof components
+        let signature_bytes = Base64Engine.decode(&proof.signature)
+            .map_err(|_| VfError::InvalidProof("Invalid signature encoding".to_string()))?;
+        
+        if signature_bytes.len() != 64 {
+            return Err(VfError::InvalidProof("Invalid signature length".to_string()));
+        }
+        
+        let mut sig_array = [0u8; 64];
+        sig_array.copy_from_slice(&signature_bytes);
+        
+        let signature = Signature::from_bytes(&sig_array);
+        
+        // Verify signature
+        verifying_key.verify(&challenge_bytes, &signature)
+            .map_err(|_| VfError::InvalidProof("Signature verification failed".to_string()))?;
+
+        // A signature over a commitment the request doesn't call for proves nothing about the
+        // commitment a client was shown before the reveal
+        let stated = Base64Engine.encode(seed_commit);
+        let legacy = (req.timestamp < LEGACY_COMMITMENT_CUTOVER)
+            .then(|| Base64Engine.encode(Self::legacy_seed_commitment(verifying_key)));
+        if proof.seed_commitment != stated && Some(&proof.seed_commitment) != legacy.as_ref() {
+            return Err(VfError::InvalidProof("Seed commitment does not match request".to_string()));
+        }
+
+        if let Some(full) = &proof.output_hash {
+            Self::check_output_hash(full, &proof.vrf_output, &signature_bytes)?;
+        }
+        
+        Ok(true)
+    }
+
+    /// The bytes a proof's signature must cover, rebuilt from the request and the context
+    /// the proof records around the proof's own seed commitment, and the commitment the
+    /// request calls for
+    fn proof_challenge(
+        domain: &'static [u8],
+        verifying_key: &VerifyingKey,
+        proof: &VrfProof,
+        req: &CoinflipRequest,
+    ) -> Result<(Vec<u8>, [u8; 32]), VfError> {
+        // Rebuild transcript
+        let prev_output = proof.prev_output.as_deref()
+            .map(hex::decode)
+            .transpose()
+            .map_err(|_| VfError::InvalidProof("Invalid prev_output encoding".to_string()))?;
+        let beacon_randomness = proof.beacon_randomness.as_deref()
+            .map(hex::decode)
+            .transpose()
+            .map_err(|_| VfError::InvalidProof("Invalid beacon_randomness encoding".to_string()))?;
+        let beacon = match (proof.beacon_round, beacon_randomness.as_deref()) {
+            (Some(round), Some(randomness)) => Some((round, randomness)),
+            (None, None) => None,
+            _ => return Err(VfError::InvalidProof("Incomplete beacon data".to_string())),
+        };
+        let node_seed = proof.node_seed.as_deref()
+            .map(hex::decode)
+            .transpose()
+            .map_err(|_| VfError::InvalidProof("Invalid node_seed encoding".to_string()))?;
+        // A proof bound to a bet id answers that bet only
+        if proof.bet_id.is_some() && proof.bet_id != req.bet_id {
+            return Err(VfError::InvalidProof("Bet id does not match request".to_string()));
+        }
+        let ctx = TranscriptContext {
+            prev_output: prev_output.as_deref(),
+            beacon,
+            win_probability_bps: proof.win_probability_bps,
+            outcome_bit: proof.outcome_bit,
+            challenge_len: proof.challenge_len,
+            node_seed: node_seed.as_deref(),
+            bet_id: proof.bet_id.as_ref(),
+        };
+        let len = Self::checked_challenge_len(ctx.challenge_len)?;
+        let input = Self::build_transcript(domain, verifying_key, req, &ctx)?;
+
+        let seed_commit = Base64Engine.decode(&proof.seed_commitment)
+            .map_err(|_| VfError::InvalidProof("Invalid seed commitment encoding".to_string()))?;
+
+        Ok((Self::challenge(&input.transcript, &seed_commit, len), input.seed_commit))
+    }
+
+    /// A coinflip proof as fixed-size byte arrays for an on-chain verifier. The proof is
+    /// verified first, so only proofs `verify_proof` accepts are exported.
+    pub fn onchain_proof(node_pubkey: &str, req: &CoinflipRequest, proof: &VrfProof) -> Result<OnchainProof, VfError> {
+        let verifying_key = Self::decode_pubkey(node_pubkey)?;
+        Self::verify_with_key(COINFLIP_DOMAIN, &verifying_key, proof, req)?;
+
+        let signature: [u8; 64] = Base64Engine.decode(&proof.signature)
+            .map_err(|_| VfError::InvalidProof("Invalid signature encoding".to_string()))?
+            .try_into()
+            .map_err(|_| VfError::InvalidProof("Invalid signature length".to_string()))?;
+        Ok(OnchainProof {
+            node_pubkey: verifying_key.to_bytes(),
+            signature,
+            challenge: Self::proof_challenge(COINFLIP_DOMAIN, &verifying_key, proof, req)?
+                .0
+                .try_into()
+                .map_err(|_| VfError::InvalidProof("On-chain proofs need a 64-byte challenge".to_string()))?,
+        })
+    }
+
+    /// A proof's stated challenge must be the one its transcript rebuilds to, so a verifier
+    /// that diverges from the node sees that before the signature check
+    fn check_challenge(stated: &str, rebuilt: &[u8]) -> Result<(), VfError> {
+        let stated = hex::decode(stated)
+            .map_err(|_| VfError::InvalidProof("Invalid challenge encoding".to_string()))?;
+        if stated != rebuilt[..] {
+            return Err(VfError::InvalidProof("Challenge does not match transcript".to_string()));
+        }
+        Ok(())
+    }
+
+    /// A full output hash must be the hash of the signature, and `vrf_output` its prefix
+    fn check_output_hash(full: &str, vrf_output: &str, signature: &[u8]) -> Result<(), VfError> {
+        let full = hex::decode(full)
+            .map_err(|_| VfError::InvalidProof("Invalid output_hash encoding".to_string()))?;
+        if full != Self::output_hash(signature) {
+            return Err(VfError::InvalidProof("Output hash does not match signature".to_string()));
+        }
+        let truncated = Base64Engine.decode(vrf_output)
+            .map_err(|_| VfError::InvalidProof("Invalid vrf_output encoding".to_string()))?;
+        if truncated != full[..8] {
+            return Err(VfError::InvalidProof("vrf_output does not match output hash".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Verify a player's consecutive chained rounds: every proof must verify and each
+    /// round's `prev_output` must be the output hash of the round before it
+    pub fn verify_chain(&self, rounds: &[(CoinflipRequest, CoinflipResponse)]) -> Result<bool, VfError> {
+        let mut expected_prev: Option<String> = None;
+
+        for (i, (req, response)) in rounds.iter().enumerate() {
+            if i > 0 && response.proof.prev_output != expected_prev {
+                return Err(VfError::InvalidProof(format!("Chain link broken at round {}", i)));
+            }
+            self.verify_proof(&response.proof, req)?;
+
+            let signature = Base64Engine.decode(&response.proof.signature)
+                .map_err(|_| VfError::InvalidProof("Invalid signature encoding".to_string()))?;
+            expected_prev = Some(hex::encode(Self::output_hash(&signature)));
+        }
+
+        Ok(true)
+    }
+}
+
+impl Default for VrfEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Thread-safe: VrfEngine can be shared across threads
+unsafe impl Send for VrfEngine {}
+unsafe impl Sync for VrfEngine {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beacon::BeaconRound;
+    use crate::types::SeedEncoding;
+
+    #[test]
+    fn test_vrf_engine_creation() {
+        let engine = VrfEngine::new();
+        assert!(!engine.node_pubkey().is_empty());
+    }
+
+    #[test]
+    fn test_pubkey_encodings_agree() {
+        let engine = VrfEngine::new();
+        let raw = engine.verifying_key().to_bytes();
+        assert_eq!(Base64Engine.decode(engine.node_pubkey()).unwrap(), raw);
+        assert_eq!(bs58::decode(engine.node_pubkey_base58()).into_vec().unwrap(), raw);
+        assert_eq!(hex::decode(engine.node_pubkey_hex()).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_onchain_proof_layout() {
+        let engine = VrfEngine::new();
+        let req = CoinflipRequest { user_seed: "onchain".to_string(), ..Default::default() };
+        let response = engine.process_coinflip(&req).unwrap();
+
+        let onchain = response.proof.to_onchain_bytes(&engine.node_pubkey(), &req).unwrap();
+        assert_eq!(onchain.node_pubkey, engine.verifying_key().to_bytes());
+        assert_eq!(hex::encode(onchain.challenge), engine.debug_transcript(&req).unwrap().challenge);
+        // What the program does: a bare Ed25519 check over the fixed-size arrays
+        VerifyingKey::from_bytes(&onchain.node_pubkey).unwrap()
+            .verify(&onchain.challenge, &Signature::from_bytes(&onchain.signature))
+            .unwrap();
+
+        #[cfg(feature = "borsh")]
+        {
+            let encoded = onchain.to_borsh();
+            assert_eq!(encoded.len(), OnchainProof::ENCODED_LEN);
+            assert_eq!(encoded[..32], onchain.node_pubkey);
+            assert_eq!(encoded[32..96], onchain.signature);
+            assert_eq!(encoded[96..], onchain.challenge);
+            assert_eq!(OnchainProof::from_borsh(&encoded).unwrap(), onchain);
+            assert!(OnchainProof::from_borsh(&encoded[..159]).is_err());
+        }
+
+        // A proof that doesn't verify is not exported
+        let other = CoinflipRequest { user_seed: "other".to_string(), ..req };
+        assert!(response.proof.to_onchain_bytes(&engine.node_pubkey(), &other).is_err());
+    }
+
+    #[test]
+    fn test_deterministic_generation() {
+        let seed = [1u8; 32];
+        let engine1 = VrfEngine::from_seed(seed);
+        let engine2 = VrfEngine::from_seed(seed);
+        
+        assert_eq!(engine1.node_pubkey(), engine2.node_pubkey());
     }
 
     #[test]
@@ -186,6 +1319,7 @@ mod tests {
         let req = CoinflipRequest {
             user_seed: "test_seed".to_string(),
             timestamp: 1234567890,
+            ..Default::default()
         };
         
         let result = engine.process_coinflip(&req);
@@ -198,12 +1332,34 @@ mod tests {
         assert!(!response.proof.signature.is_empty());
     }
 
+    #[test]
+    fn test_batch_blocking_matches_single_flips() {
+        let reqs: Vec<CoinflipRequest> = (0..16)
+            .map(|i| CoinflipRequest {
+                user_seed: if i == 5 { String::new() } else { format!("pipeline_{}", i) },
+                timestamp: 1234567890,
+                ..Default::default()
+            })
+            .collect();
+
+        let engine = VrfEngine::from_seed([9u8; 32]);
+        let results = engine.process_coinflip_batch_blocking(&reqs);
+        assert_eq!(results.len(), reqs.len());
+        for (i, (req, result)) in reqs.iter().zip(&results).enumerate() {
+            match engine.process_coinflip(req) {
+                Ok(single) => assert_eq!(result.as_ref().unwrap().proof.signature, single.proof.signature, "entry {}", i),
+                Err(_) => assert!(matches!(result, Err(VfError::InvalidInput(_))), "entry {}", i),
+            }
+        }
+    }
+
     #[test]
     fn test_proof_verification() {
         let engine = VrfEngine::new();
         let req = CoinflipRequest {
             user_seed: "test_seed".to_string(),
             timestamp: 1234567890,
+            ..Default::default()
         };
         
         let response = engine.process_coinflip(&req).unwrap();
@@ -219,6 +1375,7 @@ mod tests {
         let req = CoinflipRequest {
             user_seed: "test_seed".to_string(),
             timestamp: 1234567890,
+            ..Default::default()
         };
         
         let mut response = engine.process_coinflip(&req).unwrap();
@@ -229,4 +1386,1054 @@ mod tests {
         let verification = engine.verify_proof(&response.proof, &req);
         assert!(verification.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_signing_payload_fixed_vector() {
+        let engine = VrfEngine::from_seed([7u8; 32]);
+        let req = CoinflipRequest {
+            user_seed: "fixed_seed".to_string(),
+            timestamp: 1_700_000_000,
+            ..Default::default()
+        };
+
+        let key = engine.verifying_key();
+        let ctx = TranscriptContext::default();
+        let payload = VrfEngine::signing_payload(COINFLIP_DOMAIN, &key, &req, &ctx).unwrap().to_bytes();
+        assert_eq!(payload, VrfEngine::signing_payload(COINFLIP_DOMAIN, &key, &req, &ctx).unwrap().to_bytes());
+        assert_eq!(
+            hex::encode(&payload),
+            concat!(
+                "0b00000076665f636f696e666c6970",
+                "09000000757365725f73656564010a00000066697865645f73656564",
+                "0b0000006e6f64655f7075626b65790120000000",
+                "ea4a6c63e29c520abef5507b132ec5f9954776aebebe7b92421eea691446d22c",
+                "0900000074696d657374616d70020800000000f1536500000000",
+            )
+        );
+
+        // ed25519 signing is deterministic, so the whole proof is a fixed vector too
+        let response = engine.process_coinflip(&req).unwrap();
+        assert_eq!(response.proof.signature, "quHesB8kCvpCJeDMXHO5zO/S81Q5lwvPOM6Ikmz3doVXKQy96Ek3oiv78XshUPfhAjJkbpHynZjN2mPSRfJGCg==");
+        assert!(engine.verify_proof(&response.proof, &req).unwrap());
+
+        // Proofs signed under the old key-only commitment still verify
+        let legacy_signature = "ttz7q8YA26LHRF87UZakt26WruLJ3WM7ZcQULbZ+A3uPWHtoeYtWe5s+9iz+LR2pyUAJcf+tGQtNW3g7skDdBQ==";
+        assert_eq!(engine.legacy_signature(&req), legacy_signature);
+        let legacy = VrfEngine::proof_from_signature(&engine.node_pubkey(), legacy_signature).unwrap();
+        assert!(engine.verify_proof(&legacy, &req).unwrap());
+
+        // ...but only for requests made before the cutover
+        let late = CoinflipRequest { timestamp: LEGACY_COMMITMENT_CUTOVER, ..req.clone() };
+        let late_legacy = VrfEngine::proof_from_signature(&engine.node_pubkey(), &engine.legacy_signature(&late)).unwrap();
+        let err = engine.verify_proof(&late_legacy, &late).unwrap_err();
+        assert!(err.to_string().contains("Seed commitment does not match request"), "{}", err);
+    }
+
+    #[test]
+    fn test_player_choice_decides_win() {
+        use crate::types::CoinSide;
+
+        let engine = VrfEngine::from_seed([7u8; 32]);
+        let req = CoinflipRequest {
+            user_seed: "fixed_seed".to_string(),
+            timestamp: 1_700_000_000,
+            ..Default::default()
+        };
+        let implicit = engine.process_coinflip(&req).unwrap();
+        let heads = engine.process_coinflip(&CoinflipRequest { player_choice: CoinSide::Heads, ..req.clone() }).unwrap();
+        let tails = engine.process_coinflip(&CoinflipRequest { player_choice: CoinSide::Tails, ..req.clone() }).unwrap();
+
+        // The pick never changes the coin, only who wins it
+        assert_eq!(heads.proof.signature, tails.proof.signature);
+        assert_eq!(heads.heads, tails.heads);
+        assert_eq!(heads.win, heads.heads);
+        assert_eq!(tails.win, !tails.heads);
+        assert_eq!(implicit.win, heads.win);
+
+        // Clients that don't send a pick back heads
+        let parsed: CoinflipRequest = serde_json::from_str(r#"{"user_seed": "fixed_seed", "timestamp": 1700000000}"#).unwrap();
+        assert_eq!(parsed.player_choice, CoinSide::Heads);
+        let parsed: CoinflipRequest = serde_json::from_str(r#"{"user_seed": "s", "player_choice": "tails"}"#).unwrap();
+        assert_eq!(parsed.player_choice, CoinSide::Tails);
+    }
+
+    #[test]
+    fn test_client_seed_is_committed_only_when_present() {
+        let engine = VrfEngine::from_seed([7u8; 32]);
+        let req = CoinflipRequest {
+            user_seed: "fixed_seed".to_string(),
+            timestamp: 1_700_000_000,
+            ..Default::default()
+        };
+        let seeded = CoinflipRequest { client_seed: Some("c0ffee".to_string()), ..req.clone() };
+
+        // A proof signed before client seeds existed (see the fixed vector above) still verifies
+        let legacy = VrfEngine::proof_from_signature(
+            &engine.node_pubkey(),
+            "ttz7q8YA26LHRF87UZakt26WruLJ3WM7ZcQULbZ+A3uPWHtoeYtWe5s+9iz+LR2pyUAJcf+tGQtNW3g7skDdBQ==",
+        )
+        .unwrap();
+        assert!(engine.verify_proof(&legacy, &req).unwrap());
+
+        let response = engine.process_coinflip(&seeded).unwrap();
+        assert_ne!(response.proof.signature, legacy.signature);
+        assert!(engine.verify_proof(&response.proof, &seeded).unwrap());
+        // Dropping or swapping the client seed breaks verification both ways
+        assert!(engine.verify_proof(&response.proof, &req).is_err());
+        assert!(engine.verify_proof(&legacy, &seeded).is_err());
+        let other = CoinflipRequest { client_seed: Some("c0ffef".to_string()), ..req.clone() };
+        assert!(engine.verify_proof(&response.proof, &other).is_err());
+    }
+
+    #[test]
+    fn test_debug_transcript_matches_signed_challenge() {
+        let engine = VrfEngine::new();
+        let req = CoinflipRequest {
+            user_seed: "test_seed".to_string(),
+            timestamp: 1234567890,
+            ..Default::default()
+        };
+
+        let debug = engine.debug_transcript(&req).unwrap();
+        let response = engine.process_coinflip(&req).unwrap();
+        assert_eq!(debug.seed_commitment, response.proof.seed_commitment);
+        let key = engine.verifying_key();
+        assert_eq!(debug.canonical_payload, hex::encode(VrfEngine::signing_payload(COINFLIP_DOMAIN, &key, &req, &TranscriptContext::default()).unwrap().to_bytes()));
+
+        // The signature in the proof must be over exactly the reported challenge
+        let challenge = hex::decode(&debug.challenge).unwrap();
+        assert_eq!(challenge.len(), 64);
+        let sig_bytes: [u8; 64] = Base64Engine.decode(&response.proof.signature).unwrap().try_into().unwrap();
+        assert!(key.verify(&challenge, &Signature::from_bytes(&sig_bytes)).is_ok());
+    }
+
+    #[test]
+    fn test_proof_carries_checked_challenge() {
+        let engine = VrfEngine::new();
+        let req = CoinflipRequest {
+            user_seed: "challenged".to_string(),
+            timestamp: 1234567890,
+            ..Default::default()
+        };
+        let proof = engine.process_coinflip(&req).unwrap().proof;
+        assert_eq!(proof.challenge, engine.debug_transcript(&req).unwrap().challenge);
+        assert!(engine.verify_proof(&proof, &req).unwrap());
+
+        let message = |proof: &VrfProof, req: &CoinflipRequest| match engine.verify_proof(proof, req) {
+            Err(VfError::InvalidProof(message)) => message,
+            other => panic!("expected InvalidProof, got {:?}", other),
+        };
+        // A diverging transcript is reported as such, not as a bad signature
+        let other = CoinflipRequest { timestamp: 1234567891, ..req.clone() };
+        assert_eq!(message(&proof, &other), "Challenge does not match transcript");
+        let forged = VrfProof { challenge: hex::encode([0u8; 64]), ..proof.clone() };
+        assert_eq!(message(&forged, &req), "Challenge does not match transcript");
+        let garbled = VrfProof { challenge: "zz".to_string(), ..proof.clone() };
+        assert_eq!(message(&garbled, &req), "Invalid challenge encoding");
+
+        // Proofs issued before the field existed still verify on the signature alone
+        let legacy = VrfProof { challenge: String::new(), ..proof };
+        assert!(!serde_json::to_string(&legacy).unwrap().contains("challenge"));
+        assert!(engine.verify_proof(&legacy, &req).unwrap());
+        assert_eq!(message(&legacy, &other), "Signature verification failed");
+    }
+
+    #[test]
+    fn test_seed_commitment_binds_request() {
+        let engine = VrfEngine::from_seed([3u8; 32]);
+        let req = CoinflipRequest {
+            user_seed: "committed".to_string(),
+            timestamp: 1234567890,
+            ..Default::default()
+        };
+        let proof = engine.process_coinflip(&req).unwrap().proof;
+        assert_eq!(engine.process_coinflip(&req).unwrap().proof.seed_commitment, proof.seed_commitment);
+        assert_eq!(engine.debug_transcript(&req).unwrap().seed_commitment, proof.seed_commitment);
+
+        // Any other request, or the same request under another key, commits differently
+        for other in [
+            CoinflipRequest { user_seed: "committed2".to_string(), ..req.clone() },
+            CoinflipRequest { timestamp: 1234567891, ..req.clone() },
+            CoinflipRequest { client_seed: Some("client".to_string()), ..req.clone() },
+        ] {
+            assert_ne!(engine.process_coinflip(&other).unwrap().proof.seed_commitment, proof.seed_commitment);
+        }
+        let other_key = VrfEngine::from_seed([4u8; 32]).process_coinflip(&req).unwrap().proof;
+        assert_ne!(other_key.seed_commitment, proof.seed_commitment);
+
+        // A node that signs over a commitment the request doesn't call for is caught
+        let key = engine.current_key();
+        let ctx = TranscriptContext::default();
+        let mut input = VrfEngine::build_transcript(COINFLIP_DOMAIN, &key.verifying_key, &req, &ctx).unwrap();
+        input.seed_commit = [0u8; 32];
+        let vrf = VrfEngine::generate_vrf(&key, &input, &ctx).unwrap();
+        let bogus = VrfProof {
+            seed_commitment: vrf.seed_commit,
+            signature: Base64Engine.encode(vrf.signature),
+            challenge: hex::encode(vrf.challenge),
+            ..proof
+        };
+        match engine.verify_proof(&bogus, &req) {
+            Err(VfError::InvalidProof(message)) => assert_eq!(message, "Seed commitment does not match request"),
+            other => panic!("expected InvalidProof, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_committed_node_seed_is_mixed_in() {
+        let engine = VrfEngine::new();
+        let req = CoinflipRequest {
+            user_seed: "committed_flip".to_string(),
+            timestamp: 1234567890,
+            ..Default::default()
+        };
+        let plain = engine.process_coinflip(&req).unwrap();
+        let committed = engine.process_committed_coinflip(&req, &[8u8; 32]).unwrap();
+        let other_seed = engine.process_committed_coinflip(&req, &[9u8; 32]).unwrap();
+
+        assert_eq!(plain.proof.node_seed, None);
+        assert_eq!(committed.proof.node_seed, Some(hex::encode([8u8; 32])));
+        assert_ne!(committed.proof.signature, plain.proof.signature);
+        assert_ne!(committed.proof.signature, other_seed.proof.signature);
+        assert!(engine.verify_proof(&committed.proof, &req).unwrap());
+
+        // The revealed seed is part of the transcript, so it can't be swapped or dropped
+        let swapped = VrfProof { node_seed: other_seed.proof.node_seed.clone(), ..committed.proof.clone() };
+        assert!(engine.verify_proof(&swapped, &req).is_err());
+        let dropped = VrfProof { node_seed: None, ..committed.proof.clone() };
+        assert!(engine.verify_proof(&dropped, &req).is_err());
+
+        let replay = engine.replay_coinflip(&req, &committed.proof).unwrap();
+        assert!(replay.matches_stored);
+    }
+
+    #[test]
+    fn test_proofs_round_trip_at_each_challenge_len() {
+        let seed = [5u8; 32];
+        let req = CoinflipRequest {
+            user_seed: "lengths".to_string(),
+            timestamp: 1234567890,
+            ..Default::default()
+        };
+        let node_pubkey = VrfEngine::from_seed(seed).node_pubkey();
+
+        let mut signatures = Vec::new();
+        for len in CHALLENGE_LENS {
+            let engine = VrfEngine::from_seed_with_config(seed, EngineConfig { challenge_len: len, ..Default::default() });
+            let proof = engine.process_coinflip(&req).unwrap().proof;
+            assert_eq!(hex::decode(&proof.challenge).unwrap().len(), len);
+            assert_eq!(proof.challenge, engine.debug_transcript(&req).unwrap().challenge);
+            assert_eq!(proof.challenge_len, (len != DEFAULT_CHALLENGE_LEN).then_some(len as u8));
+            assert!(engine.verify_proof(&proof, &req).unwrap());
+            assert!(VrfEngine::verify_with_pubkey(&node_pubkey, &req, &proof).unwrap());
+            signatures.push(proof.signature);
+        }
+        // The length is committed to the transcript, so each one signs a different outcome
+        assert_ne!(signatures[0], signatures[1]);
+
+        let engine = VrfEngine::from_seed_with_config(seed, EngineConfig { challenge_len: 32, ..Default::default() });
+        let proof = engine.process_coinflip(&req).unwrap().proof;
+        let message = |proof: &VrfProof| match engine.verify_proof(proof, &req) {
+            Err(VfError::InvalidProof(message)) => message,
+            other => panic!("expected InvalidProof, got {:?}", other),
+        };
+        let relabelled = VrfProof { challenge_len: None, challenge: String::new(), ..proof.clone() };
+        assert_eq!(message(&relabelled), "Signature verification failed");
+        let unsupported = VrfProof { challenge_len: Some(48), ..proof.clone() };
+        assert_eq!(message(&unsupported), "Unsupported challenge_len 48");
+        assert!(matches!(VrfEngine::onchain_proof(&node_pubkey, &req, &proof), Err(VfError::InvalidProof(_))));
+
+        // A length that doesn't fit the proof's u8 is refused, not truncated to 64
+        let misconfigured = VrfEngine::from_seed_with_config(seed, EngineConfig { challenge_len: 320, ..Default::default() });
+        assert!(matches!(misconfigured.process_coinflip(&req), Err(VfError::VrfFailed(_))));
+        assert!(matches!(misconfigured.debug_transcript(&req), Err(VfError::VrfFailed(_))));
+    }
+
+    #[test]
+    fn test_full_output_hash_matches_truncated_value() {
+        let engine = VrfEngine::with_config(EngineConfig { full_output_hash: true, ..Default::default() });
+        let req = CoinflipRequest {
+            user_seed: "full_hash".to_string(),
+            timestamp: 1234567890,
+            ..Default::default()
+        };
+        let response = engine.process_coinflip(&req).unwrap();
+
+        let full = hex::decode(response.proof.output_hash.as_deref().expect("output_hash enabled")).unwrap();
+        assert_eq!(full.len(), 32);
+        assert_eq!(Base64Engine.decode(&response.proof.vrf_output).unwrap(), full[..8]);
+        assert!(engine.verify_proof(&response.proof, &req).unwrap());
+
+        // The truncated value alone still verifies for older clients
+        let truncated_only = VrfProof { output_hash: None, ..response.proof.clone() };
+        assert!(engine.verify_proof(&truncated_only, &req).unwrap());
+
+        let mut forged = response.proof.clone();
+        forged.output_hash = Some(hex::encode([0u8; 32]));
+        assert!(engine.verify_proof(&forged, &req).is_err());
+        let mut forged = response.proof.clone();
+        forged.vrf_output = Base64Engine.encode(0u64.to_le_bytes());
+        assert!(engine.verify_proof(&forged, &req).is_err());
+
+        assert!(VrfEngine::new().process_coinflip(&req).unwrap().proof.output_hash.is_none());
+    }
+
+    #[test]
+    fn test_house_edge_is_committed_and_shifts_win_rate() {
+        use crate::types::CoinSide;
+
+        let seed = [8u8; 32];
+        let fair = VrfEngine::from_seed(seed);
+        let edged = VrfEngine::from_seed_with_config(seed, EngineConfig { win_probability_bps: 2_000, ..Default::default() });
+        let request = |i: u32| CoinflipRequest {
+            user_seed: format!("edge-{}", i),
+            timestamp: 1_700_000_000,
+            player_choice: if i.is_multiple_of(2) { CoinSide::Heads } else { CoinSide::Tails },
+            ..Default::default()
+        };
+
+        // A fair engine leaves proofs exactly as before
+        let fair_response = fair.process_coinflip(&request(0)).unwrap();
+        assert_eq!(fair_response.proof.win_probability_bps, None);
+
+        let flips = 2_000;
+        let mut wins = 0;
+        for i in 0..flips {
+            let req = request(i);
+            let response = edged.process_coinflip(&req).unwrap();
+            assert_eq!(response.proof.win_probability_bps, Some(2_000));
+            assert_eq!(response.win, req.player_choice.wins(response.heads));
+            assert_eq!(VrfEngine::outcome_from_proof(&response.proof, req.player_choice).unwrap(), response.heads);
+            wins += response.win as u32;
+        }
+        // 20% expected; the bound is more than 5 standard deviations wide
+        assert!((300..500).contains(&wins), "won {} of {}", wins, flips);
+
+        // The threshold is signed, so a proof can't claim a different edge
+        let req = request(0);
+        let response = edged.process_coinflip(&req).unwrap();
+        assert!(edged.verify_proof(&response.proof, &req).unwrap());
+        assert_ne!(response.proof.signature, fair_response.proof.signature);
+        let stripped = VrfProof { win_probability_bps: None, ..response.proof.clone() };
+        assert!(!edged.verify_proof(&stripped, &req).unwrap_or(false));
+        let loosened = VrfProof { win_probability_bps: Some(9_000), ..response.proof };
+        assert!(!edged.verify_proof(&loosened, &req).unwrap_or(false));
+    }
+
+    #[test]
+    fn test_outcome_bit_strategies_are_stable_and_committed() {
+        let seed = [9u8; 32];
+        let req = CoinflipRequest { user_seed: "fixed_seed".to_string(), timestamp: 1_700_000_000, ..Default::default() };
+        let flip = |outcome_bit| {
+            let engine = VrfEngine::from_seed_with_config(seed, EngineConfig { outcome_bit, ..Default::default() });
+            let response = engine.process_coinflip(&req).unwrap();
+            assert!(engine.verify_proof(&response.proof, &req).unwrap());
+            assert_eq!(VrfEngine::outcome_from_proof(&response.proof, req.player_choice).unwrap(), response.heads);
+            (response, engine)
+        };
+
+        let (lsb, _) = flip(OutcomeBit::Lsb);
+        let (msb, msb_engine) = flip(OutcomeBit::Msb);
+        let (parity, _) = flip(OutcomeBit::Parity);
+
+        // Lsb is the original rule, with the original transcript
+        assert_eq!(lsb.proof.outcome_bit, None);
+        assert_eq!(lsb.proof.signature, VrfEngine::from_seed(seed).process_coinflip(&req).unwrap().proof.signature);
+        assert_eq!(msb.proof.outcome_bit, Some(OutcomeBit::Msb));
+        assert_eq!(parity.proof.outcome_bit, Some(OutcomeBit::Parity));
+
+        // Each strategy reads its own bits of its own value, the same way every time
+        for (response, outcome_bit) in [(&lsb, OutcomeBit::Lsb), (&msb, OutcomeBit::Msb), (&parity, OutcomeBit::Parity)] {
+            let value = response.random_value.unwrap();
+            assert_eq!(outcome_bit.heads(value), response.heads);
+            assert_eq!(flip(outcome_bit).0.heads, response.heads);
+        }
+        assert!(OutcomeBit::Lsb.heads(2) && !OutcomeBit::Lsb.heads(1));
+        assert!(OutcomeBit::Msb.heads(u64::MAX >> 1) && !OutcomeBit::Msb.heads(1 << 63));
+        assert!(OutcomeBit::Parity.heads(0b11) && !OutcomeBit::Parity.heads(0b111));
+
+        // Claiming a different strategy breaks the signature
+        let relabelled = VrfProof { outcome_bit: Some(OutcomeBit::Parity), ..msb.proof.clone() };
+        assert!(!msb_engine.verify_proof(&relabelled, &req).unwrap_or(false));
+    }
+
+    #[test]
+    fn test_random_in_range_has_no_modulo_skew() {
+        let engine = VrfEngine::from_seed([9u8; 32]);
+        let draws = 3_000u64;
+        let mut counts = [0u64; 6];
+        for i in 0..draws {
+            let req = CoinflipRequest { user_seed: format!("die_{}", i), timestamp: 1_700_000_000, ..Default::default() };
+            let face = engine.random_in_range(DICE_DOMAIN, &req, 6).unwrap();
+            counts[face as usize] += 1;
+            if i < 10 {
+                assert_eq!(engine.random_in_range(DICE_DOMAIN, &req, 6).unwrap(), face, "same request, same face");
+            }
+        }
+
+        // Each face expects 500 (sd ~20); a biased reduction would push some faces out of band
+        for (face, &count) in counts.iter().enumerate() {
+            assert!((400..=600).contains(&count), "face {} drawn {} times: {:?}", face, count, counts);
+        }
+        let expected = draws as f64 / 6.0;
+        let chi_squared: f64 = counts.iter().map(|&c| (c as f64 - expected).powi(2) / expected).sum();
+        assert!(chi_squared < 20.5, "chi-squared {} over 5 dof", chi_squared); // p = 0.001
+
+        let req = CoinflipRequest { user_seed: "die".to_string(), ..Default::default() };
+        assert!(matches!(engine.random_in_range(DICE_DOMAIN, &req, 0), Err(VfError::InvalidInput(_))));
+        assert_eq!(engine.random_in_range(DICE_DOMAIN, &req, 1).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_dice_is_deterministic_and_verifies() {
+        let engine = VrfEngine::from_seed([12u8; 32]);
+        let req = DiceRequest { user_seed: "dice".to_string(), timestamp: 1_700_000_000, target: 4 };
+
+        let first = engine.process_dice(&req).unwrap();
+        let second = engine.process_dice(&req).unwrap();
+        assert_eq!(first.proof.signature, second.proof.signature);
+        assert_eq!(first.roll, second.roll);
+        assert!((1..=DICE_FACES).contains(&first.roll));
+        assert_eq!(first.win, first.roll == req.target);
+        assert_eq!(VrfEngine::dice_roll_from_proof(&first.proof).unwrap(), first.roll);
+        assert!(engine.verify_dice_proof(&first.proof, &req).unwrap());
+
+        let other_seed = DiceRequest { user_seed: "other".to_string(), ..req.clone() };
+        assert!(engine.verify_dice_proof(&first.proof, &other_seed).is_err());
+
+        for target in [0, DICE_FACES + 1] {
+            let bad = DiceRequest { target, ..req.clone() };
+            assert!(matches!(engine.process_dice(&bad), Err(VfError::InvalidInput(_))));
+        }
+    }
+
+    #[test]
+    fn test_proofs_do_not_verify_across_games() {
+        let engine = VrfEngine::from_seed([13u8; 32]);
+        let flip_req = CoinflipRequest { user_seed: "cross".to_string(), timestamp: 1_700_000_000, ..Default::default() };
+        let dice_req = DiceRequest { user_seed: "cross".to_string(), timestamp: 1_700_000_000, target: 1 };
+
+        // Same seed and timestamp: only the domain label tells the transcripts apart
+        let flip = engine.process_coinflip(&flip_req).unwrap();
+        let dice = engine.process_dice(&dice_req).unwrap();
+        assert_ne!(flip.proof.signature, dice.proof.signature);
+
+        assert!(engine.verify_proof(&flip.proof, &flip_req).unwrap());
+        assert!(engine.verify_dice_proof(&flip.proof, &dice_req).is_err());
+        assert!(engine.verify_dice_proof(&dice.proof, &dice_req).unwrap());
+        assert!(engine.verify_proof(&dice.proof, &flip_req).is_err());
+    }
+
+    #[test]
+    fn test_public_key_alone_verifies() {
+        let req = CoinflipRequest {
+            user_seed: "auditor".to_string(),
+            timestamp: 1234567890,
+            ..Default::default()
+        };
+        // All an auditor keeps is the advertised pubkey and the response
+        let (node_pubkey, response) = {
+            let engine = VrfEngine::new();
+            (engine.node_pubkey(), engine.process_coinflip(&req).unwrap())
+        };
+
+        assert!(VrfEngine::verify_with_pubkey(&node_pubkey, &req, &response.proof).unwrap());
+        assert!(VrfEngine::verify_with_pubkey(&VrfEngine::new().node_pubkey(), &req, &response.proof).is_err());
+        assert!(matches!(
+            VrfEngine::verify_with_pubkey("not base64!", &req, &response.proof),
+            Err(VfError::InvalidProof(_))
+        ));
+        assert!(matches!(
+            VrfEngine::verify_with_pubkey(&Base64Engine.encode([0u8; 16]), &req, &response.proof),
+            Err(VfError::InvalidProof(_))
+        ));
+    }
+
+    #[test]
+    fn test_rotation_during_flips_never_tears() {
+        let engine = Arc::new(VrfEngine::new());
+        let original_pubkey = engine.node_pubkey();
+
+        let workers: Vec<_> = (0..4)
+            .map(|w| {
+                let engine = engine.clone();
+                std::thread::spawn(move || {
+                    (0..200)
+                        .map(|i| {
+                            let req = CoinflipRequest {
+                                user_seed: format!("seed_{}_{}", w, i),
+                                timestamp: 1234567890,
+                                ..Default::default()
+                            };
+                            let response = engine.process_coinflip(&req).unwrap();
+                            (req, response)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let rotator = {
+            let engine = engine.clone();
+            std::thread::spawn(move || {
+                for _ in 0..50 {
+                    engine.rotate_key();
+                    std::thread::yield_now();
+                }
+            })
+        };
+
+        rotator.join().unwrap();
+        assert_ne!(engine.node_pubkey(), original_pubkey);
+
+        for worker in workers {
+            for (req, response) in worker.join().unwrap() {
+                let key_bytes: [u8; 32] = Base64Engine.decode(&response.node_id).unwrap().try_into().unwrap();
+                let node_key = VerifyingKey::from_bytes(&key_bytes).unwrap();
+                assert!(VrfEngine::verify_with_key(COINFLIP_DOMAIN, &node_key, &response.proof, &req).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_seed_length_limits() {
+        let flip = |engine: &VrfEngine, len: usize| {
+            engine.process_coinflip(&CoinflipRequest {
+                user_seed: "s".repeat(len),
+                timestamp: 1234567890,
+                ..Default::default()
+            })
+        };
+        let message = |result: Result<CoinflipResponse, VfError>| match result {
+            Err(VfError::InvalidInput(message)) => message,
+            other => panic!("expected InvalidInput, got {:?}", other.map(|r| r.heads)),
+        };
+
+        // Defaults: 1..=1024
+        let engine = VrfEngine::new();
+        assert!(flip(&engine, 1).is_ok());
+        assert!(flip(&engine, 1024).is_ok());
+        assert_eq!(message(flip(&engine, 0)), "User seed cannot be empty");
+        assert_eq!(message(flip(&engine, 1025)), "User seed too long: 1025 bytes, maximum is 1024");
+
+        let engine = VrfEngine::with_config(EngineConfig { min_seed_len: 16, max_seed_len: 64, ..Default::default() });
+        assert_eq!(message(flip(&engine, 15)), "User seed too short: 15 bytes, minimum is 16");
+        assert!(flip(&engine, 16).is_ok());
+        assert!(flip(&engine, 64).is_ok());
+        assert_eq!(message(flip(&engine, 65)), "User seed too long: 65 bytes, maximum is 64");
+    }
+
+    #[test]
+    fn test_payout_uses_configured_multiplier() {
+        let engine = VrfEngine::with_config(EngineConfig { payout_multiplier_bps: 19_500, ..Default::default() });
+        for i in 0..20 {
+            let response = engine
+                .process_coinflip(&CoinflipRequest {
+                    user_seed: format!("payout{}", i),
+                    timestamp: 1234567890,
+                    wager_lamports: Some(1_000),
+                    ..Default::default()
+                })
+                .unwrap();
+            assert_eq!(response.payout_lamports, Some(if response.win { 1_950 } else { 0 }));
+        }
+
+        // Rejected whether or not the flip would have won
+        let overflowing = CoinflipRequest {
+            user_seed: "overflow".to_string(),
+            timestamp: 1234567890,
+            wager_lamports: Some(u64::MAX / 19_500 + 1),
+            ..Default::default()
+        };
+        assert!(matches!(engine.process_coinflip(&overflowing), Err(VfError::InvalidInput(_))));
+        let largest = CoinflipRequest { wager_lamports: Some(u64::MAX / 19_500), ..overflowing };
+        assert!(engine.process_coinflip(&largest).is_ok());
+    }
+
+    #[test]
+    fn test_wager_limits() {
+        let engine = VrfEngine::with_config(EngineConfig {
+            min_wager_lamports: 1_000,
+            max_wager_lamports: 5_000,
+            ..Default::default()
+        });
+        let flip = |engine: &VrfEngine, wager: u64| {
+            engine.process_coinflip(&CoinflipRequest {
+                user_seed: "limits".to_string(),
+                timestamp: 1234567890,
+                wager_lamports: Some(wager),
+                ..Default::default()
+            })
+        };
+        let message = |result: Result<CoinflipResponse, VfError>| match result {
+            Err(VfError::InvalidInput(message)) => message,
+            other => panic!("expected InvalidInput, got {:?}", other.map(|r| r.heads)),
+        };
+
+        assert_eq!(message(flip(&engine, 999)), "Wager too small: 999 lamports, minimum is 1000");
+        assert!(flip(&engine, 1_000).is_ok());
+        assert!(flip(&engine, 5_000).is_ok());
+        assert_eq!(message(flip(&engine, 5_001)), "Wager too large: 5001 lamports, maximum is 5000");
+
+        // Defaults accept any positive wager the payout can represent
+        let engine = VrfEngine::new();
+        assert_eq!(message(flip(&engine, 0)), "Wager must be positive");
+        assert!(flip(&engine, 1).is_ok());
+        assert!(flip(&engine, u64::MAX / 20_000).is_ok());
+    }
+
+    #[test]
+    fn test_mint_allowlist() {
+        let request = |mint: Option<&str>| CoinflipRequest {
+            user_seed: "mints".to_string(),
+            timestamp: 1234567890,
+            wager_lamports: Some(1_000),
+            token_mint: mint.map(str::to_string),
+            ..Default::default()
+        };
+
+        let engine = VrfEngine::with_config(EngineConfig {
+            allowed_mints: HashSet::from([NATIVE_TOKEN_MINT.to_string(), "USDC".to_string()]),
+            ..Default::default()
+        });
+        assert!(engine.process_coinflip(&request(None)).is_ok());
+        assert!(engine.process_coinflip(&request(Some("USDC"))).is_ok());
+        match engine.process_coinflip(&request(Some("BONK"))) {
+            Err(VfError::InvalidInput(message)) => assert_eq!(message, "Token mint 'BONK' is not accepted"),
+            other => panic!("expected InvalidInput, got {:?}", other.map(|r| r.heads)),
+        }
+
+        // An empty allowlist accepts any mint
+        assert!(VrfEngine::new().process_coinflip(&request(Some("BONK"))).is_ok());
+    }
+
+    #[test]
+    fn test_encoded_seeds_commit_decoded_bytes() {
+        let engine = VrfEngine::new();
+        let request = |seed: &str, encoding: SeedEncoding| CoinflipRequest {
+            user_seed: seed.to_string(),
+            seed_encoding: encoding,
+            timestamp: 1234567890,
+            ..Default::default()
+        };
+
+        let raw = request("hi", SeedEncoding::Raw);
+        let hex = request("6869", SeedEncoding::Hex);
+        let base64 = request("aGk=", SeedEncoding::Base64);
+        let proof = engine.process_coinflip(&raw).unwrap().proof;
+        assert_eq!(engine.process_coinflip(&hex).unwrap().proof.signature, proof.signature);
+        assert_eq!(engine.process_coinflip(&base64).unwrap().proof.signature, proof.signature);
+        assert_ne!(engine.process_coinflip(&request("6869", SeedEncoding::Raw)).unwrap().proof.signature, proof.signature);
+
+        // The proof verifies against the encoded request it was issued for
+        let response = engine.process_coinflip(&hex).unwrap();
+        assert!(engine.verify_proof(&response.proof, &hex).unwrap());
+
+        for bad in [request("68z9", SeedEncoding::Hex), request("686", SeedEncoding::Hex), request("aGk", SeedEncoding::Base64)] {
+            match engine.process_coinflip(&bad) {
+                Err(VfError::InvalidInput(message)) => assert!(message.starts_with("user_seed is not valid"), "{}", message),
+                other => panic!("expected InvalidInput, got {:?}", other.map(|r| r.heads)),
+            }
+        }
+
+        // Length limits count decoded bytes, not characters
+        let engine = VrfEngine::with_config(EngineConfig { max_seed_len: 2, ..Default::default() });
+        assert!(engine.process_coinflip(&hex).is_ok());
+        assert!(engine.process_coinflip(&request("686969", SeedEncoding::Hex)).is_err());
+    }
+
+    #[test]
+    fn test_wallet_signature_verification() {
+        let wallet = SigningKey::from_bytes(&[9u8; 32]);
+        let sign = |req: &CoinflipRequest| bs58::encode(wallet.sign(&req.wallet_message()).to_bytes()).into_string();
+        let mut req = CoinflipRequest {
+            user_seed: "wallet".to_string(),
+            timestamp: 1234567890,
+            player_pubkey: Some(bs58::encode(wallet.verifying_key().as_bytes()).into_string()),
+            wager_lamports: Some(1_000),
+            ..Default::default()
+        };
+        req.wallet_sig = Some(sign(&req));
+
+        let engine = VrfEngine::new();
+        assert!(engine.process_coinflip(&req).is_ok());
+
+        // The message is canonical JSON without the signature itself
+        assert_eq!(
+            String::from_utf8(req.wallet_message()).unwrap(),
+            format!(
+                r#"{{"player_choice":"heads","player_pubkey":"{}","timestamp":1234567890,"user_seed":"wallet","wager_lamports":1000}}"#,
+                req.player_pubkey.as_deref().unwrap()
+            )
+        );
+
+        let bad = |req: &CoinflipRequest| matches!(engine.process_coinflip(req), Err(VfError::BadWalletSignature(_)));
+
+        // Any signed field changed after signing
+        assert!(bad(&CoinflipRequest { wager_lamports: Some(1_000_000), ..req.clone() }));
+        assert!(bad(&CoinflipRequest { player_choice: CoinSide::Tails, ..req.clone() }));
+
+        // Signed by someone else, or claimed for another player
+        let other = SigningKey::from_bytes(&[10u8; 32]);
+        let forged = bs58::encode(other.sign(&req.wallet_message()).to_bytes()).into_string();
+        assert!(bad(&CoinflipRequest { wallet_sig: Some(forged), ..req.clone() }));
+        let other_pubkey = bs58::encode(other.verifying_key().as_bytes()).into_string();
+        assert!(bad(&CoinflipRequest { player_pubkey: Some(other_pubkey), ..req.clone() }));
+
+        // Malformed encodings and a signature with no key to check it against
+        assert!(bad(&CoinflipRequest { wallet_sig: Some("not-base58!".to_string()), ..req.clone() }));
+        assert!(bad(&CoinflipRequest { player_pubkey: Some("player".to_string()), ..req.clone() }));
+        assert!(bad(&CoinflipRequest { player_pubkey: None, ..req.clone() }));
+
+        // Unsigned bets pass unless the node requires signatures on wagers
+        let unsigned = CoinflipRequest { wallet_sig: None, ..req.clone() };
+        assert!(engine.process_coinflip(&unsigned).is_ok());
+        let strict = VrfEngine::with_config(EngineConfig { require_wallet_sig: true, ..Default::default() });
+        assert!(matches!(strict.process_coinflip(&unsigned), Err(VfError::BadWalletSignature(_))));
+        assert!(strict.process_coinflip(&CoinflipRequest { free_play: true, ..unsigned.clone() }).is_ok());
+        assert!(strict.process_coinflip(&CoinflipRequest { wager_lamports: None, ..unsigned }).is_ok());
+        assert!(strict.process_coinflip(&req).is_ok());
+    }
+
+    #[test]
+    fn test_latency_floor_applies_to_every_outcome() {
+        let floor = Duration::from_millis(3);
+        let engine = VrfEngine::with_config(EngineConfig {
+            latency_floor: Some(floor),
+            ..Default::default()
+        });
+
+        // Wall-clock means are too noisy to compare on a loaded machine; what the floor
+        // guarantees is that no outcome, heads or tails, returns before it
+        let (mut heads, mut tails) = (0, 0);
+        for i in 0..40 {
+            let req = CoinflipRequest {
+                user_seed: format!("timing_{}", i),
+                timestamp: 1234567890,
+                ..Default::default()
+            };
+            let start = std::time::Instant::now();
+            let response = engine.process_coinflip(&req).unwrap();
+            assert!(start.elapsed() >= floor);
+            if response.heads { heads += 1 } else { tails += 1 }
+        }
+        assert!(heads > 0 && tails > 0);
+    }
+
+    /// An engine with a duplicate window, having flipped one bet
+    fn flipped_once() -> (VrfEngine, CoinflipRequest) {
+        let engine = VrfEngine::with_config(EngineConfig {
+            duplicate_window: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+        let req = CoinflipRequest {
+            user_seed: "preview".to_string(),
+            timestamp: 1234567890,
+            player_pubkey: Some("player_a".to_string()),
+            ..Default::default()
+        };
+        engine.process_coinflip(&req).unwrap();
+        (engine, req)
+    }
+
+    #[test]
+    fn test_exact_duplicate_within_window_is_rejected() {
+        let (engine, req) = flipped_once();
+        assert!(matches!(engine.process_coinflip(&req), Err(VfError::DuplicateRequest(_))));
+
+        // Any change to the tuple is a new flip
+        let later = CoinflipRequest { timestamp: req.timestamp + 1, ..req.clone() };
+        let other_client_seed = CoinflipRequest { client_seed: Some("client".to_string()), ..req.clone() };
+        assert!(engine.process_coinflip(&later).is_ok());
+        assert!(engine.process_coinflip(&other_client_seed).is_ok());
+
+        // Without a window, repeats stay deterministic and allowed
+        let engine = VrfEngine::new();
+        assert_eq!(engine.process_coinflip(&req).unwrap().heads, engine.process_coinflip(&req).unwrap().heads);
+    }
+
+    #[test]
+    fn test_duplicate_ignores_player_choice_and_wager() {
+        // None of them is in the transcript, so changing them replays the outcome
+        let (engine, req) = flipped_once();
+        let other_player = CoinflipRequest {
+            player_pubkey: Some("player_b".to_string()),
+            player_choice: CoinSide::Tails,
+            wager_lamports: Some(5_000),
+            ..req.clone()
+        };
+        let anonymous = CoinflipRequest { player_pubkey: None, ..req.clone() };
+        assert!(matches!(engine.process_coinflip(&other_player), Err(VfError::DuplicateRequest(_))));
+        assert!(matches!(engine.process_coinflip(&anonymous), Err(VfError::DuplicateRequest(_))));
+    }
+
+    #[test]
+    fn test_duplicate_ignores_seed_encoding() {
+        // The transcript commits the decoded seed bytes, however they were sent
+        let (engine, req) = flipped_once();
+        let hex = CoinflipRequest { user_seed: hex::encode("preview"), seed_encoding: SeedEncoding::Hex, ..req.clone() };
+        let upper_hex = CoinflipRequest { user_seed: hex.user_seed.to_uppercase(), ..hex.clone() };
+        let base64 = CoinflipRequest { user_seed: Base64Engine.encode("preview"), seed_encoding: SeedEncoding::Base64, ..req.clone() };
+        for reencoded in [&hex, &upper_hex, &base64] {
+            assert!(matches!(engine.process_coinflip(reencoded), Err(VfError::DuplicateRequest(_))));
+        }
+    }
+
+    #[test]
+    fn test_stale_and_future_timestamps_are_rejected() {
+        let engine = VrfEngine::with_config(EngineConfig {
+            max_request_age: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+        let now = 1_700_000_000;
+
+        assert!(engine.check_timestamp(now, now).is_ok());
+        assert!(engine.check_timestamp(now - 60, now).is_ok());
+        assert!(matches!(engine.check_timestamp(now - 120, now), Err(VfError::InvalidTimestamp(_))));
+        assert!(engine.check_timestamp(now + TIMESTAMP_SKEW_TOLERANCE.as_secs(), now).is_ok());
+        assert!(matches!(engine.check_timestamp(now + 30, now), Err(VfError::InvalidTimestamp(_))));
+
+        // Rejected before any VRF work, and only when a window is configured
+        let stale = CoinflipRequest { user_seed: "stale".to_string(), timestamp: 1_234_567_890, ..Default::default() };
+        assert!(matches!(engine.process_coinflip(&stale), Err(VfError::InvalidTimestamp(_))));
+        assert!(VrfEngine::new().process_coinflip(&stale).is_ok());
+    }
+
+    #[test]
+    fn test_replayed_bet_id_is_rejected() {
+        let engine = Arc::new(VrfEngine::with_config(EngineConfig { seen_bet_ids: Some(16), ..Default::default() }));
+        let req = CoinflipRequest {
+            user_seed: "replay".to_string(),
+            timestamp: 1_700_000_000,
+            bet_id: Some(uuid::Uuid::new_v4()),
+            ..Default::default()
+        };
+
+        // Racing replays: exactly one wins
+        let accepted = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8).map(|_| scope.spawn(|| engine.process_coinflip(&req))).collect();
+            handles.into_iter().map(|h| h.join().unwrap()).filter(Result::is_ok).count()
+        });
+        assert_eq!(accepted, 1);
+        assert!(matches!(engine.process_coinflip(&req), Err(VfError::DuplicateBet(_))));
+
+        // A fresh id, or no id at all, still goes through
+        assert!(engine.process_coinflip(&CoinflipRequest { bet_id: Some(uuid::Uuid::new_v4()), ..req.clone() }).is_ok());
+        let anonymous = CoinflipRequest { bet_id: None, ..req };
+        assert!(engine.process_coinflip(&anonymous).is_ok());
+        assert!(engine.process_coinflip(&anonymous).is_ok());
+    }
+
+    #[test]
+    fn test_proof_is_bound_to_its_bet_id() {
+        let engine = VrfEngine::new();
+        let req = CoinflipRequest {
+            user_seed: "bound".to_string(),
+            timestamp: 1_700_000_000,
+            bet_id: Some(uuid::Uuid::new_v4()),
+            ..Default::default()
+        };
+        let response = engine.process_coinflip(&req).unwrap();
+        assert!(engine.verify_proof(&response.proof, &req).unwrap());
+
+        let other = CoinflipRequest { bet_id: Some(uuid::Uuid::new_v4()), ..req.clone() };
+        assert!(!engine.verify_proof(&response.proof, &other).unwrap_or(false));
+        let anonymous = CoinflipRequest { bet_id: None, ..req };
+        assert!(!engine.verify_proof(&response.proof, &anonymous).unwrap_or(false));
+    }
+
+    #[test]
+    fn test_large_clock_drift_is_counted() {
+        let engine = VrfEngine::with_config(EngineConfig {
+            max_clock_drift: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let on_time = CoinflipRequest { user_seed: "on_time".to_string(), timestamp: now, ..Default::default() };
+        engine.process_coinflip(&on_time).unwrap();
+        assert_eq!(engine.clock_drift().unwrap().exceeded, 0);
+
+        // A client a day behind is still served, but shows up in the drift metric
+        let skewed = CoinflipRequest { user_seed: "skewed".to_string(), timestamp: now - 86_400, ..Default::default() };
+        assert!(engine.process_coinflip(&skewed).is_ok());
+
+        let stats = engine.clock_drift().unwrap();
+        assert_eq!(stats.checked, 2);
+        assert_eq!(stats.exceeded, 1);
+        assert!(stats.max_observed_secs >= 86_400);
+        assert!(VrfEngine::new().clock_drift().is_none());
+    }
+
+    #[test]
+    fn test_seed_chaining_detects_tampered_link() {
+        let engine = VrfEngine::with_config(EngineConfig {
+            seed_chaining: true,
+            ..Default::default()
+        });
+
+        let rounds: Vec<_> = (0..5)
+            .map(|i| {
+                let req = CoinflipRequest {
+                    user_seed: format!("round_{}", i),
+                    timestamp: 1234567890 + i,
+                    player_pubkey: Some("player_a".to_string()),
+                    ..Default::default()
+                };
+                let response = engine.process_coinflip(&req).unwrap();
+                (req, response)
+            })
+            .collect();
+
+        assert!(rounds[0].1.proof.prev_output.is_none());
+        assert!(rounds[1..].iter().all(|(_, r)| r.proof.prev_output.is_some()));
+        assert!(engine.verify_chain(&rounds).unwrap());
+
+        // Swap in a different (individually valid) proof for round 2
+        let mut tampered = rounds.clone();
+        let other = engine.process_coinflip(&CoinflipRequest {
+            user_seed: "round_2".to_string(),
+            timestamp: 1234567892,
+            player_pubkey: Some("player_b".to_string()),
+            ..Default::default()
+        }).unwrap();
+        tampered[2].1 = other;
+        assert!(engine.verify_chain(&tampered).is_err());
+        assert!(engine.verify_chain(&tampered[2..]).is_err());
+
+        // Rewriting a link invalidates that round's own proof
+        let mut rewritten = rounds.clone();
+        rewritten[3].1.proof.prev_output = Some(hex::encode([0u8; 32]));
+        assert!(engine.verify_proof(&rewritten[3].1.proof, &rewritten[3].0).is_err());
+        assert!(engine.verify_chain(&rewritten).is_err());
+
+        // Chains are per player and require an identity
+        let anonymous = CoinflipRequest {
+            user_seed: "seed".to_string(),
+            timestamp: 1234567890,
+            ..Default::default()
+        };
+        assert!(matches!(engine.process_coinflip(&anonymous), Err(VfError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_seed_chains_resume_after_restart() {
+        let config = EngineConfig { seed_chaining: true, ..Default::default() };
+        let engine = VrfEngine::from_seed_with_config([5u8; 32], config.clone());
+        let round = |engine: &VrfEngine, i: u64| {
+            let req = CoinflipRequest {
+                user_seed: format!("resume_{}", i),
+                timestamp: 1234567890 + i,
+                player_pubkey: Some("player_a".to_string()),
+                ..Default::default()
+            };
+            let response = engine.process_coinflip(&req).unwrap();
+            (req, response)
+        };
+        let mut rounds: Vec<_> = (0..3).map(|i| round(&engine, i)).collect();
+
+        // Stored out of flip order, the way one drainer flush can write them
+        let restarted = VrfEngine::from_seed_with_config([5u8; 32], config);
+        let stored: Vec<_> = [2, 0, 1].iter().map(|&i| ("player_a".to_string(), rounds[i].1.proof.clone())).collect();
+        assert_eq!(restarted.restore_chain_heads(&stored), 1);
+
+        rounds.push(round(&restarted, 3));
+        assert!(rounds[3].1.proof.prev_output.is_some());
+        assert!(restarted.verify_chain(&rounds).unwrap());
+    }
+
+    #[test]
+    fn test_replay_reproduces_stored_flip() {
+        let engine = VrfEngine::with_config(EngineConfig {
+            seed_chaining: true,
+            duplicate_window: Some(Duration::from_secs(300)),
+            max_request_age: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let rounds: Vec<_> = (0..2)
+            .map(|i| {
+                let req = CoinflipRequest {
+                    user_seed: format!("dispute_{}", i),
+                    timestamp: now,
+                    player_pubkey: Some("player_a".to_string()),
+                    ..Default::default()
+                };
+                let response = engine.process_coinflip(&req).unwrap();
+                (req, response)
+            })
+            .collect();
+
+        // A chained round replays from the link its proof recorded, and replaying twice
+        // trips neither duplicate detection nor the chain
+        let (req, stored) = &rounds[1];
+        for _ in 0..2 {
+            let replay = engine.replay_coinflip(req, &stored.proof).unwrap();
+            assert!(replay.matches_stored);
+            assert_eq!(replay.reason, None);
+            assert_eq!(replay.response.heads, stored.heads);
+            assert_eq!(replay.response.proof.signature, stored.proof.signature);
+        }
+
+        // A stale request no longer passes freshness, but still replays
+        let old = CoinflipRequest { user_seed: "old".to_string(), timestamp: now - 3600, ..Default::default() };
+        let old_proof = VrfEngine::from_seed([0u8; 32]).process_coinflip(&old).unwrap().proof;
+        let fresh = VrfEngine::from_seed([0u8; 32]).replay_coinflip(&old, &old_proof).unwrap();
+        assert!(fresh.matches_stored);
+
+        // Same key, altered request or proof
+        let altered = CoinflipRequest { user_seed: "dispute_x".to_string(), ..req.clone() };
+        assert_eq!(engine.replay_coinflip(&altered, &stored.proof).unwrap().reason, Some(ReplayDivergence::Tampered));
+        let mut forged = stored.proof.clone();
+        forged.vrf_output = Base64Engine.encode(0u64.to_le_bytes());
+        assert_eq!(engine.replay_coinflip(req, &forged).unwrap().reason, Some(ReplayDivergence::Tampered));
+        forged.prev_output = Some("not hex".to_string());
+        assert!(matches!(engine.replay_coinflip(req, &forged), Err(VfError::InvalidProof(_))));
+
+        // After rotation the stored proof commits to the old key
+        engine.rotate_key();
+        let replay = engine.replay_coinflip(req, &stored.proof).unwrap();
+        assert!(!replay.matches_stored);
+        assert_eq!(replay.reason, Some(ReplayDivergence::KeyRotated));
+    }
+
+    struct MockBeacon(RwLock<BeaconRound>);
+
+    impl EntropyBeacon for MockBeacon {
+        fn latest(&self) -> Result<BeaconRound, VfError> {
+            Ok(self.0.read().unwrap().clone())
+        }
+    }
+
+    #[test]
+    fn test_beacon_entropy_changes_output_and_verifies() {
+        let beacon = Arc::new(MockBeacon(RwLock::new(BeaconRound { round: 1, randomness: vec![1u8; 32] })));
+        let engine = VrfEngine::from_seed([9u8; 32]).with_beacon(beacon.clone());
+        let req = CoinflipRequest {
+            user_seed: "test_seed".to_string(),
+            timestamp: 1234567890,
+            ..Default::default()
+        };
+
+        let first = engine.process_coinflip(&req).unwrap();
+        assert_eq!(first.proof.beacon_round, Some(1));
+        assert!(engine.verify_proof(&first.proof, &req).unwrap());
+
+        *beacon.0.write().unwrap() = BeaconRound { round: 2, randomness: vec![2u8; 32] };
+        let second = engine.process_coinflip(&req).unwrap();
+        assert_eq!(second.proof.beacon_round, Some(2));
+        assert_ne!(first.proof.signature, second.proof.signature);
+        assert!(engine.verify_proof(&second.proof, &req).unwrap());
+
+        // Unmixed output differs, and the revealed beacon value can't be swapped
+        let plain = VrfEngine::from_seed([9u8; 32]).process_coinflip(&req).unwrap();
+        assert_ne!(plain.proof.signature, first.proof.signature);
+
+        let mut swapped = second.proof.clone();
+        swapped.beacon_randomness = first.proof.beacon_randomness.clone();
+        assert!(engine.verify_proof(&swapped, &req).is_err());
+    }
+}